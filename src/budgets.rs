@@ -0,0 +1,186 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How many of the largest contributors are listed for an exceeded budget.
+const MAX_CONTRIBUTORS: usize = 5;
+
+/// A number of bytes, optionally with a binary suffix (e.g., 512K, 200M or 1G).
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, factor) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&s[..s.len() - 1], 1 << 10),
+            Some('M') => (&s[..s.len() - 1], 1 << 20),
+            Some('G') => (&s[..s.len() - 1], 1 << 30),
+            _ => (s, 1),
+        };
+        digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(factor))
+            .map(ByteSize)
+            .ok_or_else(|| format!("Invalid size '{}' (expected bytes, e.g., 1048576 or 1M)", s))
+    }
+}
+
+/// A budget of the closure of an analyzed file that is exceeded, with the largest contributors.
+pub enum BudgetViolation {
+    /// The closure has more libraries than allowed (see --max-deps). The contributors are the
+    /// direct dependencies with the number of libraries they pull in (including themselves).
+    TooManyDependencies {
+        root: PathBuf,
+        count: usize,
+        max: usize,
+        contributors: Vec<(PathBuf, usize)>,
+    },
+    /// The files of the closure (including the analyzed file) are larger than allowed (see
+    /// --max-closure-size). The contributors are the largest files.
+    ClosureTooLarge {
+        root: PathBuf,
+        size: u64,
+        max: u64,
+        contributors: Vec<(PathBuf, u64)>,
+    },
+}
+
+impl BudgetViolation {
+    /// The analyzed file whose closure exceeds the budget.
+    pub fn root(&self) -> &Path {
+        match self {
+            &BudgetViolation::TooManyDependencies { ref root, .. }
+            | &BudgetViolation::ClosureTooLarge { ref root, .. } => root,
+        }
+    }
+
+    /// A stable fingerprint of the violation (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let kind = match *self {
+            BudgetViolation::TooManyDependencies { .. } => "dependency-budget",
+            BudgetViolation::ClosureTooLarge { .. } => "closure-size-budget",
+        };
+        fingerprint(kind, &[&normalize_path(self.root())])
+    }
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BudgetViolation::TooManyDependencies {
+                ref root,
+                count,
+                max,
+                ref contributors,
+            } => write!(
+                f,
+                "{:?}: Depends on {} libraries (budget: {}), most of them are pulled in by {}",
+                root,
+                count,
+                max,
+                contributors
+                    .iter()
+                    .map(|&(ref lib, n)| format!("{:?} ({})", lib, n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            BudgetViolation::ClosureTooLarge {
+                ref root,
+                size,
+                max,
+                ref contributors,
+            } => write!(
+                f,
+                "{:?}: The closure takes {} bytes (budget: {}), the largest files are {}",
+                root,
+                size,
+                max,
+                contributors
+                    .iter()
+                    .map(|&(ref lib, size)| format!("{:?} ({} bytes)", lib, size))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// The libraries (transitively) reachable from `start`, including itself.
+fn reachable_from<'a>(libs: &'a LibraryDependencies, start: &'a PathBuf) -> HashSet<&'a PathBuf> {
+    let mut reachable = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(path) = queue.pop_front() {
+        if reachable.insert(path) {
+            queue.extend(libs.resolved_dependencies(path));
+        }
+    }
+    reachable
+}
+
+/// Check the closure of the analyzed file against the given budgets of the number of libraries and
+/// their total size (in bytes).
+pub fn check_budgets(
+    libs: &LibraryDependencies,
+    max_deps: Option<usize>,
+    max_size: Option<u64>,
+) -> Vec<BudgetViolation> {
+    let mut closure = Vec::new();
+    for (_, path) in libs.needed_in_load_order() {
+        if let Some(path) = path.filter(|p| *p != &libs.root) {
+            if !closure.contains(&path) {
+                closure.push(path);
+            }
+        }
+    }
+
+    let mut violations = Vec::new();
+    if let Some(max) = max_deps.filter(|max| closure.len() > *max) {
+        let mut direct = libs.resolved_dependencies(&libs.root).collect::<Vec<_>>();
+        direct.sort();
+        direct.dedup();
+        let mut contributors = direct
+            .into_iter()
+            .map(|path| (path.clone(), reachable_from(libs, path).len()))
+            .collect::<Vec<_>>();
+        contributors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        contributors.truncate(MAX_CONTRIBUTORS);
+        violations.push(BudgetViolation::TooManyDependencies {
+            root: libs.root.clone(),
+            count: closure.len(),
+            max,
+            contributors,
+        });
+    }
+
+    if let Some(max) = max_size {
+        let mut sizes = Some(&libs.root)
+            .into_iter()
+            .chain(closure)
+            .filter_map(|path| {
+                libs.opened_libs
+                    .get(path)
+                    .map(|lib| (path.clone(), lib.get_bytes().len() as u64))
+            })
+            .collect::<Vec<_>>();
+        let size = sizes.iter().map(|&(_, size)| size).sum::<u64>();
+        if size > max {
+            sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            sizes.truncate(MAX_CONTRIBUTORS);
+            violations.push(BudgetViolation::ClosureTooLarge {
+                root: libs.root.clone(),
+                size,
+                max,
+                contributors: sizes,
+            });
+        }
+    }
+    violations
+}
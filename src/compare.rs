@@ -0,0 +1,195 @@
+use objinfo::ObjectInfo;
+use rootfs::image_search_methods;
+
+use files::{FileProvider, ImageFs};
+use libraries::{reroot, try_find_for_elfs, Environment, ErrorMsg, LibraryCache,
+                LibraryDependencies, LibraryIdentity, ResolveSettings, Traversal};
+use symbols::SymbolSummary;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How a dependency resolves in one of the compared roots: the path inside the root, the name of
+/// the actual file (after following symbolic links, e.g., libfoo.so.1.2.3) and the build id.
+#[derive(PartialEq)]
+pub struct ResolvedLibrary {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub build_id: Option<String>,
+}
+
+/// A difference between the closures of a binary in two roots.
+pub enum LibraryDelta<'a> {
+    /// The library resolves in root a, but not in root b.
+    OnlyInA(&'a ResolvedLibrary),
+    /// The library resolves in root b, but not in root a.
+    OnlyInB(&'a ResolvedLibrary),
+    /// The library resolves to files at different paths.
+    DifferentProvider(&'a ResolvedLibrary, &'a ResolvedLibrary),
+    /// The library resolves to the same path, but the file is a different version or build.
+    DifferentVersion(&'a ResolvedLibrary, &'a ResolvedLibrary),
+}
+
+/// A difference between the libraries providing a symbol (needed in both roots) in two roots.
+pub enum SymbolDelta<'a> {
+    /// The symbol is provided in root a, but not in root b.
+    OnlyInA(&'a BTreeSet<String>),
+    /// The symbol is provided in root b, but not in root a.
+    OnlyInB(&'a BTreeSet<String>),
+    /// The symbol is provided by different libraries.
+    DifferentProviders(&'a BTreeSet<String>, &'a BTreeSet<String>),
+}
+
+/// The analysis of a binary against the libraries and loader configuration of one root.
+pub struct RootAnalysis {
+    pub root: PathBuf,
+    pub libs: LibraryDependencies,
+    /// The resolved dependencies by the name they are requested by.
+    pub resolved: BTreeMap<String, ResolvedLibrary>,
+    /// The symbols the closure needs, with the names of the libraries exporting them (empty for
+    /// unresolved symbols).
+    pub symbols: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl RootAnalysis {
+    fn new(root: &Path, libs: LibraryDependencies) -> Self {
+        let image_path = |path: &Path| {
+            path.strip_prefix(root)
+                .map(|p| Path::new("/").join(p))
+                .unwrap_or(path.to_path_buf())
+        };
+        let resolved = libs.resolved
+            .iter()
+            .map(|(name, path)| {
+                let real_path = libs.files.canonicalize(path).unwrap_or(path.clone());
+                let library = ResolvedLibrary {
+                    path: image_path(path),
+                    file_name: real_path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    build_id: libs.opened_libs
+                        .get(path)
+                        .and_then(|lib| ObjectInfo::of_library(lib).build_id),
+                };
+                (name.to_string_lossy().into_owned(), library)
+            })
+            .collect();
+
+        let summary = SymbolSummary::from_libs(&libs);
+        let symbols = summary
+            .unresolved
+            .keys()
+            .map(|symbol| {
+                let providers = summary
+                    .exporters(symbol)
+                    .map(|libs| libs.iter().cloned().collect())
+                    .unwrap_or_default();
+                (symbol.clone(), providers)
+            })
+            .collect();
+
+        RootAnalysis {
+            root: root.to_path_buf(),
+            libs,
+            resolved,
+            symbols,
+        }
+    }
+}
+
+/// The analyses of the same binary against two roots (e.g., the current and a new base image).
+pub struct RootComparison {
+    pub binary: PathBuf,
+    pub a: RootAnalysis,
+    pub b: RootAnalysis,
+}
+
+impl RootComparison {
+    /// Analyze `binary` against both roots. The binary is taken from inside each root if it
+    /// exists there, otherwise the given file is analyzed against both roots.
+    pub fn analyze(binary: &Path, root_a: &Path, root_b: &Path) -> Result<Self, Box<dyn Error>> {
+        // Relative roots would make every resolved path relative (and thus a problem).
+        let canonical_root = |root: &Path| {
+            fs::canonicalize(root).map_err(|e| ErrorMsg(format!("{:?}: {}", root, e)))
+        };
+        let root_a = canonical_root(root_a)?;
+        let root_b = canonical_root(root_b)?;
+        let cache = LibraryCache::new();
+        let jobs = [&root_a, &root_b]
+            .iter()
+            .map(|root| {
+                let files = Arc::new(ImageFs::new(root));
+                let in_root = reroot(&Path::new("/").join(binary), Some(root));
+                let file = if files.exists(&in_root) {
+                    in_root
+                } else {
+                    binary.to_path_buf()
+                };
+                let settings = ResolveSettings {
+                    search_methods: image_search_methods(root),
+                    secure_execution: false,
+                    sysroot: Some(root.to_path_buf()),
+                    identity: LibraryIdentity::default(),
+                    traversal: Traversal::default(),
+                    environment: Environment::default(),
+                    files,
+                };
+                (file, settings)
+            })
+            .collect::<Vec<_>>();
+        let mut results = try_find_for_elfs(&jobs, &cache).into_iter();
+        let libs_a = results.next().unwrap().map_err(ErrorMsg)?;
+        let libs_b = results.next().unwrap().map_err(ErrorMsg)?;
+        Ok(RootComparison {
+            binary: binary.to_path_buf(),
+            a: RootAnalysis::new(&root_a, libs_a),
+            b: RootAnalysis::new(&root_b, libs_b),
+        })
+    }
+
+    /// The differences of the resolved dependencies, by the name they are requested by.
+    pub fn library_deltas(&self) -> Vec<(&str, LibraryDelta<'_>)> {
+        let names = self.a
+            .resolved
+            .keys()
+            .chain(self.b.resolved.keys())
+            .collect::<BTreeSet<_>>();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let delta = match (self.a.resolved.get(name), self.b.resolved.get(name)) {
+                    (Some(a), None) => LibraryDelta::OnlyInA(a),
+                    (None, Some(b)) => LibraryDelta::OnlyInB(b),
+                    (Some(a), Some(b)) if a.path != b.path => LibraryDelta::DifferentProvider(a, b),
+                    (Some(a), Some(b)) if a != b => LibraryDelta::DifferentVersion(a, b),
+                    _ => return None,
+                };
+                Some((name.as_str(), delta))
+            })
+            .collect()
+    }
+
+    /// The differences of the symbols needed in both roots.
+    pub fn symbol_deltas(&self) -> Vec<(&str, SymbolDelta<'_>)> {
+        self.a
+            .symbols
+            .iter()
+            .filter_map(|(symbol, providers_a)| {
+                let providers_b = self.b.symbols.get(symbol)?;
+                let delta = match (providers_a.is_empty(), providers_b.is_empty()) {
+                    (false, true) => SymbolDelta::OnlyInA(providers_a),
+                    (true, false) => SymbolDelta::OnlyInB(providers_b),
+                    (false, false) if providers_a != providers_b => {
+                        SymbolDelta::DifferentProviders(providers_a, providers_b)
+                    }
+                    _ => return None,
+                };
+                Some((symbol.as_str(), delta))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,64 @@
+use libraries::ErrorMsg;
+use theme::Theme;
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// Settings read from the user's configuration file. Command line options take precedence.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub theme: Option<Theme>,
+}
+
+/// $XDG_CONFIG_HOME/linkcheck/config, falling back to ~/.config/linkcheck/config.
+pub fn config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("linkcheck").join("config"))
+}
+
+impl Config {
+    /// Load the configuration file. A missing file results in the default configuration.
+    ///
+    /// The file consists of 'key = value' lines. Empty lines and lines starting with '#' are
+    /// ignored.
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let path = match config_path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        };
+        let mut content = String::new();
+        match File::open(&path) {
+            Ok(mut file) => {
+                file.read_to_string(&mut content)?;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(Box::new(e)),
+        }
+
+        let mut config = Config::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: String| ErrorMsg(format!("{:?}:{}: {}", path, i + 1, msg));
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| error(format!("Expected 'key = value', found '{}'", line)))?
+                .trim();
+            match key {
+                "theme" => config.theme = Some(value.parse().map_err(error)?),
+                other => return Err(Box::new(error(format!("Unknown setting '{}'", other)))),
+            }
+        }
+        Ok(config)
+    }
+}
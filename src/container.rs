@@ -0,0 +1,79 @@
+use libraries::ErrorMsg;
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Container runtimes that are asked for the init process of a container, in this order.
+const RUNTIMES: &[&str] = &["docker", "podman"];
+
+/// Run a container runtime command and return its (trimmed) output if it succeeded.
+fn runtime_output(runtime: &str, args: &[&str]) -> Option<String> {
+    Command::new(runtime)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The root file system of a container as seen from the host: /proc/<pid>/root of the container's
+/// init process or, for containers that are not running, the directory where `podman mount`
+/// mounted it (unmounted again when dropped).
+pub struct ContainerRoot {
+    pub root: PathBuf,
+    mounted: Option<String>,
+}
+
+impl ContainerRoot {
+    /// Find the root file system of the container with the given id or name. The pid of a
+    /// process inside the container is accepted as well.
+    pub fn open(id: &str) -> Result<ContainerRoot, Box<dyn Error>> {
+        let pid = if id.chars().all(|c| c.is_ascii_digit()) {
+            Some(id.to_string())
+        } else {
+            RUNTIMES
+                .iter()
+                .filter_map(|runtime| {
+                    runtime_output(runtime, &["inspect", "--format", "{{.State.Pid}}", id])
+                })
+                .find(|pid| !pid.is_empty() && pid != "0")
+        };
+
+        if let Some(pid) = pid {
+            let root = Path::new("/proc").join(pid).join("root");
+            fs::read_dir(&root).map_err(|e| {
+                ErrorMsg(format!(
+                    "Cannot access the file system of container {} at {:?}: {}",
+                    id, root, e
+                ))
+            })?;
+            return Ok(ContainerRoot {
+                root,
+                mounted: None,
+            });
+        }
+
+        if let Some(root) = runtime_output("podman", &["mount", id]).filter(|r| !r.is_empty()) {
+            return Ok(ContainerRoot {
+                root: PathBuf::from(root),
+                mounted: Some(id.to_string()),
+            });
+        }
+        Err(Box::new(ErrorMsg(format!(
+            "No container {} found (tried {})",
+            id,
+            RUNTIMES.join(", ")
+        ))))
+    }
+}
+
+impl Drop for ContainerRoot {
+    fn drop(&mut self) {
+        if let Some(ref id) = self.mounted {
+            let _ = runtime_output("podman", &["unmount", id]);
+        }
+    }
+}
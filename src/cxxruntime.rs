@@ -0,0 +1,231 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{Library, LibraryDependencies};
+use symbol_versions::SymbolVersions;
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const LIBSTDCXX: &str = "libstdc++.so.6";
+const GLIBCXX_PREFIX: &str = "GLIBCXX_3.4";
+/// GLIBCXX_3.4.21 (GCC 5) introduced the new std::string and std::list ABI.
+const NEW_ABI_GLIBCXX_REVISION: u32 = 21;
+const NEW_ABI_GCC_MAJOR: u32 = 5;
+
+/// The revision of a GLIBCXX_3.4[.N] version name (0 for GLIBCXX_3.4 itself).
+fn glibcxx_revision(version: &str) -> Option<u32> {
+    if version == GLIBCXX_PREFIX {
+        Some(0)
+    } else if version.starts_with(GLIBCXX_PREFIX) && version[GLIBCXX_PREFIX.len()..].starts_with('.') {
+        version[GLIBCXX_PREFIX.len() + 1..].parse().ok()
+    } else {
+        None
+    }
+}
+
+/// The compiler identification strings in the .comment section of a library.
+fn compiler_comments(lib: &Library) -> Vec<String> {
+    let elf = lib.get_elf();
+    let bytes = lib.get_bytes();
+    let section = elf.section_headers.iter().find(|sh| {
+        elf.shdr_strtab.get(sh.sh_name).and_then(|n| n.ok()) == Some(".comment")
+    });
+    let content = section.and_then(|sh| {
+        bytes.get(sh.sh_offset as usize..(sh.sh_offset + sh.sh_size) as usize)
+    });
+    content
+        .map(|content| {
+            content
+                .split(|b| *b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The GCC version (e.g., "4.8.5") of a "GCC: (<vendor version>) <version> [<date>]" comment.
+fn gcc_version(comment: &str) -> Option<&str> {
+    if !comment.starts_with("GCC: ") {
+        return None;
+    }
+    let version = comment.rfind(')').map_or(&comment[5..], |i| &comment[i + 1..]);
+    version.split_whitespace().next()
+}
+
+fn major(version: &str) -> Option<u32> {
+    version.split('.').next().and_then(|major| major.parse().ok())
+}
+
+pub enum CxxRuntimeProblem {
+    /// An object requires versions that the resolved libstdc++ does not provide.
+    RuntimeTooOld {
+        lib: PathBuf,
+        missing: Vec<String>,
+        runtime: PathBuf,
+        newest: String,
+    },
+    /// C++ objects built before GCC 5 are mixed with objects of the newer libstdc++ ABI
+    /// generation (each with the reason for the classification).
+    MixedGenerations {
+        old: Vec<(PathBuf, String)>,
+        new: Vec<(PathBuf, String)>,
+    },
+}
+
+impl CxxRuntimeProblem {
+    /// The object that has to be rebuilt (or replaced) to fix the problem.
+    pub fn owner(&self) -> &Path {
+        match self {
+            CxxRuntimeProblem::RuntimeTooOld { lib, .. } => lib,
+            CxxRuntimeProblem::MixedGenerations { old, .. } => &old[0].0,
+        }
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        match self {
+            CxxRuntimeProblem::RuntimeTooOld {
+                lib,
+                runtime,
+                ..
+            } => fingerprint(
+                "cxx-runtime-too-old",
+                &[&normalize_path(lib), &normalize_path(runtime)],
+            ),
+            CxxRuntimeProblem::MixedGenerations { old, .. } => {
+                let old = old.iter()
+                    .map(|(path, _)| normalize_path(path))
+                    .collect::<Vec<_>>();
+                fingerprint(
+                    "cxx-mixed-generations",
+                    &old.iter().map(|p| p.as_str()).collect::<Vec<_>>(),
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for CxxRuntimeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |objects: &[(PathBuf, String)]| {
+            objects
+                .iter()
+                .map(|(path, reason)| format!("{:?} ({})", path, reason))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            CxxRuntimeProblem::RuntimeTooOld {
+                lib,
+                missing,
+                runtime,
+                newest,
+            } => write!(
+                f,
+                "{:?} requires {}, but {:?} only provides up to {}",
+                lib,
+                missing.join(", "),
+                runtime,
+                newest
+            ),
+            CxxRuntimeProblem::MixedGenerations { old, new } => write!(
+                f,
+                "C++ objects built before GCC 5 [{}] are mixed with objects of the GCC 5+ ABI \
+                 generation [{}]; std::string and std::list are incompatible between them unless \
+                 _GLIBCXX_USE_CXX11_ABI=0 was used",
+                list(old),
+                list(new)
+            ),
+        }
+    }
+}
+
+/// Check that the resolved libstdc++ provides all versions the C++ objects of the closure
+/// require, and that the closure does not mix objects of the old (pre-GCC 5) and new libstdc++
+/// ABI generations. Generations are derived from the GCC versions in the .comment sections, or
+/// from the required GLIBCXX versions for objects without such information.
+pub fn find_cxx_runtime_problems(libs: &LibraryDependencies) -> Vec<CxxRuntimeProblem> {
+    let mut problems = Vec::new();
+    let runtime_path = libs.resolved.get(OsStr::new(LIBSTDCXX));
+    let runtime = runtime_path.and_then(|path| {
+        libs.opened_libs
+            .get(path)
+            .map(|lib| (path, SymbolVersions::of_library(lib)))
+    });
+
+    let mut paths = libs.opened_libs
+        .keys()
+        .filter(|path| Some(*path) != runtime_path)
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut old = Vec::new();
+    let mut new = Vec::new();
+    for path in paths {
+        let needs_runtime = libs.needed
+            .get(path)
+            .is_some_and(|needed| needed.iter().any(|n| n == LIBSTDCXX));
+        if !needs_runtime {
+            continue;
+        }
+        let lib = &libs.opened_libs[path];
+        let versions = SymbolVersions::of_library(lib);
+        let mut required = versions
+            .required
+            .get(LIBSTDCXX)
+            .map(|r| r.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        required.sort_by_key(|v| (glibcxx_revision(v), v.clone()));
+
+        if let Some((runtime_path, ref runtime)) = runtime {
+            let newest = runtime
+                .defined
+                .iter()
+                .filter_map(|v| glibcxx_revision(v).map(|r| (r, v)))
+                .max();
+            if let Some((_, newest)) = newest {
+                let missing = required
+                    .iter()
+                    .filter(|v| !runtime.defined.contains(*v))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    problems.push(CxxRuntimeProblem::RuntimeTooOld {
+                        lib: path.clone(),
+                        missing,
+                        runtime: runtime_path.clone(),
+                        newest: newest.clone(),
+                    });
+                }
+            }
+        }
+
+        let newest_gcc = compiler_comments(lib)
+            .iter()
+            .filter_map(|c| gcc_version(c).and_then(|v| major(v).map(|m| (m, v.to_string()))))
+            .max();
+        let newest_required = required
+            .iter()
+            .filter_map(|v| glibcxx_revision(v).map(|r| (r, v)))
+            .max();
+        match (newest_gcc, newest_required) {
+            (Some((major, version)), _) => {
+                let reason = format!("GCC {}", version);
+                if major < NEW_ABI_GCC_MAJOR {
+                    old.push((path.clone(), reason));
+                } else {
+                    new.push((path.clone(), reason));
+                }
+            }
+            (None, Some((revision, version))) if revision >= NEW_ABI_GLIBCXX_REVISION => {
+                new.push((path.clone(), format!("requires {}", version)));
+            }
+            _ => {}
+        }
+    }
+    if !old.is_empty() && !new.is_empty() {
+        problems.push(CxxRuntimeProblem::MixedGenerations { old, new });
+    }
+    problems
+}
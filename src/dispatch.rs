@@ -0,0 +1,242 @@
+use glob::glob;
+use serde_json::{self, Value};
+
+use files::FileProvider;
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{reroot, ElfArch, LibraryDependencies, LibraryLocations};
+use symbols::exported_symbol_names;
+
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A dispatch library (by soname) and the prefix of the symbols it forwards to vendor drivers
+/// that are only loaded at runtime, which must export the vendor entry point.
+struct DispatchLibrary {
+    soname: &'static str,
+    symbol_prefix: &'static str,
+    entry_point: &'static str,
+    manifest_dirs: &'static [&'static str],
+    manifest_env: Option<&'static str>,
+    driver_glob: Option<&'static str>,
+}
+
+const DISPATCH_LIBRARIES: &[DispatchLibrary] = &[
+    DispatchLibrary {
+        soname: "libvulkan.so.1",
+        symbol_prefix: "vk",
+        entry_point: "vk_icdGetInstanceProcAddr",
+        manifest_dirs: &[
+            "/etc/vulkan/icd.d",
+            "/usr/local/share/vulkan/icd.d",
+            "/usr/share/vulkan/icd.d",
+        ],
+        manifest_env: Some("VK_ICD_FILENAMES"),
+        driver_glob: None,
+    },
+    DispatchLibrary {
+        soname: "libEGL.so.1",
+        symbol_prefix: "egl",
+        entry_point: "__egl_Main",
+        manifest_dirs: &["/etc/glvnd/egl_vendor.d", "/usr/share/glvnd/egl_vendor.d"],
+        manifest_env: Some("__EGL_VENDOR_LIBRARY_FILENAMES"),
+        driver_glob: None,
+    },
+    DispatchLibrary {
+        soname: "libGLX.so.0",
+        symbol_prefix: "glX",
+        entry_point: "__glx_Main",
+        manifest_dirs: &[],
+        manifest_env: None,
+        driver_glob: Some("libGLX_*.so.0"),
+    },
+];
+
+/// Read the driver library named in a Vulkan ICD or GLVND vendor JSON manifest.
+fn manifest_library(manifest: &Path) -> Option<String> {
+    let content = fs::read_to_string(manifest).ok()?;
+    let json: Value = serde_json::from_str(&content).ok()?;
+    json.get("ICD")?
+        .get("library_path")?
+        .as_str()
+        .map(|s| s.to_owned())
+}
+
+/// A driver of a dispatch library in the closure that the dispatch library cannot use.
+pub struct DriverProblem {
+    /// The dispatch library (e.g., libvulkan.so.1)
+    pub dispatch_lib: PathBuf,
+    /// The driver (as named in the manifest if it cannot be found)
+    pub driver: PathBuf,
+    /// The manifest naming the driver (if it is not found by its file name)
+    pub manifest: Option<PathBuf>,
+    pub description: String,
+}
+
+impl DriverProblem {
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "dispatch-driver",
+            &[&normalize_path(&self.dispatch_lib), &normalize_path(&self.driver)],
+        )
+    }
+}
+
+impl fmt::Display for DriverProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: Driver {:?}", self.dispatch_lib, self.driver)?;
+        if let Some(ref manifest) = self.manifest {
+            write!(f, " (from {:?})", manifest)?;
+        }
+        write!(f, " {}, so it cannot be used", self.description)
+    }
+}
+
+/// Find the driver libraries of a dispatch library, together with the manifests naming them:
+/// those named in the manifests (bare names are searched like the loader does, relative paths
+/// are relative to the manifest) and those whose file name matches the driver naming convention
+/// in any of the search locations. Bare names that are not found (in a file of architecture
+/// `arch`) are returned as they are.
+fn find_drivers(
+    dispatch: &DispatchLibrary,
+    locations: Option<&LibraryLocations>,
+    arch: Option<ElfArch>,
+    sysroot: Option<&Path>,
+    files: &dyn FileProvider,
+) -> Vec<(PathBuf, Option<PathBuf>)> {
+    let manifests = match dispatch.manifest_env.and_then(|var| env::var(var).ok()) {
+        Some(files) => files
+            .split(':')
+            .filter(|f| !f.is_empty())
+            .map(|f| reroot(Path::new(f), sysroot))
+            .collect::<Vec<_>>(),
+        None => {
+            let mut manifests = Vec::new();
+            for dir in dispatch.manifest_dirs {
+                let pattern = reroot(Path::new(dir), sysroot).join("*.json");
+                if let Ok(paths) = glob(&pattern.to_string_lossy()) {
+                    manifests.extend(paths.filter_map(|p| p.ok()));
+                }
+            }
+            manifests
+        }
+    };
+
+    let mut drivers = Vec::new();
+    for manifest in manifests {
+        let library = match manifest_library(&manifest) {
+            Some(library) => library,
+            None => continue,
+        };
+        let path = if library.starts_with('/') {
+            reroot(Path::new(&library), sysroot)
+        } else if library.contains('/') {
+            let dir = manifest.parent().unwrap_or(Path::new("/"));
+            dir.join(library.trim_start_matches("./"))
+        } else {
+            match locations.map(|l| l.try_find_compatible_library(&library, arch, files)) {
+                Some((Some((path, _)), _)) => path,
+                // A driver of another architecture is reported as such.
+                Some((None, skipped)) => skipped
+                    .into_iter()
+                    .next()
+                    .unwrap_or(PathBuf::from(&library)),
+                None => PathBuf::from(&library),
+            }
+        };
+        drivers.push((path, Some(manifest)));
+    }
+
+    if let (Some(driver_glob), Some(locations)) = (dispatch.driver_glob, locations) {
+        for (dir, _) in locations.iter() {
+            if let Ok(paths) = files.glob(&dir.join(driver_glob)) {
+                drivers.extend(paths.into_iter().map(|path| (path, None)));
+            }
+        }
+    }
+
+    // Drivers found at several paths (e.g., below /lib and /usr/lib on merged-/usr systems) are
+    // only checked once.
+    drivers.sort();
+    let mut seen = HashSet::new();
+    drivers.retain(|(path, _)| seen.insert(files.canonicalize(path).unwrap_or(path.clone())));
+    drivers
+}
+
+/// Why the dispatch library cannot use the driver at `path`, if it cannot.
+fn driver_problem(
+    dispatch: &DispatchLibrary,
+    path: &Path,
+    arch: Option<ElfArch>,
+    libs: &LibraryDependencies,
+) -> Option<String> {
+    if path.is_relative() {
+        return Some("is not found in the search locations".to_owned());
+    }
+    if !libs.files.exists(path) {
+        return Some("does not exist".to_owned());
+    }
+    let lib = match libs.open_library(path) {
+        Ok(lib) => lib,
+        Err(e) => return Some(format!("cannot be loaded ({})", e)),
+    };
+    if arch.is_some() && ElfArch::of_file(path, &*libs.files) != arch {
+        return Some("is built for another architecture".to_owned());
+    }
+    if !exported_symbol_names(lib.get_elf()).contains(dispatch.entry_point) {
+        return Some(format!("does not export the entry point {}", dispatch.entry_point));
+    }
+    None
+}
+
+/// Check the drivers of the dispatch libraries (libvulkan, libEGL, libGLX) in the closure: each
+/// must exist, match the architecture of the analyzed file and export the vendor entry point.
+/// The unresolved symbols that a dispatch library forwards are removed if it has a usable
+/// driver. Returns the removed symbols together with the usable drivers, and the problems of the
+/// other drivers.
+pub fn resolve_via_dispatch(
+    libs: &LibraryDependencies,
+    sysroot: Option<&Path>,
+    unresolved_symbols: &mut Vec<(String, HashSet<String>)>,
+) -> (Vec<(String, Vec<PathBuf>)>, Vec<DriverProblem>) {
+    let locations = libs.search_locations.get(&libs.root);
+    let arch = ElfArch::of_file(&libs.root, &*libs.files);
+
+    let mut dispatched = Vec::new();
+    let mut problems = Vec::new();
+    for dispatch in DISPATCH_LIBRARIES {
+        let dispatch_lib = match libs.lookup(OsStr::new(dispatch.soname)) {
+            Some(path) => path.clone(),
+            None => continue,
+        };
+        let mut drivers = Vec::new();
+        for (driver, manifest) in find_drivers(dispatch, locations, arch, sysroot, &*libs.files) {
+            match driver_problem(dispatch, &driver, arch, libs) {
+                Some(description) => problems.push(DriverProblem {
+                    dispatch_lib: dispatch_lib.clone(),
+                    driver,
+                    manifest,
+                    description,
+                }),
+                None => drivers.push(driver),
+            }
+        }
+        if drivers.is_empty() {
+            continue;
+        }
+
+        unresolved_symbols.retain(|(symbol, _)| {
+            if !symbol.starts_with(dispatch.symbol_prefix) {
+                return true;
+            }
+            dispatched.push((symbol.clone(), drivers.clone()));
+            false
+        });
+    }
+    dispatched.sort();
+    (dispatched, problems)
+}
@@ -0,0 +1,162 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+use symbols::exported_symbol_names;
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const ET_EXEC: u16 = 2;
+const DF_1_PIE: u64 = 0x08000000;
+
+/// A file that is (also) loaded with dlopen, optionally with the entry symbols its users look up
+/// with dlsym ('file=symbol,...', see --dlopen-target).
+#[derive(Debug, Clone)]
+pub struct DlopenTarget {
+    pub path: PathBuf,
+    pub entry_symbols: Vec<String>,
+}
+
+impl FromStr for DlopenTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let path = parts.next().unwrap_or("").trim();
+        if path.is_empty() {
+            return Err(format!(
+                "Invalid dlopen target '{}'. Expected <file>[=<symbol>,...]",
+                s
+            ));
+        }
+        Ok(DlopenTarget {
+            path: PathBuf::from(path),
+            entry_symbols: parts
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .map(|symbol| symbol.trim())
+                .filter(|symbol| !symbol.is_empty())
+                .map(|symbol| symbol.to_owned())
+                .collect(),
+        })
+    }
+}
+
+impl DlopenTarget {
+    /// Whether the target is the (analyzed) file at `path`.
+    pub fn is(&self, path: &Path) -> bool {
+        let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_owned());
+        self.path == path || canonical(&self.path) == canonical(path)
+    }
+}
+
+/// A reason why loading an analyzed file with dlopen fails (or is likely to fail).
+pub enum DlopenProblem {
+    /// The file is an executable of type ET_EXEC, which dlopen refuses to load.
+    NotPositionIndependent { file: PathBuf },
+    /// The file is marked as position-independent executable (DF_1_PIE), which dlopen of glibc
+    /// 2.30 and later refuses to load.
+    MarkedAsPie { file: PathBuf },
+    /// The file does not export an entry symbol, so dlsym does not find it.
+    MissingEntrySymbol { file: PathBuf, symbol: String },
+}
+
+impl DlopenProblem {
+    /// The file that is loaded with dlopen.
+    pub fn file(&self) -> &Path {
+        match self {
+            &DlopenProblem::NotPositionIndependent { ref file }
+            | &DlopenProblem::MarkedAsPie { ref file }
+            | &DlopenProblem::MissingEntrySymbol { ref file, .. } => file,
+        }
+    }
+
+    /// Whether dlopen (or dlsym) fails regardless of the version of the C library.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, &DlopenProblem::MarkedAsPie { .. })
+    }
+
+    /// Whether the problem is a missing symbol (instead of the type of the file).
+    pub fn is_symbol_problem(&self) -> bool {
+        matches!(self, &DlopenProblem::MissingEntrySymbol { .. })
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        match self {
+            DlopenProblem::NotPositionIndependent { file } => {
+                fingerprint("dlopen-non-pie", &[&normalize_path(file)])
+            }
+            DlopenProblem::MarkedAsPie { file } => {
+                fingerprint("dlopen-pie", &[&normalize_path(file)])
+            }
+            DlopenProblem::MissingEntrySymbol {
+                file,
+                symbol,
+            } => fingerprint("dlopen-missing-entry", &[&normalize_path(file), symbol]),
+        }
+    }
+}
+
+impl fmt::Display for DlopenProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DlopenProblem::NotPositionIndependent { file } => write!(
+                f,
+                "{:?}: Is a non-PIE executable (ET_EXEC), which dlopen refuses to load (\"cannot \
+                 dynamically load executable\"). Build it with -fPIE -pie or as a shared library",
+                file
+            ),
+            DlopenProblem::MarkedAsPie { file } => write!(
+                f,
+                "{:?}: Is marked as position-independent executable (DF_1_PIE), which dlopen of \
+                 glibc 2.30 and later refuses to load (\"cannot dynamically load \
+                 position-independent executable\"). Build it as a shared library (-shared) \
+                 instead",
+                file
+            ),
+            DlopenProblem::MissingEntrySymbol {
+                file,
+                symbol,
+            } => write!(
+                f,
+                "{:?}: Does not export the entry symbol {}, so dlsym will not find it (link with \
+                 -rdynamic or --export-dynamic-symbol={})",
+                file, symbol, symbol
+            ),
+        }
+    }
+}
+
+/// Check whether the analyzed file can be loaded with dlopen as the given target: executables
+/// have to be position-independent, and all entry symbols have to be exported.
+pub fn check_dlopen_target(
+    libs: &LibraryDependencies,
+    target: &DlopenTarget,
+) -> Vec<DlopenProblem> {
+    let root = &libs.opened_libs[&libs.root];
+    let elf = root.get_elf();
+    let mut problems = Vec::new();
+    if elf.header.e_type == ET_EXEC {
+        problems.push(DlopenProblem::NotPositionIndependent {
+            file: libs.root.clone(),
+        });
+    } else if root.dynamic_flags().1 & DF_1_PIE != 0 {
+        problems.push(DlopenProblem::MarkedAsPie {
+            file: libs.root.clone(),
+        });
+    }
+
+    let exported = exported_symbol_names(elf);
+    for symbol in target.entry_symbols.iter() {
+        if !exported.contains(symbol) {
+            problems.push(DlopenProblem::MissingEntrySymbol {
+                file: libs.root.clone(),
+                symbol: symbol.clone(),
+            });
+        }
+    }
+    problems
+}
@@ -0,0 +1,114 @@
+use libraries::LibraryDependencies;
+use report::Report;
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Quote a string as DOT identifier.
+fn quote(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn node_id(path: &Path) -> String {
+    quote(&path.to_string_lossy())
+}
+
+/// The graph of one closure: a node per opened library and an edge per DT_NEEDED entry. Edges
+/// with library resolution problems (and the libraries with problems not about a single
+/// dependency) are red, unresolved dependencies point to dashed "missing" nodes.
+fn write_graph(out: &mut String, index: usize, libs: &LibraryDependencies) {
+    // Problems by (dependent library, needed name) for the edges, and by library for the rest.
+    let mut edge_problems = HashMap::<(&Path, &str), Vec<String>>::new();
+    let mut lib_problems = HashMap::<&Path, Vec<String>>::new();
+    for problem in libs.problems.iter() {
+        match problem.lib_name() {
+            Some(lib_name) => edge_problems
+                .entry((problem.dependent_lib(), lib_name))
+                .or_default()
+                .push(problem.to_string()),
+            None => lib_problems
+                .entry(problem.dependent_lib())
+                .or_default()
+                .push(problem.to_string()),
+        }
+    }
+
+    writeln!(out, "digraph closure{} {{", index).unwrap();
+    writeln!(out, "\tlabel={};", node_id(&libs.root)).unwrap();
+    writeln!(out, "\tnode [shape=box];").unwrap();
+
+    let mut libraries = libs.opened_libs.keys().collect::<Vec<&PathBuf>>();
+    libraries.sort();
+    for path in libraries.iter() {
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        write!(
+            out,
+            "\t{} [label={}, tooltip={}",
+            node_id(path),
+            quote(&name),
+            node_id(path)
+        ).unwrap();
+        if **path == libs.root {
+            write!(out, ", style=bold").unwrap();
+        }
+        if let Some(problems) = lib_problems.get(path.as_path()) {
+            write!(out, ", color=red, xlabel={}", quote(&problems.join("\n"))).unwrap();
+        }
+        writeln!(out, "];").unwrap();
+    }
+
+    let mut missing = Vec::new();
+    for path in libraries.iter() {
+        for name in libs.needed.get(*path).into_iter().flat_map(|n| n.iter()) {
+            let dependency = match libs.lookup(OsStr::new(name)) {
+                Some(dependency) => node_id(dependency),
+                None => {
+                    let id = quote(&format!("missing:{}", name));
+                    if !missing.contains(&id) {
+                        writeln!(
+                            out,
+                            "\t{} [label={}, style=dashed, color=red];",
+                            id,
+                            quote(name)
+                        ).unwrap();
+                        missing.push(id.clone());
+                    }
+                    id
+                }
+            };
+            write!(out, "\t{} -> {}", node_id(path), dependency).unwrap();
+            if let Some(problems) = edge_problems.get(&(path.as_path(), name.as_str())) {
+                write!(
+                    out,
+                    " [color=red, fontcolor=red, label={}, tooltip={}]",
+                    quote(name),
+                    quote(&problems.join("\n"))
+                ).unwrap();
+            }
+            writeln!(out, ";").unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+}
+
+/// The dependency graphs of all analyzed files in DOT format (one digraph per file). Files that
+/// could not be analyzed are listed as comments.
+pub fn reports_to_dot(reports: &[(PathBuf, Result<Report, String>)]) -> String {
+    let mut out = String::new();
+    for (i, (file, report)) in reports.iter().enumerate() {
+        match report {
+            Ok(report) => write_graph(&mut out, i + 1, &report.libs),
+            Err(err) => {
+                writeln!(out, "// {:?}: {}", file, err.replace('\n', " ")).unwrap();
+            }
+        }
+    }
+    out
+}
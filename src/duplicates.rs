@@ -0,0 +1,380 @@
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_X86_64};
+use goblin::elf::reloc::{R_386_COPY, R_AARCH64_COPY, R_ARM_COPY, R_X86_64_COPY};
+use goblin::elf::Elf;
+
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{Library, LibraryDependencies};
+use symbols::demangle;
+
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_TLS: u8 = 6;
+const STT_GNU_IFUNC: u8 = 10;
+
+const NDX_UNDEFINED: usize = 0;
+
+const BIND_GLOBAL: u8 = 1;
+const BIND_WEAK: u8 = 2;
+const VIS_DEFAULT: u8 = 0;
+
+/// Sonames of dynamic loaders (glibc's ld-linux*.so.2 and ld64.so.*, and musl's loader).
+const LOADER_SONAME_PREFIXES: &[&str] = &["ld-linux", "ld64.so", "ld.so", "ld-musl"];
+
+/// What a definition of a symbol is (definitions without a type are not compared).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefinitionKind {
+    Function,
+    Object,
+    ThreadLocal,
+}
+
+impl DefinitionKind {
+    fn of_type(st_type: u8) -> Option<Self> {
+        match st_type {
+            STT_FUNC | STT_GNU_IFUNC => Some(DefinitionKind::Function),
+            STT_OBJECT => Some(DefinitionKind::Object),
+            STT_TLS => Some(DefinitionKind::ThreadLocal),
+            _ => None,
+        }
+    }
+}
+
+/// The definition of a duplicate symbol by one library.
+pub struct Definition {
+    pub lib: PathBuf,
+    pub kind: DefinitionKind,
+    pub size: u64,
+}
+
+impl fmt::Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            DefinitionKind::Function => write!(f, "a function in {:?}", self.lib),
+            DefinitionKind::Object => {
+                write!(f, "an object of {} bytes in {:?}", self.size, self.lib)
+            }
+            DefinitionKind::ThreadLocal => write!(
+                f,
+                "a thread-local object of {} bytes in {:?}",
+                self.size, self.lib
+            ),
+        }
+    }
+}
+
+/// A duplicate symbol whose definitions differ in type (e.g., a function interposing an object)
+/// or, for objects, in size (which copy relocations turn into overflows). Unlike benign
+/// interposition, this almost certainly corrupts memory at runtime.
+pub struct MismatchedDuplicate {
+    pub root: PathBuf,
+    pub symbol: String,
+    pub definitions: Vec<Definition>,
+}
+
+impl MismatchedDuplicate {
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "mismatched-duplicate-symbol",
+            &[&normalize_path(&self.root), &self.symbol],
+        )
+    }
+
+    /// The file whose closure contains the mismatching definitions.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl fmt::Display for MismatchedDuplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Duplicate symbol {} has mismatching definitions: {}",
+            demangle(&self.symbol).unwrap_or(self.symbol.clone()),
+            self.definitions
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// The (typed) definition of `symbol` (possibly with a version suffix) in the library at `path`.
+fn definition(libs: &LibraryDependencies, path: &Path, symbol: &str) -> Option<Definition> {
+    let name = symbol.split('@').next().unwrap_or(symbol);
+    let elf = libs.opened_libs.get(path)?.get_elf();
+    elf.dynsyms
+        .iter()
+        .filter(|sym| sym.st_shndx != NDX_UNDEFINED)
+        .find(|sym| {
+            elf.dynstrtab
+                .get(sym.st_name)
+                .and_then(|n| n.ok()) == Some(name)
+        })
+        .and_then(|sym| {
+            DefinitionKind::of_type(sym.st_type()).map(|kind| Definition {
+                lib: path.to_path_buf(),
+                kind,
+                size: sym.st_size,
+            })
+        })
+}
+
+/// Find the duplicate symbols (with the names of the libraries exporting them) whose definitions
+/// differ in type or object size.
+pub fn find_mismatched_duplicates(
+    libs: &LibraryDependencies,
+    duplicate_symbols: &[(String, HashSet<String>)],
+) -> Vec<MismatchedDuplicate> {
+    let mut mismatched = Vec::new();
+    for (symbol, lib_names) in duplicate_symbols {
+        let mut paths = lib_names
+            .iter()
+            .filter_map(|name| libs.lookup(OsStr::new(name)))
+            .collect::<Vec<_>>();
+        paths.sort();
+        let definitions = paths
+            .into_iter()
+            .filter_map(|path| definition(libs, path, symbol))
+            .collect::<Vec<_>>();
+
+        let (kinds_differ, sizes_differ) = match definitions.first() {
+            Some(first) => (
+                definitions.iter().any(|d| d.kind != first.kind),
+                first.kind != DefinitionKind::Function
+                    && definitions.iter().any(|d| d.size != first.size),
+            ),
+            None => continue,
+        };
+        if kinds_differ || sizes_differ {
+            mismatched.push(MismatchedDuplicate {
+                root: libs.root.clone(),
+                symbol: symbol.clone(),
+                definitions,
+            });
+        }
+    }
+    mismatched
+}
+
+/// The type of copy relocations on the given machine (if known). These reference the copy of a
+/// library's object in the executable, which is not an import of the executable's own symbol.
+fn copy_reloc_type(machine: u16) -> Option<u32> {
+    match machine {
+        EM_X86_64 => Some(R_X86_64_COPY),
+        EM_386 => Some(R_386_COPY),
+        EM_AARCH64 => Some(R_AARCH64_COPY),
+        EM_ARM => Some(R_ARM_COPY),
+        _ => None,
+    }
+}
+
+/// The symbols that the object both exports (with default visibility) and imports: It references
+/// them through the global symbol lookup (i.e., with dynamic relocations) or lists them as
+/// undefined with another version as well.
+fn self_imported_names(elf: &Elf) -> HashSet<String> {
+    let name = |st_name| elf.dynstrtab.get(st_name).and_then(|n| n.ok());
+    let copy_type = copy_reloc_type(elf.header.e_machine);
+    let mut names = elf.dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .chain(elf.pltrelocs.iter())
+        .filter(|reloc| reloc.r_sym != 0 && Some(reloc.r_type) != copy_type)
+        .filter_map(|reloc| elf.dynsyms.get(reloc.r_sym))
+        .filter(|sym| {
+            sym.st_shndx != NDX_UNDEFINED
+                && (sym.st_bind() == BIND_GLOBAL || sym.st_bind() == BIND_WEAK)
+                && sym.st_other & 0x3 == VIS_DEFAULT
+        })
+        .filter_map(|sym| name(sym.st_name))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect::<HashSet<_>>();
+
+    let (defined, undefined): (Vec<_>, Vec<_>) = elf.dynsyms
+        .iter()
+        .filter(|sym| sym.st_bind() == BIND_GLOBAL || sym.st_bind() == BIND_WEAK)
+        .partition(|sym| sym.st_shndx != NDX_UNDEFINED);
+    let undefined = undefined
+        .iter()
+        .filter_map(|sym| name(sym.st_name))
+        .collect::<HashSet<_>>();
+    names.extend(
+        defined
+            .iter()
+            .filter(|sym| sym.st_other & 0x3 == VIS_DEFAULT)
+            .filter_map(|sym| name(sym.st_name))
+            .filter(|name| undefined.contains(name))
+            .map(|name| name.to_owned()),
+    );
+    names
+}
+
+/// The symbols that the object defines through copy relocations (i.e., copies of data objects of
+/// libraries, which the references of the libraries are meant to bind to).
+fn copy_relocated_names(elf: &Elf) -> HashSet<String> {
+    let copy_type = match copy_reloc_type(elf.header.e_machine) {
+        Some(copy_type) => copy_type,
+        None => return HashSet::new(),
+    };
+    elf.dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .filter(|reloc| reloc.r_sym != 0 && reloc.r_type == copy_type)
+        .filter_map(|reloc| elf.dynsyms.get(reloc.r_sym))
+        .filter_map(|sym| elf.dynstrtab.get(sym.st_name).and_then(|n| n.ok()))
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+/// The symbols that each opened library both exports and imports (see `self_imported_names`).
+/// The loader (identified by its soname or as the interpreter of an object of the closure) is
+/// skipped, as it imports some of its own functions on purpose (so that the copies of libc take
+/// over once it is loaded). So are libraries linked with -Bsymbolic, whose references bind to
+/// their own definitions regardless of other copies in the lookup scope, and symbols that an
+/// object of the closure copies via copy relocations.
+pub fn find_self_imports(libs: &LibraryDependencies) -> HashMap<PathBuf, HashSet<String>> {
+    let mut interpreters = HashSet::new();
+    let mut copied = HashSet::new();
+    for lib in libs.opened_libs.values() {
+        let elf = lib.get_elf();
+        if let Some(name) = elf.interpreter.and_then(|i| Path::new(i).file_name()) {
+            let _ = interpreters.insert(name.to_owned());
+        }
+        copied.extend(copy_relocated_names(elf));
+    }
+    let is_loader = |lib: &Library| {
+        let soname = lib.get_soname();
+        interpreters.contains(lib.get_name()) || interpreters.contains(&soname)
+            || LOADER_SONAME_PREFIXES
+                .iter()
+                .any(|prefix| soname.to_string_lossy().starts_with(prefix))
+    };
+    libs.opened_libs
+        .iter()
+        .filter(|&(_, lib)| !is_loader(lib) && !lib.is_symbolic())
+        .map(|(path, lib)| {
+            let mut names = self_imported_names(lib.get_elf());
+            names.retain(|name| !copied.contains(name));
+            (path.clone(), names)
+        })
+        .filter(|(_, names)| !names.is_empty())
+        .collect()
+}
+
+/// A duplicate symbol that one of its providers also imports. With default visibility, the
+/// references of the object bind to the first definition in load order, so either its copy
+/// interposes the others for all objects, or it uses another copy than its own.
+pub struct SelfImportedSymbol {
+    pub lib: PathBuf,
+    pub symbol: String,
+    /// The other objects exporting the symbol
+    pub other_providers: Vec<PathBuf>,
+    /// The object whose definition all references bind to
+    pub winner: PathBuf,
+}
+
+impl SelfImportedSymbol {
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "self-imported-symbol",
+            &[&normalize_path(&self.lib), &self.symbol],
+        )
+    }
+
+    /// The object that exports and imports the symbol.
+    pub fn lib(&self) -> &Path {
+        &self.lib
+    }
+}
+
+impl fmt::Display for SelfImportedSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = demangle(&self.symbol).unwrap_or(self.symbol.clone());
+        let others = self.other_providers
+            .iter()
+            .map(|p| format!("{:?}", p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.winner == self.lib {
+            write!(
+                f,
+                "{:?}: Exports and imports {}, which is also exported by {}. Its definition comes first in load order, so it interposes the other copies for all objects",
+                self.lib, symbol, others
+            )
+        } else {
+            write!(
+                f,
+                "{:?}: Exports and imports {}, which is also exported by {}. With default visibility, its own references bind to the copy in {:?} (first in load order) instead of its own",
+                self.lib, symbol, others, self.winner
+            )
+        }
+    }
+}
+
+/// Find the providers of duplicate symbols (with the names of the libraries exporting them) that
+/// import the symbol themselves.
+pub fn find_self_imported_symbols(
+    libs: &LibraryDependencies,
+    self_imports: &HashMap<PathBuf, HashSet<String>>,
+    duplicate_symbols: &[(String, HashSet<String>)],
+) -> Vec<SelfImportedSymbol> {
+    let mut load_order = Vec::new();
+    for path in iter::once(&libs.root)
+        .chain(libs.needed_in_load_order().into_iter().filter_map(|(_, path)| path))
+    {
+        if !load_order.contains(&path) {
+            load_order.push(path);
+        }
+    }
+    let provider_path = |name: &String| {
+        libs.lookup(OsStr::new(name)).or_else(|| {
+            libs.opened_libs
+                .get(&libs.root)
+                .filter(|root| root.get_name() == OsStr::new(name))
+                .map(|_| &libs.root)
+        })
+    };
+
+    let mut found = Vec::new();
+    for (symbol, lib_names) in duplicate_symbols {
+        let mut providers = lib_names.iter().filter_map(&provider_path).collect::<Vec<_>>();
+        providers.sort();
+        providers.dedup();
+        let winner = match providers
+            .iter()
+            .min_by_key(|path| load_order.iter().position(|p| p == *path).unwrap_or(usize::MAX))
+        {
+            Some(winner) => (*winner).clone(),
+            None => continue,
+        };
+        for provider in providers.iter() {
+            let imports = self_imports
+                .get(*provider)
+                .is_some_and(|names| names.contains(symbol));
+            if !imports {
+                continue;
+            }
+            found.push(SelfImportedSymbol {
+                lib: (*provider).clone(),
+                symbol: symbol.clone(),
+                other_providers: providers
+                    .iter()
+                    .filter(|p| *p != provider)
+                    .map(|p| (*p).clone())
+                    .collect(),
+                winner: winner.clone(),
+            });
+        }
+    }
+    found
+}
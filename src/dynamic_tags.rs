@@ -0,0 +1,172 @@
+use goblin::elf::dyn::*;
+
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// RELR relocations (glibc 2.36). They do not affect dependencies or symbols.
+const DT_RELRSZ: u64 = 35;
+const DT_RELR: u64 = 36;
+const DT_RELRENT: u64 = 37;
+const DT_GNU_PRELINKED: u64 = 0x6ffffdf5;
+const DT_GNU_CONFLICTSZ: u64 = 0x6ffffdf6;
+const DT_GNU_LIBLISTSZ: u64 = 0x6ffffdf7;
+const DT_CHECKSUM: u64 = 0x6ffffdf8;
+const DT_PLTPADSZ: u64 = 0x6ffffdf9;
+const DT_MOVEENT: u64 = 0x6ffffdfa;
+const DT_MOVESZ: u64 = 0x6ffffdfb;
+const DT_FEATURE_1: u64 = 0x6ffffdfc;
+const DT_POSFLAG_1: u64 = 0x6ffffdfd;
+const DT_SYMINSZ: u64 = 0x6ffffdfe;
+const DT_SYMINENT: u64 = 0x6ffffdff;
+/// Filters, whose filtees are resolved like dependencies.
+const DT_AUXILIARY: u64 = 0x7ffffffd;
+const DT_FILTER: u64 = 0x7fffffff;
+
+/// Dynamic tags that are either modeled or do not affect how dependencies and symbols are
+/// resolved.
+const KNOWN_TAGS: &[u64] = &[
+    DT_NULL,
+    DT_NEEDED,
+    DT_PLTRELSZ,
+    DT_PLTGOT,
+    DT_HASH,
+    DT_STRTAB,
+    DT_SYMTAB,
+    DT_RELA,
+    DT_RELASZ,
+    DT_RELAENT,
+    DT_STRSZ,
+    DT_SYMENT,
+    DT_INIT,
+    DT_FINI,
+    DT_SONAME,
+    DT_RPATH,
+    DT_SYMBOLIC,
+    DT_REL,
+    DT_RELSZ,
+    DT_RELENT,
+    DT_PLTREL,
+    DT_DEBUG,
+    DT_TEXTREL,
+    DT_JMPREL,
+    DT_BIND_NOW,
+    DT_INIT_ARRAY,
+    DT_FINI_ARRAY,
+    DT_INIT_ARRAYSZ,
+    DT_FINI_ARRAYSZ,
+    DT_RUNPATH,
+    DT_FLAGS,
+    DT_PREINIT_ARRAY,
+    DT_PREINIT_ARRAYSZ,
+    DT_RELRSZ,
+    DT_RELR,
+    DT_RELRENT,
+    DT_GNU_PRELINKED,
+    DT_GNU_CONFLICTSZ,
+    DT_GNU_LIBLISTSZ,
+    DT_CHECKSUM,
+    DT_PLTPADSZ,
+    DT_MOVEENT,
+    DT_MOVESZ,
+    DT_FEATURE_1,
+    DT_POSFLAG_1,
+    DT_SYMINSZ,
+    DT_SYMINENT,
+    DT_GNU_HASH,
+    DT_TLSDESC_PLT,
+    DT_TLSDESC_GOT,
+    DT_GNU_CONFLICT,
+    DT_GNU_LIBLIST,
+    DT_PLTPAD,
+    DT_MOVETAB,
+    DT_SYMINFO,
+    DT_VERSYM,
+    DT_RELACOUNT,
+    DT_RELCOUNT,
+    DT_FLAGS_1,
+    DT_VERDEF,
+    DT_VERDEFNUM,
+    DT_VERNEED,
+    DT_VERNEEDNUM,
+    DT_AUXILIARY,
+    DT_FILTER,
+];
+
+/// A dynamic entry of an object of the closure whose effect on loading is not simulated.
+pub struct UnknownDynamicTag {
+    pub lib: PathBuf,
+    pub tag: u64,
+}
+
+impl UnknownDynamicTag {
+    /// The name of the tag, if it is a known one.
+    pub fn name(&self) -> Option<&'static str> {
+        match self.tag {
+            DT_CONFIG => Some("DT_CONFIG"),
+            DT_DEPAUDIT => Some("DT_DEPAUDIT"),
+            DT_AUDIT => Some("DT_AUDIT"),
+            _ => None,
+        }
+    }
+
+    /// A stable fingerprint of the entry (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "unknown-dynamic-tag",
+            &[&normalize_path(&self.lib), &format!("{:#x}", self.tag)],
+        )
+    }
+
+    /// What the loader does differently because of the tag (as far as known).
+    fn effect(&self) -> &'static str {
+        match self.tag {
+            DT_CONFIG => "makes the loader use another configuration file",
+            DT_DEPAUDIT | DT_AUDIT => "loads audit libraries",
+            DT_LOPROC..=DT_HIPROC => "is specific to the processor",
+            DT_LOOS..=DT_HIOS => "is specific to the operating system or vendor",
+            _ => "is unknown",
+        }
+    }
+}
+
+impl fmt::Display for UnknownDynamicTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: Dynamic entry {}{:#x} {} and is not simulated, so the results may be incomplete",
+            self.lib,
+            self.name().map(|n| format!("{} ", n)).unwrap_or_default(),
+            self.tag,
+            self.effect()
+        )
+    }
+}
+
+/// Find the dynamic entries of the objects of the closure that are not simulated (once per object
+/// and tag).
+pub fn find_unknown_dynamic_tags(libs: &LibraryDependencies) -> Vec<UnknownDynamicTag> {
+    let mut paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    paths.sort();
+    let mut unknown = Vec::new();
+    for path in paths {
+        let elf = libs.opened_libs[path].get_elf();
+        let mut tags = elf.dynamic
+            .as_ref()
+            .map(|dynamic| dynamic.dyns.iter().map(|d| d.d_tag).collect::<Vec<_>>())
+            .unwrap_or_default();
+        tags.sort();
+        tags.dedup();
+        unknown.extend(
+            tags.into_iter()
+                .filter(|tag| !KNOWN_TAGS.contains(tag))
+                .map(|tag| UnknownDynamicTag {
+                    lib: path.clone(),
+                    tag,
+                }),
+        );
+    }
+    unknown
+}
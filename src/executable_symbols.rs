@@ -0,0 +1,146 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+use symbols::{demangle, exported_symbol_names, strongly_undefined_symbol_names,
+              unexported_global_symbol_names};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const ET_EXEC: u16 = 2;
+
+/// A symbol that libraries of the closure expect to be provided by the analyzed executable.
+pub enum ExecutableSymbol {
+    /// The executable exports the symbol, because it was linked with -rdynamic/--export-dynamic
+    /// (or against a library referencing the symbol). Nothing else can provide it.
+    Exported {
+        executable: PathBuf,
+        symbol: String,
+        users: Vec<PathBuf>,
+    },
+    /// The executable defines the symbol, but does not export it, so it stays unresolved.
+    NotExported {
+        executable: PathBuf,
+        symbol: String,
+        users: Vec<PathBuf>,
+    },
+}
+
+impl ExecutableSymbol {
+    /// The executable, which has to export the symbol.
+    pub fn executable(&self) -> &Path {
+        match self {
+            &ExecutableSymbol::Exported { ref executable, .. }
+            | &ExecutableSymbol::NotExported { ref executable, .. } => executable,
+        }
+    }
+
+    /// Whether the symbol is actually unresolved at runtime.
+    pub fn is_error(&self) -> bool {
+        match *self {
+            ExecutableSymbol::Exported { .. } => false,
+            ExecutableSymbol::NotExported { .. } => true,
+        }
+    }
+
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let (kind, symbol) = match self {
+            ExecutableSymbol::Exported { symbol, .. } => ("exported-by-executable", symbol),
+            ExecutableSymbol::NotExported { symbol, .. } => {
+                ("not-exported-by-executable", symbol)
+            }
+        };
+        fingerprint(kind, &[&normalize_path(self.executable()), symbol])
+    }
+}
+
+impl fmt::Display for ExecutableSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |symbol: &String, users: &[PathBuf]| {
+            (
+                demangle(symbol).unwrap_or(symbol.to_string()),
+                users
+                    .iter()
+                    .map(|u| format!("{:?}", u))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        };
+        match self {
+            ExecutableSymbol::Exported {
+                executable,
+                symbol,
+                users,
+            } => {
+                let (symbol, users) = describe(symbol, users);
+                write!(
+                    f,
+                    "{} (used by {}) is provided by the executable {:?}, which works only because \
+                     the executable exports it (due to -rdynamic or because it was linked against \
+                     the using library)",
+                    symbol, users, executable
+                )
+            }
+            ExecutableSymbol::NotExported {
+                executable,
+                symbol,
+                users,
+            } => {
+                let (symbol, users) = describe(symbol, users);
+                write!(
+                    f,
+                    "{} (used by {}) is defined by the executable {:?}, but will fail to resolve \
+                     because the executable does not export it (link it with -rdynamic)",
+                    symbol, users, executable
+                )
+            }
+        }
+    }
+}
+
+/// Find undefined symbols of the dependencies that no library but the analyzed executable itself
+/// could provide. Nothing is reported if the analyzed file is not an executable (i.e., neither of
+/// type ET_EXEC nor with a program interpreter).
+pub fn find_executable_symbols(libs: &LibraryDependencies) -> Vec<ExecutableSymbol> {
+    let root = &libs.opened_libs[&libs.root];
+    let root_elf = root.get_elf();
+    if root_elf.header.e_type != ET_EXEC && root_elf.interpreter.is_none() {
+        return Vec::new();
+    }
+    let root_exported = exported_symbol_names(root_elf);
+    let root_unexported = unexported_global_symbol_names(root_elf);
+
+    let mut exported_by_libs = HashSet::new();
+    let mut users = BTreeMap::new();
+    for (path, lib) in libs.opened_libs.iter().filter(|&(path, _)| path != &libs.root) {
+        let elf = lib.get_elf();
+        exported_by_libs.extend(exported_symbol_names(elf));
+        for symbol in strongly_undefined_symbol_names(elf) {
+            if root_exported.contains(&symbol) || root_unexported.contains(&symbol) {
+                users.entry(symbol).or_insert(Vec::new()).push(path.clone());
+            }
+        }
+    }
+
+    users
+        .into_iter()
+        .filter(|(symbol, _)| !exported_by_libs.contains(symbol))
+        .map(|(symbol, mut users)| {
+            users.sort();
+            if root_exported.contains(&symbol) {
+                ExecutableSymbol::Exported {
+                    executable: libs.root.clone(),
+                    symbol,
+                    users,
+                }
+            } else {
+                ExecutableSymbol::NotExported {
+                    executable: libs.root.clone(),
+                    symbol,
+                    users,
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,422 @@
+use compare::{LibraryDelta, RootComparison, SymbolDelta};
+use history::HistoryRun;
+use namespaces::NamespaceReport;
+use plugins::PluginCheck;
+use report::Report;
+use rootfs::RootfsAnalysis;
+use wine::PeCheck;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// The exit status is a combination (bitwise or) of the following codes. Their values are stable
+// and can be relied upon by scripts (see --help-exit-codes). --fail-on restricts which of them
+// are used.
+
+/// No findings.
+pub const SUCCESS: i32 = 0;
+/// A library (or preload) could not be resolved correctly or lives on a noexec file system, or a
+/// file loaded with dlopen is a non-PIE executable (also for plugins and the DLLs of PE files).
+pub const LIB_PROBLEMS: i32 = 1;
+/// Symbols are unresolved or resolved only in unexpected ways (symbol versions, C++ runtime,
+/// symbols of the executable, duplicates with mismatching definitions, expected overrides of
+/// simulated preloads that do not happen, missing entry symbols of dlopen targets).
+pub const SYMBOL_PROBLEMS: i32 = 2;
+/// Warnings only: duplicate symbols (also those imported by their exporter), packaging problems,
+/// search path oddities, libraries on network file systems, text relocations, underlinked
+/// libraries, unusable graphics drivers, weakly required or unversioned symbol versions, symbols
+/// bound to the oldest non-default version, unused dependencies, unexpected overrides of
+/// simulated preloads and dlopen targets marked as PIE.
+pub const WARNINGS: i32 = 4;
+/// At least one file could not be analyzed.
+pub const ANALYSIS_ERROR: i32 = 8;
+/// The closure of a file exceeds a budget (see --max-deps and --max-closure-size).
+pub const BUDGETS: i32 = 16;
+/// linkcheck itself failed (e.g., invalid arguments or configuration). Not combined with others.
+pub const TOOL_ERROR: i32 = 64;
+
+/// Code, name and description of every exit code.
+pub const EXIT_CODES: &[(i32, &str, &str)] = &[
+    (SUCCESS, "success", "No findings"),
+    (
+        LIB_PROBLEMS,
+        "lib-problems",
+        "Library resolution, preload, noexec mount, non-PIE dlopen target or DLL problems were found",
+    ),
+    (
+        SYMBOL_PROBLEMS,
+        "symbol-problems",
+        "Unresolved symbols, symbol version, C++ runtime, executable symbol, mismatched duplicate symbol, preload override or missing dlopen entry symbol problems were found",
+    ),
+    (
+        WARNINGS,
+        "warnings",
+        "Duplicate symbols, packaging problems, search path oddities, libraries on network file systems, text relocations, underlinked libraries, unusable graphics drivers, weakly required or unversioned symbol versions, symbols bound to the oldest non-default version, unused dependencies, unexpected preload overrides or dlopen targets marked as PIE were found",
+    ),
+    (
+        ANALYSIS_ERROR,
+        "analysis-error",
+        "At least one file could not be analyzed",
+    ),
+    (
+        BUDGETS,
+        "budgets",
+        "The closure of a file has more libraries or bytes than allowed",
+    ),
+    (
+        TOOL_ERROR,
+        "tool-error",
+        "Invalid arguments or configuration, or another internal failure (never combined)",
+    ),
+];
+
+/// A kind of findings with its own exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FindingKind {
+    LibProblems,
+    SymbolProblems,
+    Warnings,
+    AnalysisError,
+    Budgets,
+}
+
+impl FindingKind {
+    /// The exit code of the kind (see EXIT_CODES).
+    pub fn code(self) -> i32 {
+        match self {
+            FindingKind::LibProblems => LIB_PROBLEMS,
+            FindingKind::SymbolProblems => SYMBOL_PROBLEMS,
+            FindingKind::Warnings => WARNINGS,
+            FindingKind::AnalysisError => ANALYSIS_ERROR,
+            FindingKind::Budgets => BUDGETS,
+        }
+    }
+}
+
+/// The outcome of a successful run of linkcheck, which `main` maps to the exit status (see
+/// `FailOn::exit_code`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// Nothing was found that affects the exit status.
+    Success,
+    /// Findings of the given kinds were reported.
+    Findings(BTreeSet<FindingKind>),
+}
+
+impl Status {
+    /// The status of a run with findings of the given kinds (if any).
+    pub fn of<I: IntoIterator<Item = FindingKind>>(kinds: I) -> Self {
+        let kinds = kinds.into_iter().collect::<BTreeSet<_>>();
+        if kinds.is_empty() {
+            Status::Success
+        } else {
+            Status::Findings(kinds)
+        }
+    }
+
+    /// The status with the findings of both `self` and `other`.
+    pub fn combine(self, other: Status) -> Self {
+        Status::of(self.kinds().chain(other.kinds()))
+    }
+
+    fn kinds(&self) -> Box<dyn Iterator<Item = FindingKind> + '_> {
+        match self {
+            Status::Success => Box::new(::std::iter::empty()),
+            Status::Findings(kinds) => Box::new(kinds.iter().cloned()),
+        }
+    }
+}
+
+/// The exit codes that make linkcheck fail (see --fail-on). Other findings are still reported,
+/// but do not change the exit status. Tool errors always do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FailOn(i32);
+
+impl FailOn {
+    /// The exit status for the status of a run: The codes of all selected kinds of findings.
+    pub fn exit_code(&self, status: &Status) -> i32 {
+        status
+            .kinds()
+            .map(FindingKind::code)
+            .fold(SUCCESS, |code, kind_code| code | kind_code)
+            & self.0
+    }
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+    /// Parse a comma separated list of exit code names (or "none").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let selectable = EXIT_CODES
+            .iter()
+            .filter(|&&(code, _, _)| code != SUCCESS && code != TOOL_ERROR)
+            .collect::<Vec<_>>();
+        let mut mask = SUCCESS;
+        for name in s.split(',').filter(|n| !n.is_empty() && *n != "none") {
+            match selectable.iter().find(|&&&(_, n, _)| n == name) {
+                Some(&&(code, _, _)) => mask |= code,
+                None => {
+                    return Err(format!(
+                        "Invalid exit code '{}'. Valid exit codes are: none, {}",
+                        name,
+                        selectable
+                            .iter()
+                            .map(|&&(_, n, _)| n)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(FailOn(mask))
+    }
+}
+
+/// Print the exit codes as tab separated table (code, name, description) with a header line.
+pub fn print_exit_codes() {
+    println!("code\tname\tdescription");
+    for &(code, name, description) in EXIT_CODES {
+        println!("{}\t{}\t{}", code, name, description);
+    }
+}
+
+/// The status for the findings of one report.
+fn report_status(report: &Report) -> Status {
+    let mut kinds = Vec::new();
+    if !report.libs.problems.is_empty() || !report.preload_problems.is_empty()
+        || report.mount_problems.iter().any(|p| p.is_error())
+        || report.dlopen_problems.iter().any(|p| p.is_error() && !p.is_symbol_problem())
+    {
+        kinds.push(FindingKind::LibProblems);
+    }
+    if !report.unresolved_symbols.is_empty()
+        || report.version_problems.iter().any(|p| p.is_error())
+        || report.missing_versions.iter().any(|m| m.is_error())
+        || !report.cxx_runtime_problems.is_empty()
+        || report.executable_symbols.iter().any(|s| s.is_error())
+        || !report.mismatched_duplicates.is_empty()
+        || report.interpositions.iter().any(|i| i.is_error())
+        || report.dlopen_problems.iter().any(|p| p.is_symbol_problem())
+    {
+        kinds.push(FindingKind::SymbolProblems);
+    }
+    if !report.duplicate_symbols.is_empty() || !report.self_imported_symbols.is_empty()
+        || !report.packaging_problems.is_empty()
+        || !report.search_path_oddities.is_empty()
+        || report.mount_problems.iter().any(|p| !p.is_error())
+        || report.missing_versions.iter().any(|m| !m.is_error())
+        || report.version_problems.iter().any(|p| !p.is_error())
+        || !report.text_relocations.is_empty()
+        || !report.underlinked_libraries.is_empty()
+        || !report.driver_problems.is_empty()
+        || report.unused_dependencies.iter().any(|u| u.is_speculative())
+        || report.interpositions.iter().any(|i| i.is_unintended())
+        || report.dlopen_problems.iter().any(|p| !p.is_error())
+    {
+        kinds.push(FindingKind::Warnings);
+    }
+    if !report.budget_violations.is_empty() {
+        kinds.push(FindingKind::Budgets);
+    }
+    Status::of(kinds)
+}
+
+/// The status for the checks of all plugins (see --plugin-env).
+pub fn plugin_status(checks: &[PluginCheck]) -> Status {
+    let mut kinds = Vec::new();
+    for check in checks {
+        if check.error.is_some() {
+            kinds.push(FindingKind::AnalysisError);
+        }
+        if !check.problems.is_empty() {
+            kinds.push(FindingKind::LibProblems);
+        }
+        if !check.unresolved_symbols.is_empty() {
+            kinds.push(FindingKind::SymbolProblems);
+        }
+    }
+    Status::of(kinds)
+}
+
+/// The status for the checks of all PE files (see --wine-prefix).
+pub fn pe_status(checks: &[PeCheck]) -> Status {
+    let mut kinds = Vec::new();
+    for check in checks {
+        if check.error.is_some() {
+            kinds.push(FindingKind::AnalysisError);
+        }
+        for problem in check.problems.iter() {
+            kinds.push(if problem.is_dll_problem() {
+                FindingKind::LibProblems
+            } else {
+                FindingKind::SymbolProblems
+            });
+        }
+    }
+    Status::of(kinds)
+}
+
+/// The status for the comparison of two roots (see compare-roots): Libraries or symbols that are
+/// only available in the first root are problems, all other differences are warnings.
+pub fn comparison_status(comparison: &RootComparison) -> Status {
+    let mut kinds = Vec::new();
+    for (_, delta) in comparison.library_deltas() {
+        kinds.push(match delta {
+            LibraryDelta::OnlyInA(_) => FindingKind::LibProblems,
+            _ => FindingKind::Warnings,
+        });
+    }
+    for (_, delta) in comparison.symbol_deltas() {
+        kinds.push(match delta {
+            SymbolDelta::OnlyInA(_) => FindingKind::SymbolProblems,
+            _ => FindingKind::Warnings,
+        });
+    }
+    Status::of(kinds)
+}
+
+/// The status for the reports of all analyzed files.
+pub fn reports_status(reports: &[(PathBuf, Result<Report, String>)]) -> Status {
+    reports
+        .iter()
+        .map(|(_, report)| match report {
+            Ok(report) => report_status(report),
+            Err(_) => Status::of(vec![FindingKind::AnalysisError]),
+        })
+        .fold(Status::Success, Status::combine)
+}
+
+/// The status for the closed-world analysis of a root file system image (see rootfs):
+/// Executables that cannot be started are library problems, symbols that no closure defines are
+/// symbol problems.
+pub fn rootfs_status(analysis: &RootfsAnalysis) -> Status {
+    let mut kinds = Vec::new();
+    if analysis.executables.iter().any(|(_, _, libs)| libs.is_err()) {
+        kinds.push(FindingKind::AnalysisError);
+    }
+    if !analysis.startup_problems().is_empty() {
+        kinds.push(FindingKind::LibProblems);
+    }
+    if !analysis.unsatisfiable_symbols().is_empty() {
+        kinds.push(FindingKind::SymbolProblems);
+    }
+    Status::of(kinds)
+}
+
+/// The status for the symbol analysis of link namespaces (see --namespace). Unresolved and
+/// duplicate symbols only count if they are shown.
+pub fn namespace_status(
+    reports: &[NamespaceReport],
+    show_unresolved: bool,
+    show_duplicates: bool,
+) -> Status {
+    let mut kinds = Vec::new();
+    for report in reports {
+        if !report.missing_proxies.is_empty() || show_unresolved && !report.unresolved.is_empty()
+        {
+            kinds.push(FindingKind::SymbolProblems);
+        }
+        if show_duplicates && !report.duplicates.is_empty() {
+            kinds.push(FindingKind::Warnings);
+        }
+    }
+    Status::of(kinds)
+}
+
+/// The kind of a finding recorded with the given rule and level (see `AnalysisResult::findings`),
+/// if it affects the exit status. Errors of the resolution rules are library problems, all other
+/// errors are symbol problems.
+fn recorded_finding_kind(rule: &str, level: &str) -> Option<FindingKind> {
+    match (rule, level) {
+        ("analysis-error", _) => Some(FindingKind::AnalysisError),
+        ("budget", _) => Some(FindingKind::Budgets),
+        ("library-resolution", "error")
+        | ("preload", "error")
+        | ("mount", "error")
+        | ("dlopen", "error") => Some(FindingKind::LibProblems),
+        (_, "error") => Some(FindingKind::SymbolProblems),
+        (_, "warning") => Some(FindingKind::Warnings),
+        _ => None,
+    }
+}
+
+/// The status for the history of a target (see history): The findings of its latest run.
+pub fn history_status(runs: &[HistoryRun]) -> Status {
+    Status::of(runs.last().into_iter().flat_map(|run| {
+        run.levels
+            .iter()
+            .filter_map(|(rule, level)| recorded_finding_kind(rule, level))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::{BTreeMap, HashSet};
+
+    fn run(levels: &[(&str, &str)]) -> HistoryRun {
+        HistoryRun {
+            time: "2018-06-01 12:00:00".to_owned(),
+            build_id: None,
+            counts: BTreeMap::new(),
+            levels: levels
+                .iter()
+                .map(|&(rule, level)| (rule.to_owned(), level.to_owned()))
+                .collect(),
+            fingerprints: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn fail_on_selects_exit_codes() {
+        let status = Status::of(vec![
+            FindingKind::LibProblems,
+            FindingKind::Warnings,
+            FindingKind::AnalysisError,
+        ]);
+        let all = "lib-problems,symbol-problems,warnings,analysis-error,budgets";
+        assert_eq!(FailOn::from_str(all).unwrap().exit_code(&status), 13);
+        assert_eq!(FailOn::from_str("warnings").unwrap().exit_code(&status), WARNINGS);
+        assert_eq!(FailOn::from_str("none").unwrap().exit_code(&status), SUCCESS);
+        assert_eq!(FailOn::from_str(all).unwrap().exit_code(&Status::Success), SUCCESS);
+        assert!(FailOn::from_str("tool-error").is_err());
+        assert!(FailOn::from_str("warning").is_err());
+    }
+
+    #[test]
+    fn statuses_combine_their_findings() {
+        let lib_problems = Status::of(vec![FindingKind::LibProblems]);
+        assert_eq!(Status::of(Vec::new()), Status::Success);
+        assert_eq!(Status::Success.combine(Status::Success), Status::Success);
+        assert_eq!(lib_problems.clone().combine(Status::Success), lib_problems);
+        assert_eq!(
+            lib_problems.combine(Status::of(vec![FindingKind::Budgets])),
+            Status::of(vec![FindingKind::LibProblems, FindingKind::Budgets])
+        );
+    }
+
+    #[test]
+    fn recorded_findings_map_to_kinds() {
+        let kind = recorded_finding_kind;
+        assert_eq!(kind("analysis-error", "error"), Some(FindingKind::AnalysisError));
+        assert_eq!(kind("budget", "error"), Some(FindingKind::Budgets));
+        assert_eq!(kind("library-resolution", "error"), Some(FindingKind::LibProblems));
+        assert_eq!(kind("dlopen", "error"), Some(FindingKind::LibProblems));
+        assert_eq!(kind("dlopen", "warning"), Some(FindingKind::Warnings));
+        assert_eq!(kind("unresolved-symbol", "error"), Some(FindingKind::SymbolProblems));
+        assert_eq!(kind("duplicate-symbol", "warning"), Some(FindingKind::Warnings));
+        assert_eq!(kind("skipped-incompatible", "note"), None);
+    }
+
+    #[test]
+    fn history_status_is_that_of_the_latest_run() {
+        assert_eq!(history_status(&[]), Status::Success);
+        let runs = vec![
+            run(&[("unresolved-symbol", "error")]),
+            run(&[("duplicate-symbol", "warning"), ("rpath-consolidation", "note")]),
+        ];
+        assert_eq!(history_status(&runs), Status::of(vec![FindingKind::Warnings]));
+    }
+}
@@ -0,0 +1,454 @@
+use glob::{glob, Pattern};
+use memmap2::Mmap;
+use tar::{Archive, EntryType};
+
+use libraries::{reroot, ErrorMsg};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::ops::Deref;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// The content of a file, either mapped into memory or read into a buffer.
+#[derive(Debug)]
+pub enum FileContent {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileContent::Mapped(map) => map,
+            FileContent::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// Access to the files of the analyzed system. The resolver reads libraries and ld.so.conf files
+/// and probes search locations only via this trait, so that it can work on virtual roots (e.g.,
+/// images with symbolic links that must not be followed onto the host) and in-memory fixtures as
+/// well as on the real file system.
+pub trait FileProvider: fmt::Debug + Send + Sync {
+    /// The content of the file at `path`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// The content of the file at `path`, mapped into memory if the provider supports it (the
+    /// file must not be modified while the content is in use).
+    fn map(&self, path: &Path) -> io::Result<FileContent> {
+        self.read(path).map(FileContent::Read)
+    }
+
+    /// The first (up to) `len` bytes of the file at `path`.
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = self.read(path)?;
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+
+    /// Whether a file or directory exists at `path`.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// The path with all symbolic links and "." and ".." components resolved (like realpath),
+    /// or an error if it does not exist.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// The names of the entries of the directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>>;
+
+    /// Whether a directory exists at `path`.
+    fn is_dir(&self, path: &Path) -> bool {
+        self.read_dir(path).is_ok()
+    }
+
+    /// The mode (permission bits, including setuid and setgid) of the file at `path`.
+    fn mode(&self, path: &Path) -> io::Result<u32>;
+
+    /// The paths of all files matching the glob `pattern`, in alphabetical order.
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>>;
+}
+
+/// The file system of the machine linkcheck runs on.
+#[derive(Debug)]
+pub struct RealFs;
+
+impl FileProvider for RealFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn map(&self, path: &Path) -> io::Result<FileContent> {
+        let file = fs::File::open(path)?;
+        // Empty files cannot be mapped.
+        if file.metadata()?.len() == 0 {
+            return Ok(FileContent::Read(Vec::new()));
+        }
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(FileContent::Mapped(map))
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let _ = fs::File::open(path)?
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        Ok(fs::metadata(path)?.permissions().mode())
+    }
+
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let pattern = pattern
+            .to_str()
+            .ok_or_else(|| ErrorMsg(format!("Pattern {:?} not valid utf8", pattern)))?;
+        let mut paths = Vec::new();
+        for path in glob(pattern)? {
+            paths.push(path?);
+        }
+        Ok(paths)
+    }
+}
+
+/// The files below `root` of another provider, accessed via absolute paths of the analyzed system
+/// (i.e., relative to `root`).
+#[derive(Debug)]
+pub struct SysrootFs {
+    root: PathBuf,
+    files: Arc<dyn FileProvider>,
+}
+
+impl SysrootFs {
+    pub fn new(root: &Path, files: Arc<dyn FileProvider>) -> Self {
+        SysrootFs {
+            root: root.to_path_buf(),
+            files,
+        }
+    }
+}
+
+impl FileProvider for SysrootFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.read(&reroot(path, Some(&self.root)))
+    }
+
+    fn map(&self, path: &Path) -> io::Result<FileContent> {
+        self.files.map(&reroot(path, Some(&self.root)))
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        self.files.read_head(&reroot(path, Some(&self.root)), len)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.exists(&reroot(path, Some(&self.root)))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = self.files.canonicalize(&reroot(path, Some(&self.root)))?;
+        Ok(path.strip_prefix(&self.root)
+            .map(|path| Path::new("/").join(path))
+            .unwrap_or(path.clone()))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        self.files.read_dir(&reroot(path, Some(&self.root)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files.is_dir(&reroot(path, Some(&self.root)))
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        self.files.mode(&reroot(path, Some(&self.root)))
+    }
+
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let paths = self.files.glob(&reroot(pattern, Some(&self.root)))?;
+        Ok(paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok())
+            .map(|path| Path::new("/").join(path))
+            .collect())
+    }
+}
+
+/// The files below `root` of the real file system, accessed via their real paths (unlike
+/// `SysrootFs`), with symbolic links resolved inside `root` as in a chroot: Absolute link targets
+/// refer to files below `root`, and ".." does not leave it.
+#[derive(Debug)]
+pub struct ImageFs {
+    root: PathBuf,
+}
+
+impl ImageFs {
+    pub fn new(root: &Path) -> Self {
+        ImageFs {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// The path with all symbolic links resolved inside the image and "." and ".." removed.
+    /// Paths that are not below the root are interpreted relative to it.
+    fn resolve(&self, path: &Path, depth: usize) -> PathBuf {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let mut resolved = self.root.clone();
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => {
+                    resolved.push(name);
+                    if depth >= MAX_SYMLINK_DEPTH {
+                        continue;
+                    }
+                    if let Ok(target) = fs::read_link(&resolved) {
+                        let target = if target.is_absolute() {
+                            reroot(&target, Some(&self.root))
+                        } else {
+                            resolved.parent().unwrap_or(&self.root).join(target)
+                        };
+                        resolved = self.resolve(&target, depth + 1);
+                    }
+                }
+                Component::ParentDir => {
+                    if resolved != self.root {
+                        let _ = resolved.pop();
+                    }
+                }
+                Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+            }
+        }
+        resolved
+    }
+}
+
+impl FileProvider for ImageFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        RealFs.read(&self.resolve(path, 0))
+    }
+
+    fn map(&self, path: &Path) -> io::Result<FileContent> {
+        RealFs.map(&self.resolve(path, 0))
+    }
+
+    fn read_head(&self, path: &Path, len: usize) -> io::Result<Vec<u8>> {
+        RealFs.read_head(&self.resolve(path, 0), len)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path, 0).exists()
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path, 0);
+        let _ = fs::symlink_metadata(&resolved)?;
+        Ok(resolved)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        RealFs.read_dir(&self.resolve(path, 0))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        RealFs.is_dir(&self.resolve(path, 0))
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        RealFs.mode(&self.resolve(path, 0))
+    }
+
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        // Only the directory of the pattern may contain symbolic links to resolve (wildcards are
+        // expected in the file name).
+        let pattern = match (pattern.parent(), pattern.file_name()) {
+            (Some(dir), Some(name)) => self.resolve(dir, 0).join(name),
+            _ => pattern.to_path_buf(),
+        };
+        RealFs.glob(&pattern)
+    }
+}
+
+/// Files held in memory, e.g., test fixtures or the content of an archive. Paths are absolute and
+/// symbolic links are followed (also in parent directories).
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: HashMap<PathBuf, Vec<u8>>,
+    symlinks: HashMap<PathBuf, PathBuf>,
+    modes: HashMap<PathBuf, u32>,
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        MemoryFs::default()
+    }
+
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P, content: Vec<u8>) {
+        let _ = self.files.insert(Path::new("/").join(path), content);
+    }
+
+    /// Add a symbolic link at `path`. Relative targets are interpreted relative to the directory
+    /// containing the link.
+    pub fn add_symlink<P: AsRef<Path>, T: AsRef<Path>>(&mut self, path: P, target: T) {
+        let _ = self.symlinks
+            .insert(Path::new("/").join(path), target.as_ref().to_path_buf());
+    }
+
+    /// Set the mode of the file at `path` (0o644 unless set).
+    pub fn set_mode<P: AsRef<Path>>(&mut self, path: P, mode: u32) {
+        let _ = self.modes.insert(Path::new("/").join(path), mode);
+    }
+
+    /// Load the regular files (with their modes), symbolic links and hard links of a tar archive.
+    /// Paths in the archive are interpreted relative to the root directory.
+    pub fn from_tar<R: Read>(archive: R) -> Result<Self, Box<dyn Error>> {
+        let mut files = MemoryFs::new();
+        for entry in Archive::new(archive).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            match entry.header().entry_type() {
+                EntryType::Regular => {
+                    let mut content = Vec::new();
+                    let _ = entry.read_to_end(&mut content)?;
+                    files.set_mode(&path, entry.header().mode()?);
+                    files.add_file(path, content);
+                }
+                EntryType::Symlink => {
+                    if let Some(target) = entry.link_name()? {
+                        files.add_symlink(path, target);
+                    }
+                }
+                EntryType::Link => {
+                    // Hard link targets are relative to the archive root.
+                    if let Some(target) = entry.link_name()? {
+                        files.add_symlink(path, Path::new("/").join(target));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(files)
+    }
+
+    /// The path with all symbolic links resolved and "." and ".." removed.
+    fn resolve(&self, path: &Path, depth: usize) -> PathBuf {
+        let mut resolved = PathBuf::from("/");
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => {
+                    resolved.push(name);
+                    if depth >= MAX_SYMLINK_DEPTH {
+                        continue;
+                    }
+                    if let Some(target) = self.symlinks.get(&resolved) {
+                        let target = resolved.parent().unwrap_or(Path::new("/")).join(target);
+                        resolved = self.resolve(&target, depth + 1);
+                    }
+                }
+                Component::ParentDir => {
+                    let _ = resolved.pop();
+                }
+                Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
+            }
+        }
+        resolved
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("{:?}", path))
+    }
+}
+
+impl FileProvider for MemoryFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&self.resolve(path, 0))
+            .cloned()
+            .ok_or_else(|| MemoryFs::not_found(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let path = self.resolve(path, 0);
+        // Directories exist implicitly if they contain anything.
+        self.files.keys().chain(self.symlinks.keys()).any(|p| p.starts_with(&path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path, 0);
+        if self.exists(&resolved) {
+            Ok(resolved)
+        } else {
+            Err(MemoryFs::not_found(path))
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<OsString>> {
+        let dir = self.resolve(path, 0);
+        let mut names = self.files
+            .keys()
+            .chain(self.symlinks.keys())
+            .filter_map(|p| p.strip_prefix(&dir).ok())
+            .filter_map(|p| p.components().next())
+            .map(|name| name.as_os_str().to_os_string())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Err(MemoryFs::not_found(path));
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn mode(&self, path: &Path) -> io::Result<u32> {
+        let path = self.resolve(path, 0);
+        if self.files.contains_key(&path) {
+            Ok(self.modes.get(&path).cloned().unwrap_or(0o644))
+        } else if self.is_dir(&path) {
+            Ok(0o755)
+        } else {
+            Err(MemoryFs::not_found(&path))
+        }
+    }
+
+    fn glob(&self, pattern: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let pattern = pattern
+            .to_str()
+            .ok_or_else(|| ErrorMsg(format!("Pattern {:?} not valid utf8", pattern)))?;
+        let pattern = Pattern::new(pattern)?;
+        let mut paths = self.files
+            .keys()
+            .chain(self.symlinks.keys())
+            .filter(|path| pattern.matches_path(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        paths.sort();
+        Ok(paths)
+    }
+}
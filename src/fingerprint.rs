@@ -0,0 +1,53 @@
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A fingerprint identifying a finding across runs: a 64 bit FNV-1a hash (which, unlike the
+/// hashers of std, is stable across platforms and compiler versions) over the kind of the finding
+/// and the parts identifying it (objects, symbols, paths).
+pub fn fingerprint(kind: &str, parts: &[&str]) -> String {
+    let mut hash = FNV_OFFSET_BASIS;
+    for part in Some(&kind).into_iter().chain(parts.iter()) {
+        // Terminate every part, so that ("ab", "c") and ("a", "bc") do not collide.
+        hash = fnv1a(hash, part.as_bytes());
+        hash = fnv1a(hash, &[0]);
+    }
+    format!("{:016x}", hash)
+}
+
+/// A hash of the content of a file (64 bit FNV-1a, see `fingerprint`).
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(FNV_OFFSET_BASIS, bytes))
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Normalize a path for fingerprinting, so that the same file yields the same fingerprint
+/// regardless of how it was passed on the command line. Symbolic links are deliberately not
+/// resolved: libraries are found by the names they are requested by (e.g., libfoo.so.1), and
+/// following those to the versioned file (libfoo.so.1.2.3) would change the fingerprints of all
+/// findings of a library on every update of it.
+pub fn normalize_path(path: &Path) -> String {
+    let path = env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or(path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let _ = normalized.pop();
+            }
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized.to_string_lossy().to_string()
+}
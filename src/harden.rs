@@ -0,0 +1,167 @@
+use goblin::elf::dyn::DT_BIND_NOW;
+use goblin::elf::header::ET_DYN;
+use goblin::elf::program_header::{PF_X, PT_GNU_RELRO, PT_GNU_STACK};
+
+use libraries::LibraryDependencies;
+use symbols::exported_symbol_names;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+
+const NDX_UNDEFINED: usize = 0;
+const DF_BIND_NOW: u64 = 0x8;
+const DF_1_NOW: u64 = 0x1;
+const DF_1_PIE: u64 = 0x08000000;
+
+/// Functions whose use indicates that the object was built with stack protection.
+const STACK_PROTECTOR_SYMBOLS: &[&str] =
+    &["__stack_chk_fail", "__stack_chk_guard", "__intel_security_cookie"];
+
+/// How much of the relocation data is made read-only after relocation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relro {
+    None,
+    /// PT_GNU_RELRO without BIND_NOW: the GOT entries of functions stay writable
+    Partial,
+    Full,
+}
+
+/// The exploit mitigations of an analyzed file (as checksec would show them).
+#[derive(Clone, Serialize)]
+pub struct HardeningAudit {
+    pub file: PathBuf,
+    /// Whether the file is loaded at a random address (ET_DYN)
+    pub pie: bool,
+    /// Whether the file is a shared library (for which PIE is implied)
+    pub shared_library: bool,
+    pub relro: Relro,
+    pub bind_now: bool,
+    pub stack_canary: bool,
+    /// Whether the stack is not executable (PT_GNU_STACK without PF_X)
+    pub nx_stack: bool,
+    /// Fortified functions (__*_chk) that the file uses
+    pub fortified: Vec<String>,
+    /// Functions that the file uses although a library of the closure provides a fortified
+    /// variant
+    pub fortifiable: Vec<String>,
+}
+
+impl HardeningAudit {
+    /// Inspect the analyzed file of the closure. The fortifiable functions are those for which a
+    /// library of the closure exports a __*_chk variant.
+    pub fn of_root(libs: &LibraryDependencies) -> Self {
+        let root = &libs.opened_libs[&libs.root];
+        let elf = root.get_elf();
+        let (flags, flags_1) = root.dynamic_flags();
+
+        let has_segment = |p_type| elf.program_headers.iter().any(|ph| ph.p_type == p_type);
+        let bind_now = flags & DF_BIND_NOW != 0 || flags_1 & DF_1_NOW != 0
+            || elf.dynamic
+                .as_ref()
+                .is_some_and(|dynamic| dynamic.dyns.iter().any(|d| d.d_tag == DT_BIND_NOW));
+        let relro = match (has_segment(PT_GNU_RELRO), bind_now) {
+            (false, _) => Relro::None,
+            (true, false) => Relro::Partial,
+            (true, true) => Relro::Full,
+        };
+        // Without PT_GNU_STACK, the loader makes the stack executable on most architectures.
+        let nx_stack = elf.program_headers
+            .iter()
+            .find(|ph| ph.p_type == PT_GNU_STACK)
+            .is_some_and(|ph| ph.p_flags & PF_X == 0);
+
+        let imported = elf.dynsyms
+            .iter()
+            .filter(|sym| sym.st_shndx == NDX_UNDEFINED)
+            .filter_map(|sym| elf.dynstrtab.get(sym.st_name).and_then(|name| name.ok()))
+            .filter(|name| !name.is_empty())
+            .collect::<HashSet<_>>();
+        let stack_canary = STACK_PROTECTOR_SYMBOLS
+            .iter()
+            .any(|symbol| imported.contains(symbol));
+
+        let mut fortified = imported
+            .iter()
+            .filter(|name| {
+                name.starts_with("__") && name.ends_with("_chk") && !name.starts_with("__stack_chk")
+            })
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        fortified.sort();
+        let mut fortified_variants = HashSet::new();
+        for (path, lib) in libs.opened_libs.iter() {
+            if path != &libs.root {
+                fortified_variants.extend(exported_symbol_names(lib.get_elf()));
+            }
+        }
+        let mut fortifiable = imported
+            .iter()
+            .filter(|name| fortified_variants.contains(&format!("__{}_chk", name)))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        fortifiable.sort();
+
+        HardeningAudit {
+            file: libs.root.clone(),
+            pie: elf.header.e_type == ET_DYN,
+            shared_library: elf.header.e_type == ET_DYN && elf.interpreter.is_none()
+                && flags_1 & DF_1_PIE == 0,
+            relro,
+            bind_now,
+            stack_canary,
+            nx_stack,
+            fortified,
+            fortifiable,
+        }
+    }
+
+    /// The checks with their outcome and whether it is the hardened one.
+    pub fn checks(&self) -> Vec<(&'static str, String, bool)> {
+        let yes_no = |value| if value { "yes" } else { "no" }.to_owned();
+        vec![
+            (
+                "PIE",
+                if self.shared_library {
+                    "yes (shared library)".to_owned()
+                } else {
+                    yes_no(self.pie)
+                },
+                self.pie,
+            ),
+            ("RELRO", self.relro.to_string(), self.relro == Relro::Full),
+            ("BIND_NOW", yes_no(self.bind_now), self.bind_now),
+            ("Stack canary", yes_no(self.stack_canary), self.stack_canary),
+            ("NX stack", yes_no(self.nx_stack), self.nx_stack),
+            ("FORTIFY", self.fortify_summary(), self.fortifiable.is_empty()),
+        ]
+    }
+
+    fn fortify_summary(&self) -> String {
+        let mut summary = format!(
+            "{} ({} fortified",
+            if self.fortified.is_empty() { "no" } else { "yes" },
+            self.fortified.len()
+        );
+        if !self.fortifiable.is_empty() {
+            summary.push_str(&format!(
+                ", {} unfortified: {}",
+                self.fortifiable.len(),
+                self.fortifiable.join(", ")
+            ));
+        }
+        summary.push(')');
+        summary
+    }
+}
+
+impl fmt::Display for Relro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Relro::None => write!(f, "none"),
+            Relro::Partial => write!(f, "partial"),
+            Relro::Full => write!(f, "full"),
+        }
+    }
+}
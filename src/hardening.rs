@@ -0,0 +1,158 @@
+use goblin::elf::Elf;
+
+/// Files larger than this are not read at all in hardened mode.
+pub const MAX_FILE_SIZE: usize = 1 << 30;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+const SHT_NOBITS: u64 = 8;
+
+const NEEDED_D_TAG: u64 = 1;
+const SONAME_D_TAG: u64 = 14;
+const RPATH_D_TAG: u64 = 15;
+const RUNPATH_D_TAG: u64 = 29;
+const AUXILIARY_D_TAG: u64 = 0x7ffffffd;
+const FILTER_D_TAG: u64 = 0x7fffffff;
+/// The dynamic entries whose values are offsets into the dynamic string table.
+const STRING_D_TAGS: &[u64] = &[
+    NEEDED_D_TAG,
+    SONAME_D_TAG,
+    RPATH_D_TAG,
+    RUNPATH_D_TAG,
+    AUXILIARY_D_TAG,
+    FILTER_D_TAG,
+];
+
+/// Reads the fields of the ELF header and of the program and section headers without trusting
+/// any offset.
+struct RawElf<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl<'a> RawElf<'a> {
+    fn read(&self, offset: u64, size: usize) -> Result<u64, String> {
+        let start = offset as usize;
+        let bytes = start
+            .checked_add(size)
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(|| format!("Field at offset {:#x} is outside of the file", offset))?;
+        let mut value = 0u64;
+        for (i, &b) in bytes.iter().enumerate() {
+            let shift = if self.little_endian { i * 8 } else { (size - 1 - i) * 8 };
+            value |= (b as u64) << shift;
+        }
+        Ok(value)
+    }
+
+    /// Read an address or offset (4 or 8 bytes large depending on the class of the file) at the
+    /// offset for the class.
+    fn read_word(&self, offset32: u64, offset64: u64) -> Result<u64, String> {
+        if self.is_64 {
+            self.read(offset64, 8)
+        } else {
+            self.read(offset32, 4)
+        }
+    }
+
+    /// Check that the `count` entries of `entry_size` bytes starting at `offset` are inside of the
+    /// file.
+    fn check_table(&self, what: &str, offset: u64, count: u64, entry_size: u64) -> Result<(), String> {
+        let end = count
+            .checked_mul(entry_size)
+            .and_then(|size| size.checked_add(offset));
+        match end {
+            Some(end) if end <= self.bytes.len() as u64 => Ok(()),
+            _ => Err(format!("{} at offset {:#x} exceeds the file", what, offset)),
+        }
+    }
+}
+
+/// Check the headers of the (unparsed) ELF file of untrusted origin: Tables and sections that
+/// point outside of the file are rejected, as the parser would otherwise read or allocate based on
+/// bogus sizes.
+pub fn check_headers(bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() < 0x34 || &bytes[..4] != ELF_MAGIC {
+        return Err("Not an ELF file".to_owned());
+    }
+    let is_64 = match bytes[4] {
+        ELFCLASS32 => false,
+        ELFCLASS64 => true,
+        class => return Err(format!("Invalid ELF class {}", class)),
+    };
+    let raw = RawElf {
+        bytes,
+        is_64,
+        little_endian: bytes[5] == ELFDATA2LSB,
+    };
+    let (phentsize, shentsize) = if is_64 { (56, 64) } else { (32, 40) };
+
+    let phoff = raw.read_word(0x1c, 0x20)?;
+    let shoff = raw.read_word(0x20, 0x28)?;
+    let (phnum, shnum) = if is_64 {
+        (raw.read(0x38, 2)?, raw.read(0x3c, 2)?)
+    } else {
+        (raw.read(0x2c, 2)?, raw.read(0x30, 2)?)
+    };
+    let (actual_phentsize, actual_shentsize) = if is_64 {
+        (raw.read(0x36, 2)?, raw.read(0x3a, 2)?)
+    } else {
+        (raw.read(0x2a, 2)?, raw.read(0x2e, 2)?)
+    };
+    if phnum > 0 && actual_phentsize != phentsize {
+        return Err(format!("Invalid program header size {}", actual_phentsize));
+    }
+    if shnum > 0 && actual_shentsize != shentsize {
+        return Err(format!("Invalid section header size {}", actual_shentsize));
+    }
+    raw.check_table("Program headers", phoff, phnum, phentsize)?;
+    raw.check_table("Section headers", shoff, shnum, shentsize)?;
+
+    for i in 0..phnum {
+        let header = phoff + i * phentsize;
+        let offset = raw.read_word(header + 4, header + 8)?;
+        let filesz = raw.read_word(header + 0x10, header + 0x20)?;
+        raw.check_table("Segment", offset, filesz, 1)?;
+    }
+    for i in 0..shnum {
+        let header = shoff + i * shentsize;
+        if raw.read(header + 4, 4)? == SHT_NOBITS {
+            continue;
+        }
+        let offset = raw.read_word(header + 0x10, header + 0x18)?;
+        let size = raw.read_word(header + 0x14, header + 0x20)?;
+        raw.check_table("Section", offset, size, 1)?;
+    }
+    Ok(())
+}
+
+/// Check the dynamic information of the (parsed) ELF file of untrusted origin: It has to exist,
+/// and all names the analysis looks up in the dynamic string table (of dynamic entries and dynamic
+/// symbols) have to be in range and valid UTF-8.
+pub fn check_dynamic_info(elf: &Elf) -> Result<(), String> {
+    let dynamic = elf.dynamic
+        .as_ref()
+        .ok_or_else(|| "File has no dynamic section".to_owned())?;
+    let valid = |offset: usize| elf.dynstrtab.get(offset).is_some_and(|s| s.is_ok());
+    for dyn in dynamic.dyns.iter() {
+        if STRING_D_TAGS.contains(&dyn.d_tag) && !valid(dyn.d_val as usize) {
+            return Err(format!(
+                "Dynamic entry {} has an invalid string offset {:#x}",
+                dyn.d_tag, dyn.d_val
+            ));
+        }
+    }
+    for sym in elf.dynsyms.iter() {
+        if !valid(sym.st_name) {
+            return Err(format!(
+                "Dynamic symbol has an invalid name offset {:#x}",
+                sym.st_name
+            ));
+        }
+    }
+    Ok(())
+}
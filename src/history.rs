@@ -0,0 +1,151 @@
+use fingerprint::normalize_path;
+use model::Finding;
+use objinfo::ObjectInfo;
+use report::Report;
+
+use rusqlite::{Connection, OptionalExtension};
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        target TEXT NOT NULL,
+        build_id TEXT
+    );
+    CREATE TABLE IF NOT EXISTS findings (
+        run INTEGER NOT NULL REFERENCES runs(id),
+        rule TEXT NOT NULL,
+        level TEXT NOT NULL,
+        fingerprint TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_by_target ON runs(target, timestamp);
+    CREATE INDEX IF NOT EXISTS findings_by_run ON findings(run);
+";
+
+/// The summarized findings of one analysis of a target, as stored in the history database.
+pub struct HistoryRun {
+    pub time: String, // UTC, e.g. 2018-06-01 12:00:00
+    pub build_id: Option<String>,
+    pub counts: BTreeMap<String, usize>, // Number of findings per rule
+    pub levels: BTreeSet<(String, String)>, // Distinct rules and levels of the findings
+    pub fingerprints: HashSet<String>,
+}
+
+impl HistoryRun {
+    pub fn num_findings(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+impl fmt::Display for HistoryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.time)?;
+        if let Some(ref build_id) = self.build_id {
+            write!(f, " (build id {})", build_id)?;
+        }
+        write!(f, ": {} findings", self.num_findings())
+    }
+}
+
+/// A local SQLite database that collects the findings of all runs (see --append-history).
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<History, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(History { conn })
+    }
+
+    /// Append the findings of all analyzed files as runs with the current time. Files that could
+    /// not be analyzed are recorded with a single analysis-error finding.
+    pub fn append(
+        &mut self,
+        reports: &[(PathBuf, Result<Report, String>)],
+    ) -> Result<(), Box<dyn Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+        let tx = self.conn.transaction()?;
+        for (file, report) in reports {
+            let build_id = report.as_ref().ok().and_then(|report| {
+                let libs = &report.libs;
+                libs.opened_libs
+                    .get(&libs.root)
+                    .and_then(|lib| ObjectInfo::of_library(lib).build_id)
+            });
+            tx.execute(
+                "INSERT INTO runs (timestamp, target, build_id) VALUES (?1, ?2, ?3)",
+                (timestamp, normalize_path(file), build_id),
+            )?;
+            let run = tx.last_insert_rowid();
+
+            let findings = match report {
+                Ok(report) => report.to_result().findings(),
+                Err(err) => vec![Finding::analysis_error(file, err)],
+            };
+            for finding in findings {
+                tx.execute(
+                    "INSERT INTO findings (run, rule, level, fingerprint, message)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    (run, finding.rule, finding.level, finding.fingerprint, finding.message),
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All recorded runs of the given target, oldest first.
+    pub fn runs(&self, target: &Path) -> Result<Vec<HistoryRun>, Box<dyn Error>> {
+        let target = normalize_path(target);
+        let known = self.conn
+            .query_row("SELECT 1 FROM runs WHERE target = ?1 LIMIT 1", [&target], |_| Ok(()))
+            .optional()?;
+        if known.is_none() {
+            return Err(format!("No runs of {:?} are recorded", target).into());
+        }
+
+        let mut run_stmt = self.conn.prepare(
+            "SELECT id, datetime(timestamp, 'unixepoch'), build_id FROM runs
+             WHERE target = ?1 ORDER BY timestamp, id",
+        )?;
+        let mut finding_stmt = self.conn
+            .prepare("SELECT rule, level, fingerprint FROM findings WHERE run = ?1")?;
+
+        let runs = run_stmt
+            .query_map([&target], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<(i64, String, Option<String>)>, _>>()?;
+
+        let mut history = Vec::new();
+        for (id, time, build_id) in runs {
+            let mut run = HistoryRun {
+                time,
+                build_id,
+                counts: BTreeMap::new(),
+                levels: BTreeSet::new(),
+                fingerprints: HashSet::new(),
+            };
+            let findings = finding_stmt
+                .query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<(String, String, String)>, _>>()?;
+            for (rule, level, fingerprint) in findings {
+                *run.counts.entry(rule.clone()).or_insert(0) += 1;
+                let _ = run.levels.insert((rule, level));
+                let _ = run.fingerprints.insert(fingerprint);
+            }
+            history.push(run);
+        }
+        Ok(history)
+    }
+}
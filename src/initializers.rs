@@ -0,0 +1,128 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{Library, LibraryDependencies};
+use symbols::demangle;
+
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const INIT_ARRAYSZ_D_TAG: u64 = 27;
+const PREINIT_ARRAYSZ_D_TAG: u64 = 33;
+
+const STT_OBJECT: u8 = 1;
+const STT_TLS: u8 = 6;
+
+const BIND_GLOBAL: u8 = 1;
+
+const NDX_UNDEFINED: usize = 0;
+
+/// The number of initializers (DT_INIT_ARRAY and DT_PREINIT_ARRAY entries) that the loader runs
+/// when it loads the library. Every object built with the usual startup files has one
+/// DT_INIT_ARRAY entry (frame_dummy of crtbegin.o), which is not counted.
+pub fn initializer_count(lib: &Library) -> u64 {
+    let elf = lib.get_elf();
+    let dynamic = match &elf.dynamic {
+        Some(dynamic) => dynamic,
+        &None => return 0,
+    };
+    let pointer_size = if elf.is_64 { 8 } else { 4 };
+    let entries = |tag| {
+        dynamic
+            .dyns
+            .iter()
+            .find(|d| d.d_tag == tag)
+            .map_or(0, |d| d.d_val / pointer_size)
+    };
+    entries(INIT_ARRAYSZ_D_TAG).saturating_sub(1) + entries(PREINIT_ARRAYSZ_D_TAG)
+}
+
+/// Unresolved references of a library to data (objects or thread-local variables). Unlike
+/// functions, which are bound lazily when they are first called, data references are bound when
+/// the library is loaded, so the library cannot be loaded at all.
+pub struct UnresolvedData {
+    pub lib: PathBuf,
+    pub symbols: Vec<String>,
+    pub initializers: u64,
+}
+
+impl UnresolvedData {
+    /// The library referencing the data.
+    pub fn lib(&self) -> &Path {
+        &self.lib
+    }
+
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint("unresolved-data", &[&normalize_path(&self.lib)])
+    }
+}
+
+impl fmt::Display for UnresolvedData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbols = self.symbols
+            .iter()
+            .map(|s| demangle(s).unwrap_or(s.clone()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.initializers > 0 {
+            write!(
+                f,
+                "{:?}: References unresolved data symbols {}, which are bound at load time, so the process fails while loading the library and its {} initializers (DT_INIT_ARRAY) never run",
+                self.lib, symbols, self.initializers
+            )
+        } else {
+            write!(
+                f,
+                "{:?}: References unresolved data symbols {}, which are bound at load time, so the process fails while loading the library",
+                self.lib, symbols
+            )
+        }
+    }
+}
+
+/// Find the libraries that (non-weakly) reference unresolved symbols (given with the names of the
+/// libraries referencing them) as data.
+pub fn find_unresolved_data(
+    libs: &LibraryDependencies,
+    unresolved_symbols: &[(String, HashSet<String>)],
+) -> Vec<UnresolvedData> {
+    let mut unresolved_by_lib = BTreeMap::new();
+    for (symbol, lib_names) in unresolved_symbols {
+        let name = symbol.split('@').next().unwrap_or(symbol);
+        for lib_name in lib_names {
+            if let Some(path) = libs.lookup(OsStr::new(lib_name)) {
+                unresolved_by_lib
+                    .entry(path)
+                    .or_insert(Vec::new())
+                    .push((name, symbol));
+            }
+        }
+    }
+
+    let mut unresolved_data = Vec::new();
+    for (path, symbols) in unresolved_by_lib {
+        let lib = &libs.opened_libs[path];
+        let elf = lib.get_elf();
+        let data_names = elf.dynsyms
+            .iter()
+            // Weak references stay null instead of failing.
+            .filter(|sym| sym.st_shndx == NDX_UNDEFINED && sym.st_bind() == BIND_GLOBAL)
+            .filter(|sym| sym.st_type() == STT_OBJECT || sym.st_type() == STT_TLS)
+            .filter_map(|sym| elf.dynstrtab.get(sym.st_name).and_then(|n| n.ok()))
+            .collect::<HashSet<_>>();
+        let data_symbols = symbols
+            .into_iter()
+            .filter(|&(name, _)| data_names.contains(name))
+            .map(|(_, symbol)| symbol.clone())
+            .collect::<Vec<_>>();
+        if !data_symbols.is_empty() {
+            unresolved_data.push(UnresolvedData {
+                lib: path.clone(),
+                symbols: data_symbols,
+                initializers: initializer_count(lib),
+            });
+        }
+    }
+    unresolved_data
+}
@@ -0,0 +1,58 @@
+use libc;
+
+use std::env;
+
+const DEFAULT_WIDTH: usize = 80;
+const TAB_WIDTH: usize = 8;
+const COLUMN_SEPARATOR: &str = "  ";
+
+/// Determine the width of the terminal that stdout is connected to. Falls back to $COLUMNS and
+/// finally to 80 columns. `None` if stdout is not a terminal (e.g., redirected to a file or piped
+/// into grep), in which case nothing should be wrapped or truncated.
+pub fn terminal_width() -> Option<usize> {
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return None;
+    }
+    let mut size: libc::winsize = unsafe { ::std::mem::zeroed() };
+    let res = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if res == 0 && size.ws_col > 0 {
+        return Some(size.ws_col as usize);
+    }
+    Some(env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(DEFAULT_WIDTH))
+}
+
+fn truncate(item: &str, width: usize) -> String {
+    if item.chars().count() <= width {
+        item.to_owned()
+    } else {
+        let mut truncated = item.chars().take(width.saturating_sub(1)).collect::<String>();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// Arrange `items` in aligned columns (similar to ls) so that every line, prefixed by `indent`
+/// tabs, fits into `width` columns. Items that do not fit into a single line are truncated.
+pub fn columnize(items: &[String], indent: usize, width: usize) -> Vec<String> {
+    let available = width.saturating_sub(indent * TAB_WIDTH).max(1);
+    let prefix = (0..indent).map(|_| "\t").collect::<String>();
+
+    let max_len = items.iter().map(|i| i.chars().count()).max().unwrap_or(0);
+    let column_width = max_len.min(available);
+    let num_columns = ((available + COLUMN_SEPARATOR.len())
+        / (column_width + COLUMN_SEPARATOR.len()))
+        .max(1);
+
+    items
+        .chunks(num_columns)
+        .map(|row| {
+            let cells = row.iter()
+                .map(|item| format!("{:1$}", truncate(item, column_width), column_width))
+                .collect::<Vec<_>>();
+            format!("{}{}", prefix, cells.join(COLUMN_SEPARATOR).trim_end())
+        })
+        .collect()
+}
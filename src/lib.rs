@@ -0,0 +1,70 @@
+//! The library resolution and symbol analysis of linkcheck, for tools that want to embed it
+//! instead of running the `linkcheck` command.
+//!
+//! `LibraryDependencies` mimics how the loader resolves the dependency closure of an ELF file
+//! (according to `ResolveSettings`, most importantly the `LibSearchMethod`s to use) and records
+//! all problems it encounters. `SymbolSummary` summarizes the dynamic symbols of such a closure,
+//! taking GNU symbol versions into account (see `SymbolVersions`).
+//!
+//! `AnalysisResult` is the result of the complete analysis of one file as plain data (the JSON
+//! output of linkcheck is its serialization), and `AnalysisResult::findings` lists its findings
+//! uniformly, the way SARIF, the history and the grouped views of linkcheck show them.
+//!
+//! ```no_run
+//! extern crate linkcheck;
+//!
+//! use linkcheck::{gnuld_default_search_methods, LibraryDependencies, RealFs, ResolveSettings,
+//!                 SymbolSummary};
+//! use std::path::Path;
+//! use std::sync::Arc;
+//!
+//! let settings = ResolveSettings {
+//!     search_methods: gnuld_default_search_methods(),
+//!     secure_execution: false,
+//!     sysroot: None,
+//!     identity: Default::default(),
+//!     traversal: Default::default(),
+//!     environment: Default::default(),
+//!     files: Arc::new(RealFs),
+//! };
+//! let libs = LibraryDependencies::try_find_for_elf(Path::new("/bin/ls"), &settings).unwrap();
+//! for problem in libs.problems.iter() {
+//!     println!("{}", problem);
+//! }
+//! let summary = SymbolSummary::from_libs(&libs);
+//! println!("{} exported symbols", summary.exported.len());
+//! ```
+
+extern crate cpp_demangle;
+extern crate glob;
+extern crate goblin;
+extern crate libc;
+extern crate memmap2;
+extern crate ouroboros;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate tar;
+
+pub mod files;
+pub mod fingerprint;
+pub mod harden;
+pub mod hardening;
+pub mod libraries;
+pub mod loader;
+pub mod min_versions;
+pub mod model;
+pub mod objinfo;
+pub mod symbol_versions;
+pub mod symbols;
+#[cfg(test)]
+mod testing;
+
+pub use files::{FileContent, FileProvider, ImageFs, MemoryFs, RealFs, SysrootFs};
+pub use libraries::{gnuld_default_search_methods, Environment, Filtee, LibResolveProblem,
+                    LibSearchMethod, LibraryDependencies, LibraryIdentity, NoProgress,
+                    ParseProfile, Progress, ResolveSettings, SkippedCandidate, Traversal};
+pub use model::{AnalysisResult, Finding, Remediation, Rule, RULES};
+pub use symbol_versions::SymbolVersions;
+pub use symbols::SymbolSummary;
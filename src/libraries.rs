@@ -1,23 +1,47 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::ffi::{OsStr, OsString};
 use std::fmt;
-use std::fs;
-use std::io;
+use std::panic;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
-use glob::glob;
+use glob::Pattern;
+use files::{FileContent, FileProvider, SysrootFs};
+use fingerprint::{content_hash, fingerprint, normalize_path};
+use goblin::elf::header::machine_to_str;
 use goblin::elf::Elf;
+use hardening;
+use libc;
+use objinfo::ObjectInfo;
+use ouroboros::self_referencing;
+use symbols::{parallel_map, LibrarySymbols};
 
 const LIBS_D_TAG: u64 = 1;
 const RPATH_D_TAG: u64 = 15;
+const SYMBOLIC_D_TAG: u64 = 16;
 const RUNPATH_D_TAG: u64 = 29;
+const FLAGS_D_TAG: u64 = 30;
+const FLAGS_1_D_TAG: u64 = 0x6ffffffb;
+const AUXILIARY_D_TAG: u64 = 0x7ffffffd;
+const FILTER_D_TAG: u64 = 0x7fffffff;
+/// The references of the object bind to its own definitions first (-Bsymbolic).
+const DF_SYMBOLIC: u64 = 0x2;
+/// The loader does not search the default directories for the dependencies of the object.
+const DF_1_NODEFLIB: u64 = 0x800;
 
 #[derive(Debug)]
 struct DynInfo<'a> {
     rpath: Vec<&'a str>,
     runpath: Vec<&'a str>,
     libs: Vec<&'a str>,
+    filters: Vec<&'a str>,
+    auxiliaries: Vec<&'a str>,
+    flags: u64,
+    flags_1: u64,
+    symbolic: bool,
 }
 
 impl<'a> DynInfo<'a> {
@@ -26,242 +50,1548 @@ impl<'a> DynInfo<'a> {
             rpath: Vec::new(),
             runpath: Vec::new(),
             libs: Vec::new(),
+            filters: Vec::new(),
+            auxiliaries: Vec::new(),
+            flags: 0,
+            flags_1: 0,
+            symbolic: false,
         }
     }
 
-    fn from_elf(elf: &'a Elf) -> Option<Self> {
-        if let &Some(ref dynamic) = &elf.dynamic {
-            let mut dyninfo = DynInfo::new();
-            for dyn in dynamic.dyns.iter() {
-                match dyn.d_tag {
-                    RPATH_D_TAG => {
-                        let rpath_str = elf.dynstrtab
-                            .get(dyn.d_val as usize)
-                            .expect("RPATH should be in string table")
-                            .expect("rpath must be utf8");
-                        dyninfo.rpath.extend(rpath_str.split(":"))
-                    }
-                    RUNPATH_D_TAG => {
-                        let runpath_str = elf.dynstrtab
-                            .get(dyn.d_val as usize)
-                            .expect("RUNPATH should be in string table")
-                            .expect("runpath must be utf8");
-                        dyninfo.runpath.extend(runpath_str.split(":"))
-                    }
-                    LIBS_D_TAG => {
-                        let lib_str = elf.dynstrtab
-                            .get(dyn.d_val as usize)
-                            .expect("RPATH should be in string table")
-                            .expect("lib must be utf8");
-                        dyninfo.libs.push(lib_str)
-                    }
-                    _ => {}
+    /// The dynamic entries of `elf`, or why they cannot be read (e.g., because there is no dynamic
+    /// section or an entry refers to an invalid string).
+    fn from_elf(elf: &'a Elf) -> Result<Self, String> {
+        let dynamic = elf.dynamic
+            .as_ref()
+            .ok_or_else(|| "has no dynamic section (statically linked?)".to_owned())?;
+        let string = |d_val: u64, tag: &str| match elf.dynstrtab.get(d_val as usize) {
+            Some(Ok(string)) => Ok(string),
+            Some(Err(_)) => Err(format!("has a {} entry that is not valid utf8", tag)),
+            None => Err(format!("has a {} entry outside of the string table", tag)),
+        };
+        let mut dyninfo = DynInfo::new();
+        for dyn in dynamic.dyns.iter() {
+            match dyn.d_tag {
+                RPATH_D_TAG => dyninfo.rpath.extend(string(dyn.d_val, "DT_RPATH")?.split(":")),
+                RUNPATH_D_TAG => {
+                    dyninfo.runpath.extend(string(dyn.d_val, "DT_RUNPATH")?.split(":"))
                 }
+                LIBS_D_TAG => dyninfo.libs.push(string(dyn.d_val, "DT_NEEDED")?),
+                FILTER_D_TAG => dyninfo.filters.push(string(dyn.d_val, "DT_FILTER")?),
+                AUXILIARY_D_TAG => dyninfo.auxiliaries.push(string(dyn.d_val, "DT_AUXILIARY")?),
+                SYMBOLIC_D_TAG => dyninfo.symbolic = true,
+                FLAGS_D_TAG => dyninfo.flags = dyn.d_val,
+                FLAGS_1_D_TAG => dyninfo.flags_1 = dyn.d_val,
+                _ => {}
             }
-            Some(dyninfo)
-        } else {
-            None
         }
+        Ok(dyninfo)
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct LibraryLocations(Vec<(PathBuf, &'static str)>);
 
+/// A library found in the search locations: its path and the location entry that produced it.
+pub type FoundLibrary<'a> = (PathBuf, &'a (PathBuf, &'static str));
+
 impl LibraryLocations {
-    fn try_find_library(&self, lib_name: &str) -> Option<PathBuf> {
-        self.0
-            .iter()
-            .filter_map(|&(ref dir, _)| {
-                let potential_lib_path = dir.join(lib_name);
-                if potential_lib_path.exists() {
-                    Some(potential_lib_path)
-                } else {
-                    None
-                }
-            })
-            .next()
+    /// Iterate over all search locations (directory and origin) in search order.
+    pub fn iter(&self) -> ::std::slice::Iter<'_, (PathBuf, &'static str)> {
+        self.0.iter()
+    }
+
+    /// Returns the path of the first matching library together with the location entry (i.e.,
+    /// directory and search method) that produced it. Like the loader, files whose architecture
+    /// differs from `arch` are skipped; these are returned as well (in search order).
+    pub fn try_find_compatible_library(
+        &self,
+        lib_name: &str,
+        arch: Option<ElfArch>,
+        files: &dyn FileProvider,
+    ) -> (Option<FoundLibrary<'_>>, Vec<PathBuf>) {
+        let mut skipped = Vec::new();
+        for location in self.0.iter() {
+            let potential_lib_path = location.0.join(lib_name);
+            if !files.exists(&potential_lib_path) {
+                continue;
+            }
+            match (arch, ElfArch::of_file(&potential_lib_path, files)) {
+                (Some(arch), Some(lib_arch)) if arch != lib_arch => skipped.push(potential_lib_path),
+                _ => return (Some((potential_lib_path, location)), skipped),
+            }
+        }
+        (None, skipped)
     }
 }
 
 impl fmt::Display for LibraryLocations {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "[")?;
-        for &(ref lib, ref origin) in self.0.iter() {
+        for (lib, origin) in self.0.iter() {
             writeln!(f, "\t{:?} ({})", lib, origin)?;
         }
         writeln!(f, "]")
     }
 }
 
-#[derive(Debug)]
+/// A way the loader searches for libraries.
+#[derive(Debug, Clone)]
 pub enum LibSearchMethod {
+    /// The DT_RPATH entries of the dependent object (and its loaders)
     RPath,
+    /// The DT_RUNPATH entries of the dependent object
     RunPath,
+    /// The directories in $LD_LIBRARY_PATH
     LDLibraryPath,
+    /// The directories listed in the given ld.so.conf file (and the files it includes)
     LDConfig(PathBuf),
+    /// A fixed directory
     Fixed(PathBuf),
+    /// A fixed directory of the machine linkcheck runs on, which is not looked up inside the
+    /// sysroot (e.g., an internal library directory of a cross compiler)
+    Host(PathBuf),
 }
-#[derive(Debug)]
-pub enum NoError {
+
+/// The methods which GNU ld.so uses (if not specified otherwise) to locate libraries. At least
+/// according to https://en.wikipedia.org/wiki/Rpath
+pub fn gnuld_default_search_methods() -> Vec<LibSearchMethod> {
+    vec![
+        LibSearchMethod::RPath,
+        LibSearchMethod::RunPath,
+        LibSearchMethod::LDLibraryPath,
+        LibSearchMethod::LDConfig(PathBuf::from("/etc/ld.so.conf")),
+        LibSearchMethod::Fixed(PathBuf::from("/usr/lib")),
+        LibSearchMethod::Fixed(PathBuf::from("/lib")),
+    ]
 }
 
-impl ::std::string::ToString for NoError {
-    fn to_string(&self) -> String {
-        panic!("cannot create NoError");
+impl LibSearchMethod {
+    /// Check whether the search method refers to locations that actually exist (in `files`) and
+    /// return a warning otherwise.
+    pub fn validate(&self, files: &dyn FileProvider, sysroot: Option<&Path>) -> Option<String> {
+        let is_file = |path: &Path| files.exists(path) && !files.is_dir(path);
+        match self {
+            LibSearchMethod::LDConfig(conf_file) if !is_file(&reroot(conf_file, sysroot)) => {
+                Some(format!(
+                    "ld.so.conf file {:?} does not exist",
+                    reroot(conf_file, sysroot)
+                ))
+            }
+            LibSearchMethod::Fixed(dir) if !files.is_dir(&reroot_fixed_dir(dir, sysroot)) => {
+                Some(format!(
+                    "Fixed library location {:?} is not a directory",
+                    reroot_fixed_dir(dir, sysroot)
+                ))
+            }
+            LibSearchMethod::Host(dir) if !files.is_dir(dir) => {
+                Some(format!("Host library location {:?} is not a directory", dir))
+            }
+            _ => None,
+        }
+    }
+
+    /// Expand environment variable references in the path of the search method (if any).
+    pub fn expand_env(self, env: &Environment) -> Result<Self, String> {
+        let expand = |path: PathBuf| {
+            let path = path.to_str()
+                .ok_or_else(|| format!("Path {:?} is not valid utf8", path))?
+                .to_owned();
+            expand_env_vars(&path, env).map(PathBuf::from)
+        };
+        Ok(match self {
+            LibSearchMethod::LDConfig(conf_file) => LibSearchMethod::LDConfig(expand(conf_file)?),
+            LibSearchMethod::Fixed(dir) => LibSearchMethod::Fixed(expand(dir)?),
+            LibSearchMethod::Host(dir) => LibSearchMethod::Host(expand(dir)?),
+            other => other,
+        })
+    }
+}
+
+/// The environment of the process that loads the analyzed files.
+#[derive(Debug, Clone)]
+#[derive(Default)]
+pub enum Environment {
+    /// The environment of linkcheck itself
+    #[default]
+    Inherited,
+    /// A snapshot of another environment (e.g., of a service account)
+    Snapshot(HashMap<OsString, OsString>),
+}
+
+
+impl Environment {
+    /// The value of the environment variable `name`, if it is set.
+    pub fn var_os(&self, name: &str) -> Option<OsString> {
+        match self {
+            &Environment::Inherited => ::std::env::var_os(name),
+            Environment::Snapshot(vars) => vars.get(OsStr::new(name)).cloned(),
+        }
+    }
+
+    /// The environment with the variable `name` set to `value` (e.g., given on the command line).
+    pub fn with_var(self, name: &str, value: OsString) -> Environment {
+        let mut vars = match self {
+            Environment::Inherited => ::std::env::vars_os().collect::<HashMap<_, _>>(),
+            Environment::Snapshot(vars) => vars,
+        };
+        let _ = vars.insert(OsString::from(name), value);
+        Environment::Snapshot(vars)
     }
 }
 
+/// Expand `$VAR` and `${VAR}` references to variables of the environment `env` in `s`.
+pub fn expand_env_vars(s: &str, env: &Environment) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        let (name, remainder) = if rest.starts_with('{') {
+            let end = rest
+                .find('}')
+                .ok_or_else(|| format!("Unterminated variable reference in '{}'", s))?;
+            (&rest[1..end], &rest[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        if name.is_empty() {
+            result.push('$');
+        } else {
+            let value = env
+                .var_os(name)
+                .ok_or_else(|| format!("Environment variable '{}' (used in '{}') is not set", name, s))?;
+            result.push_str(&value.to_string_lossy());
+        }
+        rest = remainder;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+const SEARCH_METHOD_KEYWORDS: &[&str] =
+    &["rpath", "runpath", "ld_library_path", "ldconfig", "fixed", "host"];
+
+/// Levenshtein distance between two strings, used to detect misspelled search method keywords.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev_row = (0..b.len() + 1).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = prev_row[j] + if ca == cb { 0 } else { 1 };
+            row.push(substitution.min(prev_row[j + 1] + 1).min(row[j] + 1));
+        }
+        prev_row = row;
+    }
+    prev_row[b.len()]
+}
+
 impl ::std::str::FromStr for LibSearchMethod {
-    type Err = NoError;
+    type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        const LD_CONFIG_PREFIX: &'static str = "ldconfig:";
+        const LD_CONFIG_PREFIX: &str = "ldconfig:";
+        const FIXED_PREFIX: &str = "fixed:";
+        const HOST_PREFIX: &str = "host:";
         Ok(match s {
             "rpath" => LibSearchMethod::RPath,
             "runpath" => LibSearchMethod::RunPath,
             "ld_library_path" => LibSearchMethod::LDLibraryPath,
-            other => if other.starts_with(LD_CONFIG_PREFIX) {
-                LibSearchMethod::LDConfig(PathBuf::from(other[LD_CONFIG_PREFIX.len()..].to_owned()))
+            "ldconfig" | "fixed" | "host" => {
+                return Err(format!("Search method '{}' requires a path: '{}:<path>'", s, s));
+            }
+            other => if let Some(path) = other.strip_prefix(LD_CONFIG_PREFIX) {
+                LibSearchMethod::LDConfig(PathBuf::from(path))
+            } else if let Some(path) = other.strip_prefix(FIXED_PREFIX) {
+                LibSearchMethod::Fixed(PathBuf::from(path))
+            } else if let Some(path) = other.strip_prefix(HOST_PREFIX) {
+                LibSearchMethod::Host(PathBuf::from(path))
             } else {
+                // Paths usually contain a slash, keywords never do. Anything else that is close
+                // to a keyword is most likely a typo rather than a relative path.
+                let keyword = other.split(':').next().unwrap_or(other);
+                if !keyword.contains('/') {
+                    let lowercase = keyword.to_lowercase();
+                    if let Some(near_miss) = SEARCH_METHOD_KEYWORDS
+                        .iter()
+                        .find(|k| edit_distance(&lowercase, k) <= 2)
+                    {
+                        return Err(format!(
+                            "Unknown search method '{}'. Did you mean '{}'? Use 'fixed:{}' if you really mean a directory",
+                            other, near_miss, other
+                        ));
+                    }
+                }
                 LibSearchMethod::Fixed(PathBuf::from(other))
             },
         })
     }
-}
-#[derive(Debug)]
-pub struct Library {
-    path: PathBuf,
-    bytes: Vec<u8>, //Invariant: Valid ELF!
-}
+}
+
+impl fmt::Display for LibSearchMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LibSearchMethod::RPath => write!(f, "rpath"),
+            &LibSearchMethod::RunPath => write!(f, "runpath"),
+            &LibSearchMethod::LDLibraryPath => write!(f, "ld_library_path"),
+            LibSearchMethod::LDConfig(conf_file) => write!(f, "ldconfig:{}", conf_file.display()),
+            LibSearchMethod::Fixed(dir) => write!(f, "fixed:{}", dir.display()),
+            LibSearchMethod::Host(dir) => write!(f, "host:{}", dir.display()),
+        }
+    }
+}
+
+const PT_LOAD: u32 = 1;
+
+/// Translate a virtual address to an offset in the ELF file using the loadable segments.
+pub fn vaddr_to_offset(elf: &Elf, vaddr: u64) -> Option<usize> {
+    elf.program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .find(|ph| ph.p_vaddr <= vaddr && vaddr < ph.p_vaddr + ph.p_filesz)
+        .map(|ph| (vaddr - ph.p_vaddr + ph.p_offset) as usize)
+}
+
+/// The content of an ELF file together with its parsed representation, which borrows from it.
+#[self_referencing]
+struct ParsedElf {
+    bytes: FileContent,
+    #[borrows(bytes)]
+    #[covariant]
+    elf: Elf<'this>,
+}
+
+pub struct Library {
+    path: PathBuf,
+    parsed: ParsedElf, // Parsed once when the library is opened
+    symbols: OnceLock<LibrarySymbols>, // Extracted on first use
+}
+
+impl fmt::Debug for Library {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Library").field("path", &self.path).finish()
+    }
+}
+
+impl Library {
+    fn try_from_path(
+        path: PathBuf,
+        files: &dyn FileProvider,
+        profile: ParseProfile,
+    ) -> Result<Self, Box<dyn Error>> {
+        let parsed = match profile {
+            ParseProfile::Default => {
+                ParsedElf::try_new(files.map(&path)?, |bytes| Elf::parse(bytes))?
+            }
+            // Untrusted files are not mapped, as truncating a mapped file crashes the process.
+            ParseProfile::Hardened => {
+                let bytes = files.read_head(&path, hardening::MAX_FILE_SIZE + 1)?;
+                if bytes.len() > hardening::MAX_FILE_SIZE {
+                    return Err(Box::new(ErrorMsg("File is too large".to_owned())));
+                }
+                hardening::check_headers(&bytes).map_err(ErrorMsg)?;
+
+                // The parser is not trusted not to panic on malformed input.
+                let parsed = panic::catch_unwind(|| {
+                    ParsedElf::try_new(FileContent::Read(bytes), |bytes| {
+                        let elf = Elf::parse(bytes).map_err(|e| e.to_string())?;
+                        hardening::check_dynamic_info(&elf)?;
+                        Ok(elf)
+                    })
+                });
+                match parsed {
+                    Ok(Ok(parsed)) => parsed,
+                    Ok(Err(e)) => return Err(Box::new(ErrorMsg(e))),
+                    Err(_) => {
+                        return Err(Box::new(ErrorMsg(
+                            "The ELF parser failed on the file".to_owned(),
+                        )))
+                    }
+                }
+            }
+        };
+
+        Ok(Library {
+            path,
+            parsed,
+            symbols: OnceLock::new(),
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .expect("Cannot be empty because we read from the file")
+    }
+
+    /// The name the loader identifies the library by: its DT_SONAME, or the file name if it does
+    /// not have one.
+    pub fn get_soname(&self) -> OsString {
+        match self.get_elf().soname {
+            Some(soname) => OsString::from(soname),
+            None => self.get_name().to_owned(),
+        }
+    }
+
+    pub fn get_bytes(&self) -> &[u8] {
+        self.parsed.borrow_bytes()
+    }
+
+    /// Read an unsigned integer of `size` bytes (in the byte order of the ELF file) at the given
+    /// virtual address.
+    fn read_uint_at_vaddr(&self, elf: &Elf, vaddr: u64, size: usize) -> Option<u64> {
+        let offset = vaddr_to_offset(elf, vaddr)?;
+        let bytes = self.get_bytes().get(offset..offset + size)?;
+        let mut value = 0u64;
+        for (i, &b) in bytes.iter().enumerate() {
+            let shift = if elf.little_endian { i * 8 } else { (size - 1 - i) * 8 };
+            value |= (b as u64) << shift;
+        }
+        Some(value)
+    }
+
+    /// Read a u16 (in the byte order of the ELF file) at the given virtual address.
+    pub fn read_u16_at_vaddr(&self, elf: &Elf, vaddr: u64) -> Option<u16> {
+        self.read_uint_at_vaddr(elf, vaddr, 2).map(|v| v as u16)
+    }
+
+    /// Read a u32 (in the byte order of the ELF file) at the given virtual address.
+    pub fn read_u32_at_vaddr(&self, elf: &Elf, vaddr: u64) -> Option<u32> {
+        self.read_uint_at_vaddr(elf, vaddr, 4).map(|v| v as u32)
+    }
+
+    pub fn get_elf(&self) -> &Elf<'_> {
+        self.parsed.borrow_elf()
+    }
+
+    /// The DT_RPATH and DT_RUNPATH entries of the library (as they are written, e.g., with
+    /// $ORIGIN) together with their origin ("rpath" or "runpath").
+    pub fn embedded_search_paths(&self) -> Vec<(&'static str, String)> {
+        let elf = self.get_elf();
+        match DynInfo::from_elf(elf) {
+            Ok(dyninfo) => dyninfo
+                .rpath
+                .iter()
+                .map(|entry| ("rpath", entry.to_string()))
+                .chain(dyninfo.runpath.iter().map(|entry| ("runpath", entry.to_string())))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The values of DT_FLAGS and DT_FLAGS_1 of the library (0 if they are not present).
+    pub fn dynamic_flags(&self) -> (u64, u64) {
+        let elf = self.get_elf();
+        DynInfo::from_elf(elf).map_or((0, 0), |dyninfo| (dyninfo.flags, dyninfo.flags_1))
+    }
+
+    /// Whether the library was linked with -Bsymbolic (DT_SYMBOLIC or DF_SYMBOLIC), so that the
+    /// loader binds its references to its own definitions before searching the lookup scope.
+    pub fn is_symbolic(&self) -> bool {
+        let elf = self.get_elf();
+        DynInfo::from_elf(elf).is_ok_and(|dyninfo| {
+            dyninfo.symbolic || dyninfo.flags & DF_SYMBOLIC != 0
+        })
+    }
+
+    /// The dynamic symbols of the library (extracted only once, even if the library is shared
+    /// between multiple analyses).
+    pub fn symbols(&self) -> &LibrarySymbols {
+        self.symbols
+            .get_or_init(|| LibrarySymbols::of_library(self))
+    }
+}
+
+/// The properties of an ELF file that have to match for it to be loadable by another one: class
+/// (32/64 bit), data encoding (endianness) and machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElfArch {
+    class: u8,
+    data: u8,
+    machine: u16,
+}
+
+impl ElfArch {
+    /// Read the architecture from the ELF header of a file. Returns `None` if the file cannot be
+    /// read or is not an ELF file.
+    pub fn of_file(path: &Path, files: &dyn FileProvider) -> Option<ElfArch> {
+        let ident = files.read_head(path, 20).ok()?;
+        if ident.len() < 20 || &ident[..4] != b"\x7fELF" {
+            return None;
+        }
+        let (class, data) = (ident[4], ident[5]);
+        let machine = if data == 2 {
+            (ident[18] as u16) << 8 | ident[19] as u16
+        } else {
+            ident[18] as u16 | (ident[19] as u16) << 8
+        };
+        Some(ElfArch {
+            class,
+            data,
+            machine,
+        })
+    }
+}
+
+impl fmt::Display for ElfArch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-bit {}-endian {}",
+            if self.class == 2 { 64 } else { 32 },
+            if self.data == 2 { "big" } else { "little" },
+            machine_to_str(self.machine)
+        )
+    }
+}
+
+/// Describes how a dependency was resolved successfully on behalf of a dependent library.
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub dependent_lib: PathBuf,
+    pub lib_name: String,
+    pub directory: PathBuf,
+    pub origin: &'static str,
+    /// The object whose search path entry produced the directory: the dependent itself, or, for
+    /// inherited DT_RPATH entries, the object that (transitively) loaded it
+    pub entry_owner: PathBuf,
+}
+
+/// A library that a filter (see DT_FILTER) passes symbol lookups on to.
+#[derive(Debug, Clone)]
+pub struct Filtee {
+    pub name: String,
+    /// The filtee is optional (DT_AUXILIARY): If it cannot be found, the symbols are looked up
+    /// in the filter itself (as they are for symbols that a filtee does not provide).
+    pub auxiliary: bool,
+}
+
+/// The origin of DT_RPATH entries that an object without DT_RUNPATH inherits from the objects
+/// that loaded it.
+pub const INHERITED_RPATH: &str = "inherited rpath";
+
+/// Search location origins that are derived from the environment of the loading process.
+const ENVIRONMENT_ORIGINS: &[&str] = &["LD_LIBRARY_PATH"];
+
+impl Resolution {
+    /// Whether the resolution would change with the environment (shell, user) the binary is
+    /// started from.
+    pub fn is_environment_dependent(&self) -> bool {
+        ENVIRONMENT_ORIGINS.contains(&self.origin)
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "via {} entry {:?} for {:?}",
+               self.origin,
+               self.directory,
+               self.dependent_lib)?;
+        if self.entry_owner != self.dependent_lib {
+            write!(f, " (of {:?})", self.entry_owner)?;
+        }
+        Ok(())
+    }
+}
+
+/// A file of another architecture that the search for a dependency skipped (as the loader does)
+/// before it found a compatible one.
+#[derive(Debug, Clone)]
+pub struct SkippedCandidate {
+    pub dependent_lib: PathBuf,
+    pub lib_name: String,
+    pub path: PathBuf,
+    pub resolve_path: PathBuf,
+}
+
+impl SkippedCandidate {
+    /// A stable fingerprint of the note (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "skipped-incompatible",
+            &[&normalize_path(&self.dependent_lib), &self.lib_name, &normalize_path(&self.path)],
+        )
+    }
+}
+
+impl fmt::Display for SkippedCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: Skipped {:?} for dependency {:?} (wrong architecture), resolved to {:?} instead",
+               self.dependent_lib,
+               self.path,
+               self.lib_name,
+               self.resolve_path)
+    }
+}
+
+pub enum LibResolveProblem {
+    Unresolved {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        locations: LibraryLocations,
+    },
+    UnresolvedButPreviouslyResolved {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        locations: LibraryLocations,
+        prev_resolved_path: PathBuf,
+        first_resolver: PathBuf,
+    },
+    ResolveConflict {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        resolve_path: PathBuf,
+        locations: LibraryLocations,
+        prev_resolved_path: PathBuf,
+        first_resolver: PathBuf,
+    },
+    IgnoredInSecureExecution {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        ignored_path: PathBuf,
+        origin: &'static str,
+        directory: PathBuf,
+    },
+    RelativeLocation {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        resolve_path: PathBuf,
+        origin: &'static str,
+        directory: PathBuf,
+    },
+    OutsideAllowedPrefixes {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        resolve_path: PathBuf,
+    },
+    EnvironmentDependent {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        resolve_path: PathBuf,
+        origin: &'static str,
+        directory: PathBuf,
+    },
+    LoaderMismatch {
+        dependent_lib: PathBuf,
+        loader: PathBuf,
+        libc: PathBuf,
+        description: String,
+    },
+    /// The dependency only matches a file whose name differs in case, so it is only resolved on
+    /// case-insensitive filesystems.
+    CaseMismatch {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        candidate: PathBuf,
+    },
+    /// The dependency only matches files of another architecture (machine, class or byte order),
+    /// which the loader skips.
+    ArchMismatch {
+        dependent_lib: PathBuf,
+        lib_name: String,
+        lib_path: PathBuf,
+        expected: ElfArch,
+        actual: ElfArch,
+    },
+}
+
+impl LibResolveProblem {
+    /// The library whose dependency could not be resolved properly.
+    pub fn dependent_lib(&self) -> &Path {
+        match self {
+            &LibResolveProblem::Unresolved {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::UnresolvedButPreviouslyResolved {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::ResolveConflict {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::IgnoredInSecureExecution {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::RelativeLocation {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::OutsideAllowedPrefixes {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::EnvironmentDependent {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::LoaderMismatch {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::CaseMismatch {
+                ref dependent_lib, ..
+            }
+            | &LibResolveProblem::ArchMismatch {
+                ref dependent_lib, ..
+            } => dependent_lib,
+        }
+    }
+
+    /// A short, stable identifier of the kind of problem.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            LibResolveProblem::Unresolved { .. } => "unresolved",
+            LibResolveProblem::UnresolvedButPreviouslyResolved { .. } => {
+                "unresolved-but-previously-resolved"
+            }
+            LibResolveProblem::ResolveConflict { .. } => "resolve-conflict",
+            LibResolveProblem::IgnoredInSecureExecution { .. } => "ignored-in-secure-execution",
+            LibResolveProblem::RelativeLocation { .. } => "relative-location",
+            LibResolveProblem::OutsideAllowedPrefixes { .. } => "outside-allowed-prefixes",
+            LibResolveProblem::EnvironmentDependent { .. } => "environment-dependent",
+            LibResolveProblem::LoaderMismatch { .. } => "loader-mismatch",
+            LibResolveProblem::CaseMismatch { .. } => "case-mismatch",
+            LibResolveProblem::ArchMismatch { .. } => "arch-mismatch",
+        }
+    }
+
+    /// The name of the needed library the problem is about (if it is about a single dependency).
+    pub fn lib_name(&self) -> Option<&str> {
+        match self {
+            &LibResolveProblem::Unresolved { ref lib_name, .. }
+            | &LibResolveProblem::UnresolvedButPreviouslyResolved { ref lib_name, .. }
+            | &LibResolveProblem::ResolveConflict { ref lib_name, .. }
+            | &LibResolveProblem::IgnoredInSecureExecution { ref lib_name, .. }
+            | &LibResolveProblem::RelativeLocation { ref lib_name, .. }
+            | &LibResolveProblem::OutsideAllowedPrefixes { ref lib_name, .. }
+            | &LibResolveProblem::EnvironmentDependent { ref lib_name, .. }
+            | &LibResolveProblem::CaseMismatch { ref lib_name, .. }
+            | &LibResolveProblem::ArchMismatch { ref lib_name, .. } => Some(lib_name),
+            &LibResolveProblem::LoaderMismatch { .. } => None,
+        }
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let dependent_lib = normalize_path(self.dependent_lib());
+        let lib_name = match self {
+            LibResolveProblem::LoaderMismatch { libc, .. } => normalize_path(libc),
+            _ => self.lib_name().unwrap_or_default().to_owned(),
+        };
+        fingerprint(self.kind(), &[&dependent_lib, &lib_name])
+    }
+}
+
+impl fmt::Display for LibResolveProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LibResolveProblem::Unresolved {
+                dependent_lib,
+                lib_name,
+                locations,
+            } => {
+                write!(f, "{:?}: Could not resolve dependency to library {:?}. Search locations are: {}",
+                       dependent_lib,
+                       lib_name,
+                       locations)
+            },
+            LibResolveProblem::UnresolvedButPreviouslyResolved {
+                dependent_lib,
+                lib_name,
+                locations,
+                prev_resolved_path,
+                first_resolver,
+            } => {
+                write!(f, "{:?}: Could not resolve dependency {:?}, but it is already resolved to {:?} by {:?}. Search locations are: {}",
+                       dependent_lib,
+                       lib_name,
+                       prev_resolved_path,
+                       first_resolver,
+                       locations)
+            },
+            LibResolveProblem::ResolveConflict {
+                dependent_lib,
+                lib_name,
+                resolve_path,
+                locations,
+                prev_resolved_path,
+                first_resolver,
+            } => {
+                write!(f, "{:?}: Would resolve dependency {:?} to {:?}, but it is already resolved to {:?} by {:?}. Search locations are: {}",
+                       dependent_lib,
+                       lib_name,
+                       resolve_path,
+                       prev_resolved_path,
+                       first_resolver,
+                       locations)
+            },
+            LibResolveProblem::IgnoredInSecureExecution {
+                dependent_lib,
+                lib_name,
+                ignored_path,
+                origin,
+                directory,
+            } => {
+                write!(f, "{:?}: Dependency {:?} would be resolved to {:?} via {} entry {:?}, but this entry is ignored in secure-execution mode",
+                       dependent_lib,
+                       lib_name,
+                       ignored_path,
+                       origin,
+                       directory)
+            },
+            LibResolveProblem::RelativeLocation {
+                dependent_lib,
+                lib_name,
+                resolve_path,
+                origin,
+                directory,
+            } => {
+                write!(f, "{:?}: Dependency {:?} is resolved to {:?} via relative {} entry {:?}, so the result depends on the current working directory",
+                       dependent_lib,
+                       lib_name,
+                       resolve_path,
+                       origin,
+                       directory)
+            },
+            LibResolveProblem::OutsideAllowedPrefixes {
+                dependent_lib,
+                lib_name,
+                resolve_path,
+            } => {
+                write!(f, "{:?}: Dependency {:?} is resolved to {:?}, which is outside of the allowed prefixes",
+                       dependent_lib,
+                       lib_name,
+                       resolve_path)
+            },
+            LibResolveProblem::EnvironmentDependent {
+                dependent_lib,
+                lib_name,
+                resolve_path,
+                origin,
+                directory,
+            } => {
+                write!(f, "{:?}: Dependency {:?} is resolved to {:?} via {} entry {:?}, which depends on the environment",
+                       dependent_lib,
+                       lib_name,
+                       resolve_path,
+                       origin,
+                       directory)
+            },
+            LibResolveProblem::LoaderMismatch {
+                dependent_lib,
+                loader,
+                libc,
+                description,
+            } => {
+                write!(f, "{:?}: libc {:?} {} (loader {:?}). The loader and libc of different glibc builds are incompatible",
+                       dependent_lib,
+                       libc,
+                       description,
+                       loader)
+            },
+            LibResolveProblem::CaseMismatch {
+                dependent_lib,
+                lib_name,
+                candidate,
+            } => {
+                write!(f, "{:?}: Dependency {:?} only matches {:?} when ignoring case, so it is resolved on case-insensitive filesystems only",
+                       dependent_lib,
+                       lib_name,
+                       candidate)
+            },
+            LibResolveProblem::ArchMismatch {
+                dependent_lib,
+                lib_name,
+                lib_path,
+                expected,
+                actual,
+            } => {
+                write!(f, "{:?}: Dependency {:?} is only found as {:?}, which is a {} object (expected {}), so the loader skips it",
+                       dependent_lib,
+                       lib_name,
+                       lib_path,
+                       actual,
+                       expected)
+            },
+        }
+    }
+}
+
+/// How libraries that are found under different paths are identified. Libraries with the same
+/// identity are treated as one (the path under which it was found first is used), so that, e.g.,
+/// copies of a library in bind mounts or overlays do not result in conflicts or duplicate symbols.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum LibraryIdentity {
+    #[default]
+    Path,     // The path as found
+    RealPath, // The path with all symbolic links resolved
+    BuildId,  // The GNU build id (or the content hash for libraries without one)
+    Hash,     // The content hash
+}
+
+pub const LIBRARY_IDENTITY_NAMES: &[&str] = &["path", "realpath", "buildid", "hash"];
+
+impl FromStr for LibraryIdentity {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(LibraryIdentity::Path),
+            "realpath" => Ok(LibraryIdentity::RealPath),
+            "buildid" => Ok(LibraryIdentity::BuildId),
+            "hash" => Ok(LibraryIdentity::Hash),
+            other => Err(format!(
+                "Invalid library identity '{}'. Valid identities are: {}",
+                other,
+                LIBRARY_IDENTITY_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+
+impl fmt::Display for LibraryIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LibraryIdentity::Path => write!(f, "path"),
+            LibraryIdentity::RealPath => write!(f, "realpath"),
+            LibraryIdentity::BuildId => write!(f, "buildid"),
+            LibraryIdentity::Hash => write!(f, "hash"),
+        }
+    }
+}
+
+impl LibraryIdentity {
+    /// The identity of the library at `path`, or `None` if it is identified by its path (or
+    /// cannot be read).
+    fn key(&self, path: &Path, cache: &LibraryCache, files: &dyn FileProvider) -> Option<String> {
+        let hash_key = |lib: &Library| format!("hash:{}", content_hash(lib.get_bytes()));
+        match *self {
+            LibraryIdentity::Path => None,
+            LibraryIdentity::RealPath => files
+                .canonicalize(path)
+                .ok()
+                .map(|p| format!("path:{}", p.to_string_lossy())),
+            LibraryIdentity::BuildId => cache.get_or_open(path, files).ok().map(|lib| {
+                match ObjectInfo::of_library(&lib).build_id {
+                    Some(build_id) => format!("buildid:{}", build_id),
+                    None => hash_key(&lib),
+                }
+            }),
+            LibraryIdentity::Hash => cache
+                .get_or_open(path, files)
+                .ok()
+                .map(|lib| hash_key(&lib)),
+        }
+    }
+}
+
+/// The order in which the libraries of a closure are visited. The loader builds its list of
+/// loaded objects breadth-first, which decides, e.g., which of several conflicting resolutions of
+/// a library name is used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Default)]
+pub enum Traversal {
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+}
+
+pub const TRAVERSAL_NAMES: &[&str] = &["bfs", "dfs"];
+
+impl FromStr for Traversal {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bfs" => Ok(Traversal::BreadthFirst),
+            "dfs" => Ok(Traversal::DepthFirst),
+            other => Err(format!(
+                "Invalid traversal '{}'. Valid traversals are: {}",
+                other,
+                TRAVERSAL_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+
+impl fmt::Display for Traversal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Traversal::BreadthFirst => write!(f, "bfs"),
+            Traversal::DepthFirst => write!(f, "dfs"),
+        }
+    }
+}
+
+/// Settings that control how the dependencies of a file are searched for.
+#[derive(Debug, Clone)]
+pub struct ResolveSettings {
+    pub search_methods: Vec<LibSearchMethod>,
+    pub secure_execution: bool,
+    pub sysroot: Option<PathBuf>, // Root directory of the analyzed system (if not /)
+    pub identity: LibraryIdentity,
+    pub traversal: Traversal, // The order in which the closure is visited (see Traversal)
+    pub environment: Environment, // The environment of the loading process (for LD_LIBRARY_PATH)
+    pub files: Arc<dyn FileProvider>, // Access to the files (RealFs unless analyzing an image)
+}
+
+/// The dependency closure of an analyzed file, as the loader would resolve it, together with all
+/// problems found while resolving it.
+pub struct LibraryDependencies {
+    pub root: PathBuf, // The analyzed file itself
+    pub opened_libs: HashMap<PathBuf, Arc<Library>>, // Libraries that have been opened and analyzed
+    pub resolved: HashMap<OsString, PathBuf>, // A map that shows how librarynames (the DT_SONAME, e.g., libfoo.so.1) map to actual files (e.g., /usr/local/lib/libfoo.so.1.2)
+    pub aliases: HashMap<OsString, PathBuf>, // Other names of resolved libraries, which they can also be found by: file names that differ from the DT_SONAME (e.g., libfoo.so) and the names they were requested by
+    pub reverse_dependencies: HashMap<PathBuf, Vec<PathBuf>>, // Mapping resolved libraries (paths!) to those libraries (paths!) that depend on them
+    pub resolutions: HashMap<PathBuf, Vec<Resolution>>, // Mapping resolved libraries (paths!) to the search locations that produced them for each dependent
+    pub needed: HashMap<PathBuf, Vec<String>>, // Mapping opened libraries (paths!) to the names of the libraries they depend on (DT_NEEDED)
+    pub filtees: HashMap<PathBuf, Vec<Filtee>>, // Mapping opened libraries (paths!) that are filters to the libraries their symbols are looked up in (DT_FILTER, DT_AUXILIARY)
+    pub search_locations: HashMap<PathBuf, LibraryLocations>, // Mapping opened libraries (paths!) to the locations searched for their dependencies
+    pub problems: Vec<LibResolveProblem>, // Collection of all problems that appeared while resolving dependency tree
+    pub skipped_incompatible: Vec<SkippedCandidate>, // Files of another architecture that the search skipped before finding a compatible one
+    pub identities: HashMap<String, PathBuf>, // Mapping library identities (see LibraryIdentity) to the path of the opened library
+    pub files: Arc<dyn FileProvider>, // Access to the files the closure was resolved from (see ResolveSettings)
+    pub profile: ParseProfile, // How strictly the files of the closure were checked (see LibraryCache)
+}
+
+/// How strictly files are checked before they are analyzed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseProfile {
+    /// Files are trusted to be well-formed (as installed libraries are)
+    Default,
+    /// Files are untrusted (e.g., for malware triage or user uploads): Malformed files are
+    /// rejected with an error instead of causing panics or pathological memory use
+    Hardened,
+}
+
+/// Libraries that have been read from disk and parsed, shared between the analyses of multiple
+/// files (also across threads), so that common dependencies are only read, parsed and checked
+/// once (their symbols are extracted once on first use as well).
+pub struct LibraryCache {
+    libs: Mutex<HashMap<PathBuf, Arc<Library>>>,
+    profile: ParseProfile,
+}
+
+impl Default for LibraryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LibraryCache {
+    pub fn new() -> Self {
+        Self::with_profile(ParseProfile::Default)
+    }
+
+    pub fn with_profile(profile: ParseProfile) -> Self {
+        LibraryCache {
+            libs: Mutex::new(HashMap::new()),
+            profile,
+        }
+    }
+
+    /// The library at `path`, read and parsed with the profile of the cache (unless it has been
+    /// opened before).
+    pub fn get_or_open(
+        &self,
+        path: &Path,
+        files: &dyn FileProvider,
+    ) -> Result<Arc<Library>, Box<dyn Error>> {
+        if let Some(lib) = self.libs.lock().expect("Cache lock poisoned").get(path) {
+            return Ok(lib.clone());
+        }
+
+        // Read the library without holding the lock, so that other threads can continue. In the
+        // rare case that two threads read the same library concurrently, the first one wins.
+        let lib = Arc::new(Library::try_from_path(path.to_owned(), files, self.profile)?);
+        Ok(self.libs
+            .lock()
+            .expect("Cache lock poisoned")
+            .entry(path.to_path_buf())
+            .or_insert(lib)
+            .clone())
+    }
+
+    /// Open all given libraries that are not cached yet in parallel, so that analyzing them
+    /// afterwards does not wait for reading and parsing them one by one. Errors are ignored here;
+    /// they are reported when the library is opened for the analysis.
+    fn prefetch<'a, I: Iterator<Item = &'a PathBuf>>(&self, paths: I, files: &dyn FileProvider) {
+        let uncached = {
+            let libs = self.libs.lock().expect("Cache lock poisoned");
+            let mut uncached = paths
+                .filter(|path| !libs.contains_key(*path))
+                .collect::<Vec<_>>();
+            uncached.sort();
+            uncached.dedup();
+            uncached
+        };
+        if uncached.len() < 2 {
+            return;
+        }
+        let _ = parallel_map(uncached, |path| self.get_or_open(path, files).is_ok());
+    }
+}
+
+/// Receives progress events of the analysis of (possibly many) files, e.g., to show a progress bar.
+/// The events of different files may arrive concurrently.
+pub trait Progress: Sync {
+    /// A phase (e.g., "resolving") of the analysis of `total` files starts.
+    fn start(&self, _phase: &str, _total: usize) {}
+    /// The library at `path` of the closure of `root` has been analyzed.
+    fn library_analyzed(&self, _root: &Path, _path: &Path) {}
+    /// The current phase is done for `root` (successfully or not).
+    fn file_analyzed(&self, _root: &Path) {}
+}
+
+/// Ignores all progress events.
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
+
+/// Analyze the dependencies of all given files (each with its own settings) in parallel, sharing
+/// opened libraries via `cache`. The results are returned in the order of `jobs`.
+pub fn try_find_for_elfs(
+    jobs: &[(PathBuf, ResolveSettings)],
+    cache: &LibraryCache,
+) -> Vec<Result<LibraryDependencies, String>> {
+    try_find_for_elfs_with_progress(jobs, cache, &NoProgress)
+}
+
+/// Like `try_find_for_elfs`, reporting the progress to `progress`.
+pub fn try_find_for_elfs_with_progress(
+    jobs: &[(PathBuf, ResolveSettings)],
+    cache: &LibraryCache,
+    progress: &dyn Progress,
+) -> Vec<Result<LibraryDependencies, String>> {
+    progress.start("resolving", jobs.len());
+    let num_threads = ::std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(jobs.len());
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new((0..jobs.len()).map(|_| None).collect::<Vec<_>>());
+
+    ::std::thread::scope(|scope| {
+        for _ in 0..num_threads {
+            let _ = scope.spawn(|| loop {
+                let i = next_job.fetch_add(1, Ordering::SeqCst);
+                if i >= jobs.len() {
+                    break;
+                }
+                let (ref path, ref settings) = jobs[i];
+                let result = LibraryDependencies::try_find_for_elf_with_progress(
+                    path, settings, cache, progress,
+                ).map_err(|e| format!("{:?}: {}", path, e));
+                progress.file_analyzed(path);
+                results.lock().expect("Results lock poisoned")[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("Results lock poisoned")
+        .into_iter()
+        .map(|r| r.expect("Every job has been processed"))
+        .collect()
+}
+
+impl LibraryDependencies {
+    pub fn try_find_for_elf(
+        elf_path: &Path,
+        settings: &ResolveSettings,
+    ) -> Result<LibraryDependencies, Box<dyn Error>> {
+        Self::try_find_for_elf_cached(elf_path, settings, &LibraryCache::new())
+    }
+
+    pub fn try_find_for_elf_cached(
+        elf_path: &Path,
+        settings: &ResolveSettings,
+        cache: &LibraryCache,
+    ) -> Result<LibraryDependencies, Box<dyn Error>> {
+        Self::try_find_for_elf_with_progress(elf_path, settings, cache, &NoProgress)
+    }
+
+    /// Like `try_find_for_elf_cached`, reporting every analyzed library to `progress`.
+    pub fn try_find_for_elf_with_progress(
+        elf_path: &Path,
+        settings: &ResolveSettings,
+        cache: &LibraryCache,
+        progress: &dyn Progress,
+    ) -> Result<LibraryDependencies, Box<dyn Error>> {
+        let mut result = LibraryDependencies {
+            root: elf_path.to_path_buf(),
+            resolved: HashMap::new(),
+            aliases: HashMap::new(),
+            opened_libs: HashMap::new(),
+            reverse_dependencies: HashMap::new(),
+            resolutions: HashMap::new(),
+            needed: HashMap::new(),
+            filtees: HashMap::new(),
+            search_locations: HashMap::new(),
+            problems: Vec::new(),
+            skipped_incompatible: Vec::new(),
+            identities: HashMap::new(),
+            files: settings.files.clone(),
+            profile: cache.profile,
+        };
+        collect_libs(elf_path, settings, cache, progress, &mut result)?;
+        Ok(result)
+    }
+
+    /// The path of the library that a dependency named `lib_name` (e.g., in DT_NEEDED) resolves
+    /// to, looking it up by DT_SONAME and by file name like the loader does.
+    pub fn lookup(&self, lib_name: &OsStr) -> Option<&PathBuf> {
+        self.resolved
+            .get(lib_name)
+            .or_else(|| self.aliases.get(lib_name))
+    }
+
+    /// The object at `path` (e.g., a plugin candidate), read and parsed like the libraries of the
+    /// closure (i.e., with the same file provider and parse profile).
+    pub fn open_library(&self, path: &Path) -> Result<Arc<Library>, Box<dyn Error>> {
+        match self.opened_libs.get(path) {
+            Some(lib) => Ok(lib.clone()),
+            None => Ok(Arc::new(Library::try_from_path(
+                path.to_path_buf(),
+                &*self.files,
+                self.profile,
+            )?)),
+        }
+    }
+
+    /// The DT_RPATH entries (with the object carrying them) that the loader searches for the
+    /// dependencies of the library at `lib_path` after its own: those of the object that loaded
+    /// it (the first one depending on it), of the object that loaded that one and so on, and
+    /// finally those of the analyzed file. Objects with DT_RUNPATH do not pass on their DT_RPATH.
+    pub fn inherited_rpath(&self, lib_path: &Path) -> Vec<(String, PathBuf)> {
+        let mut chain = Vec::new();
+        let mut current = lib_path;
+        while let Some(loader) = self.reverse_dependencies
+            .get(current)
+            .and_then(|dependents| dependents.first())
+        {
+            if loader == lib_path || chain.contains(&loader) {
+                break;
+            }
+            chain.push(loader);
+            current = loader;
+        }
+        if lib_path != self.root && !chain.contains(&&self.root) {
+            chain.push(&self.root);
+        }
+
+        let mut entries = Vec::new();
+        for object in chain {
+            let embedded = match self.opened_libs.get(object) {
+                Some(lib) => lib.embedded_search_paths(),
+                None => continue,
+            };
+            if embedded.iter().any(|&(origin, _)| origin == "runpath") {
+                continue;
+            }
+            entries.extend(
+                embedded
+                    .into_iter()
+                    .map(|(_, entry)| (entry, object.clone())),
+            );
+        }
+        entries
+    }
+
+    /// Iterate over the paths of the libraries that the (opened) library at `lib_path` depends on
+    /// and which have been resolved.
+    pub fn resolved_dependencies<'a>(
+        &'a self,
+        lib_path: &Path,
+    ) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        self.loaded_names(lib_path)
+            .filter_map(move |name| self.lookup(OsStr::new(name)))
+    }
+
+    /// Iterate over the names of the libraries that the loader loads for the (opened) library at
+    /// `lib_path`: its DT_NEEDED entries followed by its filtees.
+    fn loaded_names<'a>(&'a self, lib_path: &Path) -> impl Iterator<Item = &'a String> + 'a {
+        self.needed
+            .get(lib_path)
+            .into_iter()
+            .flat_map(|names| names.iter())
+            .chain(
+                self.filtees
+                    .get(lib_path)
+                    .into_iter()
+                    .flat_map(|filtees| filtees.iter().map(|filtee| &filtee.name)),
+            )
+    }
+
+    /// Iterate over the paths of the resolved filtees of the (opened) library at `lib_path`. The
+    /// loader looks symbols up in these before the filter itself.
+    pub fn resolved_filtees<'a>(
+        &'a self,
+        lib_path: &Path,
+    ) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        self.filtees
+            .get(lib_path)
+            .into_iter()
+            .flat_map(|filtees| filtees.iter())
+            .filter_map(move |filtee| self.lookup(OsStr::new(&filtee.name)))
+    }
+
+    /// List the names of all (transitive) dependencies of the root in breadth-first order (similar
+    /// to ldd), together with the path they are resolved to (if any).
+    pub fn needed_in_load_order(&self) -> Vec<(&str, Option<&PathBuf>)> {
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(&self.root);
+        while let Some(path) = queue.pop_front() {
+            for name in self.loaded_names(path) {
+                if !seen.insert(name.as_str()) {
+                    continue;
+                }
+                let resolved = self.lookup(OsStr::new(name));
+                result.push((name.as_str(), resolved));
+                queue.extend(resolved);
+            }
+        }
+        result
+    }
+
+    /// Compute the dependency depth (i.e., length of the shortest dependency path from the root) of
+    /// every opened library.
+    pub fn depths(&self) -> HashMap<&PathBuf, usize> {
+        let mut depths = HashMap::new();
+        let mut queue = VecDeque::new();
+        let _ = depths.insert(&self.root, 0);
+        queue.push_back(&self.root);
+        while let Some(path) = queue.pop_front() {
+            let depth = depths[path];
+            for dependency in self.resolved_dependencies(path) {
+                if !depths.contains_key(dependency) {
+                    let _ = depths.insert(dependency, depth + 1);
+                    queue.push_back(dependency);
+                }
+            }
+        }
+        depths
+    }
+
+    /// List all libraries of the closure (including the root) in reverse topological order, i.e.,
+    /// every library is listed after all of its dependencies (unless they form a cycle).
+    pub fn topological_order(&self) -> Vec<&PathBuf> {
+        fn visit<'a>(
+            libs: &'a LibraryDependencies,
+            path: &'a PathBuf,
+            visited: &mut HashSet<&'a PathBuf>,
+            order: &mut Vec<&'a PathBuf>,
+        ) {
+            if !visited.insert(path) {
+                return;
+            }
+            for dependency in libs.resolved_dependencies(path) {
+                visit(libs, dependency, visited, order);
+            }
+            order.push(path);
+        }
+
+        let mut order = Vec::new();
+        visit(self, &self.root, &mut HashSet::new(), &mut order);
+        order
+    }
 
-impl Library {
-    fn try_from_path(path: PathBuf) -> Result<Self, Box<Error>> {
-        let bytes = {
-            use std::io::Read;
-            let mut file = fs::File::open(&path)?;
-            let mut bytes = Vec::new();
-            file.read_to_end(&mut bytes)?;
-            bytes
+    /// Find all opened libraries that either match one of the given patterns (by file name) or are
+    /// only loaded because a library matching one of the patterns depends on them, i.e., every
+    /// dependency path from the root to them passes through a matching library.
+    pub fn only_reachable_via(&self, patterns: &[Pattern]) -> HashSet<PathBuf> {
+        let matches = |path: &Path| {
+            let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+            patterns.iter().any(|pattern| pattern.matches(&name))
         };
 
-        // Try once to see if it's a valid Elf file, but we do not actually use it here
-        {
-            let _elf = Elf::parse(&bytes)?;
+        let mut reachable = HashSet::new();
+        let mut stack = vec![&self.root];
+        while let Some(path) = stack.pop() {
+            if matches(path) || !reachable.insert(path.clone()) {
+                continue;
+            }
+            stack.extend(self.resolved_dependencies(path));
         }
 
-        Ok(Library {
-            path: path,
-            bytes: bytes,
-        })
+        self.opened_libs
+            .keys()
+            .filter(|path| !reachable.contains(*path))
+            .cloned()
+            .collect()
     }
 
-    fn get_name(&self) -> &OsStr {
-        self.path
-            .file_name()
-            .expect("Cannot be empty because we read from the file")
+    /// Iterate over all resolutions (and the path they resolve to) that depend on the environment.
+    pub fn environment_dependent_resolutions<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a Resolution)> + 'a {
+        self.resolutions
+            .iter()
+            .flat_map(|(path, resolutions)| resolutions.iter().map(move |r| (path, r)))
+            .filter(|&(_, resolution)| resolution.is_environment_dependent())
     }
 
-    pub fn get_elf<'a>(&'a self) -> Elf<'a> {
-        Elf::parse(&self.bytes).expect("Invariant: Valid ELF")
+    /// Note all libraries that are resolved to paths outside of the given prefixes as problems.
+    pub fn report_outside_prefixes(&mut self, prefixes: &[PathBuf]) {
+        // Symlinks and ".." components are resolved, as they could otherwise be used to escape
+        // the prefixes. This applies to the prefixes as well (e.g., /lib on a merged-/usr system).
+        let canonical_prefixes = prefixes
+            .iter()
+            .map(|prefix| self.files.canonicalize(prefix).unwrap_or(prefix.clone()))
+            .collect::<Vec<_>>();
+        let mut outside = self.resolutions
+            .iter()
+            .filter(|&(path, _)| {
+                let canonical_path = self.files.canonicalize(path).unwrap_or(path.clone());
+                !canonical_prefixes
+                    .iter()
+                    .any(|prefix| canonical_path.starts_with(prefix))
+            })
+            .flat_map(|(path, resolutions)| resolutions.iter().map(move |r| (path, r)))
+            .collect::<Vec<_>>();
+        outside.sort_by_key(|&(path, _)| path);
+        let new_problems = outside
+            .into_iter()
+            .map(|(path, resolution)| LibResolveProblem::OutsideAllowedPrefixes {
+                dependent_lib: resolution.dependent_lib.clone(),
+                lib_name: resolution.lib_name.clone(),
+                resolve_path: path.clone(),
+            })
+            .collect::<Vec<_>>();
+        self.problems.extend(new_problems);
     }
-}
 
-pub enum LibResolveProblem {
-    Unresolved {
-        dependent_lib: PathBuf,
-        lib_name: String,
-        locations: LibraryLocations,
-    },
-    UnresolvedButPreviouslyResolved {
-        dependent_lib: PathBuf,
-        lib_name: String,
-        locations: LibraryLocations,
-        prev_resolved_path: PathBuf,
-        first_resolver: PathBuf,
-    },
-    ResolveConflict {
-        dependent_lib: PathBuf,
-        lib_name: String,
-        resolve_path: PathBuf,
-        locations: LibraryLocations,
-        prev_resolved_path: PathBuf,
-        first_resolver: PathBuf,
-    },
-}
+    /// Note all dependencies that only match a file when ignoring case as problems, i.e.,
+    /// unresolved dependencies that would be resolved on a case-insensitive filesystem and
+    /// resolved dependencies whose file name differs in case (which are only found because the
+    /// analyzed tree is on a case-insensitive filesystem).
+    pub fn report_case_mismatches(&mut self) {
+        // The file in `dir` that matches `lib_name` when ignoring case (but not exactly), if any.
+        let mut listings: HashMap<PathBuf, Vec<OsString>> = HashMap::new();
+        let mut case_insensitive_match = |dir: &Path, lib_name: &str| {
+            let entries = listings.entry(dir.to_path_buf()).or_insert_with(|| {
+                self.files.read_dir(dir).unwrap_or_default()
+            });
+            if entries.iter().any(|entry| entry == lib_name) {
+                return None;
+            }
+            entries
+                .iter()
+                .find(|entry| entry.to_string_lossy().eq_ignore_ascii_case(lib_name))
+                .map(|entry| dir.join(entry))
+        };
 
-impl fmt::Display for LibResolveProblem {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &LibResolveProblem::Unresolved {
-                ref dependent_lib,
-                ref lib_name,
-                ref locations,
-            } => {
-                write!(f, "{:?}: Could not resolve dependency to library {:?}. Search locations are: {}",
-                       dependent_lib,
-                       lib_name,
-                       locations)
-            },
-            &LibResolveProblem::UnresolvedButPreviouslyResolved {
-                ref dependent_lib,
-                ref lib_name,
-                ref locations,
-                ref prev_resolved_path,
-                ref first_resolver,
-            } => {
-                write!(f, "{:?}: Could not resolve dependency {:?}, but it is already resolved to {:?} by {:?}. Search locations are: {}",
-                       dependent_lib,
-                       lib_name,
-                       prev_resolved_path,
-                       first_resolver,
-                       locations)
-            },
-            &LibResolveProblem::ResolveConflict {
-                ref dependent_lib,
-                ref lib_name,
-                ref resolve_path,
-                ref locations,
-                ref prev_resolved_path,
-                ref first_resolver,
-            } => {
-                write!(f, "{:?}: Would resolve dependency {:?} to {:?}, but it is already resolved to {:?} by {:?}. Search locations are: {}",
-                       dependent_lib,
-                       lib_name,
-                       resolve_path,
-                       prev_resolved_path,
-                       first_resolver,
-                       locations)
-            },
+        let mut new_problems = Vec::new();
+        for problem in self.problems.iter() {
+            let (dependent_lib, lib_name) = match problem {
+                LibResolveProblem::Unresolved {
+                    dependent_lib,
+                    lib_name,
+                    ..
+                } => (dependent_lib, lib_name),
+                _ => continue,
+            };
+            let candidate = self.search_locations
+                .get(dependent_lib)
+                .into_iter()
+                .flat_map(|locations| locations.iter())
+                .filter_map(|(dir, _)| case_insensitive_match(dir, lib_name))
+                .next();
+            if let Some(candidate) = candidate {
+                new_problems.push(LibResolveProblem::CaseMismatch {
+                    dependent_lib: dependent_lib.clone(),
+                    lib_name: lib_name.clone(),
+                    candidate,
+                });
+            }
+        }
+
+        let mut resolutions = self.resolutions
+            .iter()
+            .flat_map(|(path, resolutions)| resolutions.iter().map(move |r| (path, r)))
+            .collect::<Vec<_>>();
+        resolutions.sort_by_key(|&(path, _)| path);
+        for (_, resolution) in resolutions {
+            if let Some(candidate) =
+                case_insensitive_match(&resolution.directory, &resolution.lib_name)
+            {
+                new_problems.push(LibResolveProblem::CaseMismatch {
+                    dependent_lib: resolution.dependent_lib.clone(),
+                    lib_name: resolution.lib_name.clone(),
+                    candidate,
+                });
+            }
         }
+        self.problems.extend(new_problems);
     }
-}
 
-pub struct LibraryDependencies {
-    pub opened_libs: HashMap<PathBuf, Library>, // Libraries that have been opened and analyzed
-    pub resolved: HashMap<OsString, PathBuf>, // A map that shows how librarynames (e.g., libfoo.so) map to actual files (e.g., /usr/local/lib/libfoo.so)
-    pub reverse_dependencies: HashMap<PathBuf, Vec<PathBuf>>, // Mapping resolved libraries (paths!) to those libraries (paths!) that depend on them
-    pub problems: Vec<LibResolveProblem>, // Collection of all problems that appeared while resolving dependency tree
+    /// Note all environment dependent resolutions as problems.
+    pub fn report_environment_dependent(&mut self) {
+        let mut env_resolutions = self.environment_dependent_resolutions().collect::<Vec<_>>();
+        env_resolutions.sort_by_key(|&(path, _)| path);
+        let new_problems = env_resolutions
+            .into_iter()
+            .map(|(path, resolution)| LibResolveProblem::EnvironmentDependent {
+                dependent_lib: resolution.dependent_lib.clone(),
+                lib_name: resolution.lib_name.clone(),
+                resolve_path: path.clone(),
+                origin: resolution.origin,
+                directory: resolution.directory.clone(),
+            })
+            .collect::<Vec<_>>();
+        self.problems.extend(new_problems);
+    }
 }
 
-impl LibraryDependencies {
-    pub fn try_find_for_elf(
-        elf_path: &Path,
-        search_methods: &[LibSearchMethod],
-    ) -> Result<LibraryDependencies, Box<Error>> {
-        let mut result = LibraryDependencies {
-            resolved: HashMap::new(),
-            opened_libs: HashMap::new(),
-            reverse_dependencies: HashMap::new(),
-            problems: Vec::new(),
-        };
-        collect_libs(elf_path, search_methods, None, &mut result)?;
-        Ok(result)
+/// Determine whether the loader would run the given file in secure-execution mode (AT_SECURE),
+/// and if so, why.
+pub fn secure_execution_reason(
+    path: &Path,
+    files: &dyn FileProvider,
+) -> Result<Option<&'static str>, Box<dyn Error>> {
+    let mode = files.mode(path)?;
+    if mode & 0o4000 != 0 {
+        return Ok(Some("setuid bit"));
     }
+    if mode & 0o2000 != 0 {
+        return Ok(Some("setgid bit"));
+    }
+    if has_file_capabilities(path) {
+        return Ok(Some("file capabilities"));
+    }
+    Ok(None)
+}
+
+fn has_file_capabilities(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => c_path,
+        Err(_) => return false,
+    };
+    const CAPABILITY_XATTR: &[u8] = b"security.capability\0";
+    let size = unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            CAPABILITY_XATTR.as_ptr() as *const libc::c_char,
+            ::std::ptr::null_mut(),
+            0,
+        )
+    };
+    size > 0
 }
 
 #[derive(Debug)]
-struct ErrorMsg(String);
+pub struct ErrorMsg(pub String);
 
 impl fmt::Display for ErrorMsg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -275,51 +1605,213 @@ impl ::std::error::Error for ErrorMsg {
     }
 }
 
+/// Map an absolute path on the analyzed system to the corresponding path inside the sysroot (if
+/// any). Relative paths are left untouched.
+pub fn reroot(path: &Path, sysroot: Option<&Path>) -> PathBuf {
+    match (sysroot, path.strip_prefix("/")) {
+        (Some(sysroot), Ok(relative)) => sysroot.join(relative),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether an RPATH or RUNPATH entry is relative to the directory of its object (i.e., contains
+/// $ORIGIN or ${ORIGIN}).
+pub fn is_origin_relative(entry: &str) -> bool {
+    entry.contains("$ORIGIN") || entry.contains("${ORIGIN}")
+}
+
+/// The directory that an RPATH or RUNPATH entry of the object at `lib_path` refers to: $ORIGIN is
+/// the directory of the object (inside the sysroot already), other (absolute) entries are paths
+/// on the analyzed system.
+pub fn expand_embedded_entry(entry: &str, lib_path: &Path, sysroot: Option<&Path>) -> PathBuf {
+    if is_origin_relative(entry) {
+        // Note: This is quite ugly. But Rust does not really provide string manipulation for
+        // paths or even CStrings. Maybe there is a crate for that? In any case this does not make
+        // too much of a (any?) difference, as long as goblin only provides elf information in the
+        // form of utf8 strings.
+        let origin = lib_path
+            .parent()
+            .unwrap_or(Path::new("/"))
+            .to_str()
+            .expect("Path not valid utf8");
+        PathBuf::from(entry.replace("${ORIGIN}", origin).replace("$ORIGIN", origin))
+    } else {
+        reroot(Path::new(entry), sysroot)
+    }
+}
+
+/// Map a fixed search directory to the corresponding directory inside the sysroot (see `reroot`),
+/// unless it already lies inside of the sysroot (e.g., because it was derived from it).
+pub fn reroot_fixed_dir(dir: &Path, sysroot: Option<&Path>) -> PathBuf {
+    match sysroot {
+        Some(sysroot) if dir.starts_with(sysroot) => dir.to_path_buf(),
+        _ => reroot(dir, sysroot),
+    }
+}
+
+/// The files of the analyzed system (i.e., inside the sysroot if one is given), accessed via
+/// absolute paths on that system.
+fn system_files(files: Arc<dyn FileProvider>, sysroot: Option<&Path>) -> Arc<dyn FileProvider> {
+    match sysroot {
+        Some(sysroot) => Arc::new(SysrootFs::new(sysroot, files)),
+        None => files,
+    }
+}
+
+/// Collect the library directories listed in the ld.so.conf file at `path` (a path on the
+/// analyzed system, i.e., inside the sysroot if one is given), following include directives.
+/// `files` provides the files of the analyzed system (see `system_files`).
 fn search_ld_so_conf(
     path: &Path,
+    files: &dyn FileProvider,
+    sysroot: Option<&Path>,
     library_locations: &mut LibraryLocations,
-) -> Result<(), Box<Error>> {
-    use std::io::BufRead;
+) -> Result<(), Box<dyn Error>> {
+    let content = files.read(path).map_err(|_| {
+        ErrorMsg(format!(
+            "Could not open ld.so.conf file: {:?}",
+            reroot(path, sysroot)
+        ))
+    })?;
 
-    let file = fs::File::open(path)
-        .map_err(|_| ErrorMsg(format!("Could not open ld.so.conf file: {:?}", path)))?;
-    let file = io::BufReader::new(&file);
-
-    const INCLUDE_PREFIX: &'static str = "include ";
-    for line in file.lines() {
-        let line = line?;
+    const INCLUDE_PREFIX: &str = "include ";
+    for line in String::from_utf8_lossy(&content).lines() {
         let line = line.trim();
         if line.is_empty() || line.starts_with("#") {
             // Comment or empty line. skip
-        } else if line.starts_with(INCLUDE_PREFIX) {
-            let included_path = &line[INCLUDE_PREFIX.len()..];
-            for glob_path in glob(included_path)? {
-                let glob_path = glob_path?;
-                search_ld_so_conf(&glob_path, library_locations)?;
+        } else if let Some(included_path) = line.strip_prefix(INCLUDE_PREFIX) {
+            // Like ldconfig, interpret relative includes relative to the including file.
+            let included_path = Path::new(included_path.trim());
+            let pattern = path.parent()
+                .unwrap_or(Path::new("/"))
+                .join(included_path);
+            for included_path in files.glob(&pattern)? {
+                search_ld_so_conf(&included_path, files, sysroot, library_locations)?;
             }
         } else {
-            library_locations.0.push((PathBuf::from(line), "ldconfig"));
+            library_locations
+                .0
+                .push((reroot(Path::new(line), sysroot), "ldconfig"));
         }
     }
     Ok(())
 }
 
-fn collect_libs(
+/// The library directories listed in the ld.so.conf file at `path` (including all included files).
+pub fn ld_so_conf_directories(
+    path: &Path,
+    files: Arc<dyn FileProvider>,
+    sysroot: Option<&Path>,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut locations = LibraryLocations(Vec::new());
+    let files = system_files(files, sysroot);
+    search_ld_so_conf(path, &*files, sysroot, &mut locations)?;
+    Ok(locations.0.into_iter().map(|(dir, _)| dir).collect())
+}
+
+/// Build the list of locations that are searched for the dependencies of the library at
+/// `lib_path`. In secure-execution mode, locations that the loader ignores for setuid/setgid or
+/// capability-carrying binaries ($ORIGIN-relative paths and LD_LIBRARY_PATH) are left out. Like
+/// the loader, the default directories (ldconfig and fixed locations) are not searched if the
+/// library is marked with DF_1_NODEFLIB. The DT_RPATH entries that the library inherits (see
+/// `LibraryDependencies::inherited_rpath`) are searched after its own.
+fn library_locations(
+    lib_path: &Path,
+    dyninfo: &DynInfo,
+    inherited_rpath: &[(String, PathBuf)],
+    settings: &ResolveSettings,
+    secure_execution: bool,
+) -> Result<LibraryLocations, Box<dyn Error>> {
+    let allowed = |path: &&&str| !secure_execution || !is_origin_relative(path);
+    let sysroot = settings.sysroot.as_deref();
+    let embedded_location = |path: &str| expand_embedded_entry(path, lib_path, sysroot);
+
+    let mut lib_locations = LibraryLocations(Vec::new());
+    for method in settings.search_methods.iter() {
+        match method {
+            &LibSearchMethod::RPath => {
+                lib_locations.0.extend(
+                    dyninfo
+                        .rpath
+                        .iter()
+                        .filter(&allowed)
+                        .map(|path| (embedded_location(path), "rpath")),
+                );
+                lib_locations.0.extend(
+                    inherited_rpath
+                        .iter()
+                        .filter(|&(entry, _)| allowed(&&entry.as_str()))
+                        .map(|(entry, object)| {
+                            (expand_embedded_entry(entry, object, sysroot), INHERITED_RPATH)
+                        }),
+                );
+            }
+            &LibSearchMethod::RunPath => lib_locations.0.extend(
+                dyninfo
+                    .runpath
+                    .iter()
+                    .filter(&allowed)
+                    .map(|path| (embedded_location(path), "runpath")),
+            ),
+            &LibSearchMethod::LDLibraryPath => {
+                if secure_execution {
+                    continue;
+                }
+                if let Some(ld_lib_path) = settings.environment.var_os("LD_LIBRARY_PATH") {
+                    use std::os::unix::ffi::OsStrExt;
+                    lib_locations
+                        .0
+                        .extend(ld_lib_path.as_bytes().split(|b| *b == b':').map(|slice| {
+                            (PathBuf::from(OsStr::from_bytes(slice)), "LD_LIBRARY_PATH")
+                        }))
+                }
+            }
+            &LibSearchMethod::LDConfig(_) | &LibSearchMethod::Fixed(_) | &LibSearchMethod::Host(_)
+                if dyninfo.flags_1 & DF_1_NODEFLIB != 0 =>
+            {
+                continue;
+            }
+            LibSearchMethod::LDConfig(conf_file) => {
+                let files = system_files(settings.files.clone(), sysroot);
+                search_ld_so_conf(conf_file, &*files, sysroot, &mut lib_locations)?;
+            }
+            LibSearchMethod::Fixed(p) => {
+                lib_locations.0.push((reroot_fixed_dir(p, sysroot), "fixed"));
+            }
+            LibSearchMethod::Host(p) => lib_locations.0.push((p.clone(), "host")),
+        }
+    }
+    Ok(lib_locations)
+}
+
+/// Analyze the library at `lib_path` (reached via `reverse_dependency`) and resolve its
+/// dependencies. Returns the paths of the dependencies that have not been analyzed yet.
+fn collect_lib(
     lib_path: &Path,
-    search_methods: &[LibSearchMethod],
+    settings: &ResolveSettings,
+    cache: &LibraryCache,
     reverse_dependency: Option<PathBuf>,
     result: &mut LibraryDependencies,
-) -> Result<(), Box<Error>> {
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
     // Collect the paths to all libraries that the current library (i.e., libpath) depends on
     let new_lib_paths = {
-        if result.opened_libs.get(lib_path).is_some() {
-            // Lib already analyzed
-            return Ok(());
+        if result.opened_libs.contains_key(lib_path) {
+            // Lib already analyzed (e.g., reached via another library with the same identity)
+            if let Some(reverse_dependency) = reverse_dependency {
+                let dependents = result
+                    .reverse_dependencies
+                    .entry(lib_path.to_path_buf())
+                    .or_default();
+                if !dependents.contains(&reverse_dependency) {
+                    dependents.push(reverse_dependency);
+                }
+            }
+            return Ok(Vec::new());
         }
 
         let prev_lib = result.opened_libs.insert(
             lib_path.to_path_buf(),
-            Library::try_from_path(lib_path.to_owned())?,
+            cache.get_or_open(lib_path, &*settings.files)?,
         );
         assert!(
             prev_lib.is_none(),
@@ -330,95 +1822,181 @@ fn collect_libs(
             .get(lib_path)
             .expect("We have just inserted it");
 
-        let lib_name = lib.get_name();
+        let lib_name = lib.get_soname();
+        let file_name = lib.get_name();
+
+        if let Some(key) = settings.identity.key(lib_path, cache, &*settings.files) {
+            let _ = result
+                .identities
+                .entry(key)
+                .or_insert(lib_path.to_path_buf());
+        }
 
-        // If the library has not been resolved before, we add it to the map
-        if result.resolved.get(lib_name).is_none() {
+        // If the library has not been resolved before, we add it to the map (replacing the alias
+        // of the name it was requested by, if that is its DT_SONAME). The loader considers a
+        // dependency to be loaded already if its name matches the DT_SONAME or the file name of a
+        // loaded library, so we note the latter as an alias if it differs.
+        if !result.resolved.contains_key(&lib_name) {
             let _ = result
                 .resolved
-                .insert(lib_name.to_owned(), lib_path.to_path_buf());
+                .insert(lib_name.clone(), lib_path.to_path_buf());
+            if result.aliases.get(&lib_name).map(|p| p.as_path()) == Some(lib_path) {
+                let _ = result.aliases.remove(&lib_name);
+            }
+        }
+        if file_name != lib_name && result.lookup(file_name).is_none() {
+            let _ = result
+                .aliases
+                .insert(file_name.to_owned(), lib_path.to_path_buf());
         }
 
-        // Note the reverse dependency (if there is some) of the current library. As this library
-        // has not been analyzed before we can just insert a new entry.
+        // Note the reverse dependency (if there is some) of the current library. It has usually
+        // been noted already when the dependency was resolved.
         if let Some(reverse_dependency) = reverse_dependency {
-            let res = result
+            let dependents = result
                 .reverse_dependencies
-                .insert(lib_path.to_path_buf(), vec![reverse_dependency]);
-            assert!(res.is_none(), "Overwrote reverse dependency entry");
+                .entry(lib_path.to_path_buf())
+                .or_default();
+            if !dependents.contains(&reverse_dependency) {
+                dependents.push(reverse_dependency);
+            }
         }
 
         let elf = lib.get_elf();
+        let arch = ElfArch::of_file(lib_path, &*settings.files);
 
-        // Note: It may be safe to just return in this case (as no dyninfo should imply no
-        // dependencies), but I'm not sure.
-        let dyninfo = DynInfo::from_elf(&elf).expect("file has no dyninfo");
-
-        // Note: This is quite ugly. But Rust does not really provide string manipulation for paths
-        // or even CStrings. Maybe there is a crate for that? In any case this does not make too
-        // much of a (any?) difference, as long as goblin only provides elf information in the form
-        // of utf8 strings.
-        let origin = lib_path
-            .parent()
-            .unwrap_or(Path::new("/"))
-            .to_str()
-            .expect("Path not valid utf8");
+        // Statically linked files have no dynamic section. The loader cannot load them (nor
+        // objects with broken dynamic entries), so this is an error of the analyzed file (not of
+        // the whole batch).
+        let dyninfo = DynInfo::from_elf(elf)
+            .map_err(|reason| ErrorMsg(format!("{:?} {}", lib_path, reason)))?;
 
-        // Populate the library locations array from the context created above.
-        let mut lib_locations = LibraryLocations(Vec::new());
-        for method in search_methods.iter() {
-            match method {
-                &LibSearchMethod::RPath => {
-                    lib_locations.0.extend(dyninfo.rpath.iter().map(|path| {
-                        (
-                            PathBuf::from(path.replace("$ORIGIN", origin).to_owned()),
-                            "rpath",
-                        )
-                    }))
-                }
-                &LibSearchMethod::RunPath => {
-                    lib_locations.0.extend(dyninfo.runpath.iter().map(|path| {
-                        (
-                            PathBuf::from(path.replace("$ORIGIN", origin).to_owned()),
-                            "runpath",
-                        )
-                    }))
-                }
-                &LibSearchMethod::LDLibraryPath => {
-                    if let Some(ld_lib_path) = ::std::env::var_os("LD_LIBRARY_PATH") {
-                        use std::os::unix::ffi::OsStrExt;
-                        lib_locations
-                            .0
-                            .extend(ld_lib_path.as_bytes().split(|b| *b == b':').map(|slice| {
-                                (PathBuf::from(OsStr::from_bytes(slice)), "LD_LIBRARY_PATH")
-                            }))
-                    }
-                }
-                &LibSearchMethod::LDConfig(ref conf_file) => {
-                    search_ld_so_conf(conf_file, &mut lib_locations)?;
-                }
-                &LibSearchMethod::Fixed(ref p) => {
-                    lib_locations.0.push((p.clone(), "fixed"));
-                }
+        // Without DT_RUNPATH, the loader also searches the DT_RPATH entries of the objects that
+        // loaded the library.
+        let inherited_rpath = if dyninfo.runpath.is_empty() {
+            result.inherited_rpath(lib_path)
+        } else {
+            Vec::new()
+        };
+        let sysroot = settings.sysroot.as_deref();
+        let entry_owner = |dir: &Path, origin| {
+            if origin != INHERITED_RPATH {
+                return lib_path.to_path_buf();
             }
-        }
+            inherited_rpath
+                .iter()
+                .find(|&(entry, object)| {
+                    expand_embedded_entry(entry, object, sysroot) == dir
+                })
+                .map_or(lib_path.to_path_buf(), |(_, object)| object.clone())
+        };
+
+        // Populate the library locations array from the dynamic information of the library.
+        let all_lib_locations =
+            library_locations(lib_path, &dyninfo, &inherited_rpath, settings, false)?;
+
+        // In secure-execution mode the loader ignores some of the locations, which we emulate by
+        // searching a filtered location list. We keep the unfiltered one in order to be able to
+        // report dependencies that are only found via ignored locations.
+        let lib_locations = if settings.secure_execution {
+            library_locations(lib_path, &dyninfo, &inherited_rpath, settings, true)?
+        } else {
+            all_lib_locations.clone()
+        };
 
         // Avoid borrowck errors by explicitly taking refernces to parts of the result struct here.
-        let resolved = &mut result.resolved;
+        let resolved = &result.resolved;
+        let aliases = &mut result.aliases;
         let reverse_dependencies = &mut result.reverse_dependencies;
+        let resolutions = &mut result.resolutions;
         let problems = &mut result.problems;
+        let skipped_incompatible = &mut result.skipped_incompatible;
+        let identities = &mut result.identities;
 
-        dyninfo
+        // Filtees are loaded along with the filter, but a missing auxiliary filtee is no error.
+        let new_lib_paths = dyninfo
             .libs
             .iter()
-            .filter_map(|&dependency_lib_name| {
+            .chain(dyninfo.filters.iter())
+            .map(|&name| (name, false))
+            .chain(dyninfo.auxiliaries.iter().map(|&name| (name, true)))
+            .filter_map(|(dependency_lib_name, optional)| {
                 // Try to resolve the location of the library we depend on.
-                let dependency_lib_path = lib_locations.try_find_library(dependency_lib_name);
+                let (found, incompatible_paths) =
+                    lib_locations.try_find_compatible_library(
+                        dependency_lib_name,
+                        arch,
+                        &*settings.files,
+                    );
+                // Use the path of an already resolved library with the same identity instead.
+                let identity_key = found
+                    .as_ref()
+                    .and_then(|(path, _)| settings.identity.key(path, cache, &*settings.files));
+                let dependency_lib_path = found.as_ref().map(|(path, _)| {
+                    identity_key
+                        .as_ref()
+                        .and_then(|key| identities.get(key).cloned())
+                        .unwrap_or(path.clone())
+                });
+
+                if settings.secure_execution {
+                    if let (Some((ignored_path, &(ref directory, origin))), _) =
+                        all_lib_locations.try_find_compatible_library(
+                            dependency_lib_name,
+                            arch,
+                            &*settings.files,
+                        )
+                    {
+                        if dependency_lib_path.as_ref() != Some(&ignored_path) {
+                            problems.push(LibResolveProblem::IgnoredInSecureExecution {
+                                dependent_lib: lib_path.to_path_buf(),
+                                lib_name: dependency_lib_name.to_owned(),
+                                ignored_path: ignored_path.clone(),
+                                origin,
+                                directory: directory.clone(),
+                            });
+                        }
+                    }
+                }
+
+                // Note the candidates of another architecture that the search skipped and
+                // whether the library was found relative to the working directory.
+                if let Some((ref path, &(ref dir, origin))) = found {
+                    skipped_incompatible.extend(incompatible_paths.iter().map(|skipped| {
+                        SkippedCandidate {
+                            dependent_lib: lib_path.to_path_buf(),
+                            lib_name: dependency_lib_name.to_owned(),
+                            path: skipped.clone(),
+                            resolve_path: path.clone(),
+                        }
+                    }));
+                    if dir.is_relative() {
+                        problems.push(LibResolveProblem::RelativeLocation {
+                            dependent_lib: lib_path.to_path_buf(),
+                            lib_name: dependency_lib_name.to_owned(),
+                            resolve_path: path.clone(),
+                            origin,
+                            directory: dir.clone(),
+                        });
+                    }
+                }
+
+                // Remember which search location produced the library for the current dependent.
+                // This is only kept if the resolution does not conflict with a previous one.
+                let resolution = found.map(|(_, &(ref dir, origin))| Resolution {
+                    dependent_lib: lib_path.to_path_buf(),
+                    lib_name: dependency_lib_name.to_owned(),
+                    directory: dir.clone(),
+                    origin,
+                    entry_owner: entry_owner(dir, origin),
+                });
 
                 let os_dep_lib_name = OsString::from(dependency_lib_name);
 
                 // Potentially get the path of the library if it has been resolved before.
-                let maybe_resolved_lib_path = { resolved.get(&os_dep_lib_name) };
+                let maybe_resolved_lib_path = resolved
+                    .get(&os_dep_lib_name)
+                    .or_else(|| aliases.get(&os_dep_lib_name));
 
                 // Compare the (potential) previous resolved path and the (potential) now resolved
                 // path. Note problems that arrise from that and add the now resolved dependency
@@ -426,7 +2004,7 @@ fn collect_libs(
                 if let Some(resolved_lib_path) = maybe_resolved_lib_path {
                     let reverse_dependencies =
                         reverse_dependencies.get_mut(resolved_lib_path).unwrap();
-                    if let &Some(ref dependency_lib_path) = &dependency_lib_path {
+                    if let Some(dependency_lib_path) = &dependency_lib_path {
                         if dependency_lib_path != resolved_lib_path {
                             problems.push(LibResolveProblem::ResolveConflict {
                             dependent_lib: lib_path.to_path_buf(),
@@ -438,6 +2016,10 @@ fn collect_libs(
                         });
                         } else {
                             reverse_dependencies.push(lib_path.to_path_buf());
+                            resolutions
+                                .entry(resolved_lib_path.to_path_buf())
+                                .or_default()
+                                .extend(resolution);
                         }
                     } else {
                         problems.push(LibResolveProblem::UnresolvedButPreviouslyResolved {
@@ -451,25 +2033,331 @@ fn collect_libs(
 
                     None
                 } else {
-                    if dependency_lib_path.is_none() {
-                        problems.push(LibResolveProblem::Unresolved {
-                            dependent_lib: lib_path.to_path_buf(),
-                            lib_name: dependency_lib_name.to_owned(),
-                            locations: lib_locations.clone(),
+                    if let Some(dependency_lib_path) = &dependency_lib_path {
+                        resolutions
+                            .entry(dependency_lib_path.clone())
+                            .or_default()
+                            .extend(resolution);
+                        // The loader maps the library right away, so later dependents already
+                        // find it by this name (even before it is analyzed).
+                        let _ = aliases.insert(os_dep_lib_name, dependency_lib_path.clone());
+                        if let Some(key) = identity_key {
+                            let _ = identities
+                                .entry(key)
+                                .or_insert(dependency_lib_path.clone());
+                        }
+                        let dependents = reverse_dependencies
+                            .entry(dependency_lib_path.clone())
+                            .or_default();
+                        if !dependents.iter().any(|d| d == lib_path) {
+                            dependents.push(lib_path.to_path_buf());
+                        }
+                    } else if !optional {
+                        let mismatch = incompatible_paths.first().and_then(|path| {
+                            let actual = ElfArch::of_file(path, &*settings.files)?;
+                            Some((path, arch?, actual))
+                        });
+                        problems.push(match mismatch {
+                            Some((path, expected, actual)) => LibResolveProblem::ArchMismatch {
+                                dependent_lib: lib_path.to_path_buf(),
+                                lib_name: dependency_lib_name.to_owned(),
+                                lib_path: path.clone(),
+                                expected,
+                                actual,
+                            },
+                            None => LibResolveProblem::Unresolved {
+                                dependent_lib: lib_path.to_path_buf(),
+                                lib_name: dependency_lib_name.to_owned(),
+                                locations: lib_locations.clone(),
+                            },
                         });
                     }
 
                     dependency_lib_path
                 }
             })
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+
+        let _ = result.needed.insert(
+            lib_path.to_path_buf(),
+            dyninfo.libs.iter().map(|s| s.to_string()).collect(),
+        );
+        let filtees = dyninfo
+            .filters
+            .iter()
+            .map(|name| (name, false))
+            .chain(dyninfo.auxiliaries.iter().map(|name| (name, true)))
+            .map(|(name, auxiliary)| Filtee {
+                name: name.to_string(),
+                auxiliary,
+            })
+            .collect::<Vec<_>>();
+        if !filtees.is_empty() {
+            let _ = result.filtees.insert(lib_path.to_path_buf(), filtees);
+        }
+        let _ = result
+            .search_locations
+            .insert(lib_path.to_path_buf(), lib_locations);
+
+        new_lib_paths
     };
 
-    // Call collect_libs for all of those libraries.
-    // This way, the library resolution is performed depth first.
-    for path in new_lib_paths {
-        collect_libs(&path, search_methods, Some(lib_path.to_path_buf()), result)?;
-    }
+    Ok(new_lib_paths)
+}
 
+/// Analyze the closure of the file at `root`, visiting the libraries in the order given by the
+/// traversal of the settings.
+fn collect_libs(
+    root: &Path,
+    settings: &ResolveSettings,
+    cache: &LibraryCache,
+    progress: &dyn Progress,
+    result: &mut LibraryDependencies,
+) -> Result<(), Box<dyn Error>> {
+    let mut pending = VecDeque::new();
+    pending.push_back((root.to_path_buf(), None));
+    loop {
+        // The pending libraries are analyzed one by one (as the order matters), but reading them
+        // is independent, so it is done in parallel for all of them once one is not cached yet.
+        let next = match settings.traversal {
+            Traversal::BreadthFirst => pending.front(),
+            Traversal::DepthFirst => pending.back(),
+        };
+        if let Some((next_path, _)) = next {
+            if !result.opened_libs.contains_key(next_path) {
+                cache.prefetch(pending.iter().map(|(path, _)| path), &*settings.files);
+            }
+        }
+        let popped = match settings.traversal {
+            Traversal::BreadthFirst => pending.pop_front(),
+            Traversal::DepthFirst => pending.pop_back(),
+        };
+        let (lib_path, reverse_dependency) = match popped {
+            Some(item) => item,
+            None => break,
+        };
+        let new_lib_paths = collect_lib(&lib_path, settings, cache, reverse_dependency, result)?;
+        progress.library_analyzed(root, &lib_path);
+        let dependencies = new_lib_paths
+            .into_iter()
+            .map(|path| (path, Some(lib_path.clone())));
+        match settings.traversal {
+            Traversal::BreadthFirst => pending.extend(dependencies),
+            // The first dependency has to be on top of the stack to be analyzed next.
+            Traversal::DepthFirst => pending.extend(dependencies.rev()),
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use files::MemoryFs;
+    use testing::{elf, settings, NEEDED_D_TAG};
+
+    fn resolve(settings: &ResolveSettings) -> LibraryDependencies {
+        LibraryDependencies::try_find_for_elf(Path::new("/app/bin/app"), settings)
+            .expect("The closure can be resolved")
+    }
+
+    fn resolved<'a>(deps: &'a LibraryDependencies, lib_name: &str) -> Option<&'a Path> {
+        deps.resolved.get(OsStr::new(lib_name)).map(PathBuf::as_path)
+    }
+
+    fn origin(deps: &LibraryDependencies, lib_name: &str) -> &'static str {
+        let path = resolved(deps, lib_name).expect("The library is resolved");
+        deps.resolutions[path][0].origin
+    }
+
+    #[test]
+    fn search_locations_are_tried_in_order() {
+        let mut files = MemoryFs::new();
+        files.add_file(
+            "app/bin/app",
+            elf(&[
+                (NEEDED_D_TAG, "libfoo.so"),
+                (NEEDED_D_TAG, "libbar.so"),
+                (NEEDED_D_TAG, "libbaz.so"),
+                (NEEDED_D_TAG, "libqux.so"),
+                (RPATH_D_TAG, "/rpath"),
+                (RUNPATH_D_TAG, "/runpath"),
+            ]),
+        );
+        for path in &[
+            "rpath/libfoo.so",
+            "env/libfoo.so",
+            "env/libbar.so",
+            "runpath/libbar.so",
+            "runpath/libbaz.so",
+            "lib/libbaz.so",
+            "lib/libqux.so",
+        ] {
+            files.add_file(path, elf(&[]));
+        }
+        let deps = resolve(&settings(files, Some("/env")));
+
+        assert!(deps.problems.is_empty());
+        assert_eq!(resolved(&deps, "libfoo.so"), Some(Path::new("/rpath/libfoo.so")));
+        assert_eq!(origin(&deps, "libfoo.so"), "rpath");
+        assert_eq!(resolved(&deps, "libbar.so"), Some(Path::new("/env/libbar.so")));
+        assert_eq!(origin(&deps, "libbar.so"), "LD_LIBRARY_PATH");
+        assert_eq!(resolved(&deps, "libbaz.so"), Some(Path::new("/runpath/libbaz.so")));
+        assert_eq!(origin(&deps, "libbaz.so"), "runpath");
+        assert_eq!(resolved(&deps, "libqux.so"), Some(Path::new("/lib/libqux.so")));
+        assert_eq!(origin(&deps, "libqux.so"), "fixed");
+    }
+
+    #[test]
+    fn rpath_is_inherited_by_dependencies_without_runpath() {
+        let mut files = MemoryFs::new();
+        files.add_file("app/bin/app", elf(&[(NEEDED_D_TAG, "libfoo.so"), (RPATH_D_TAG, "/rpath")]));
+        files.add_file("lib/libfoo.so", elf(&[(NEEDED_D_TAG, "libbar.so")]));
+        files.add_file("rpath/libbar.so", elf(&[]));
+        let deps = resolve(&settings(files, None));
+
+        assert!(deps.problems.is_empty());
+        assert_eq!(resolved(&deps, "libbar.so"), Some(Path::new("/rpath/libbar.so")));
+        let resolution = &deps.resolutions[Path::new("/rpath/libbar.so")][0];
+        assert_eq!(resolution.origin, INHERITED_RPATH);
+        assert_eq!(resolution.entry_owner, Path::new("/app/bin/app"));
+    }
+
+    #[test]
+    fn unresolved_dependencies_are_problems() {
+        let mut files = MemoryFs::new();
+        files.add_file("app/bin/app", elf(&[(NEEDED_D_TAG, "libmissing.so")]));
+        files.add_file("lib/libother.so", elf(&[]));
+        let deps = resolve(&settings(files, None));
+
+        assert_eq!(deps.problems.len(), 1);
+        match deps.problems[0] {
+            LibResolveProblem::Unresolved { ref lib_name, .. } => {
+                assert_eq!(lib_name, "libmissing.so")
+            }
+            _ => panic!("Expected an unresolved dependency"),
+        }
+    }
+
+    #[test]
+    fn origin_is_the_directory_of_the_object() {
+        let mut files = MemoryFs::new();
+        files.add_file(
+            "app/bin/app",
+            elf(&[
+                (NEEDED_D_TAG, "libfoo.so"),
+                (NEEDED_D_TAG, "libbar.so"),
+                (RUNPATH_D_TAG, "$ORIGIN/../lib:${ORIGIN}/plugins"),
+            ]),
+        );
+        files.add_file("app/lib/libfoo.so", elf(&[]));
+        files.add_file("app/bin/plugins/libbar.so", elf(&[]));
+        let deps = resolve(&settings(files, None));
+
+        assert!(deps.problems.is_empty());
+        assert_eq!(resolved(&deps, "libfoo.so"), Some(Path::new("/app/bin/../lib/libfoo.so")));
+        assert_eq!(resolved(&deps, "libbar.so"), Some(Path::new("/app/bin/plugins/libbar.so")));
+    }
+
+    #[test]
+    fn filtees_are_loaded_with_the_filter() {
+        let mut files = MemoryFs::new();
+        files.add_file("app/bin/app", elf(&[(NEEDED_D_TAG, "libfilter.so")]));
+        files.add_file(
+            "lib/libfilter.so",
+            elf(&[(FILTER_D_TAG, "libimpl.so"), (AUXILIARY_D_TAG, "libaux.so")]),
+        );
+        files.add_file("lib/libimpl.so", elf(&[]));
+        let deps = resolve(&settings(files, None));
+
+        // A missing auxiliary filtee is not a problem.
+        assert!(deps.problems.is_empty());
+        assert_eq!(resolved(&deps, "libimpl.so"), Some(Path::new("/lib/libimpl.so")));
+        let filtees = &deps.filtees[Path::new("/lib/libfilter.so")];
+        let filtees = filtees
+            .iter()
+            .map(|filtee| (filtee.name.as_str(), filtee.auxiliary))
+            .collect::<Vec<_>>();
+        assert_eq!(filtees, vec![("libimpl.so", false), ("libaux.so", true)]);
+    }
+
+    #[test]
+    fn secure_execution_ignores_origin_and_ld_library_path() {
+        let mut files = MemoryFs::new();
+        files.add_file(
+            "app/bin/app",
+            elf(&[
+                (NEEDED_D_TAG, "libfoo.so"),
+                (NEEDED_D_TAG, "libbar.so"),
+                (RUNPATH_D_TAG, "$ORIGIN"),
+            ]),
+        );
+        files.set_mode("app/bin/app", 0o4755);
+        files.add_file("app/bin/libfoo.so", elf(&[]));
+        files.add_file("env/libbar.so", elf(&[]));
+        files.add_file("lib/libbar.so", elf(&[]));
+        let reason = secure_execution_reason(Path::new("/app/bin/app"), &files)
+            .expect("The mode can be read");
+        assert_eq!(reason, Some("setuid bit"));
+
+        let mut settings = settings(files, Some("/env"));
+        settings.secure_execution = true;
+        let deps = resolve(&settings);
+
+        assert_eq!(resolved(&deps, "libfoo.so"), None);
+        assert_eq!(resolved(&deps, "libbar.so"), Some(Path::new("/lib/libbar.so")));
+        let mut ignored = deps
+            .problems
+            .iter()
+            .filter_map(|problem| match problem {
+                LibResolveProblem::IgnoredInSecureExecution {
+                    lib_name, origin, ..
+                } => Some((lib_name.as_str(), *origin)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        ignored.sort();
+        assert_eq!(ignored, vec![("libbar.so", "LD_LIBRARY_PATH"), ("libfoo.so", "runpath")]);
+    }
+
+    #[test]
+    fn plain_files_do_not_need_secure_execution() {
+        let mut files = MemoryFs::new();
+        files.add_file("app/bin/app", elf(&[]));
+        let reason = secure_execution_reason(Path::new("/app/bin/app"), &files)
+            .expect("The mode can be read");
+        assert_eq!(reason, None);
+    }
+
+    #[test]
+    fn closures_can_be_resolved_from_archives() {
+        use tar::{Builder, EntryType, Header};
+
+        let mut builder = Builder::new(Vec::new());
+        for &(path, ref content) in &[
+            ("app/bin/app", elf(&[(NEEDED_D_TAG, "libfoo.so.1")])),
+            ("lib/libfoo.so.1.2", elf(&[])),
+        ] {
+            let mut header = Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            builder.append_data(&mut header, path, &content[..]).unwrap();
+        }
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "lib/libfoo.so.1", "libfoo.so.1.2").unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let files = MemoryFs::from_tar(&archive[..]).expect("The archive can be read");
+        assert_eq!(files.mode(Path::new("/app/bin/app")).unwrap(), 0o755);
+        assert_eq!(
+            files.canonicalize(Path::new("/lib/libfoo.so.1")).unwrap(),
+            Path::new("/lib/libfoo.so.1.2")
+        );
+        let deps = resolve(&settings(files, None));
+
+        assert!(deps.problems.is_empty());
+        assert_eq!(resolved(&deps, "libfoo.so.1"), Some(Path::new("/lib/libfoo.so.1")));
+    }
+}
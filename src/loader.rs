@@ -0,0 +1,92 @@
+use files::FileProvider;
+use libraries::{reroot, LibResolveProblem, Library, LibraryCache, LibraryDependencies};
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Directories that distributions install the dynamic loader to.
+const STANDARD_LOADER_DIRS: &[&str] =
+    &["/lib", "/lib32", "/lib64", "/libx32", "/usr/lib", "/usr/lib64"];
+
+const LIBC_NAME: &str = "libc.so.6";
+const RELEASE_MARKER: &[u8] = b"release version ";
+
+/// The interpreter (PT_INTERP) requested by an ELF file, if any.
+pub fn interpreter(path: &Path, files: &dyn FileProvider, cache: &LibraryCache) -> Option<PathBuf> {
+    let lib = cache.get_or_open(path, files).ok()?;
+    let elf = lib.get_elf();
+    elf.interpreter.map(PathBuf::from)
+}
+
+/// Whether the loader is one installed by the distribution, as opposed to one bundled with an
+/// application (e.g., by patchelf --set-interpreter).
+pub fn is_standard_loader(loader: &Path) -> bool {
+    loader
+        .parent()
+        .is_some_and(|dir| STANDARD_LOADER_DIRS.iter().any(|d| dir == Path::new(d)))
+}
+
+/// The glibc release that a glibc loader or libc was built from (e.g., "2.36").
+fn glibc_release(lib: &Library) -> Option<String> {
+    let bytes = lib.get_bytes();
+    let start = bytes
+        .windows(RELEASE_MARKER.len())
+        .position(|w| w == RELEASE_MARKER)? + RELEASE_MARKER.len();
+    let version = bytes[start..]
+        .iter()
+        .take_while(|&&b| b == b'.' || (b as char).is_ascii_digit())
+        .map(|&b| b as char)
+        .collect::<String>();
+    let version = version.trim_end_matches('.');
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_owned())
+    }
+}
+
+impl LibraryDependencies {
+    /// Note it as a problem if the libc of the closure does not belong to the loader: glibc's
+    /// ld.so and libc.so.6 are only compatible if they stem from the same release. A libc that
+    /// does not live next to a bundled loader is most likely the system one.
+    pub fn report_loader_mismatch(&mut self, sysroot: Option<&Path>) {
+        let loader = match self.opened_libs.get(&self.root) {
+            Some(root) => match root.get_elf().interpreter {
+                Some(loader) => PathBuf::from(loader),
+                None => return,
+            },
+            None => return,
+        };
+        let libc = match self.resolved.get(OsStr::new(LIBC_NAME)) {
+            Some(libc) => libc.clone(),
+            None => return,
+        };
+        let real_loader = reroot(&loader, sysroot);
+
+        let release = |path: &Path| {
+            self.open_library(path)
+                .ok()
+                .and_then(|lib| glibc_release(&lib))
+        };
+        let description = match (release(&real_loader), release(&libc)) {
+            (Some(ref loader_release), Some(ref libc_release))
+                if loader_release != libc_release =>
+            {
+                format!(
+                    "is from glibc {}, but the loader is from glibc {}",
+                    libc_release, loader_release
+                )
+            }
+            _ if !is_standard_loader(&loader) && libc.parent() != real_loader.parent() => {
+                "does not come from the directory of the bundled loader".to_owned()
+            }
+            _ => return,
+        };
+        self.problems.push(LibResolveProblem::LoaderMismatch {
+            dependent_lib: self.root.clone(),
+            loader,
+            libc,
+            description,
+        });
+    }
+}
@@ -1,50 +1,253 @@
-#[macro_use]
 extern crate structopt;
 extern crate cpp_demangle;
 extern crate glob;
 extern crate goblin;
 extern crate groupable;
+extern crate indicatif;
 extern crate itertools;
+extern crate libc;
+extern crate linkcheck;
+extern crate regex;
+extern crate rusqlite;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 extern crate term;
 
 use cpp_demangle::Symbol;
+use linkcheck::{files, fingerprint, harden, libraries, loader, min_versions, model, objinfo,
+                symbol_versions, symbols};
+use regex::Regex;
 
-mod libraries;
-mod symbols;
+mod budgets;
+mod compare;
+mod config;
+mod container;
+mod cxxruntime;
+mod dispatch;
+mod dlopen_targets;
+mod dot;
+mod duplicates;
+mod dynamic_tags;
+mod executable_symbols;
+mod exit_codes;
+mod history;
+mod initializers;
+mod layout;
+mod method_usage;
+mod mounts;
+mod namespaces;
+mod owners;
+mod packages;
+mod packaging;
+mod pager;
+mod plugins;
+mod preload;
+mod progress;
+mod provenance;
+mod provided;
+mod remediation;
+mod remote;
+mod report;
+mod rootfs;
+mod rpath_usage;
+mod search_paths;
+#[cfg(test)]
+mod testing;
+mod textrel;
+mod theme;
+mod toolchain;
+mod underlinking;
+mod unused_dependencies;
+mod users;
+mod versions;
+mod wine;
 
+use budgets::*;
+use compare::*;
+use config::Config;
+use container::ContainerRoot;
+use cxxruntime::find_cxx_runtime_problems;
+use dispatch::*;
+use dlopen_targets::*;
+use dot::reports_to_dot;
+use duplicates::*;
+use dynamic_tags::*;
+use executable_symbols::find_executable_symbols;
+use exit_codes::*;
+use files::RealFs;
+use harden::HardeningAudit;
+use history::*;
+use initializers::find_unresolved_data;
+use layout::*;
 use libraries::*;
+use loader::*;
+use method_usage::*;
+use min_versions::*;
+use mounts::*;
+use namespaces::*;
+use objinfo::ObjectInfo;
+use owners::*;
+use packages::PackageDb;
+use packaging::*;
+use pager::*;
+use plugins::*;
+use preload::*;
+use progress::*;
+use provenance::Provenance;
+use provided::*;
+use remote::*;
+use report::*;
+use rootfs::*;
+use rpath_usage::*;
+use search_paths::*;
+use textrel::*;
 use symbols::*;
+use theme::*;
+use underlinking::*;
+use toolchain::Toolchain;
+use unused_dependencies::*;
+use users::User;
+use versions::*;
+use wine::*;
 
 use groupable::Groupable;
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::SystemTime;
 use structopt::StructOpt;
 
-/// The methods which GNU ld.so uses (if not specified otherwise) to locate libraries. At least
-/// according to https://en.wikipedia.org/wiki/Rpath
-fn gnuld_default_search_methods() -> Vec<LibSearchMethod> {
-    vec![
-        LibSearchMethod::RPath,
-        LibSearchMethod::RunPath,
-        LibSearchMethod::LDLibraryPath,
-        LibSearchMethod::LDConfig(PathBuf::from("/etc/ld.so.conf")),
-        LibSearchMethod::Fixed(PathBuf::from("/usr/lib")),
-        LibSearchMethod::Fixed(PathBuf::from("/lib")),
-    ]
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Search the exported, defined and undefined symbols of all objects in the closure of an ELF
+    /// file
+    #[structopt(name = "grep-symbols")]
+    GrepSymbols {
+        /// Regular expression that is matched against mangled and demangled symbol names
+        pattern: Regex,
+
+        /// ELF file whose closure is searched
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+
+    /// Analyze all executables of a root file system image against only the image's own libraries
+    /// and loader configuration
+    #[structopt(name = "rootfs")]
+    Rootfs {
+        /// Root directory or squashfs file (extracted using unsquashfs) of the image
+        #[structopt(parse(from_os_str))]
+        image: PathBuf,
+
+        /// Also list, for every library, the binaries that use it and the binaries that would
+        /// break if it was removed
+        #[structopt(long = "usage")]
+        usage: bool,
+    },
+
+    /// Analyze a file inside a running container in place, against only the container's own
+    /// libraries and loader configuration
+    #[structopt(name = "container")]
+    Container {
+        /// Id or name of the container (docker or podman), or the pid of a process inside it
+        id: String,
+
+        /// Path of the analyzed file inside the container
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+
+    /// Analyze a binary against the libraries and loader configuration of two roots (e.g., the
+    /// current and a new base image) and show the differences
+    #[structopt(name = "compare-roots")]
+    CompareRoots {
+        /// Binary to analyze. Taken from inside each root if it exists there
+        #[structopt(parse(from_os_str))]
+        binary: PathBuf,
+
+        /// The first (e.g., current) root directory
+        #[structopt(long = "root-a", parse(from_os_str))]
+        root_a: PathBuf,
+
+        /// The second (e.g., new) root directory
+        #[structopt(long = "root-b", parse(from_os_str))]
+        root_b: PathBuf,
+    },
+
+    /// Analyze files as usual and additionally audit their exploit mitigations (PIE, RELRO,
+    /// BIND_NOW, stack canary, NX stack and FORTIFY)
+    #[structopt(name = "harden")]
+    Harden {
+        /// ELF files to be analyzed and audited
+        #[structopt(parse(from_os_str), raw(required = "true"))]
+        files: Vec<PathBuf>,
+    },
+
+    /// Show how the findings of a file evolved over the runs recorded with --append-history
+    #[structopt(name = "history")]
+    History {
+        /// History database written by --append-history
+        #[structopt(long = "database", parse(from_os_str))]
+        database: PathBuf,
+
+        /// Analyzed file whose history is shown
+        #[structopt(parse(from_os_str))]
+        target: PathBuf,
+    },
 }
 
 /// Show potential dynamic linking problems of ELF files.
 #[derive(Debug, StructOpt)]
+#[structopt(raw(setting = "structopt::clap::AppSettings::SubcommandsNegateReqs"))]
 struct Options {
     /// Library search locations (in order specified). Special options are: rpath, runpath,
-    /// ld_library_path, ldconfig:<path_to_ld.so.conf>. All other options are interpreted as fixed
-    /// paths to library locations. If nothing is specified, the default resolution behavior of GNU
+    /// ld_library_path, ldconfig:<path_to_ld.so.conf>, fixed:<path>, host:<path> (not looked up
+    /// inside the sysroot). All other options are
+    /// interpreted as fixed paths to library locations. If nothing is specified, the default resolution behavior of GNU
     /// ld.so is mimicked.
     #[structopt(short = "l", long = "lib")]
     search_methods: Vec<LibSearchMethod>,
 
+    /// Root directory of the analyzed system (e.g., the sysroot of a cross-compilation target).
+    /// ld.so.conf files (including everything they include) and the directories listed in them,
+    /// absolute RPATH/RUNPATH entries and fixed search locations are looked up inside this
+    /// directory. A remote system can be given as ssh://[user@]host[:port]/ (accessed via sftp,
+    /// fetched files are cached in ~/.cache/linkcheck/ssh).
+    #[structopt(long = "sysroot", parse(from_os_str))]
+    sysroot: Option<PathBuf>,
+
+    /// Fail instead of assuming the default search locations of GNU ld.so if no search location is
+    /// specified
+    #[structopt(long = "no-default-search")]
+    no_default_search: bool,
+
+    /// Expand environment variables ($VAR or ${VAR}) in the paths of search locations
+    #[structopt(short = "e", long = "expand-env")]
+    expand_env: bool,
+
+    /// Load the analyzed files as the given user: ~ and $HOME in search locations and in the values
+    /// of the environment (e.g., LD_LIBRARY_PATH) refer to the home directory of the user
+    #[structopt(long = "as-user")]
+    as_user: Option<String>,
+
+    /// Environment snapshot of the user given by --as-user (NUL or line separated VAR=value
+    /// assignments, e.g., /proc/<pid>/environ of a service) used instead of the own environment
+    #[structopt(long = "user-env", parse(from_os_str), raw(requires = r#""as_user""#))]
+    user_env: Option<PathBuf>,
+
+    /// Colon separated directories used as LD_LIBRARY_PATH of the loading process (by the
+    /// ld_library_path search location) instead of the value in the environment
+    #[structopt(long = "ld-library-path")]
+    ld_library_path: Option<String>,
+
     /// Show unresolved symbols
     #[structopt(short = "u", long = "unresolved-symbols")]
     show_unresolved_symbols: bool,
@@ -57,13 +260,256 @@ struct Options {
     #[structopt(short = "r", long = "lib-resolution")]
     show_lib_resolution_problems: bool,
 
+    /// Show how each library was resolved (search method, directory and dependent)
+    #[structopt(short = "v", long = "verbose")]
+    verbose: bool,
+
+    /// Show duplicate, shadowed and unused RPATH/RUNPATH entries and inaccessible search directories
+    #[structopt(short = "s", long = "search-paths")]
+    show_search_path_oddities: bool,
+
+    /// Show how many RPATH/RUNPATH entries of each object were used and suggest a minimal
+    /// replacement for those with dead entries
+    #[structopt(long = "rpath-usage")]
+    rpath_usage: bool,
+
+    /// Check the mount table for libraries of the closure on noexec file systems (which cannot be
+    /// loaded) and on network file systems (which slow down startup and make it depend on the
+    /// availability of the server)
+    #[structopt(long = "check-mounts")]
+    check_mounts: bool,
+
+    /// Show DT_NEEDED entries that none of the references of the object bind to (like ldd -u),
+    /// distinguishing likely leftovers of linking without --as-needed from libraries only needed
+    /// for their constructors and libraries whose symbols are taken from earlier ones
+    #[structopt(long = "unused-dependencies")]
+    show_unused_dependencies: bool,
+
+    /// Show the newest symbol version (e.g., GLIBC_2.34 or GLIBCXX_3.4.29) that the closure
+    /// requires from each runtime library, i.e., the oldest runtime it can run with
+    #[structopt(long = "min-versions")]
+    show_minimum_versions: bool,
+
+    /// Resolve libraries like the loader does in secure-execution mode (ignoring $ORIGIN and
+    /// LD_LIBRARY_PATH). Enabled automatically for setuid/setgid binaries and binaries with file
+    /// capabilities.
+    #[structopt(long = "secure-execution")]
+    secure_execution: bool,
+
+    /// Check the entries of $LD_PRELOAD and /etc/ld.so.preload: report missing preloads,
+    /// preloads of the wrong architecture and preloads that can be replaced by other users
+    #[structopt(long = "audit-preload")]
+    audit_preload: bool,
+
+    /// Simulate preloading the given library like $LD_PRELOAD (repeatable) and show which symbols
+    /// it overrides. With 'lib=symbol,...', check that it overrides exactly the given symbols
+    #[structopt(long = "preload", number_of_values = 1)]
+    preload: Vec<PreloadSpec>,
+
+    /// Check that the given file, if it is analyzed, can be loaded with dlopen (i.e., is no
+    /// non-PIE executable). With 'file=symbol,...', check that it exports the given entry symbols
+    #[structopt(long = "dlopen-target", number_of_values = 1)]
+    dlopen_targets: Vec<DlopenTarget>,
+
+    /// Treat library resolutions that depend on the environment (e.g., LD_LIBRARY_PATH) as problems
+    #[structopt(long = "hermetic")]
+    hermetic: bool,
+
+    /// Report dependencies that only match a file when ignoring case, for trees that come from or
+    /// are deployed to case-insensitive filesystems
+    #[structopt(long = "case-insensitive-fs")]
+    case_insensitive_fs: bool,
+
+    /// Colon separated list of path prefixes. Report all libraries resolved to paths outside of
+    /// them (e.g., /opt/myapp:/usr/lib:/lib).
+    #[structopt(long = "allowed-prefixes")]
+    allowed_prefixes: Option<String>,
+
+    /// Fail if the closure has more than the given number of libraries
+    #[structopt(long = "max-deps")]
+    max_deps: Option<usize>,
+
+    /// Fail if the files of the closure (including the analyzed file) take more than the given
+    /// number of bytes (K, M and G suffixes are accepted)
+    #[structopt(long = "max-closure-size")]
+    max_closure_size: Option<ByteSize>,
+
+    /// Ignore problems of libraries matching the given pattern (e.g., 'libnvidia-*') and of all
+    /// libraries that are only loaded because of them
+    #[structopt(long = "ignore-via", number_of_values = 1)]
+    ignore_via: Vec<glob::Pattern>,
+
+    /// Comma separated list of libraries that the runtime environment provides without a file on
+    /// the analyzed system (e.g., libGL.so.1,libcuda.so.1 injected by GPU drivers), so they are
+    /// treated as resolved. 'name=file' additionally reads the symbols the library exports from
+    /// the stub file (one per line), which are then not reported as unresolved.
+    #[structopt(long = "assume-provided", number_of_values = 1)]
+    assume_provided: Vec<ProvidedLibraries>,
+
+    /// Only print the resolved path of every (transitive) dependency as 'name => path' lines (with
+    /// --format json: a map from each analyzed file to a map from name to path, or null)
+    #[structopt(long = "print-resolved")]
+    print_resolved: bool,
+
+    /// Only print the (transitive) dependencies in the format of ldd ('name => path (0x0)' lines),
+    /// so that linkcheck can replace ldd in scripts without executing the analyzed file
+    #[structopt(long = "ldd-compat")]
+    ldd_compat: bool,
+
+    /// Only list all libraries of the closure in reverse topological order (dependencies first)
+    /// together with their dependency depth
+    #[structopt(long = "topo")]
+    topo: bool,
+
+    /// Analyze symbols per link namespace (as created by dlmopen) instead of per file, e.g.,
+    /// 'main=app' or 'plugin=libplugin.so,libhelper.so'. Can be given multiple times.
+    #[structopt(long = "namespace", number_of_values = 1)]
+    namespaces: Vec<Namespace>,
+
+    /// Check the plugins (shared objects) in the directories listed in the given environment
+    /// variable (e.g., GST_PLUGIN_PATH or LADSPA_PATH) for whether they can be loaded into the
+    /// analyzed files. The separator of the directories defaults to ':', another one can be given
+    /// as 'VAR:;'. Can be given multiple times.
+    #[structopt(long = "plugin-env", number_of_values = 1)]
+    plugin_envs: Vec<PluginEnv>,
+
+    /// Analyze the Windows (PE) files among the given files against the given Wine prefix (e.g.,
+    /// ~/.wine): their DLLs are searched in their own directory and in the system directories of
+    /// the prefix's drive_c. ELF files (e.g., launchers) are still analyzed natively.
+    #[structopt(long = "wine-prefix", parse(from_os_str))]
+    wine_prefix: Option<PathBuf>,
+
+    /// Group all findings by the library that needs to be fixed instead of by category
+    #[structopt(long = "by-owner")]
+    by_owner: bool,
+
+    /// Group all findings by how far the library that needs to be fixed is from the analyzed file
+    /// (the file itself, its direct dependencies, depth 2 and deeper)
+    #[structopt(long = "by-depth", raw(conflicts_with = r#""by_owner""#))]
+    by_depth: bool,
+
+    /// Roll the findings of all analyzed files up per distribution package (dpkg or rpm) that
+    /// owns the library that needs to be fixed
+    #[structopt(long = "by-package")]
+    by_package: bool,
+
+    /// After the reports, show how many resolutions each search method produced, including the
+    /// methods that were never used (and why, if known)
+    #[structopt(long = "search-method-usage")]
+    search_method_usage: bool,
+
+    /// Print each symbol group on a single line instead of wrapping and truncating it to the
+    /// terminal width (the default if stdout is not a terminal)
+    #[structopt(long = "wide")]
+    wide: bool,
+
+    /// Maximum number of symbols that are shown per symbol group
+    #[structopt(long = "max-symbols-per-group", default_value = "50")]
+    max_symbols_per_group: usize,
+
+    /// Show all symbols of every symbol group (overrides --max-symbols-per-group)
+    #[structopt(long = "full")]
+    full_symbol_groups: bool,
+
+    /// How libraries found under different paths are identified for conflict detection and
+    /// deduplication (e.g., copies in bind mounts or overlays): path, realpath (symbolic links
+    /// resolved), buildid (GNU build id, falling back to the content hash) or hash (content hash)
+    #[structopt(long = "identity", default_value = "path")]
+    identity: LibraryIdentity,
+
+    /// Reject malformed files with an error instead of trusting them (bounds-checks all headers and
+    /// string table offsets, limits file sizes), for scanning untrusted binaries
+    #[structopt(long = "hardened-parse")]
+    hardened_parse: bool,
+
+    /// Order in which the closure is visited: bfs (breadth-first, like the loader builds its list
+    /// of loaded objects) or dfs (depth-first). Decides which of conflicting resolutions is used.
+    #[structopt(long = "traversal", default_value = "bfs")]
+    traversal: Traversal,
+
+    /// Prefix of a (cross) compiler toolchain, e.g., 'aarch64-linux-gnu-'. Its sysroot and
+    /// library directories (as printed by the compiler) are used unless --sysroot or -l are given.
+    #[structopt(long = "toolchain")]
+    toolchain: Option<String>,
+
+    /// Color theme of the report: default, high-contrast or monochrome. Overrides the 'theme'
+    /// setting of the config file (~/.config/linkcheck/config).
+    #[structopt(long = "theme")]
+    theme: Option<Theme>,
+
+    /// Format of the report printed to stdout: text or json (the same as written by --json-out)
+    #[structopt(long = "format", default_value = "text")]
+    format: ReportFormat,
+
+    /// Additionally write the report of all analyzed files as JSON to the given file
+    #[structopt(long = "json-out", parse(from_os_str))]
+    json_out: Option<PathBuf>,
+
+    /// Additionally write all findings as SARIF log (e.g., for code scanning services) to the
+    /// given file
+    #[structopt(long = "sarif-out", parse(from_os_str))]
+    sarif_out: Option<PathBuf>,
+
+    /// Additionally write the dependency graph of every analyzed file in Graphviz DOT format to
+    /// the given file (e.g., for 'dot -Tsvg'). Edges with library resolution problems are red.
+    #[structopt(long = "dot", parse(from_os_str))]
+    dot: Option<PathBuf>,
+
+    /// Append the findings of this run (with timestamp, build id and fingerprints of the analyzed
+    /// files) to the given SQLite database. See the history subcommand.
+    #[structopt(long = "append-history", parse(from_os_str))]
+    append_history: Option<PathBuf>,
+
+    /// Additionally write one report file per analyzed file into the given directory
+    #[structopt(long = "out-dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+
+    /// Name of the report files written to --out-dir. {name}, {stem} and {index} are replaced by
+    /// the file name, the file name without extension and the position of the analyzed file.
+    #[structopt(long = "out-template", default_value = "{name}.json")]
+    out_template: String,
+
+    /// Format of the report files written to --out-dir: json or sarif
+    #[structopt(long = "out-format", default_value = "json")]
+    out_format: OutputFormat,
+
+    /// Comma separated list of the exit codes that make linkcheck fail (e.g.,
+    /// 'lib-problems,symbol-problems' to pass despite warnings, or 'none'). Defaults to all.
+    #[structopt(long = "fail-on", default_value = "lib-problems,symbol-problems,warnings,analysis-error,budgets")]
+    fail_on: FailOn,
+
+    /// Print the exit codes (which are combined bitwise) as tab separated table and exit. The codes
+    /// are stable.
+    #[structopt(long = "help-exit-codes")]
+    help_exit_codes: bool,
+
+    /// Do not print notices (e.g., about assumed default search locations) to stderr. They are
+    /// still included in the JSON and SARIF output.
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+
+    /// Show the progress of the analysis on stderr: auto (a progress bar on terminals when
+    /// analyzing multiple files), bar, json (one event object per line, at most once per second)
+    /// or none
+    #[structopt(long = "progress", default_value = "auto")]
+    progress: ProgressMode,
+
+    /// Do not pipe the report through $PAGER, even if stdout is a terminal
+    #[structopt(long = "no-pager")]
+    no_pager: bool,
+
     /// Perform full analysis (default if neither -u, -d, nor -r are specified)
     #[structopt(short = "f", long = "full analysis")]
     full_analysis: bool,
 
-    /// ELF file to be analyzed
-    #[structopt(parse(from_os_str))]
-    file: PathBuf,
+    /// ELF files to be analyzed. Multiple files are analyzed in parallel and share the libraries
+    /// they have in common; the report is grouped per file.
+    #[structopt(parse(from_os_str),
+                raw(required_unless_one = r#"&["namespaces", "help_exit_codes"]"#))]
+    files: Vec<PathBuf>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
 fn libs_to_key(lib_names: &HashSet<String>) -> String {
@@ -72,106 +518,2093 @@ fn libs_to_key(lib_names: &HashSet<String>) -> String {
     libs.iter().map(|s| s.to_string()).join(", ")
 }
 
-fn symbols_to_key(symbols: &[&String]) -> String {
+/// Remove the libraries that are to be ignored from a set of library names.
+fn without_ignored(lib_names: &HashSet<String>, ignored: &HashSet<String>) -> HashSet<String> {
+    lib_names.difference(ignored).cloned().collect()
+}
+
+/// Demangle the symbols and mark those whose class is special (e.g., "foo [ifunc]").
+fn pretty_symbols(symbols: &[&String], classes: &HashMap<String, SymbolClass>) -> Vec<String> {
     let mut pretty_symbols = symbols
         .iter()
         .map(|symbol| {
-            if let Ok(dsym) = Symbol::new(&symbol) {
+            let pretty = if let Ok(dsym) = Symbol::new(&symbol) {
                 dsym.to_string()
             } else {
                 symbol.to_string()
+            };
+            match classes.get(*symbol) {
+                Some(class) => format!("{} [{}]", pretty, class),
+                None => pretty,
             }
         })
         .collect::<Vec<_>>();
     pretty_symbols.sort();
-    pretty_symbols.join(", ")
+    pretty_symbols
 }
 
-fn run(mut options: Options) -> Result<(), Box<Error>> {
-    let search_methods = if options.search_methods.is_empty() {
-        eprintln!("No search location specified. Assuming default locations for GNU ld");
-        gnuld_default_search_methods()
-    } else {
-        options.search_methods
+/// The note that replaces `num_hidden` entries of a list cut short (see --full).
+fn hidden_note(num_hidden: usize) -> String {
+    format!("… and {} more (use --full to see all)", num_hidden)
+}
+
+/// Write a group of symbols (already preceded by its header) either on a single line (if `width`
+/// is `None`) or arranged in columns fitting into `width`. At most `max_symbols` symbols are
+/// printed, if specified.
+fn write_symbols(
+    t: &mut ReportTerminal,
+    symbols: &[&String],
+    classes: &HashMap<String, SymbolClass>,
+    width: Option<usize>,
+    max_symbols: Option<usize>,
+) {
+    let mut symbols = pretty_symbols(symbols, classes);
+    let num_hidden = match max_symbols {
+        Some(max_symbols) if symbols.len() > max_symbols => symbols.len() - max_symbols,
+        _ => 0,
     };
+    let num_shown = symbols.len() - num_hidden;
+    symbols.truncate(num_shown);
 
-    if !options.show_duplicate_symbols && !options.show_unresolved_symbols
-        && !options.show_lib_resolution_problems || options.full_analysis
+    let hidden_note = hidden_note(num_hidden);
+    if let Some(width) = width {
+        writeln!(t).unwrap();
+        for line in columnize(&symbols, 2, width) {
+            writeln!(t, "{}", line).unwrap();
+        }
+        if num_hidden > 0 {
+            writeln!(t, "\t\t{}", hidden_note).unwrap();
+        }
+        writeln!(t).unwrap();
+    } else if num_hidden > 0 {
+        writeln!(t, " [{}, {}]\n", symbols.join(", "), hidden_note).unwrap();
+    } else {
+        writeln!(t, " [{}]\n", symbols.join(", ")).unwrap();
+    }
+}
+
+/// Print the results of the --print-resolved, --ldd-compat and --topo modes for one analyzed file.
+fn print_closure(options: &Options, libs: &LibraryDependencies) {
+    if options.ldd_compat {
+        print_ldd_compat(libs);
+    } else if options.print_resolved {
+        for (name, path) in libs.needed_in_load_order() {
+            if let Some(path) = path {
+                println!("{} => {}", name, path.display());
+            } else if options.assume_provided.iter().any(|l| l.0.iter().any(|p| p.name == name)) {
+                println!("{} => provided by the environment", name);
+            } else {
+                println!("{} => not found", name);
+            }
+        }
+    } else if options.topo {
+        let depths = libs.depths();
+        for path in libs.topological_order() {
+            println!("{}\t{}", depths[path], path.display());
+        }
+    }
+}
+
+/// The result of --print-resolved for one analyzed file as JSON: A map from the name of every
+/// (transitive) dependency to its path, or null if it does not resolve to a file.
+fn resolved_map_to_json(libs: &LibraryDependencies) -> serde_json::Value {
+    serde_json::Value::Object(
+        libs.needed_in_load_order()
+            .into_iter()
+            .map(|(name, path)| (name.to_owned(), json!(path)))
+            .collect(),
+    )
+}
+
+/// Print the dependencies like ldd does: The interpreter is listed last (by the path requested
+/// in PT_INTERP) instead of as a dependency of libc, and names containing a slash are loaded from
+/// that path, so there is nothing to resolve. As nothing is loaded, all addresses are 0 and
+/// linux-vdso.so.1 (provided by the kernel) is omitted.
+fn print_ldd_compat(libs: &LibraryDependencies) {
+    let interpreter = libs.opened_libs
+        .get(&libs.root)
+        .and_then(|root| root.get_elf().interpreter)
+        .map(PathBuf::from);
+    let interpreter_name = interpreter.as_ref().and_then(|i| i.file_name());
+    for (name, path) in libs.needed_in_load_order() {
+        if interpreter_name == Some(OsStr::new(name)) {
+            continue;
+        }
+        match path {
+            Some(path) if name.contains('/') => println!("\t{} (0x0)", path.display()),
+            Some(path) => println!("\t{} => {} (0x0)", name, path.display()),
+            None => println!("\t{} => not found", name),
+        }
+    }
+    if let Some(interpreter) = interpreter {
+        println!("\t{} (0x0)", interpreter.display());
+    }
+}
+
+/// Write the final one-line summary of the number of problems found.
+fn write_tally(
+    t: &mut ReportTerminal,
+    options: &Options,
+    num_lib_problems: usize,
+    num_unresolved_symbols: usize,
+    num_duplicate_symbols: usize,
+) {
+    let mut tally = Vec::new();
+    if options.show_lib_resolution_problems {
+        tally.push(format!("{} library resolution problems", num_lib_problems));
+    }
+    if options.show_unresolved_symbols {
+        tally.push(format!("{} unresolved symbols", num_unresolved_symbols));
+    }
+    if options.show_duplicate_symbols {
+        tally.push(format!("{} duplicate symbols", num_duplicate_symbols));
+    }
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(t, "Found {}.", tally.join(", ")).unwrap();
+    t.reset().unwrap();
+}
+
+/// Resolve the closures of the simulated preloads (see --preload) of an analyzed file. Preloads
+/// that cannot be found or are of another architecture are skipped (and reported by the preload
+/// audit).
+fn load_preloads(
+    options: &Options,
+    settings: &ResolveSettings,
+    libs: &LibraryDependencies,
+    cache: &LibraryCache,
+) -> Vec<Preloaded> {
+    let sysroot = options.sysroot.as_deref();
+    let arch = ElfArch::of_file(&libs.root, &*libs.files);
+    options
+        .preload
+        .iter()
+        .filter_map(|spec| {
+            let path = locate_preload(
+                &spec.name,
+                arch,
+                libs.search_locations.get(&libs.root),
+                &*settings.files,
+                sysroot,
+            )?;
+            if ElfArch::of_file(&path, &*settings.files) != arch {
+                return None;
+            }
+            let libs = LibraryDependencies::try_find_for_elf_cached(&path, settings, cache).ok()?;
+            Some(Preloaded {
+                spec: spec.clone(),
+                libs,
+            })
+        })
+        .collect()
+}
+
+/// Apply the filtering options to the analysis results of one file and collect the findings that
+/// are to be reported.
+fn build_report(
+    options: &Options,
+    environment: &Environment,
+    mounts: Option<&MountTable>,
+    provided: &AssumedProvided,
+    preloads: &[Preloaded],
+    mut libs: LibraryDependencies,
+) -> Report {
+    libs.report_loader_mismatch(options.sysroot.as_deref());
+    if options.hermetic {
+        libs.report_environment_dependent();
+    }
+    if let Some(ref allowed_prefixes) = options.allowed_prefixes {
+        let prefixes = allowed_prefixes
+            .split(':')
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        libs.report_outside_prefixes(&prefixes);
+    }
+    if options.case_insensitive_fs {
+        libs.report_case_mismatches();
+    }
+
+    // Libraries (and their dependencies) whose problems the user is not interested in.
+    let ignored_paths = libs.only_reachable_via(&options.ignore_via);
+    let ignored_names = libs.resolved
+        .iter()
+        .filter(|&(_, path)| ignored_paths.contains(path))
+        .map(|(name, _)| name.to_string_lossy().to_string())
+        .collect::<HashSet<_>>();
+    libs.problems
+        .retain(|problem| !ignored_paths.contains(problem.dependent_lib()));
+    libs.problems.retain(|problem| !provided.covers(problem));
+
+    let mut duplicate_symbols = Vec::new();
+    let mut symbol_classes = HashMap::new();
+    let mut unresolved_symbols = Vec::new();
+    let self_imports = if options.show_duplicate_symbols {
+        find_self_imports(&libs)
+    } else {
+        HashMap::new()
+    };
     {
-        options.show_duplicate_symbols = true;
-        options.show_unresolved_symbols = true;
-        options.show_lib_resolution_problems = true;
+        let mut symbol_summary = SymbolSummary::from_libs(&libs);
+        // Preloads (and their dependencies) are part of the lookup scope as well.
+        for preload in preloads {
+            symbol_summary.merge(SymbolSummary::from_libs(&preload.libs));
+        }
+
+        if options.show_duplicate_symbols {
+            // Symbols that an exporting object imports itself are used even if no other object
+            // references them.
+            let self_imported = self_imports
+                .iter()
+                .filter(|&(path, _)| !ignored_paths.contains(path))
+                .flat_map(|(_, names)| names.iter().map(|name| name.as_str()))
+                .collect::<HashSet<_>>();
+            duplicate_symbols = symbol_summary
+                .exported
+                .iter()
+                .map(|(symbol, libs)| {
+                    let libs = without_ignored(libs, &ignored_names);
+                    (symbol.clone(), symbol_summary.duplicate_exporters(symbol, &libs))
+                })
+                .filter(|(symbol, libs)| {
+                    libs.len() >= 2
+                        && (symbol_summary.is_referenced(symbol)
+                            || self_imported.contains(symbol.as_str()))
+                })
+                .collect::<Vec<_>>();
+            duplicate_symbols.sort_by(|a, b| a.0.cmp(&b.0));
+            symbol_classes = duplicate_symbols
+                .iter()
+                .filter_map(|(symbol, _)| {
+                    symbol_summary
+                        .special_classes
+                        .get(symbol)
+                        .map(|&class| (symbol.clone(), class))
+                })
+                .collect();
+        }
+
+        if options.show_unresolved_symbols {
+            unresolved_symbols = symbol_summary
+                .unresolved
+                .iter()
+                .map(|(symbol, libs)| (symbol.clone(), without_ignored(libs, &ignored_names)))
+                .filter(|(symbol, libs)| {
+                    !libs.is_empty() && !symbol_summary.defined.contains_key(symbol.as_str())
+                        && !provided.provides_symbol(symbol)
+                })
+                .collect::<Vec<_>>();
+            unresolved_symbols.sort_by(|a, b| a.0.cmp(&b.0));
+        }
     }
+    let mismatched_duplicates = find_mismatched_duplicates(&libs, &duplicate_symbols);
+    let self_imported_symbols =
+        find_self_imported_symbols(&libs, &self_imports, &duplicate_symbols);
+    let sysroot = options.sysroot.as_deref();
+    let (dispatched_symbols, driver_problems) =
+        resolve_via_dispatch(&libs, sysroot, &mut unresolved_symbols);
+    let unresolved_data = find_unresolved_data(&libs, &unresolved_symbols);
 
-    let libs = LibraryDependencies::try_find_for_elf(&options.file, &search_methods)?;
+    let mut packaging_problems = Vec::new();
+    if options.show_lib_resolution_problems {
+        let root = libs.opened_libs
+            .get(&libs.root)
+            .expect("The root is always opened");
+        packaging_problems.extend(check_shared_library(root));
+
+        let mut opened_libs = libs.opened_libs.values().collect::<Vec<_>>();
+        opened_libs.sort_by_key(|lib| lib.get_path());
+        for lib in opened_libs {
+            packaging_problems.extend(check_hash_tables(lib));
+            packaging_problems.extend(check_prelink(lib));
+            if lib.get_path() != libs.root {
+                let mut dependents = libs.opened_libs
+                    .keys()
+                    .filter(|path| libs.resolved_dependencies(path).any(|d| d == lib.get_path()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                dependents.sort();
+                packaging_problems.extend(check_dependency_exports(lib, dependents));
+            }
+        }
+    } else {
+        libs.problems.clear();
+    }
 
-    let symbol_summary = SymbolSummary::from_libs(&libs);
+    let (
+        version_problems,
+        missing_versions,
+        cxx_runtime_problems,
+        executable_symbols,
+        underlinked_libraries,
+    ) = if options.show_unresolved_symbols {
+        (
+            find_non_default_version_only(&libs),
+            find_missing_versions(&libs),
+            find_cxx_runtime_problems(&libs),
+            find_executable_symbols(&libs),
+            find_underlinked_libraries(&libs),
+        )
+    } else {
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
+    };
 
-    let duplicate_groups = symbol_summary
-        .exported
+    let mut entries = Vec::new();
+    if options.audit_preload {
+        let ld_preload = environment
+            .var_os("LD_PRELOAD")
+            .map(|s| s.to_string_lossy().to_string());
+        entries.extend(preload_entries(ld_preload.as_deref(), sysroot));
+    }
+    let simulated = options
+        .preload
         .iter()
-        .filter(|&(symbol, libs)| {
-            libs.len() >= 2 && symbol_summary.unresolved.get(symbol.as_str()).is_some()
+        .map(|spec| PreloadEntry {
+            name: spec.name.clone(),
+            source: "--preload",
         })
+        .collect::<Vec<_>>();
+    let preload_problems = {
+        let mut problems = audit_preloads(&entries, &libs, sysroot);
+        // The location of simulated preloads is up to the user, only whether they can be loaded
+        // matters.
+        problems.extend(
+            audit_preloads(&simulated, &libs, sysroot)
+                .into_iter()
+                .filter(|p| !matches!(p, &PreloadProblem::InsecureLocation { .. })),
+        );
+        problems
+    };
+    let interpositions = find_interpositions(&libs, preloads);
+
+    let search_path_oddities = if options.show_search_path_oddities {
+        find_search_path_oddities(&libs)
+    } else {
+        Vec::new()
+    };
+
+    let dlopen_problems = options
+        .dlopen_targets
+        .iter()
+        .filter(|target| target.is(&libs.root))
+        .flat_map(|target| check_dlopen_target(&libs, target))
+        .collect();
+
+    let budget_violations = check_budgets(
+        &libs,
+        options.max_deps,
+        options.max_closure_size.map(|size| size.0),
+    );
+
+    let rpath_usages = if options.rpath_usage {
+        find_rpath_usages(&libs, options.sysroot.as_deref())
+    } else {
+        Vec::new()
+    };
+
+    let unknown_dynamic_tags = find_unknown_dynamic_tags(&libs);
+    let hardening = match options.command {
+        Some(Command::Harden { .. }) => Some(HardeningAudit::of_root(&libs)),
+        _ => None,
+    };
+
+    let mount_problems = match mounts {
+        Some(mounts) => find_mount_problems(&libs, mounts),
+        None => Vec::new(),
+    };
+    let text_relocations = find_text_relocations(&libs);
+
+    let minimum_versions = if options.show_minimum_versions {
+        find_minimum_versions(&libs)
+    } else {
+        Vec::new()
+    };
+
+    let unused_dependencies = if options.show_unused_dependencies {
+        find_unused_dependencies(&libs)
+    } else {
+        Vec::new()
+    };
+
+    Report {
+        libs,
+        packaging_problems,
+        preload_problems,
+        interpositions,
+        search_path_oddities,
+        rpath_usages,
+        unknown_dynamic_tags,
+        hardening,
+        minimum_versions,
+        mount_problems,
+        text_relocations,
+        budget_violations,
+        dlopen_problems,
+        unused_dependencies,
+        unresolved_symbols,
+        unresolved_data,
+        dispatched_symbols,
+        driver_problems,
+        version_problems,
+        missing_versions,
+        cxx_runtime_problems,
+        executable_symbols,
+        underlinked_libraries,
+        duplicate_symbols,
+        symbol_classes,
+        mismatched_duplicates,
+        self_imported_symbols,
+    }
+}
+
+/// Write how many analyzed files are affected by each kind of finding, per package that owns the
+/// library that needs to be fixed.
+fn write_package_report(
+    theme: Theme,
+    reports: &[(PathBuf, Result<Report, String>)],
+    packages: &mut PackageDb,
+    t: &mut ReportTerminal,
+) {
+    // Package -> kind of finding -> affected files and number of findings
+    let mut rollup = BTreeMap::new();
+    for (file, report) in reports.iter() {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                theme.apply(t, Severity::Error);
+                writeln!(t, "{}: {}", file.display(), err).unwrap();
+                t.reset().unwrap();
+                continue;
+            }
+        };
+        for finding in report.to_result().findings() {
+            for owner in finding.owners.iter() {
+                let package = packages
+                    .package_of(owner)
+                    .unwrap_or(format!("{} (not packaged)", owner.display()));
+                let entry = rollup
+                    .entry(package)
+                    .or_insert(BTreeMap::new())
+                    .entry(finding.kind())
+                    .or_insert((BTreeSet::new(), 0));
+                let _ = entry.0.insert(file.clone());
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let count = |n: usize, singular: &str, plural: &str| match n {
+        1 => format!("1 {}", singular),
+        n => format!("{} {}", n, plural),
+    };
+    for (package, kinds) in rollup {
+        let files = kinds
+            .values()
+            .flat_map(|(files, _)| files.iter())
+            .collect::<BTreeSet<_>>();
+        theme.apply(t, Severity::Error);
+        writeln!(t, "{}: {}", package, count(files.len(), "binary", "binaries")).unwrap();
+        t.reset().unwrap();
+        for (kind, (files, num_findings)) in kinds {
+            writeln!(
+                t,
+                "\t{} with {} against it ({})",
+                count(files.len(), "binary", "binaries"),
+                kind,
+                count(num_findings, "finding", "findings")
+            ).unwrap();
+        }
+    }
+}
+
+/// Write the findings of one analyzed file grouped by the library that needs to be fixed.
+fn write_report_by_owner(theme: Theme, report: &Report, t: &mut ReportTerminal) {
+    let findings = report.to_result().findings();
+    for (owner, descriptions) in group_by_owner(findings) {
+        theme.apply(t, Severity::Error);
+        writeln!(t, "{} ({} findings):", owner.display(), descriptions.len()).unwrap();
+        t.reset().unwrap();
+        for description in descriptions {
+            writeln!(t, "\t{}", description).unwrap();
+        }
+    }
+}
+
+/// Write the findings of one analyzed file grouped by the dependency depth of the library that
+/// needs to be fixed.
+fn write_report_by_depth(theme: Theme, report: &Report, t: &mut ReportTerminal) {
+    let findings = report.to_result().findings();
+    let depths = report.libs.depths();
+    for (bucket, owners) in group_by_depth(findings, &depths) {
+        let count = owners.values().map(|d| d.len()).sum::<usize>();
+        theme.apply(t, Severity::Error);
+        writeln!(t, "{} ({} findings):", bucket, count).unwrap();
+        t.reset().unwrap();
+        for (owner, descriptions) in owners {
+            writeln!(t, "\t{}:", owner.display()).unwrap();
+            for description in descriptions {
+                writeln!(t, "\t\t{}", description).unwrap();
+            }
+        }
+    }
+}
+
+/// Write a single finding line, followed by its fingerprint in verbose mode.
+fn write_finding<F: fmt::Display>(
+    t: &mut ReportTerminal,
+    verbose: bool,
+    finding: &F,
+    fingerprint: String,
+) {
+    if verbose {
+        writeln!(t, "\t{} [{}]", finding, fingerprint).unwrap();
+    } else {
+        writeln!(t, "\t{}", finding).unwrap();
+    }
+}
+
+/// Write the human-readable report for one analyzed file.
+fn write_report(options: &Options, theme: Theme, report: &Report, t: &mut ReportTerminal) {
+    let libs = &report.libs;
+
+    if options.by_owner || options.by_depth {
+        if options.by_owner {
+            write_report_by_owner(theme, report, t);
+        } else {
+            write_report_by_depth(theme, report, t);
+        }
+        write_tally(
+            t,
+            options,
+            libs.problems.len(),
+            report.unresolved_symbols.len(),
+            report.duplicate_symbols.len(),
+        );
+        return;
+    }
+
+    let duplicate_groups = report
+        .duplicate_symbols
+        .iter()
         .map(|(symbol, libs)| (libs_to_key(libs), symbol))
         .group::<HashMap<_, Vec<_>>>();
-
-    let unresolved_groups = symbol_summary
-        .unresolved
+    let unresolved_groups = report
+        .unresolved_symbols
         .iter()
-        .filter(|&(symbol, libs)| {
-            libs.len() >= 1 && symbol_summary.defined.get(symbol.as_str()).is_none()
-        })
         .map(|(symbol, libs)| (libs_to_key(libs), symbol))
         .group::<HashMap<_, Vec<_>>>();
 
-    let mut t = term::stdout().unwrap();
+    let width = if options.wide {
+        None
+    } else {
+        terminal_width()
+    };
+    let max_symbols = if options.full_symbol_groups {
+        None
+    } else {
+        Some(options.max_symbols_per_group)
+    };
+
+    if options.verbose {
+        theme.apply(t, Severity::Info);
+        writeln!(t, "Resolved libraries:").unwrap();
+        t.reset().unwrap();
+
+        let mut resolved = libs.resolved.iter().collect::<Vec<_>>();
+        resolved.sort();
+        for (lib_name, lib_path) in resolved {
+            t.attr(term::Attr::Bold).unwrap();
+            writeln!(t, "\t{} => {:?}", lib_name.to_string_lossy(), lib_path).unwrap();
+            t.reset().unwrap();
+            if let Some(lib) = libs.opened_libs.get(lib_path) {
+                writeln!(t, "\t\t{}", ObjectInfo::of_library(lib)).unwrap();
+            }
+            for resolution in libs.resolutions.get(lib_path).into_iter().flat_map(|r| r.iter()) {
+                write!(t, "\t\t{}", resolution).unwrap();
+                if resolution.is_environment_dependent() {
+                    theme.apply(t, Severity::Warning);
+                    write!(t, " [environment dependent]").unwrap();
+                    t.reset().unwrap();
+                }
+                writeln!(t).unwrap();
+            }
+        }
+    }
+
+    if !report.driver_problems.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Unusable drivers of graphics dispatch libraries ({}):",
+            report.driver_problems.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.driver_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if options.verbose && !report.dispatched_symbols.is_empty() {
+        theme.apply(t, Severity::Info);
+        writeln!(
+            t,
+            "Symbols provided by graphics drivers through dispatch libraries ({}):",
+            report.dispatched_symbols.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for (symbol, drivers) in report.dispatched_symbols.iter() {
+            let drivers = drivers.iter().map(|d| d.to_string_lossy()).join(", ");
+            writeln!(t, "\t{} ({})", symbol, drivers).unwrap();
+        }
+    }
+
+    if options.verbose && !report.unknown_dynamic_tags.is_empty() {
+        theme.apply(t, Severity::Info);
+        writeln!(
+            t,
+            "Dynamic entries that are not simulated ({}):",
+            report.unknown_dynamic_tags.len()
+        ).unwrap();
+        t.reset().unwrap();
+        for tag in report.unknown_dynamic_tags.iter() {
+            write_finding(t, options.verbose, tag, tag.fingerprint());
+        }
+    }
+
+    if let Some(ref hardening) = report.hardening {
+        theme.apply(t, Severity::Info);
+        writeln!(t, "Hardening:").unwrap();
+        t.reset().unwrap();
+        for (name, outcome, hardened) in hardening.checks() {
+            write!(t, "\t{}: ", name).unwrap();
+            if !hardened {
+                theme.apply(t, Severity::Warning);
+            }
+            writeln!(t, "{}", outcome).unwrap();
+            t.reset().unwrap();
+        }
+    }
+
+    if !report.minimum_versions.is_empty() {
+        theme.apply(t, Severity::Info);
+        writeln!(t, "Minimum required versions ({}):", report.minimum_versions.len()).unwrap();
+        t.reset().unwrap();
+        for version in report.minimum_versions.iter() {
+            writeln!(t, "\t{}", version).unwrap();
+        }
+    }
 
     if options.show_lib_resolution_problems && !libs.problems.is_empty() {
-        t.fg(term::color::RED).unwrap();
-        t.attr(term::Attr::Bold).unwrap();
-        writeln!(t, "Library resolving problems:").unwrap();
+        theme.apply(t, Severity::Error);
+        writeln!(t, "Library resolving problems ({}):", libs.problems.len()).unwrap();
         t.reset().unwrap();
 
         for problem in libs.problems.iter() {
-            writeln!(t, "\t{}", problem).unwrap();
+            write_finding(t, options.verbose, problem, problem.fingerprint());
         }
     }
 
-    if options.show_unresolved_symbols && !unresolved_groups.is_empty() {
-        t.fg(term::color::RED).unwrap();
-        t.attr(term::Attr::Bold).unwrap();
-        writeln!(t, "Unresolved symbols:").unwrap();
+    if options.show_lib_resolution_problems && !libs.skipped_incompatible.is_empty() {
+        theme.apply(t, Severity::Info);
+        writeln!(
+            t,
+            "Skipped library candidates of another architecture ({}):",
+            libs.skipped_incompatible.len()
+        ).unwrap();
         t.reset().unwrap();
 
-        for (libs, unresolved_symbols) in unresolved_groups {
-            t.attr(term::Attr::Bold).unwrap();
-            write!(t, "\t{}:", libs).unwrap();
+        for skipped in libs.skipped_incompatible.iter() {
+            write_finding(t, options.verbose, skipped, skipped.fingerprint());
+        }
+    }
+
+    if options.show_lib_resolution_problems && !options.hermetic {
+        let mut env_resolutions = libs.environment_dependent_resolutions().collect::<Vec<_>>();
+        if !env_resolutions.is_empty() {
+            env_resolutions.sort_by_key(|&(path, _)| path);
+
+            theme.apply(t, Severity::Warning);
+            writeln!(t, "Environment dependent library resolutions (use --hermetic to treat as problems):").unwrap();
             t.reset().unwrap();
-            writeln!(t, " [{}]\n", symbols_to_key(unresolved_symbols.as_slice())).unwrap();
+
+            for (path, resolution) in env_resolutions {
+                writeln!(t, "\t{:?}: {}", path, resolution).unwrap();
+            }
         }
     }
 
-    if options.show_duplicate_symbols && !duplicate_groups.is_empty() {
-        t.fg(term::color::RED).unwrap();
-        t.attr(term::Attr::Bold).unwrap();
-        writeln!(t, "Exported duplicate symbols:").unwrap();
+    if !report.preload_problems.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(t, "Preload problems ({}):", report.preload_problems.len()).unwrap();
         t.reset().unwrap();
 
-        for (libs, duplicate_symbols) in duplicate_groups {
-            t.attr(term::Attr::Bold).unwrap();
-            write!(t, "\t{}:", libs).unwrap();
-            t.reset().unwrap();
-            writeln!(t, " [{}]\n", symbols_to_key(duplicate_symbols.as_slice())).unwrap();
+        for problem in report.preload_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
         }
     }
 
-    Ok(())
-}
+    if !report.interpositions.is_empty() {
+        let severity = if report.interpositions.iter().any(|i| i.is_error()) {
+            Severity::Error
+        } else if report.interpositions.iter().any(|i| i.is_unintended()) {
+            Severity::Warning
+        } else {
+            Severity::Info
+        };
+        theme.apply(t, severity);
+        writeln!(
+            t,
+            "Symbols overridden by preloads ({}):",
+            report.interpositions.len()
+        ).unwrap();
+        t.reset().unwrap();
 
-fn main() {
-    let options = Options::from_args();
-    if let Err(err) = run(options) {
-        println!("{}", err);
+        for interposition in report.interpositions.iter() {
+            write_finding(t, options.verbose, interposition, interposition.fingerprint());
+        }
+    }
+
+    if !report.packaging_problems.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(t, "Packaging problems ({}):", report.packaging_problems.len()).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.packaging_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if !report.search_path_oddities.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(t, "Search path oddities ({}):", report.search_path_oddities.len()).unwrap();
+        t.reset().unwrap();
+
+        for oddity in report.search_path_oddities.iter() {
+            write_finding(t, options.verbose, oddity, oddity.fingerprint());
+        }
+    }
+
+    if !report.rpath_usages.is_empty() {
+        theme.apply(t, Severity::Info);
+        writeln!(t, "RPATH/RUNPATH usage ({} objects):", report.rpath_usages.len()).unwrap();
+        t.reset().unwrap();
+
+        for usage in report.rpath_usages.iter() {
+            write_finding(t, options.verbose, usage, usage.fingerprint());
+        }
+    }
+
+    if !report.budget_violations.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(t, "Exceeded budgets ({}):", report.budget_violations.len()).unwrap();
+        t.reset().unwrap();
+
+        for violation in report.budget_violations.iter() {
+            write_finding(t, options.verbose, violation, violation.fingerprint());
+        }
+    }
+
+    if !report.dlopen_problems.is_empty() {
+        let severity = if report.dlopen_problems.iter().any(|p| p.is_error()) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        theme.apply(t, severity);
+        writeln!(t, "Problems loading with dlopen ({}):", report.dlopen_problems.len()).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.dlopen_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if !report.mount_problems.is_empty() {
+        let severity = if report.mount_problems.iter().any(|p| p.is_error()) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        theme.apply(t, severity);
+        writeln!(
+            t,
+            "Libraries on noexec or network file systems ({}):",
+            report.mount_problems.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.mount_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if !report.text_relocations.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Libraries with text relocations ({}):",
+            report.text_relocations.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for relocations in report.text_relocations.iter() {
+            write_finding(t, options.verbose, relocations, relocations.fingerprint());
+        }
+    }
+
+    if !report.unused_dependencies.is_empty() {
+        let (speculative, others): (Vec<_>, Vec<_>) = report
+            .unused_dependencies
+            .iter()
+            .partition(|u| u.is_speculative());
+        let (shadowed, constructors_only): (Vec<_>, Vec<_>) =
+            others.into_iter().partition(|u| u.is_shadowed());
+        if !speculative.is_empty() {
+            theme.apply(t, Severity::Warning);
+            writeln!(
+                t,
+                "Unused dependencies (probably linked without --as-needed, {}):",
+                speculative.len()
+            ).unwrap();
+            t.reset().unwrap();
+
+            for unused in speculative {
+                write_finding(t, options.verbose, unused, unused.fingerprint());
+            }
+        }
+        if !constructors_only.is_empty() {
+            theme.apply(t, Severity::Info);
+            writeln!(
+                t,
+                "Dependencies only needed for their constructors ({}):",
+                constructors_only.len()
+            ).unwrap();
+            t.reset().unwrap();
+
+            for unused in constructors_only {
+                write_finding(t, options.verbose, unused, unused.fingerprint());
+            }
+        }
+        if !shadowed.is_empty() {
+            theme.apply(t, Severity::Info);
+            writeln!(
+                t,
+                "Dependencies whose symbols are taken from other libraries ({}):",
+                shadowed.len()
+            ).unwrap();
+            t.reset().unwrap();
+
+            for unused in shadowed {
+                write_finding(t, options.verbose, unused, unused.fingerprint());
+            }
+        }
+    }
+
+    if options.show_unresolved_symbols && !unresolved_groups.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Unresolved symbols ({} in {} groups):",
+            report.unresolved_symbols.len(),
+            unresolved_groups.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for (libs, unresolved_symbols) in unresolved_groups {
+            t.attr(term::Attr::Bold).unwrap();
+            write!(t, "\t{}:", libs).unwrap();
+            t.reset().unwrap();
+            let classes = HashMap::new();
+            write_symbols(&mut *t, unresolved_symbols.as_slice(), &classes, width, max_symbols);
+        }
+    }
+
+    if !report.unresolved_data.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Libraries that fail to load because of unresolved data symbols ({}):",
+            report.unresolved_data.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for data in report.unresolved_data.iter() {
+            write_finding(t, options.verbose, data, data.fingerprint());
+        }
+    }
+
+    if !report.version_problems.is_empty() {
+        let severity = if report.version_problems.iter().any(|p| p.is_error()) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        theme.apply(t, severity);
+        writeln!(
+            t,
+            "Symbols only exported in non-default versions ({}):",
+            report.version_problems.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.version_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if !report.missing_versions.is_empty() {
+        let severity = if report.missing_versions.iter().any(|m| m.is_error()) {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        theme.apply(t, severity);
+        writeln!(
+            t,
+            "Required symbol versions not defined by the providers ({}):",
+            report.missing_versions.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for missing in report.missing_versions.iter() {
+            write_finding(t, options.verbose, missing, missing.fingerprint());
+        }
+    }
+
+    if !report.cxx_runtime_problems.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(t, "C++ runtime problems ({}):", report.cxx_runtime_problems.len()).unwrap();
+        t.reset().unwrap();
+
+        for problem in report.cxx_runtime_problems.iter() {
+            write_finding(t, options.verbose, problem, problem.fingerprint());
+        }
+    }
+
+    if !report.executable_symbols.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Symbols only the executable can provide ({}):",
+            report.executable_symbols.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for symbol in report.executable_symbols.iter() {
+            write_finding(t, options.verbose, symbol, symbol.fingerprint());
+        }
+    }
+
+    if !report.underlinked_libraries.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Libraries using symbols of objects they do not depend on ({}):",
+            report.underlinked_libraries.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for underlinked in report.underlinked_libraries.iter() {
+            write_finding(t, options.verbose, underlinked, underlinked.fingerprint());
+        }
+    }
+
+    if options.show_duplicate_symbols && !duplicate_groups.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Exported duplicate symbols ({} in {} groups):",
+            report.duplicate_symbols.len(),
+            duplicate_groups.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for (libs, duplicate_symbols) in duplicate_groups {
+            t.attr(term::Attr::Bold).unwrap();
+            write!(t, "\t{}:", libs).unwrap();
+            t.reset().unwrap();
+            write_symbols(
+                &mut *t,
+                duplicate_symbols.as_slice(),
+                &report.symbol_classes,
+                width,
+                max_symbols,
+            );
+        }
+    }
+
+    if !report.mismatched_duplicates.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Duplicate symbols with mismatching type or size ({}):",
+            report.mismatched_duplicates.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for duplicate in report.mismatched_duplicates.iter() {
+            write_finding(t, options.verbose, duplicate, duplicate.fingerprint());
+        }
+    }
+
+    if !report.self_imported_symbols.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Duplicate symbols imported by their exporter ({}):",
+            report.self_imported_symbols.len()
+        ).unwrap();
+        t.reset().unwrap();
+
+        for symbol in report.self_imported_symbols.iter() {
+            write_finding(t, options.verbose, symbol, symbol.fingerprint());
+        }
+    }
+
+    write_tally(
+        t,
+        options,
+        libs.problems.len(),
+        report.unresolved_symbols.len(),
+        report.duplicate_symbols.len(),
+    );
+}
+
+/// Write the results of the symbol analysis of one link namespace.
+fn write_namespace_report(
+    options: &Options,
+    theme: Theme,
+    report: &NamespaceReport,
+    t: &mut ReportTerminal,
+) {
+    let width = if options.wide {
+        None
+    } else {
+        terminal_width()
+    };
+    let max_symbols = if options.full_symbol_groups {
+        None
+    } else {
+        Some(options.max_symbols_per_group)
+    };
+
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(t, "==> namespace {} <==", report.name).unwrap();
+    t.reset().unwrap();
+
+    if !report.missing_proxies.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Symbols only defined in other namespaces ({}):",
+            report.missing_proxies.len()
+        ).unwrap();
+        t.reset().unwrap();
+        for (symbol, libs, providers) in report.missing_proxies.iter() {
+            let symbol = demangle(symbol).unwrap_or(symbol.to_string());
+            writeln!(
+                t,
+                "\t{} (needed by {}, defined in namespace {})",
+                symbol,
+                libs_to_key(libs),
+                providers.join(", ")
+            ).unwrap();
+        }
+    }
+
+    if options.show_unresolved_symbols && !report.unresolved.is_empty() {
+        let groups = report
+            .unresolved
+            .iter()
+            .map(|(symbol, libs)| (libs_to_key(libs), symbol))
+            .group::<HashMap<_, Vec<_>>>();
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Unresolved symbols ({} in {} groups):",
+            report.unresolved.len(),
+            groups.len()
+        ).unwrap();
+        t.reset().unwrap();
+        let classes = HashMap::new();
+        for (libs, symbols) in groups {
+            t.attr(term::Attr::Bold).unwrap();
+            write!(t, "\t{}:", libs).unwrap();
+            t.reset().unwrap();
+            write_symbols(&mut *t, symbols.as_slice(), &classes, width, max_symbols);
+        }
+    }
+
+    if options.show_duplicate_symbols && !report.duplicates.is_empty() {
+        let groups = report
+            .duplicates
+            .iter()
+            .map(|(symbol, libs)| (libs_to_key(libs), symbol))
+            .group::<HashMap<_, Vec<_>>>();
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Exported duplicate symbols ({} in {} groups):",
+            report.duplicates.len(),
+            groups.len()
+        ).unwrap();
+        t.reset().unwrap();
+        for (libs, symbols) in groups {
+            t.attr(term::Attr::Bold).unwrap();
+            write!(t, "\t{}:", libs).unwrap();
+            t.reset().unwrap();
+            write_symbols(&mut *t, symbols.as_slice(), &report.classes, width, max_symbols);
+        }
+    }
+
+    if report.num_cross_namespace_duplicates > 0 {
+        theme.apply(t, Severity::Info);
+        writeln!(
+            t,
+            "{} symbols are also exported in other namespaces (no conflict)",
+            report.num_cross_namespace_duplicates
+        ).unwrap();
+        t.reset().unwrap();
+    }
+    writeln!(t).unwrap();
+}
+
+/// Write the results of the closed-world analysis of a root file system image.
+fn write_rootfs_report(
+    theme: Theme,
+    analysis: &RootfsAnalysis,
+    show_usage: bool,
+    t: &mut ReportTerminal,
+) {
+    if show_usage {
+        let usage = analysis.library_usage();
+        theme.apply(t, Severity::Info);
+        writeln!(t, "Library usage ({} libraries):", usage.len()).unwrap();
+        t.reset().unwrap();
+        for (lib, usage) in usage.iter() {
+            t.attr(term::Attr::Bold).unwrap();
+            writeln!(
+                t,
+                "\t{} (used by {}, breaks {} if removed):",
+                lib.display(),
+                usage.users.len(),
+                usage.dependents.len()
+            ).unwrap();
+            t.reset().unwrap();
+            let users = usage.users.iter().map(|b| b.to_string_lossy()).join(", ");
+            writeln!(t, "\t\tused by: {}", users).unwrap();
+            if !usage.dependents.is_empty() {
+                let dependents = usage.dependents.iter().map(|b| b.to_string_lossy()).join(", ");
+                writeln!(t, "\t\tbreaks: {}", dependents).unwrap();
+            }
+        }
+    }
+
+    let startup_problems = analysis.startup_problems();
+    if !startup_problems.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(t, "Binaries that cannot start ({}):", startup_problems.len()).unwrap();
+        t.reset().unwrap();
+        for (path, reasons) in startup_problems.iter() {
+            t.attr(term::Attr::Bold).unwrap();
+            writeln!(t, "\t{}:", path.display()).unwrap();
+            t.reset().unwrap();
+            for reason in reasons {
+                writeln!(t, "\t\t{}", reason).unwrap();
+            }
+        }
+    }
+
+    let unsatisfiable_symbols = analysis.unsatisfiable_symbols();
+    if !unsatisfiable_symbols.is_empty() {
+        theme.apply(t, Severity::Error);
+        writeln!(
+            t,
+            "Symbols that cannot be satisfied within the image ({}):",
+            unsatisfiable_symbols.len()
+        ).unwrap();
+        t.reset().unwrap();
+        for (symbol, binaries) in unsatisfiable_symbols.iter() {
+            let symbol = demangle(symbol).unwrap_or(symbol.to_string());
+            let binaries = binaries.iter().map(|b| b.to_string_lossy()).join(", ");
+            writeln!(t, "\t{} (needed by {})", symbol, binaries).unwrap();
+        }
+    }
+
+    let unused_libraries = analysis.unused_libraries();
+    if !unused_libraries.is_empty() {
+        theme.apply(t, Severity::Warning);
+        writeln!(
+            t,
+            "Libraries that no binary uses ({}, may still be loaded with dlopen):",
+            unused_libraries.len()
+        ).unwrap();
+        t.reset().unwrap();
+        for lib in unused_libraries.iter() {
+            writeln!(t, "\t{}", lib.display()).unwrap();
+        }
+    }
+
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(
+        t,
+        "Analyzed {} binaries and {} libraries: {} binaries cannot start, {} unsatisfiable symbols, {} unused libraries.",
+        analysis.executables.len(),
+        analysis.libraries.len(),
+        startup_problems.len(),
+        unsatisfiable_symbols.len(),
+        unused_libraries.len()
+    ).unwrap();
+    t.reset().unwrap();
+}
+
+fn write_comparison_report(theme: Theme, comparison: &RootComparison, t: &mut ReportTerminal) {
+    let (a, b) = (&comparison.a, &comparison.b);
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(
+        t,
+        "Comparing {} in {} (a) and {} (b):",
+        comparison.binary.display(),
+        a.root.display(),
+        b.root.display()
+    ).unwrap();
+    t.reset().unwrap();
+    writeln!(
+        t,
+        "\ta: {} libraries, {} library resolution problems",
+        a.resolved.len(),
+        a.libs.problems.len()
+    ).unwrap();
+    writeln!(
+        t,
+        "\tb: {} libraries, {} library resolution problems",
+        b.resolved.len(),
+        b.libs.problems.len()
+    ).unwrap();
+    writeln!(t).unwrap();
+
+    let library_deltas = comparison.library_deltas();
+    if !library_deltas.is_empty() {
+        let regressions = library_deltas
+            .iter()
+            .any(|(_, delta)| matches!(delta, &LibraryDelta::OnlyInA(_)));
+        theme.apply(t, if regressions { Severity::Error } else { Severity::Warning });
+        writeln!(t, "Library differences ({}):", library_deltas.len()).unwrap();
+        t.reset().unwrap();
+        for &(name, ref delta) in library_deltas.iter() {
+            match *delta {
+                LibraryDelta::OnlyInA(a) => writeln!(
+                    t,
+                    "\t{}: resolves in a ({}), but not in b",
+                    name,
+                    a.path.display()
+                ),
+                LibraryDelta::OnlyInB(b) => writeln!(
+                    t,
+                    "\t{}: resolves in b ({}), but not in a",
+                    name,
+                    b.path.display()
+                ),
+                LibraryDelta::DifferentProvider(a, b) => writeln!(
+                    t,
+                    "\t{}: different provider: {} (a), {} (b)",
+                    name,
+                    a.path.display(),
+                    b.path.display()
+                ),
+                LibraryDelta::DifferentVersion(a, b) if a.file_name != b.file_name => writeln!(
+                    t,
+                    "\t{}: different version: {} (a), {} (b)",
+                    name, a.file_name, b.file_name
+                ),
+                LibraryDelta::DifferentVersion(a, b) => writeln!(
+                    t,
+                    "\t{}: different build: {} (a), {} (b)",
+                    name,
+                    a.build_id.as_ref().map_or("no build id", |id| id.as_str()),
+                    b.build_id.as_ref().map_or("no build id", |id| id.as_str())
+                ),
+            }.unwrap();
+        }
+        writeln!(t).unwrap();
+    }
+
+    let symbol_deltas = comparison.symbol_deltas();
+    if !symbol_deltas.is_empty() {
+        let regressions = symbol_deltas
+            .iter()
+            .any(|(_, delta)| matches!(delta, &SymbolDelta::OnlyInA(_)));
+        theme.apply(t, if regressions { Severity::Error } else { Severity::Warning });
+        writeln!(t, "Symbol differences ({}):", symbol_deltas.len()).unwrap();
+        t.reset().unwrap();
+        for &(symbol, ref delta) in symbol_deltas.iter() {
+            let symbol = demangle(symbol).unwrap_or(symbol.to_string());
+            match *delta {
+                SymbolDelta::OnlyInA(a) => writeln!(
+                    t,
+                    "\t{}: provided in a (by {}), but not in b",
+                    symbol,
+                    a.iter().join(", ")
+                ),
+                SymbolDelta::OnlyInB(b) => writeln!(
+                    t,
+                    "\t{}: provided in b (by {}), but not in a",
+                    symbol,
+                    b.iter().join(", ")
+                ),
+                SymbolDelta::DifferentProviders(a, b) => writeln!(
+                    t,
+                    "\t{}: different providers: {} (a), {} (b)",
+                    symbol,
+                    a.iter().join(", "),
+                    b.iter().join(", ")
+                ),
+            }.unwrap();
+        }
+        writeln!(t).unwrap();
+    }
+
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(
+        t,
+        "Found {} library differences, {} symbol differences.",
+        library_deltas.len(),
+        symbol_deltas.len()
+    ).unwrap();
+    t.reset().unwrap();
+}
+
+fn write_history_report(theme: Theme, target: &Path, runs: &[HistoryRun], t: &mut ReportTerminal) {
+    t.attr(term::Attr::Bold).unwrap();
+    writeln!(t, "History of {:?} ({} runs):", target, runs.len()).unwrap();
+    t.reset().unwrap();
+
+    let mut previous: Option<&HistoryRun> = None;
+    for run in runs {
+        write!(t, "\t{}", run).unwrap();
+        if let Some(previous) = previous {
+            let new = run.fingerprints.difference(&previous.fingerprints).count();
+            let fixed = previous.fingerprints.difference(&run.fingerprints).count();
+            write!(t, " (").unwrap();
+            theme.apply(t, if new > 0 { Severity::Error } else { Severity::Info });
+            write!(t, "{} new", new).unwrap();
+            t.reset().unwrap();
+            write!(t, ", {} fixed)", fixed).unwrap();
+        }
+        writeln!(t).unwrap();
+
+        for (rule, count) in run.counts.iter() {
+            writeln!(t, "\t\t{}: {}", rule, count).unwrap();
+        }
+        previous = Some(run);
+    }
+}
+
+/// Check the plugins in the directories of the --plugin-env variables against the closures of all
+/// analyzed files, which together make up the host process.
+fn check_plugins(
+    options: &Options,
+    search_methods: &[LibSearchMethod],
+    environment: &Environment,
+    reports: &[(PathBuf, Result<Report, String>)],
+    cache: &LibraryCache,
+) -> Vec<PluginCheck> {
+    let sysroot = options.sysroot.as_deref();
+    let mut plugins = Vec::new();
+    for plugin_env in options.plugin_envs.iter() {
+        for plugin in plugin_env.plugins(sysroot) {
+            plugins.push((plugin, plugin_env.variable.clone()));
+        }
+    }
+    if plugins.is_empty() {
+        return Vec::new();
+    }
+
+    let jobs = plugins
+        .iter()
+        .map(|(plugin, _)| {
+            let settings = ResolveSettings {
+                search_methods: search_methods.to_vec(),
+                secure_execution: options.secure_execution,
+                sysroot: options.sysroot.clone(),
+                identity: options.identity,
+                traversal: options.traversal,
+                environment: environment.clone(),
+                files: Arc::new(RealFs),
+            };
+            (plugin.clone(), settings)
+        })
+        .collect::<Vec<_>>();
+    let results = try_find_for_elfs(&jobs, cache);
+
+    let mut host = SymbolSummary::default();
+    let mut host_libs = HashSet::new();
+    for report in reports.iter().filter_map(|(_, report)| report.as_ref().ok()) {
+        host.merge(SymbolSummary::from_libs(&report.libs));
+        host_libs.extend(
+            report
+                .libs
+                .resolved
+                .keys()
+                .chain(report.libs.aliases.keys())
+                .map(|name| name.to_string_lossy().into_owned()),
+        );
+    }
+    plugins
+        .iter()
+        .zip(results.iter())
+        .map(|((plugin, variable), libs)| {
+            check_plugin(plugin, variable, libs, &host, &host_libs)
+        })
+        .collect()
+}
+
+/// Write whether each plugin can be loaded, with the reasons if it cannot.
+fn write_plugin_report(
+    options: &Options,
+    theme: Theme,
+    checks: &[PluginCheck],
+    t: &mut ReportTerminal,
+) {
+    let failed = checks.iter().filter(|c| !c.is_loadable()).count();
+    theme.apply(t, if failed > 0 { Severity::Error } else { Severity::Info });
+    writeln!(
+        t,
+        "Plugins ({}, {} not loadable):",
+        checks.len(),
+        failed
+    ).unwrap();
+    t.reset().unwrap();
+
+    for check in checks {
+        write!(t, "\t{} (${}): ", check.plugin.display(), check.variable).unwrap();
+        if check.is_loadable() {
+            writeln!(t, "ok").unwrap();
+            continue;
+        }
+        if let Some(ref err) = check.error {
+            writeln!(t, "{}", err).unwrap();
+            continue;
+        }
+        writeln!(
+            t,
+            "{} library resolution problems, {} unresolved symbols",
+            check.problems.len(),
+            check.unresolved_symbols.len()
+        ).unwrap();
+        for problem in check.problems.iter() {
+            writeln!(t, "\t\t{}", problem).unwrap();
+        }
+        let max_symbols = if options.full_symbol_groups {
+            check.unresolved_symbols.len()
+        } else {
+            options.max_symbols_per_group
+        };
+        for symbol in check.unresolved_symbols.iter().take(max_symbols) {
+            writeln!(t, "\t\t{}", demangle(symbol).unwrap_or(symbol.to_string())).unwrap();
+        }
+        if check.unresolved_symbols.len() > max_symbols {
+            let num_hidden = check.unresolved_symbols.len() - max_symbols;
+            writeln!(t, "\t\t{}", hidden_note(num_hidden)).unwrap();
+        }
+    }
+    writeln!(t).unwrap();
+}
+
+/// Write the closure and problems of each PE file analyzed against a Wine prefix.
+fn write_pe_report(theme: Theme, checks: &[PeCheck], t: &mut ReportTerminal) {
+    let failed = checks
+        .iter()
+        .filter(|c| c.error.is_some() || !c.problems.is_empty())
+        .count();
+    theme.apply(t, if failed > 0 { Severity::Error } else { Severity::Info });
+    writeln!(
+        t,
+        "Windows (PE) files ({}, {} with problems):",
+        checks.len(),
+        failed
+    ).unwrap();
+    t.reset().unwrap();
+
+    for check in checks {
+        t.attr(term::Attr::Bold).unwrap();
+        writeln!(t, "\t{}:", check.file.display()).unwrap();
+        t.reset().unwrap();
+        if let Some(ref err) = check.error {
+            writeln!(t, "\t\t{}", err).unwrap();
+            continue;
+        }
+        for (dll, path) in check.dlls.iter() {
+            writeln!(t, "\t\t{} => {}", dll, path.display()).unwrap();
+        }
+        for problem in check.problems.iter() {
+            theme.apply(t, Severity::Error);
+            writeln!(t, "\t\t{}", problem).unwrap();
+            t.reset().unwrap();
+        }
+    }
+}
+
+/// Write how many resolutions each configured search method produced.
+fn write_search_method_usage(theme: Theme, usages: &[MethodUsage], t: &mut ReportTerminal) {
+    let unused = usages.iter().filter(|u| u.resolutions == 0).count();
+    theme.apply(t, if unused > 0 { Severity::Warning } else { Severity::Info });
+    writeln!(
+        t,
+        "Search method usage ({} methods, {} never used):",
+        usages.len(),
+        unused
+    ).unwrap();
+    t.reset().unwrap();
+    for usage in usages {
+        match (usage.resolutions, &usage.problem) {
+            (0, Some(problem)) => {
+                writeln!(t, "\t{}: never used ({})", usage.method, problem).unwrap()
+            }
+            (0, &None) => writeln!(t, "\t{}: never used", usage.method).unwrap(),
+            (1, _) => writeln!(t, "\t{}: 1 resolution", usage.method).unwrap(),
+            (n, _) => writeln!(t, "\t{}: {} resolutions", usage.method, n).unwrap(),
+        }
+    }
+}
+
+/// Write the reports of all analyzed files (or the per-package rollup) as text.
+fn write_reports(
+    options: &Options,
+    theme: Theme,
+    reports: &[(PathBuf, Result<Report, String>)],
+    t: &mut ReportTerminal,
+) {
+    if options.by_package {
+        let mut packages = PackageDb::new(options.sysroot.as_deref());
+        write_package_report(theme, reports, &mut packages, t);
+        return;
+    }
+    let multiple_files = reports.len() > 1;
+    for (file, report) in reports {
+        if multiple_files {
+            t.attr(term::Attr::Bold).unwrap();
+            writeln!(t, "==> {} <==", file.display()).unwrap();
+            t.reset().unwrap();
+        }
+        match report {
+            Ok(report) => write_report(options, theme, report, t),
+            Err(err) => {
+                theme.apply(t, Severity::Error);
+                writeln!(t, "{}", err).unwrap();
+                t.reset().unwrap();
+            }
+        }
+        if multiple_files {
+            writeln!(t).unwrap();
+        }
+    }
+}
+
+/// The cache of opened libraries, which checks files as strictly as requested (see
+/// --hardened-parse).
+fn library_cache(options: &Options) -> LibraryCache {
+    LibraryCache::with_profile(if options.hardened_parse {
+        ParseProfile::Hardened
+    } else {
+        ParseProfile::Default
+    })
+}
+
+/// Run linkcheck and return the status of the run (see exit_codes).
+fn run(mut options: Options) -> Result<Status, Box<dyn Error>> {
+    let started = SystemTime::now();
+    if options.help_exit_codes {
+        print_exit_codes();
+        return Ok(Status::Success);
+    }
+
+    if let Some(Command::Rootfs { ref image, usage }) = options.command {
+        let image = Image::open(image)?;
+        let analysis = RootfsAnalysis::analyze(&image.root, &library_cache(&options))?;
+
+        let theme = options.theme.or(Config::load()?.theme).unwrap_or_default();
+        let (mut t, pager) = open_report_terminal(!options.no_pager);
+        write_rootfs_report(theme, &analysis, usage, &mut *t);
+        drop(t);
+        if let Some(pager) = pager {
+            pager.wait();
+        }
+        return Ok(rootfs_status(&analysis));
+    }
+
+    if let Some(Command::CompareRoots {
+        ref binary,
+        ref root_a,
+        ref root_b,
+    }) = options.command
+    {
+        let comparison = RootComparison::analyze(binary, root_a, root_b)?;
+
+        let theme = options.theme.or(Config::load()?.theme).unwrap_or_default();
+        let (mut t, pager) = open_report_terminal(!options.no_pager);
+        write_comparison_report(theme, &comparison, &mut *t);
+        drop(t);
+        if let Some(pager) = pager {
+            pager.wait();
+        }
+        return Ok(comparison_status(&comparison));
+    }
+
+    let container = match options.command {
+        Some(Command::Container { ref id, ref path }) => {
+            Some((ContainerRoot::open(id)?, path.clone()))
+        }
+        _ => None,
+    };
+    if let Some((ref container, ref path)) = container {
+        let root = container.root.clone();
+        options.files = vec![reroot(&Path::new("/").join(path), Some(&root))];
+        if options.search_methods.is_empty() {
+            options.search_methods = image_search_methods(&root);
+        }
+        // Anything resolved outside of the container would not be available inside of it. The
+        // check compares canonical paths, and paths below /proc/<pid>/root canonicalize to paths
+        // relative to the container root, so the canonical form of the root is used as prefix.
+        if options.allowed_prefixes.is_none() {
+            let prefix = std::fs::canonicalize(&root).unwrap_or(root.clone());
+            options.allowed_prefixes = Some(prefix.to_string_lossy().into_owned());
+        }
+        options.sysroot = Some(root);
+    }
+    if let Some(Command::Harden { ref files }) = options.command {
+        options.files = files.clone();
+    }
+
+    if let Some(ref prefix) = options.toolchain {
+        let toolchain = Toolchain::query(prefix)?;
+        if options.sysroot.is_none() {
+            options.sysroot = toolchain.sysroot.clone();
+        }
+        if options.search_methods.is_empty() {
+            options.search_methods = toolchain.search_methods();
+        }
+    }
+
+    if let Some(Command::History {
+        ref database,
+        ref target,
+    }) = options.command
+    {
+        let runs = History::open(database)?.runs(target)?;
+        let theme = options.theme.or(Config::load()?.theme).unwrap_or_default();
+        let (mut t, pager) = open_report_terminal(!options.no_pager);
+        write_history_report(theme, target, &runs, &mut *t);
+        drop(t);
+        if let Some(pager) = pager {
+            pager.wait();
+        }
+        return Ok(history_status(&runs));
+    }
+
+    let local_sysroot = options.sysroot.clone().filter(|s| !is_remote_sysroot(s));
+    let user = match options.as_user {
+        Some(ref name) => Some(User::lookup(
+            name,
+            options.user_env.as_deref(),
+            local_sysroot.as_deref(),
+        )?),
+        None => None,
+    };
+    let mut environment = user.as_ref()
+        .map_or(Environment::Inherited, |user| user.environment.clone());
+    if let Some(ref paths) = options.ld_library_path {
+        let paths = user.as_ref().map_or(paths.clone(), |user| user.expand_value(paths));
+        environment = environment.with_var("LD_LIBRARY_PATH", paths.into());
+    }
+    if let Some(ref user) = user {
+        options.search_methods = options
+            .search_methods
+            .drain(..)
+            .map(|m| user.expand_search_method(m))
+            .collect();
+    }
+
+    let mut notices = Vec::new();
+    let search_methods = if options.search_methods.is_empty() {
+        if options.no_default_search {
+            return Err(Box::new(ErrorMsg(
+                "No search location specified (see --lib) and --no-default-search given"
+                    .to_string(),
+            )));
+        }
+        notices.push(Notice {
+            id: "default-search-methods",
+            message: "No search location specified. Assuming default locations for GNU ld"
+                .to_string(),
+        });
+        gnuld_default_search_methods()
+    } else if options.expand_env {
+        options
+            .search_methods
+            .drain(..)
+            .map(|m| m.expand_env(&environment))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(ErrorMsg)?
+    } else {
+        options.search_methods.clone()
+    };
+    let cache = library_cache(&options);
+    if let Some(url) = options.sysroot.clone().filter(|s| is_remote_sysroot(s)) {
+        let mut files = options.files.clone();
+        for namespace in options.namespaces.iter() {
+            files.extend(namespace.roots.iter().cloned());
+        }
+        if let Some(Command::GrepSymbols { ref file, .. }) = options.command {
+            files.push(file.clone());
+        }
+        let mut remote = RemoteSysroot::open(&url)?;
+        remote.mirror(&files, &search_methods, &cache)?;
+        options.sysroot = Some(remote.root.clone());
+    }
+    if !options.quiet {
+        for notice in notices.iter() {
+            eprintln!("{}", notice);
+        }
+    }
+    let sysroot = options.sysroot.as_deref();
+    for warning in search_methods.iter().filter_map(|m| m.validate(&RealFs, sysroot)) {
+        eprintln!("Warning: {}", warning);
+    }
+    if options.ld_library_path.is_some()
+        && !search_methods.iter().any(|m| matches!(m, &LibSearchMethod::LDLibraryPath))
+    {
+        eprintln!(
+            "Warning: --ld-library-path has no effect without the ld_library_path search location"
+        );
+    }
+    for target in options.dlopen_targets.iter() {
+        if !options.files.iter().any(|file| target.is(file)) {
+            eprintln!("Warning: dlopen target {:?} is not analyzed", target.path);
+        }
+    }
+
+    if !options.show_duplicate_symbols && !options.show_unresolved_symbols
+        && !options.show_lib_resolution_problems || options.full_analysis
+    {
+        options.show_duplicate_symbols = true;
+        options.show_unresolved_symbols = true;
+        options.show_lib_resolution_problems = true;
+    }
+
+    if let Some(Command::GrepSymbols {
+        ref pattern,
+        ref file,
+    }) = options.command
+    {
+        let settings = ResolveSettings {
+            search_methods,
+            secure_execution: options.secure_execution,
+            sysroot: options.sysroot.clone(),
+            identity: options.identity,
+            traversal: options.traversal,
+            environment: environment.clone(),
+            files: Arc::new(RealFs),
+        };
+        let libs = LibraryDependencies::try_find_for_elf_cached(file, &settings, &cache)?;
+        for m in grep_symbols(&libs, pattern) {
+            let lib = m.lib.display();
+            if let Some(demangled) = m.demangled {
+                println!("{}: {} {}\t{} ({})", lib, m.role, m.class, demangled, m.name);
+            } else {
+                println!("{}: {} {}\t{}", lib, m.role, m.class, m.name);
+            }
+        }
+        return Ok(Status::Success);
+    }
+
+    let files = if options.namespaces.is_empty() {
+        // All files share the cache of opened libraries, so common dependencies are only read
+        // once. Files given multiple times (e.g., by overlapping globs) are analyzed once.
+        let mut files = Vec::new();
+        for file in options.files.iter() {
+            if !files.contains(file) {
+                files.push(file.clone());
+            }
+        }
+        files
+    } else {
+        options
+            .namespaces
+            .iter()
+            .flat_map(|namespace| namespace.roots.iter().cloned())
+            .collect()
+    };
+    let (pe_files, files): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|file| options.wine_prefix.is_some() && is_pe(file));
+    let pe_checks = match options.wine_prefix {
+        Some(ref prefix) => {
+            let prefix = WinePrefix::open(prefix)?;
+            pe_files
+                .iter()
+                .map(|file| check_pe_file(file, &prefix))
+                .collect::<Vec<_>>()
+        }
+        None => Vec::new(),
+    };
+
+    let mut jobs = Vec::new();
+    for file in files.iter() {
+        let mut secure_execution = options.secure_execution;
+        if !secure_execution {
+            if let Ok(Some(reason)) = secure_execution_reason(file, &RealFs) {
+                eprintln!(
+                    "{:?} has {}. Assuming secure-execution mode of the loader",
+                    file, reason
+                );
+                secure_execution = true;
+            }
+        }
+        let mut file_search_methods = search_methods.clone();
+        if options.search_methods.is_empty() {
+            let loader = interpreter(file, &RealFs, &cache).filter(|l| !is_standard_loader(l));
+            if let Some(loader) = loader {
+                // Bundled loaders usually come with their own libc (and friends) next to them.
+                let loader_dir = loader.parent().unwrap_or(Path::new("/")).to_path_buf();
+                eprintln!(
+                    "{:?} uses the nonstandard loader {:?}. Also searching {:?} by default",
+                    file, loader, loader_dir
+                );
+                let system_locations = file_search_methods
+                    .iter()
+                    .position(|m| {
+                        matches!(
+                            m,
+                            &LibSearchMethod::LDConfig(_)
+                                | &LibSearchMethod::Fixed(_)
+                                | &LibSearchMethod::Host(_)
+                        )
+                    })
+                    .unwrap_or(file_search_methods.len());
+                file_search_methods.insert(system_locations, LibSearchMethod::Fixed(loader_dir));
+            }
+        }
+        let settings = ResolveSettings {
+            search_methods: file_search_methods,
+            secure_execution,
+            sysroot: options.sysroot.clone(),
+            identity: options.identity,
+            traversal: options.traversal,
+            environment: environment.clone(),
+            files: Arc::new(RealFs),
+        };
+        jobs.push((file.clone(), settings));
+    }
+    let progress_mode = if options.quiet && options.progress == ProgressMode::Auto {
+        ProgressMode::None
+    } else {
+        options.progress
+    };
+    let progress = ProgressReporter::new(progress_mode);
+    let results = try_find_for_elfs_with_progress(&jobs, &cache, &progress);
+    let multiple_files = jobs.len() > 1;
+
+    if options.print_resolved && !options.ldd_compat && options.format == ReportFormat::Json {
+        progress.finish();
+        let mut closures = serde_json::Map::new();
+        for ((file, _), libs) in jobs.iter().zip(results) {
+            match libs {
+                Ok(libs) => {
+                    let file = file.display().to_string();
+                    let _ = closures.insert(file, resolved_map_to_json(&libs));
+                }
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        serde_json::to_writer_pretty(std::io::stdout(), &closures)?;
+        println!();
+        return Ok(Status::Success);
+    }
+    if options.print_resolved || options.ldd_compat || options.topo {
+        progress.finish();
+        for ((file, _), libs) in jobs.iter().zip(results) {
+            if multiple_files {
+                println!("{}:", file.display());
+            }
+            match libs {
+                Ok(libs) => print_closure(&options, &libs),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        return Ok(Status::Success);
+    }
+
+    let config = Config::load()?;
+    let theme = options.theme.or(config.theme).unwrap_or_default();
+
+    if !options.namespaces.is_empty() {
+        progress.finish();
+        let mut results = results.into_iter();
+        let mut namespaces = Vec::new();
+        for namespace in options.namespaces.iter() {
+            let closures = results
+                .by_ref()
+                .take(namespace.roots.len())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(ErrorMsg)?;
+            namespaces.push((namespace.name.clone(), closures));
+        }
+        let reports = analyze_namespaces(namespaces);
+        let (mut t, pager) = open_report_terminal(!options.no_pager);
+        for report in reports.iter() {
+            write_namespace_report(&options, theme, report, &mut *t);
+        }
+        drop(t);
+        if let Some(pager) = pager {
+            pager.wait();
+        }
+        return Ok(namespace_status(
+            &reports,
+            options.show_unresolved_symbols,
+            options.show_duplicate_symbols,
+        ));
+    }
+
+    let mounts = if options.check_mounts {
+        Some(MountTable::read()?)
+    } else {
+        None
+    };
+    let provided = AssumedProvided::load(&options.assume_provided)?;
+    progress.start("reporting", jobs.len());
+    let reports = jobs.iter()
+        .zip(results)
+        .map(|((file, settings), libs)| {
+            let report = libs.map(|libs| {
+                let preloads = load_preloads(&options, settings, &libs, &cache);
+                build_report(&options, &environment, mounts.as_ref(), &provided, &preloads, libs)
+            });
+            progress.file_analyzed(file);
+            (file.clone(), report)
+        })
+        .collect::<Vec<_>>();
+    progress.finish();
+    let plugin_checks = check_plugins(&options, &search_methods, &environment, &reports, &cache);
+
+    // The variables of the environment of the loading process that the analysis reads.
+    let mut variables = Vec::new();
+    if search_methods.iter().any(|m| matches!(m, &LibSearchMethod::LDLibraryPath)) {
+        variables.push("LD_LIBRARY_PATH");
+    }
+    if options.audit_preload {
+        variables.push("LD_PRELOAD");
+    }
+    if user.is_some() {
+        variables.push("HOME");
+    }
+    let provenance = Provenance::collect(
+        started,
+        &ResolveSettings {
+            search_methods: search_methods.clone(),
+            secure_execution: options.secure_execution,
+            sysroot: options.sysroot.clone(),
+            identity: options.identity,
+            traversal: options.traversal,
+            environment: environment.clone(),
+            files: Arc::new(RealFs),
+        },
+        options.hardened_parse,
+        options.as_user.clone(),
+        &variables,
+    );
+    let reports_json = || {
+        serde_json::Value::Array(
+            reports
+                .iter()
+                .map(|(file, report)| {
+                    report_to_json(file, report, &notices, &provenance)
+                })
+                .chain(
+                    pe_checks
+                        .iter()
+                        .map(|check| pe_check_to_json(check, &notices, &provenance)),
+                )
+                .collect(),
+        )
+    };
+    match options.format {
+        ReportFormat::Text => {
+            let (mut t, pager) = open_report_terminal(!options.no_pager);
+            write_reports(&options, theme, &reports, &mut *t);
+            if !plugin_checks.is_empty() {
+                write_plugin_report(&options, theme, &plugin_checks, &mut *t);
+            }
+            if !pe_checks.is_empty() {
+                write_pe_report(theme, &pe_checks, &mut *t);
+            }
+            if let (true, Some((_, settings))) = (options.search_method_usage, jobs.first()) {
+                let closures = reports
+                    .iter()
+                    .filter_map(|(_, report)| report.as_ref().ok())
+                    .map(|report| &report.libs)
+                    .collect::<Vec<_>>();
+                let usages = search_method_usage(&search_methods, settings, &closures);
+                write_search_method_usage(theme, &usages, &mut *t);
+            }
+
+            // Close the pipe to the pager (if any) so that it sees the end of the report.
+            drop(t);
+            if let Some(pager) = pager {
+                pager.wait();
+            }
+        }
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(std::io::stdout(), &reports_json())?;
+            println!();
+        }
+    }
+
+    if let Some(ref path) = options.json_out {
+        write_json_file(path, &reports_json())?;
+    }
+    if let Some(ref path) = options.sarif_out {
+        write_json_file(path, &reports_to_sarif(&reports, &notices, &provenance))?;
+    }
+    if let Some(ref path) = options.dot {
+        std::fs::write(path, reports_to_dot(&reports))?;
+    }
+    if let Some(ref out_dir) = options.out_dir {
+        write_report_files(
+            &reports,
+            out_dir,
+            &options.out_template,
+            options.out_format,
+            &notices,
+            &provenance,
+        )?;
+    }
+    if let Some(ref path) = options.append_history {
+        History::open(path)?.append(&reports)?;
+    }
+
+    Ok(reports_status(&reports)
+        .combine(plugin_status(&plugin_checks))
+        .combine(pe_status(&pe_checks)))
+}
+
+fn main() {
+    let matches = match Options::clap().get_matches_safe() {
+        Ok(matches) => matches,
+        Err(ref err) if err.use_stderr() => {
+            eprintln!("{}", err.message);
+            process::exit(TOOL_ERROR);
+        }
+        Err(err) => err.exit(),
+    };
+    let options = Options::from_clap(&matches);
+    let fail_on = options.fail_on;
+    match run(options) {
+        Ok(status) => process::exit(fail_on.exit_code(&status)),
+        Err(err) => {
+            println!("{}", err);
+            process::exit(TOOL_ERROR);
+        }
     }
 }
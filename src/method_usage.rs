@@ -0,0 +1,79 @@
+use libraries::{ld_so_conf_directories, reroot_fixed_dir, LibSearchMethod, LibraryDependencies,
+                ResolveSettings, INHERITED_RPATH};
+
+/// How many resolutions of the analyzed files a configured search method produced.
+pub struct MethodUsage {
+    pub method: LibSearchMethod,
+    pub resolutions: usize,
+    /// Why the method cannot produce any resolutions (if known), e.g., a missing ld.so.conf file
+    pub problem: Option<String>,
+}
+
+/// Attribute the resolutions of all closures to the configured search methods (the first one
+/// that lists the directory a library was found in). Locations that were not configured (e.g.,
+/// the directory of a nonstandard loader) are not counted.
+pub fn search_method_usage(
+    methods: &[LibSearchMethod],
+    settings: &ResolveSettings,
+    closures: &[&LibraryDependencies],
+) -> Vec<MethodUsage> {
+    let sysroot = settings.sysroot.as_deref();
+    let mut usages = Vec::new();
+    let mut ldconfig_dirs = Vec::new();
+    for method in methods {
+        let mut problem = method.validate(&*settings.files, sysroot);
+        let dirs = match method {
+            LibSearchMethod::LDConfig(conf_file) if problem.is_none() => {
+                match ld_so_conf_directories(conf_file, settings.files.clone(), sysroot) {
+                    Ok(ref dirs) if dirs.is_empty() => {
+                        problem = Some(format!("{:?} lists no directories", conf_file));
+                        Vec::new()
+                    }
+                    Ok(dirs) => dirs,
+                    Err(err) => {
+                        problem = Some(err.to_string());
+                        Vec::new()
+                    }
+                }
+            }
+            &LibSearchMethod::LDLibraryPath
+                if settings.environment.var_os("LD_LIBRARY_PATH").is_none() =>
+            {
+                problem = Some("LD_LIBRARY_PATH is not set".to_owned());
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+        ldconfig_dirs.push(dirs);
+        usages.push(MethodUsage {
+            method: method.clone(),
+            resolutions: 0,
+            problem,
+        });
+    }
+
+    let resolutions = closures
+        .iter()
+        .flat_map(|libs| libs.resolutions.values())
+        .flat_map(|resolutions| resolutions.iter());
+    for resolution in resolutions {
+        let produced = |i: &usize| match (&methods[*i], resolution.origin) {
+            (&LibSearchMethod::RPath, "rpath")
+            | (&LibSearchMethod::RPath, INHERITED_RPATH)
+            | (&LibSearchMethod::RunPath, "runpath")
+            | (&LibSearchMethod::LDLibraryPath, "LD_LIBRARY_PATH") => true,
+            (&LibSearchMethod::LDConfig(_), "ldconfig") => {
+                ldconfig_dirs[*i].contains(&resolution.directory)
+            }
+            (LibSearchMethod::Fixed(dir), "fixed") => {
+                reroot_fixed_dir(dir, sysroot) == resolution.directory
+            }
+            (LibSearchMethod::Host(dir), "host") => dir == &resolution.directory,
+            _ => false,
+        };
+        if let Some(i) = (0..methods.len()).find(produced) {
+            usages[i].resolutions += 1;
+        }
+    }
+    usages
+}
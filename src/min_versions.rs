@@ -0,0 +1,80 @@
+use libraries::LibraryDependencies;
+use symbol_versions::SymbolVersions;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The newest version of a version family (e.g., GLIBC_2 or GLIBCXX_3.4) that the closure
+/// requires from a runtime library, which determines the oldest system it can run on.
+#[derive(Clone, Serialize)]
+pub struct MinimumVersion {
+    /// The library that has to provide the version (as named in DT_NEEDED)
+    pub library: String,
+    /// The newest required version (e.g., GLIBC_2.34)
+    pub version: String,
+    /// The objects of the closure that require exactly this version
+    pub required_by: Vec<PathBuf>,
+}
+
+/// Split a version name like GLIBCXX_3.4.21 into its family (GLIBCXX) and numeric components.
+/// None for versions without a numeric suffix (e.g., GLIBC_PRIVATE).
+fn parse_version(version: &str) -> Option<(&str, Vec<u32>)> {
+    let separator = version.rfind('_')?;
+    let numbers = version[separator + 1..]
+        .split('.')
+        .map(|n| n.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    Some((&version[..separator], numbers))
+}
+
+/// Find the newest version of each version family that the objects of the closure (including the
+/// analyzed file) require, per library that has to provide it.
+pub fn find_minimum_versions(libs: &LibraryDependencies) -> Vec<MinimumVersion> {
+    let mut newest = BTreeMap::new();
+    for (path, lib) in libs.opened_libs.iter() {
+        for (library, versions) in SymbolVersions::of_library(lib).required {
+            for version in versions {
+                let (family, numbers) = match parse_version(&version) {
+                    Some((family, numbers)) => (family.to_owned(), numbers),
+                    None => continue,
+                };
+                let entry = newest
+                    .entry((library.clone(), family))
+                    .or_insert((Vec::new(), String::new(), Vec::new()));
+                if numbers > entry.0 {
+                    *entry = (numbers, version, vec![path.clone()]);
+                } else if numbers == entry.0 {
+                    entry.2.push(path.clone());
+                }
+            }
+        }
+    }
+    newest
+        .into_iter()
+        .map(|((library, _), (_, version, mut required_by))| {
+            required_by.sort();
+            MinimumVersion {
+                library,
+                version,
+                required_by,
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for MinimumVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (required by {})",
+            self.library,
+            self.version,
+            self.required_by
+                .iter()
+                .map(|p| format!("{:?}", p))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
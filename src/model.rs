@@ -0,0 +1,564 @@
+use fingerprint::{fingerprint, normalize_path};
+use harden::HardeningAudit;
+use min_versions::MinimumVersion;
+use objinfo::ObjectInfo;
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// The analysis result of one file as plain data, independent of any output format. The JSON
+// output is the serialization of `AnalysisResult`, and the other formats (SARIF, the views by
+// owner and package, the history) build on its findings (see `AnalysisResult::findings`).
+
+pub const UNRESOLVED_SYMBOL: &str = "unresolved-symbol";
+pub const DUPLICATE_SYMBOL: &str = "duplicate-symbol";
+pub const ANALYSIS_ERROR: &str = "analysis-error";
+
+/// A kind of findings: its id (as used in SARIF and the history), its name in plural (as used in
+/// the grouped views) and a short description.
+pub struct Rule {
+    pub id: &'static str,
+    pub kind: &'static str,
+    pub description: &'static str,
+}
+
+/// All rules, in the order in which `AnalysisResult::findings` lists their findings.
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "library-resolution",
+        kind: "library resolution problems",
+        description: "A needed library cannot be resolved (correctly)",
+    },
+    Rule {
+        id: "skipped-incompatible",
+        kind: "skipped incompatible libraries",
+        description: "A file of another architecture was skipped while searching a needed library",
+    },
+    Rule {
+        id: "packaging",
+        kind: "packaging problems",
+        description: "A library is not packaged according to common conventions",
+    },
+    Rule {
+        id: "preload",
+        kind: "preload problems",
+        description: "A preloaded library is missing, unusable or insecure",
+    },
+    Rule {
+        id: "preload-interposition",
+        kind: "preload interposition",
+        description: "A simulated preload overrides a symbol, or does not override an expected one",
+    },
+    Rule {
+        id: "search-path",
+        kind: "search path oddities",
+        description: "An RPATH or RUNPATH entry is useless",
+    },
+    Rule {
+        id: "budget",
+        kind: "budget violations",
+        description: "The closure has more libraries or bytes than allowed",
+    },
+    Rule {
+        id: "dlopen",
+        kind: "dlopen problems",
+        description: "A file loaded with dlopen is an executable or lacks an entry symbol",
+    },
+    Rule {
+        id: "rpath-consolidation",
+        kind: "dead rpath entries",
+        description: "Some RPATH/RUNPATH entries of an object are dead and can be removed",
+    },
+    Rule {
+        id: "mount",
+        kind: "mount problems",
+        description: "A library lives on a noexec or network file system",
+    },
+    Rule {
+        id: "text-relocations",
+        kind: "text relocations",
+        description: "An object has relocations in read-only segments (DT_TEXTREL)",
+    },
+    Rule {
+        id: "unused-dependency",
+        kind: "unused dependencies",
+        description: "A needed library provides none of the used symbols",
+    },
+    Rule {
+        id: UNRESOLVED_SYMBOL,
+        kind: "unresolved symbols",
+        description: "A referenced symbol is not defined by any library",
+    },
+    Rule {
+        id: "unresolved-data",
+        kind: "unresolved data symbols",
+        description: "A library references unresolved data symbols, so it fails to load",
+    },
+    Rule {
+        id: "dispatch-driver",
+        kind: "graphics drivers",
+        description: "A driver of a graphics dispatch library is missing or cannot be used",
+    },
+    Rule {
+        id: "symbol-version",
+        kind: "symbol version problems",
+        description: "A symbol is only exported in non-default versions",
+    },
+    Rule {
+        id: "missing-version",
+        kind: "missing symbol versions",
+        description: "A library does not define a symbol version that an object requires from it",
+    },
+    Rule {
+        id: "cxx-runtime",
+        kind: "C++ runtime problems",
+        description: "The C++ objects of the closure need different libstdc++ generations",
+    },
+    Rule {
+        id: "executable-symbol",
+        kind: "executable symbol problems",
+        description: "A library relies on a symbol of the executable being exported",
+    },
+    Rule {
+        id: "underlinked-library",
+        kind: "underlinking",
+        description: "A library uses symbols that none of its direct dependencies provide",
+    },
+    Rule {
+        id: DUPLICATE_SYMBOL,
+        kind: "duplicate symbols",
+        description: "A symbol is exported by multiple libraries",
+    },
+    Rule {
+        id: "mismatched-duplicate-symbol",
+        kind: "mismatched duplicate symbols",
+        description: "The definitions of a duplicate symbol differ in type or size",
+    },
+    Rule {
+        id: "self-imported-symbol",
+        kind: "self-imported symbols",
+        description: "An object imports a duplicate symbol that it exports itself",
+    },
+    Rule {
+        id: ANALYSIS_ERROR,
+        kind: "analysis errors",
+        description: "The file could not be analyzed",
+    },
+];
+
+/// A suggestion how to fix a finding, for tools that act on findings (e.g., bots or ticket
+/// generators) without parsing the messages.
+#[derive(Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Remediation {
+    /// Add a directory to the RUNPATH of an object.
+    AddRpath { object: PathBuf, directory: PathBuf },
+    /// Replace the RPATH/RUNPATH of an object by the given colon separated entries (as
+    /// `patchelf --set-rpath` does).
+    SetRpath { object: PathBuf, rpath: String },
+    /// Add a library to the DT_NEEDED entries of an object (i.e., link it against the library).
+    AddNeeded { object: PathBuf, library: String },
+    /// Install the (unknown, if `package` is None) package that contains a library.
+    InstallPackage {
+        library: String,
+        package: Option<String>,
+    },
+    /// Rebuild an object against the given library (as it is found at runtime).
+    RebuildAgainst { object: PathBuf, library: PathBuf },
+}
+
+/// A library of the closure: the name it was requested by and the file it resolved to.
+#[derive(Serialize)]
+pub struct ClosureEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub info: Option<ObjectInfo>,
+}
+
+/// A resolved dependency of one object of the closure on another one, together with its
+/// provenance (the search location that produced it).
+#[derive(Serialize)]
+pub struct Edge {
+    pub dependent: PathBuf,
+    pub dependency: PathBuf,
+    pub name: String,
+    pub directory: PathBuf,
+    pub origin: &'static str,
+}
+
+/// A resolution that relies on the environment (e.g., LD_LIBRARY_PATH) of the loading process.
+#[derive(Serialize)]
+pub struct EnvironmentDependentResolution {
+    pub library: PathBuf,
+    pub name: String,
+    pub dependent_library: PathBuf,
+    pub origin: &'static str,
+    pub directory: PathBuf,
+}
+
+/// A file of another architecture that was skipped while searching a needed library.
+#[derive(Serialize)]
+pub struct SkippedLibraryCandidate {
+    pub dependent_library: PathBuf,
+    pub name: String,
+    pub path: PathBuf,
+    pub resolved_path: PathBuf,
+    pub message: String,
+    pub fingerprint: String,
+}
+
+/// A dynamic entry of a library whose effect is not simulated.
+#[derive(Serialize)]
+pub struct UnknownDynamicTagResult {
+    pub library: PathBuf,
+    pub tag: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'static str>,
+    pub message: String,
+    pub fingerprint: String,
+}
+
+/// An RPATH/RUNPATH entry and how many dependencies of its object it produced.
+#[derive(Serialize)]
+pub struct RpathEntryUsage {
+    pub entry: String,
+    pub directory: PathBuf,
+    pub resolutions: usize,
+}
+
+/// The RPATH (or RUNPATH) entries of one object, with the minimal replacement if some are dead.
+#[derive(Serialize)]
+pub struct RpathUsageResult {
+    pub object: PathBuf,
+    pub origin: &'static str,
+    pub entries: Vec<RpathEntryUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consolidated: Option<String>,
+    pub message: String,
+    pub fingerprint: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remediations: Vec<Remediation>,
+}
+
+/// A problem attributed to a library, with a human-readable message and suggestions how to fix
+/// it (if any).
+#[derive(Serialize)]
+pub struct Problem {
+    pub library: PathBuf,
+    /// The SARIF level of the problem (error, warning or note)
+    pub level: &'static str,
+    /// The libraries that need to be changed to fix the problem, if not `library` itself
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub owners: Vec<PathBuf>,
+    pub message: String,
+    pub fingerprint: String,
+    /// The dependency depth of the library (0 for the analyzed file, none if it is not part of
+    /// the dependency tree)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remediations: Vec<Remediation>,
+}
+
+impl Problem {
+    pub fn new(library: &Path, level: &'static str, message: String, fingerprint: String) -> Self {
+        Problem {
+            library: library.to_path_buf(),
+            level,
+            owners: Vec::new(),
+            message,
+            fingerprint,
+            depth: None,
+            remediations: Vec::new(),
+        }
+    }
+
+    pub fn with_remediations(mut self, remediations: Vec<Remediation>) -> Self {
+        self.remediations = remediations;
+        self
+    }
+
+    pub fn with_owners(mut self, owners: Vec<PathBuf>) -> Self {
+        self.owners = owners;
+        self
+    }
+}
+
+/// An unresolved or duplicate symbol and the libraries referencing or exporting it.
+#[derive(Serialize)]
+pub struct SymbolGroup {
+    pub symbol: String,
+    pub demangled: Option<String>,
+    pub libraries: Vec<String>,
+    /// The paths of the libraries (or their names, if they are not resolved)
+    pub owners: Vec<PathBuf>,
+    /// The class of the symbol if it is special (ifunc, tls or unique)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<&'static str>,
+    pub fingerprint: String,
+    /// The smallest dependency depth of the libraries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remediations: Vec<Remediation>,
+}
+
+/// A symbol that is provided by the drivers of a graphics dispatch library.
+#[derive(Serialize)]
+pub struct DispatchedSymbol {
+    pub symbol: String,
+    pub drivers: Vec<PathBuf>,
+}
+
+/// The number of findings of an analyzed file, like in the section headers and the final tally
+/// of the text report.
+#[derive(Serialize, Default)]
+pub struct Counts {
+    /// The number of findings per category, named like the lists of `AnalysisResult`
+    pub categories: BTreeMap<&'static str, usize>,
+    /// The number of groups (by the libraries involved) the unresolved symbols are listed in
+    pub unresolved_symbol_groups: usize,
+    /// The number of groups (by the libraries involved) the duplicate symbols are listed in
+    pub duplicate_symbol_groups: usize,
+    /// The number of findings in all categories
+    pub total: usize,
+}
+
+impl Counts {
+    pub fn of_result(result: &AnalysisResult) -> Self {
+        let categories = vec![
+            ("library_resolution_problems", result.library_resolution_problems.len()),
+            ("packaging_problems", result.packaging_problems.len()),
+            ("preload_problems", result.preload_problems.len()),
+            ("interpositions", result.interpositions.len()),
+            ("search_path_oddities", result.search_path_oddities.len()),
+            ("unknown_dynamic_tags", result.unknown_dynamic_tags.len()),
+            ("mount_problems", result.mount_problems.len()),
+            ("text_relocations", result.text_relocations.len()),
+            ("budget_violations", result.budget_violations.len()),
+            ("dlopen_problems", result.dlopen_problems.len()),
+            ("unused_dependencies", result.unused_dependencies.len()),
+            ("unresolved_symbols", result.unresolved_symbols.len()),
+            ("unresolved_data", result.unresolved_data.len()),
+            ("driver_problems", result.driver_problems.len()),
+            ("version_problems", result.version_problems.len()),
+            ("missing_versions", result.missing_versions.len()),
+            ("cxx_runtime_problems", result.cxx_runtime_problems.len()),
+            ("executable_symbols", result.executable_symbols.len()),
+            ("underlinked_libraries", result.underlinked_libraries.len()),
+            ("duplicate_symbols", result.duplicate_symbols.len()),
+            ("mismatched_duplicate_symbols", result.mismatched_duplicate_symbols.len()),
+            ("self_imported_symbols", result.self_imported_symbols.len()),
+        ];
+        let num_groups = |symbols: &[SymbolGroup]| {
+            symbols
+                .iter()
+                .map(|s| &s.libraries)
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        Counts {
+            total: categories.iter().map(|&(_, count)| count).sum(),
+            categories: categories.into_iter().collect(),
+            unresolved_symbol_groups: num_groups(&result.unresolved_symbols),
+            duplicate_symbol_groups: num_groups(&result.duplicate_symbols),
+        }
+    }
+}
+
+/// Everything linkcheck found out about one analyzed file.
+#[derive(Serialize)]
+pub struct AnalysisResult {
+    pub file: PathBuf,
+    pub counts: Counts,
+    pub resolved: Vec<ClosureEntry>,
+    pub edges: Vec<Edge>,
+    pub library_resolution_problems: Vec<Problem>,
+    pub environment_dependent_resolutions: Vec<EnvironmentDependentResolution>,
+    pub skipped_incompatible_candidates: Vec<SkippedLibraryCandidate>,
+    pub packaging_problems: Vec<Problem>,
+    pub preload_problems: Vec<Problem>,
+    pub interpositions: Vec<Problem>,
+    pub search_path_oddities: Vec<Problem>,
+    pub rpath_usages: Vec<RpathUsageResult>,
+    pub unknown_dynamic_tags: Vec<UnknownDynamicTagResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hardening: Option<HardeningAudit>,
+    pub minimum_versions: Vec<MinimumVersion>,
+    pub mount_problems: Vec<Problem>,
+    pub text_relocations: Vec<Problem>,
+    pub budget_violations: Vec<Problem>,
+    pub dlopen_problems: Vec<Problem>,
+    pub unused_dependencies: Vec<Problem>,
+    pub unresolved_symbols: Vec<SymbolGroup>,
+    pub unresolved_data: Vec<Problem>,
+    pub dispatched_symbols: Vec<DispatchedSymbol>,
+    pub driver_problems: Vec<Problem>,
+    pub version_problems: Vec<Problem>,
+    pub missing_versions: Vec<Problem>,
+    pub cxx_runtime_problems: Vec<Problem>,
+    pub executable_symbols: Vec<Problem>,
+    pub underlinked_libraries: Vec<Problem>,
+    pub duplicate_symbols: Vec<SymbolGroup>,
+    pub mismatched_duplicate_symbols: Vec<Problem>,
+    pub self_imported_symbols: Vec<Problem>,
+}
+
+/// A single finding in a uniform representation, independent of the list of `AnalysisResult` it
+/// comes from.
+#[derive(Serialize)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub level: &'static str,
+    pub location: PathBuf,
+    /// The libraries that need to be changed to fix the finding
+    pub owners: Vec<PathBuf>,
+    pub message: String,
+    pub fingerprint: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remediations: Vec<Remediation>,
+}
+
+impl Finding {
+    /// The finding for a file that could not be analyzed at all.
+    pub fn analysis_error(file: &Path, error: &str) -> Self {
+        Finding {
+            rule: ANALYSIS_ERROR,
+            level: "error",
+            location: file.to_path_buf(),
+            owners: vec![file.to_path_buf()],
+            message: error.to_string(),
+            fingerprint: fingerprint(ANALYSIS_ERROR, &[&normalize_path(file)]),
+            remediations: Vec::new(),
+        }
+    }
+
+    fn of_problem(rule: &'static str, problem: &Problem) -> Self {
+        Finding {
+            rule,
+            level: problem.level,
+            location: problem.library.clone(),
+            owners: if problem.owners.is_empty() {
+                vec![problem.library.clone()]
+            } else {
+                problem.owners.clone()
+            },
+            message: problem.message.clone(),
+            fingerprint: problem.fingerprint.clone(),
+            remediations: problem.remediations.clone(),
+        }
+    }
+
+    /// The name of the kind of the finding in plural (see `Rule`).
+    pub fn kind(&self) -> &'static str {
+        RULES
+            .iter()
+            .find(|rule| rule.id == self.rule)
+            .map(|rule| rule.kind)
+            .unwrap_or(self.rule)
+    }
+}
+
+impl AnalysisResult {
+    /// All findings, ordered by rule (see `RULES`). Resolutions that merely depend on the
+    /// environment, unknown dynamic entries and the purely informational lists are not findings.
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let problems = |findings: &mut Vec<Finding>, rule, problems: &[Problem]| {
+            findings.extend(problems.iter().map(|p| Finding::of_problem(rule, p)))
+        };
+        let symbols = |findings: &mut Vec<Finding>, rule, level, what, symbols: &[SymbolGroup]| {
+            findings.extend(symbols.iter().map(|group| Finding {
+                rule,
+                level,
+                location: self.file.clone(),
+                owners: group.owners.clone(),
+                message: format!(
+                    "{} {} ({} {})",
+                    if rule == UNRESOLVED_SYMBOL {
+                        "Unresolved symbol"
+                    } else {
+                        "Duplicate symbol"
+                    },
+                    group.demangled.as_ref().unwrap_or(&group.symbol),
+                    what,
+                    group.libraries.join(", ")
+                ),
+                fingerprint: group.fingerprint.clone(),
+                remediations: group.remediations.clone(),
+            }))
+        };
+
+        problems(&mut findings, "library-resolution", &self.library_resolution_problems);
+        findings.extend(self.skipped_incompatible_candidates.iter().map(|skipped| Finding {
+            rule: "skipped-incompatible",
+            level: "note",
+            location: skipped.dependent_library.clone(),
+            owners: vec![skipped.dependent_library.clone()],
+            message: skipped.message.clone(),
+            fingerprint: skipped.fingerprint.clone(),
+            remediations: Vec::new(),
+        }));
+        problems(&mut findings, "packaging", &self.packaging_problems);
+        problems(&mut findings, "preload", &self.preload_problems);
+        problems(&mut findings, "preload-interposition", &self.interpositions);
+        problems(&mut findings, "search-path", &self.search_path_oddities);
+        problems(&mut findings, "budget", &self.budget_violations);
+        problems(&mut findings, "dlopen", &self.dlopen_problems);
+        findings.extend(
+            self.rpath_usages
+                .iter()
+                .filter(|usage| usage.consolidated.is_some())
+                .map(|usage| Finding {
+                    rule: "rpath-consolidation",
+                    level: "note",
+                    location: usage.object.clone(),
+                    owners: vec![usage.object.clone()],
+                    message: usage.message.clone(),
+                    fingerprint: usage.fingerprint.clone(),
+                    remediations: usage.remediations.clone(),
+                }),
+        );
+        problems(&mut findings, "mount", &self.mount_problems);
+        problems(&mut findings, "text-relocations", &self.text_relocations);
+        problems(&mut findings, "unused-dependency", &self.unused_dependencies);
+        symbols(
+            &mut findings,
+            UNRESOLVED_SYMBOL,
+            "error",
+            "referenced by",
+            &self.unresolved_symbols,
+        );
+        problems(&mut findings, "unresolved-data", &self.unresolved_data);
+        problems(&mut findings, "dispatch-driver", &self.driver_problems);
+        problems(&mut findings, "symbol-version", &self.version_problems);
+        problems(&mut findings, "missing-version", &self.missing_versions);
+        problems(&mut findings, "cxx-runtime", &self.cxx_runtime_problems);
+        problems(&mut findings, "executable-symbol", &self.executable_symbols);
+        problems(&mut findings, "underlinked-library", &self.underlinked_libraries);
+        symbols(
+            &mut findings,
+            DUPLICATE_SYMBOL,
+            "warning",
+            "exported by",
+            &self.duplicate_symbols,
+        );
+        problems(
+            &mut findings,
+            "mismatched-duplicate-symbol",
+            &self.mismatched_duplicate_symbols,
+        );
+        problems(&mut findings, "self-imported-symbol", &self.self_imported_symbols);
+        findings
+    }
+}
+
+/// The result of checking a Windows (PE) file against a Wine prefix.
+#[derive(Serialize)]
+pub struct PeAnalysisResult {
+    pub file: PathBuf,
+    pub dlls: BTreeMap<String, PathBuf>,
+    pub problems: Vec<Problem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -0,0 +1,173 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{ErrorMsg, LibraryDependencies};
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MOUNT_TABLE: &str = "/proc/self/mounts";
+
+/// File system types whose files are served over the network.
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ncpfs", "9p", "afs", "ceph", "glusterfs", "lustre",
+    "gpfs", "beegfs", "fuse.sshfs", "fuse.glusterfs", "fuse.s3fs",
+];
+
+/// An entry of the mount table.
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub options: Vec<String>,
+}
+
+/// Undo the octal escapes (e.g., \040 for spaces) of a field of the mount table.
+fn unescape(field: &str) -> String {
+    let mut result = Vec::new();
+    let bytes = field.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| ::std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match escaped {
+            Some(b) => {
+                result.push(b);
+                i += 4;
+            }
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// The mount table of the machine linkcheck runs on, in mount order.
+pub struct MountTable(Vec<Mount>);
+
+impl MountTable {
+    pub fn read() -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(MOUNT_TABLE)
+            .map_err(|e| ErrorMsg(format!("Cannot read mount table {}: {}", MOUNT_TABLE, e)))?;
+        Ok(MountTable(
+            content
+                .lines()
+                .filter_map(|line| {
+                    let fields = line.split_whitespace().collect::<Vec<_>>();
+                    if fields.len() < 4 {
+                        return None;
+                    }
+                    Some(Mount {
+                        mount_point: PathBuf::from(unescape(fields[1])),
+                        fs_type: fields[2].to_owned(),
+                        options: fields[3].split(',').map(|o| o.to_owned()).collect(),
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    /// The mount that the (canonical) path lives on: the one with the longest mount point,
+    /// mounted last if there are several (as later mounts hide earlier ones).
+    pub fn mount_of(&self, path: &Path) -> Option<&Mount> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|&(_, mount)| path.starts_with(&mount.mount_point))
+            .max_by_key(|&(i, mount)| (mount.mount_point.components().count(), i))
+            .map(|(_, mount)| mount)
+    }
+}
+
+/// A library of the closure that lives on a problematic file system.
+pub enum MountProblem {
+    /// The loader cannot map the library executable, so loading it fails.
+    Noexec { lib: PathBuf, mount_point: PathBuf },
+    /// Loading the library depends on the availability and latency of a network file system.
+    NetworkFs {
+        lib: PathBuf,
+        mount_point: PathBuf,
+        fs_type: String,
+    },
+}
+
+impl MountProblem {
+    /// The library on the problematic file system.
+    pub fn lib(&self) -> &Path {
+        match self {
+            &MountProblem::Noexec { ref lib, .. } | &MountProblem::NetworkFs { ref lib, .. } => lib,
+        }
+    }
+
+    /// Whether loading the library fails (as opposed to being risky).
+    pub fn is_error(&self) -> bool {
+        match *self {
+            MountProblem::Noexec { .. } => true,
+            MountProblem::NetworkFs { .. } => false,
+        }
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let kind = match *self {
+            MountProblem::Noexec { .. } => "noexec-mount",
+            MountProblem::NetworkFs { .. } => "network-mount",
+        };
+        fingerprint(kind, &[&normalize_path(self.lib())])
+    }
+}
+
+impl fmt::Display for MountProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MountProblem::Noexec {
+                lib,
+                mount_point,
+            } => write!(
+                f,
+                "{:?}: Lives on {:?}, which is mounted noexec, so it cannot be loaded",
+                lib, mount_point
+            ),
+            MountProblem::NetworkFs {
+                lib,
+                mount_point,
+                fs_type,
+            } => write!(
+                f,
+                "{:?}: Lives on the network file system {:?} ({}), which slows down startup and fails it if the server is unavailable",
+                lib, mount_point, fs_type
+            ),
+        }
+    }
+}
+
+/// Find the libraries of the closure (including the analyzed file) that live on noexec or network
+/// file systems.
+pub fn find_mount_problems(libs: &LibraryDependencies, mounts: &MountTable) -> Vec<MountProblem> {
+    let mut paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    paths.sort();
+    let mut problems = Vec::new();
+    for path in paths {
+        let canonical = fs::canonicalize(path).unwrap_or(path.clone());
+        let mount = match mounts.mount_of(&canonical) {
+            Some(mount) => mount,
+            None => continue,
+        };
+        if mount.options.iter().any(|o| o == "noexec") {
+            problems.push(MountProblem::Noexec {
+                lib: path.clone(),
+                mount_point: mount.mount_point.clone(),
+            });
+        } else if NETWORK_FS_TYPES.contains(&mount.fs_type.as_str()) {
+            problems.push(MountProblem::NetworkFs {
+                lib: path.clone(),
+                mount_point: mount.mount_point.clone(),
+                fs_type: mount.fs_type.clone(),
+            });
+        }
+    }
+    problems
+}
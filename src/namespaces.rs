@@ -0,0 +1,140 @@
+use libraries::LibraryDependencies;
+use symbols::{SymbolClass, SymbolSummary};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A link namespace (as created by dlmopen) with the objects that are loaded into it.
+#[derive(Debug)]
+pub struct Namespace {
+    pub name: String,
+    pub roots: Vec<PathBuf>,
+}
+
+impl FromStr for Namespace {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap();
+        let roots = parts.next().unwrap_or("");
+        if name.is_empty() || roots.is_empty() {
+            return Err(format!(
+                "Invalid namespace '{}'. Expected <name>=<file>[,<file>...]",
+                s
+            ));
+        }
+        Ok(Namespace {
+            name: name.to_owned(),
+            roots: roots
+                .split(',')
+                .filter(|r| !r.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+        })
+    }
+}
+
+/// The results of the symbol analysis of one namespace.
+pub struct NamespaceReport {
+    pub name: String,
+    /// Undefined symbols that are not defined anywhere, with the libraries referencing them.
+    pub unresolved: Vec<(String, HashSet<String>)>,
+    /// Undefined symbols that are only defined in other namespaces (and thus need a proxy), with
+    /// the libraries referencing them and the namespaces defining them.
+    pub missing_proxies: Vec<(String, HashSet<String>, Vec<String>)>,
+    /// Symbols exported by multiple libraries of this namespace.
+    pub duplicates: Vec<(String, HashSet<String>)>,
+    /// The classes of the duplicates whose class is special (e.g., IFUNC or TLS).
+    pub classes: HashMap<String, SymbolClass>,
+    /// Number of symbols that are also exported in other namespaces. These do not interfere with
+    /// each other.
+    pub num_cross_namespace_duplicates: usize,
+}
+
+/// Analyze symbols per namespace. Symbols are only bound within a namespace, so duplicates across
+/// namespaces are fine, but symbols that are only defined in another namespace cannot be
+/// resolved.
+pub fn analyze_namespaces(
+    namespaces: Vec<(String, Vec<LibraryDependencies>)>,
+) -> Vec<NamespaceReport> {
+    let summaries = namespaces
+        .into_iter()
+        .map(|(name, closures)| {
+            let mut summary = SymbolSummary::default();
+            for libs in closures.iter() {
+                summary.merge(SymbolSummary::from_libs(libs));
+            }
+            (name, summary)
+        })
+        .collect::<Vec<_>>();
+
+    let mut reports = Vec::new();
+    for (name, summary) in summaries.iter() {
+        let others = summaries
+            .iter()
+            .filter(|&(other, _)| other != name)
+            .collect::<Vec<_>>();
+
+        let mut unresolved = Vec::new();
+        let mut missing_proxies = Vec::new();
+        for (symbol, lib_names) in summary.unresolved.iter() {
+            if summary.defined.contains_key(symbol) {
+                continue;
+            }
+            let providers = others
+                .iter()
+                .filter(|&&(_, other)| other.exporters(symbol).is_some())
+                .map(|&(other, _)| other.clone())
+                .collect::<Vec<_>>();
+            if providers.is_empty() {
+                unresolved.push((symbol.clone(), lib_names.clone()));
+            } else {
+                missing_proxies.push((symbol.clone(), lib_names.clone(), providers));
+            }
+        }
+        unresolved.sort_by(|a, b| a.0.cmp(&b.0));
+        missing_proxies.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut duplicates = summary
+            .exported
+            .iter()
+            .map(|(symbol, lib_names)| {
+                (symbol.clone(), summary.duplicate_exporters(symbol, lib_names))
+            })
+            .filter(|(symbol, lib_names)| {
+                lib_names.len() >= 2 && summary.is_referenced(symbol)
+            })
+            .collect::<Vec<_>>();
+        duplicates.sort_by(|a, b| a.0.cmp(&b.0));
+        let classes = duplicates
+            .iter()
+            .filter_map(|(symbol, _)| {
+                summary
+                    .special_classes
+                    .get(symbol)
+                    .map(|&class| (symbol.clone(), class))
+            })
+            .collect();
+
+        let num_cross_namespace_duplicates = summary
+            .exported
+            .keys()
+            .filter(|symbol| {
+                others
+                    .iter()
+                    .any(|&(_, other)| other.exporters(symbol).is_some())
+            })
+            .count();
+
+        reports.push(NamespaceReport {
+            name: name.clone(),
+            unresolved,
+            missing_proxies,
+            duplicates,
+            classes,
+            num_cross_namespace_duplicates,
+        });
+    }
+    reports
+}
@@ -0,0 +1,183 @@
+use goblin::elf::header::{et_to_str, machine_to_str};
+
+use libraries::Library;
+
+use std::fmt;
+
+const EI_CLASS: usize = 4;
+const EI_OSABI: usize = 7;
+const ELFCLASS64: u8 = 2;
+const ET_DYN: u16 = 3;
+const DF_1_PIE: u64 = 0x08000000;
+/// The DT_FLAGS (first) and DT_FLAGS_1 bits that change how the loader treats an object, with the
+/// names `readelf -d` shows for them.
+const DYNAMIC_FLAGS: &[(bool, u64, &str)] = &[
+    (false, 0x1, "ORIGIN"),
+    (false, 0x2, "SYMBOLIC"),
+    (false, 0x8, "BIND_NOW"),
+    (false, 0x10, "STATIC_TLS"),
+    (true, 0x2, "GLOBAL"),
+    (true, 0x8, "NODELETE"),
+    (true, 0x20, "INITFIRST"),
+    (true, 0x40, "NOOPEN"),
+    (true, 0x800, "NODEFLIB"),
+];
+const BUILD_ID_SECTION: &str = ".note.gnu.build-id";
+
+/// File format details of an ELF object, as `file` or `readelf -h` would show them.
+#[derive(Serialize)]
+pub struct ObjectInfo {
+    pub class: &'static str,
+    pub machine: &'static str,
+    pub osabi: String,
+    #[serde(rename = "type")]
+    pub object_type: &'static str,
+    pub pie: bool,
+    /// Notable DT_FLAGS and DT_FLAGS_1 flags (e.g., NODELETE or GLOBAL)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<&'static str>,
+    pub interpreter: Option<String>,
+    pub soname: Option<String>,
+    pub build_id: Option<String>,
+    pub stripped: bool,
+    pub sections: Sections,
+}
+
+/// Which of the sections relevant for symbol lookup and debugging an object contains.
+#[derive(Serialize)]
+pub struct Sections {
+    pub dynsym: bool,
+    pub symtab: bool,
+    pub debug: bool,
+}
+
+fn osabi_to_str(osabi: u8) -> String {
+    match osabi {
+        0 => "SYSV".to_owned(),
+        3 => "GNU/Linux".to_owned(),
+        6 => "Solaris".to_owned(),
+        9 => "FreeBSD".to_owned(),
+        97 => "ARM".to_owned(),
+        255 => "Standalone".to_owned(),
+        other => format!("OSABI {}", other),
+    }
+}
+
+/// Read the build id (as hex string) from the GNU build id note.
+fn read_build_id(bytes: &[u8], offset: usize, size: usize, little_endian: bool) -> Option<String> {
+    let note = bytes.get(offset..offset + size)?;
+    let read_u32 = |at: usize| -> Option<usize> {
+        let b = note.get(at..at + 4)?;
+        Some(if little_endian {
+            (b[0] as usize) | (b[1] as usize) << 8 | (b[2] as usize) << 16 | (b[3] as usize) << 24
+        } else {
+            (b[3] as usize) | (b[2] as usize) << 8 | (b[1] as usize) << 16 | (b[0] as usize) << 24
+        })
+    };
+    let name_size = read_u32(0)?;
+    let desc_size = read_u32(4)?;
+    let desc_start = 12 + name_size.div_ceil(4) * 4;
+    let desc = note.get(desc_start..desc_start + desc_size)?;
+    Some(desc.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl ObjectInfo {
+    pub fn of_library(lib: &Library) -> ObjectInfo {
+        let elf = lib.get_elf();
+        let section_names = elf.section_headers
+            .iter()
+            .map(|sh| (sh, elf.shdr_strtab.get(sh.sh_name).and_then(|n| n.ok()).unwrap_or("")))
+            .collect::<Vec<_>>();
+        let has_section = |name: &str| section_names.iter().any(|&(_, n)| n == name);
+
+        let (flags, flags_1) = lib.dynamic_flags();
+        let pie = elf.header.e_type == ET_DYN
+            && (elf.interpreter.is_some() || flags_1 & DF_1_PIE != 0);
+
+        let build_id = section_names
+            .iter()
+            .find(|&&(_, name)| name == BUILD_ID_SECTION)
+            .and_then(|&(sh, _)| {
+                read_build_id(
+                    lib.get_bytes(),
+                    sh.sh_offset as usize,
+                    sh.sh_size as usize,
+                    elf.little_endian,
+                )
+            });
+        let has_symtab = has_section(".symtab");
+
+        ObjectInfo {
+            class: if elf.header.e_ident[EI_CLASS] == ELFCLASS64 {
+                "ELF64"
+            } else {
+                "ELF32"
+            },
+            machine: machine_to_str(elf.header.e_machine),
+            osabi: osabi_to_str(elf.header.e_ident[EI_OSABI]),
+            object_type: et_to_str(elf.header.e_type),
+            pie,
+            flags: DYNAMIC_FLAGS
+                .iter()
+                .filter(|&&(is_flags_1, bit, _)| {
+                    (if is_flags_1 { flags_1 } else { flags }) & bit != 0
+                })
+                .map(|&(_, _, name)| name)
+                .collect(),
+            interpreter: elf.interpreter.map(|i| i.to_owned()),
+            soname: elf.soname.map(|s| s.to_owned()),
+            build_id,
+            stripped: !has_symtab,
+            sections: Sections {
+                dynsym: has_section(".dynsym"),
+                symtab: has_symtab,
+                debug: section_names
+                    .iter()
+                    .any(|&(_, name)| name.starts_with(".debug_") || name.starts_with(".zdebug_")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ObjectInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.class, self.machine, self.osabi, self.object_type
+        )?;
+        if self.pie {
+            write!(f, " (PIE)")?;
+        }
+        if let Some(ref interpreter) = self.interpreter {
+            write!(f, ", interpreter {}", interpreter)?;
+        }
+        if let Some(ref soname) = self.soname {
+            write!(f, ", soname {}", soname)?;
+        }
+        if !self.flags.is_empty() {
+            write!(f, ", flags {}", self.flags.join(" "))?;
+        }
+        if let Some(ref build_id) = self.build_id {
+            write!(f, ", build id {}", build_id)?;
+        }
+        let sections = [
+            (self.sections.dynsym, ".dynsym"),
+            (self.sections.symtab, ".symtab"),
+            (self.sections.debug, "debug info"),
+        ].iter()
+            .filter(|&&(present, _)| present)
+            .map(|&(_, name)| name)
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            ", {}, sections: {}",
+            if self.stripped { "stripped" } else { "not stripped" },
+            if sections.is_empty() {
+                "none".to_owned()
+            } else {
+                sections.join(", ")
+            }
+        )
+    }
+}
@@ -0,0 +1,75 @@
+use model::Finding;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Group the messages of all findings by their owners (a finding with multiple owners is listed
+/// for each of them). Both owners and messages are sorted to keep the output stable.
+pub fn group_by_owner(findings: Vec<Finding>) -> BTreeMap<PathBuf, Vec<String>> {
+    let mut groups = BTreeMap::new();
+    for finding in findings {
+        for owner in finding.owners {
+            groups
+                .entry(owner)
+                .or_insert(Vec::new())
+                .push(finding.message.clone());
+        }
+    }
+    for descriptions in groups.values_mut() {
+        descriptions.sort();
+    }
+    groups
+}
+
+/// How far from the analyzed file the library that needs to be fixed is in its dependency tree.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DepthBucket {
+    Root,
+    Direct,
+    Second,
+    Deeper,
+    /// Not part of the dependency tree (e.g., preloaded libraries or unresolved names)
+    Outside,
+}
+
+impl DepthBucket {
+    pub fn of(depth: Option<usize>) -> Self {
+        match depth {
+            Some(0) => DepthBucket::Root,
+            Some(1) => DepthBucket::Direct,
+            Some(2) => DepthBucket::Second,
+            Some(_) => DepthBucket::Deeper,
+            None => DepthBucket::Outside,
+        }
+    }
+}
+
+impl fmt::Display for DepthBucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            DepthBucket::Root => "The analyzed file itself",
+            DepthBucket::Direct => "Direct dependencies",
+            DepthBucket::Second => "Dependencies at depth 2",
+            DepthBucket::Deeper => "Deeper dependencies",
+            DepthBucket::Outside => "Outside of the dependency tree",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Group the findings by the depth of their owner (see `LibraryDependencies::depths`) and then by
+/// owner, closest to the analyzed file first.
+pub fn group_by_depth(
+    findings: Vec<Finding>,
+    depths: &HashMap<&PathBuf, usize>,
+) -> BTreeMap<DepthBucket, BTreeMap<PathBuf, Vec<String>>> {
+    let mut buckets = BTreeMap::new();
+    for (owner, messages) in group_by_owner(findings) {
+        let _ = buckets
+            .entry(DepthBucket::of(depths.get(&owner).cloned()))
+            .or_insert(BTreeMap::new())
+            .insert(owner, messages);
+    }
+    buckets
+}
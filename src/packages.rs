@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Looks up (and caches) the distribution packages that own files, using dpkg or rpm. With a
+/// sysroot, the package database inside of it is queried.
+pub struct PackageDb {
+    sysroot: Option<PathBuf>,
+    cache: HashMap<PathBuf, Option<String>>,
+}
+
+/// Run a package manager query and return the first line of its output if it succeeded.
+fn query(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|line| line.trim().to_string())
+        })
+        .filter(|line| !line.is_empty())
+}
+
+/// The paths under which a package database may know a file: with and without symbolic links
+/// resolved and (for merged /usr systems) with the /usr prefix added or removed.
+fn candidate_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    for path in paths {
+        let toggled = match path.strip_prefix("/usr") {
+            Ok(rest) => Path::new("/").join(rest),
+            Err(_) => Path::new("/usr").join(path.strip_prefix("/").unwrap_or(&path)),
+        };
+        for candidate in [path, toggled] {
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+impl PackageDb {
+    pub fn new(sysroot: Option<&Path>) -> Self {
+        PackageDb {
+            sysroot: sysroot.map(|s| s.to_path_buf()),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn query_path(&self, path: &Path) -> Option<String> {
+        let path_str = path.to_string_lossy();
+        let (dpkg_admindir, rpm_root) = match self.sysroot {
+            Some(ref sysroot) => (
+                Some(sysroot.join("var/lib/dpkg").to_string_lossy().into_owned()),
+                Some(sysroot.to_string_lossy().into_owned()),
+            ),
+            None => (None, None),
+        };
+
+        let mut dpkg_args = Vec::new();
+        if let Some(ref admindir) = dpkg_admindir {
+            dpkg_args.push("--admindir");
+            dpkg_args.push(admindir.as_str());
+        }
+        dpkg_args.push("-S");
+        dpkg_args.push(&path_str);
+        // Output format: "<package>[:<arch>][, <package>...]: <path>"
+        let dpkg = query("dpkg-query", &dpkg_args).and_then(|line| {
+            line.rfind(": ")
+                .map(|i| line[..i].split(", ").next().unwrap_or("").to_string())
+                .map(|package| package.split(':').next().unwrap_or("").to_string())
+        });
+        if dpkg.is_some() {
+            return dpkg;
+        }
+
+        let mut rpm_args = Vec::new();
+        if let Some(ref root) = rpm_root {
+            rpm_args.push("--root");
+            rpm_args.push(root.as_str());
+        }
+        rpm_args.extend(&["-qf", "--queryformat", "%{NAME}\\n"]);
+        rpm_args.push(&path_str);
+        query("rpm", &rpm_args)
+    }
+
+    /// The name of the package that owns the file at `path` (a path on the analyzed system or
+    /// inside the sysroot), if any.
+    pub fn package_of(&mut self, path: &Path) -> Option<String> {
+        if let Some(package) = self.cache.get(path) {
+            return package.clone();
+        }
+        // Map the file and its canonical path back to paths on the analyzed system.
+        let mut local_paths = vec![path.to_path_buf()];
+        local_paths.extend(fs::canonicalize(path).ok());
+        let system_paths = match self.sysroot {
+            Some(ref sysroot) => {
+                let mut sysroots = vec![sysroot.clone()];
+                sysroots.extend(fs::canonicalize(sysroot).ok());
+                local_paths
+                    .iter()
+                    .filter_map(|p| sysroots.iter().filter_map(|s| p.strip_prefix(s).ok()).next())
+                    .map(|p| Path::new("/").join(p))
+                    .collect()
+            }
+            None => local_paths,
+        };
+        let package = candidate_paths(system_paths)
+            .iter()
+            .filter_map(|candidate| self.query_path(candidate))
+            .next();
+        let _ = self.cache.insert(path.to_path_buf(), package.clone());
+        package
+    }
+}
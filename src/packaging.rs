@@ -0,0 +1,321 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::Library;
+use symbols::count_exported_symbols;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const ET_DYN: u16 = 3;
+const SHT_DYNSYM: u32 = 11;
+const HASH_D_TAG: u64 = 4;
+const GNU_HASH_D_TAG: u64 = 0x6ffffef5;
+
+/// Dynamic tags (and their names) that are only added by prelink.
+const PRELINK_D_TAGS: &[(u64, &str)] = &[
+    (0x6ffffdf5, "DT_GNU_PRELINKED"),
+    (0x6ffffdf8, "DT_CHECKSUM"),
+    (0x6ffffef8, "DT_GNU_CONFLICT"),
+    (0x6ffffef9, "DT_GNU_LIBLIST"),
+];
+
+/// Sections that are only added by prelink.
+const PRELINK_SECTIONS: &[&str] =
+    &[".gnu.prelink_undo", ".gnu.conflict", ".gnu.liblist"];
+
+pub enum PackagingProblem {
+    MissingSoname {
+        lib: PathBuf,
+    },
+    UnconventionalSoname {
+        lib: PathBuf,
+        soname: String,
+    },
+    SonameFileNameMismatch {
+        lib: PathBuf,
+        soname: String,
+    },
+    NoExportedSymbols {
+        lib: PathBuf,
+    },
+    DependencyWithoutExports {
+        lib: PathBuf,
+        dependents: Vec<PathBuf>,
+    },
+    MissingHashTable {
+        lib: PathBuf,
+    },
+    Prelinked {
+        lib: PathBuf,
+        indicators: Vec<&'static str>,
+    },
+    InconsistentHashTable {
+        lib: PathBuf,
+        table: &'static str,
+        description: String,
+    },
+}
+
+impl PackagingProblem {
+    /// The library that has the problem.
+    pub fn lib(&self) -> &Path {
+        match self {
+            &PackagingProblem::MissingSoname { ref lib }
+            | &PackagingProblem::UnconventionalSoname { ref lib, .. }
+            | &PackagingProblem::SonameFileNameMismatch { ref lib, .. }
+            | &PackagingProblem::NoExportedSymbols { ref lib }
+            | &PackagingProblem::DependencyWithoutExports { ref lib, .. }
+            | &PackagingProblem::MissingHashTable { ref lib }
+            | &PackagingProblem::Prelinked { ref lib, .. }
+            | &PackagingProblem::InconsistentHashTable { ref lib, .. } => lib,
+        }
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let kind = match *self {
+            PackagingProblem::MissingSoname { .. } => "missing-soname",
+            PackagingProblem::UnconventionalSoname { .. } => "unconventional-soname",
+            PackagingProblem::SonameFileNameMismatch { .. } => "soname-file-name-mismatch",
+            PackagingProblem::NoExportedSymbols { .. } => "no-exported-symbols",
+            PackagingProblem::DependencyWithoutExports { .. } => "dependency-without-exports",
+            PackagingProblem::MissingHashTable { .. } => "missing-hash-table",
+            PackagingProblem::Prelinked { .. } => "prelinked",
+            PackagingProblem::InconsistentHashTable { table, .. } => table,
+        };
+        fingerprint(kind, &[&normalize_path(self.lib())])
+    }
+}
+
+impl fmt::Display for PackagingProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackagingProblem::MissingSoname { lib } => {
+                write!(f, "{:?}: Shared library has no DT_SONAME", lib)
+            }
+            PackagingProblem::UnconventionalSoname {
+                lib,
+                soname,
+            } => write!(
+                f,
+                "{:?}: SONAME {:?} does not follow the lib<name>.so[.<version>] convention",
+                lib, soname
+            ),
+            PackagingProblem::SonameFileNameMismatch {
+                lib,
+                soname,
+            } => write!(
+                f,
+                "{:?}: SONAME {:?} does not match the file name of the library",
+                lib, soname
+            ),
+            PackagingProblem::NoExportedSymbols { lib } => {
+                write!(f, "{:?}: Library does not export any symbols", lib)
+            }
+            PackagingProblem::DependencyWithoutExports {
+                lib,
+                dependents,
+            } => write!(
+                f,
+                "{:?}: Library does not export any symbols (stripped of exports or all symbols hidden), but is needed by {}. Probably the wrong file was shipped",
+                lib,
+                dependents
+                    .iter()
+                    .map(|d| format!("{:?}", d))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            PackagingProblem::MissingHashTable { lib } => write!(
+                f,
+                "{:?}: Library has neither DT_HASH nor DT_GNU_HASH, so its symbols cannot be looked up at runtime",
+                lib
+            ),
+            PackagingProblem::Prelinked {
+                lib,
+                indicators,
+            } => write!(
+                f,
+                "{:?}: Library is prelinked ({}). Its load address and symbol conflict resolution are fixed by prelink and may differ from this analysis",
+                lib,
+                indicators.join(", ")
+            ),
+            PackagingProblem::InconsistentHashTable {
+                lib,
+                table,
+                description,
+            } => write!(f, "{:?}: Inconsistent {} table: {}", lib, table, description),
+        }
+    }
+}
+
+/// Check a library that is itself the subject of the analysis (as opposed to one of its
+/// dependencies) for common packaging mistakes. Executables are not checked.
+pub fn check_shared_library(lib: &Library) -> Vec<PackagingProblem> {
+    let elf = lib.get_elf();
+    let path = lib.get_path();
+
+    // PIE executables are ET_DYN as well, but (unlike libraries) request an interpreter.
+    if elf.header.e_type != ET_DYN || elf.interpreter.is_some() {
+        return Vec::new();
+    }
+
+    let mut problems = Vec::new();
+    if let Some(soname) = elf.soname {
+        if !soname.starts_with("lib") || !soname.contains(".so") {
+            problems.push(PackagingProblem::UnconventionalSoname {
+                lib: path.to_path_buf(),
+                soname: soname.to_owned(),
+            });
+        }
+
+        // The file is either the real library (e.g., libfoo.so.1.2.3 for SONAME libfoo.so.1) or
+        // a development symlink (e.g., libfoo.so).
+        let file_name = lib.get_name().to_string_lossy();
+        if !file_name.starts_with(soname) && !soname.starts_with(file_name.as_ref()) {
+            problems.push(PackagingProblem::SonameFileNameMismatch {
+                lib: path.to_path_buf(),
+                soname: soname.to_owned(),
+            });
+        }
+    } else {
+        problems.push(PackagingProblem::MissingSoname {
+            lib: path.to_path_buf(),
+        });
+    }
+
+    if count_exported_symbols(elf) == 0 {
+        problems.push(PackagingProblem::NoExportedSymbols {
+            lib: path.to_path_buf(),
+        });
+    }
+    problems
+}
+
+/// Check a dependency (needed by `dependents`) for exporting no symbols at all, either because
+/// its dynamic symbol table is empty or because all of its symbols are hidden. Depending on such
+/// a library is almost always a packaging mistake, as it cannot contribute anything.
+pub fn check_dependency_exports(
+    lib: &Library,
+    dependents: Vec<PathBuf>,
+) -> Option<PackagingProblem> {
+    let elf = lib.get_elf();
+    if dependents.is_empty() || count_exported_symbols(elf) > 0 {
+        return None;
+    }
+    Some(PackagingProblem::DependencyWithoutExports {
+        lib: lib.get_path().to_path_buf(),
+        dependents,
+    })
+}
+
+/// Check that a library that exports symbols carries a dynamic hash table (DT_HASH or
+/// DT_GNU_HASH) that is consistent with its dynamic symbol table.
+pub fn check_hash_tables(lib: &Library) -> Vec<PackagingProblem> {
+    let elf = lib.get_elf();
+    let path = lib.get_path();
+
+    let dynamic = match &elf.dynamic {
+        Some(dynamic) => dynamic,
+        &None => return Vec::new(),
+    };
+    let find_tag = |tag| dynamic.dyns.iter().find(|d| d.d_tag == tag).map(|d| d.d_val);
+    let hash = find_tag(HASH_D_TAG);
+    let gnu_hash = find_tag(GNU_HASH_D_TAG);
+
+    let mut problems = Vec::new();
+    if hash.is_none() && gnu_hash.is_none() {
+        if count_exported_symbols(elf) > 0 {
+            problems.push(PackagingProblem::MissingHashTable {
+                lib: path.to_path_buf(),
+            });
+        }
+        return problems;
+    }
+
+    // The number of dynamic symbols can only be determined reliably from the section headers.
+    let num_dynsyms = match elf.section_headers
+        .iter()
+        .find(|sh| sh.sh_type == SHT_DYNSYM && sh.sh_entsize > 0)
+    {
+        Some(sh) => sh.sh_size / sh.sh_entsize,
+        None => return problems,
+    };
+
+    if let Some(hash) = hash {
+        // DT_HASH layout: nbucket, nchain, ...; nchain equals the number of dynamic symbols.
+        match lib.read_u32_at_vaddr(elf, hash + 4) {
+            Some(nchain) if nchain as u64 != num_dynsyms => {
+                problems.push(PackagingProblem::InconsistentHashTable {
+                    lib: path.to_path_buf(),
+                    table: "DT_HASH",
+                    description: format!(
+                        "hash table covers {} symbols, but the library has {} dynamic symbols",
+                        nchain, num_dynsyms
+                    ),
+                })
+            }
+            Some(_) => {}
+            None => problems.push(PackagingProblem::InconsistentHashTable {
+                lib: path.to_path_buf(),
+                table: "DT_HASH",
+                description: "table is not located in a loadable segment".to_owned(),
+            }),
+        }
+    }
+
+    if let Some(gnu_hash) = gnu_hash {
+        // DT_GNU_HASH layout: nbuckets, symoffset, ...; symbols below symoffset are not hashed.
+        match lib.read_u32_at_vaddr(elf, gnu_hash + 4) {
+            Some(symoffset) if symoffset as u64 > num_dynsyms => {
+                problems.push(PackagingProblem::InconsistentHashTable {
+                    lib: path.to_path_buf(),
+                    table: "DT_GNU_HASH",
+                    description: format!(
+                        "first hashed symbol index {} exceeds the {} dynamic symbols",
+                        symoffset, num_dynsyms
+                    ),
+                })
+            }
+            Some(_) => {}
+            None => problems.push(PackagingProblem::InconsistentHashTable {
+                lib: path.to_path_buf(),
+                table: "DT_GNU_HASH",
+                description: "table is not located in a loadable segment".to_owned(),
+            }),
+        }
+    }
+    problems
+}
+
+/// Detect artifacts of prelink (dynamic tags and sections), which change the resolution behavior
+/// of the loader.
+pub fn check_prelink(lib: &Library) -> Option<PackagingProblem> {
+    let elf = lib.get_elf();
+
+    let mut indicators = Vec::new();
+    if let Some(dynamic) = &elf.dynamic {
+        for &(tag, name) in PRELINK_D_TAGS {
+            if dynamic.dyns.iter().any(|d| d.d_tag == tag) {
+                indicators.push(name);
+            }
+        }
+    }
+    for &section in PRELINK_SECTIONS {
+        let present = elf.section_headers.iter().any(|sh| {
+            elf.shdr_strtab
+                .get(sh.sh_name)
+                .and_then(|name| name.ok()) == Some(section)
+        });
+        if present {
+            indicators.push(section);
+        }
+    }
+
+    if indicators.is_empty() {
+        None
+    } else {
+        Some(PackagingProblem::Prelinked {
+            lib: lib.get_path().to_path_buf(),
+            indicators,
+        })
+    }
+}
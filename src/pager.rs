@@ -0,0 +1,63 @@
+use libc;
+use term;
+
+use std::env;
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+pub type ReportTerminal = dyn term::Terminal<Output = Box<dyn Write + Send>> + Send;
+
+/// A pager process that the report is piped through.
+pub struct Pager {
+    child: Child,
+}
+
+impl Pager {
+    /// Wait for the user to quit the pager. The terminal writing to the pager has to be dropped
+    /// before, otherwise the pager will never see the end of its input.
+    pub fn wait(mut self) {
+        let _ = self.child.wait();
+    }
+}
+
+fn stdout_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+fn start_pager() -> Option<Child> {
+    let pager = env::var("PAGER").unwrap_or("less".to_owned());
+    if pager.is_empty() || pager == "cat" {
+        return None;
+    }
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(Stdio::piped());
+
+    // Keep colors (R), do not clear the screen on exit (X) and do not page at all if the report
+    // fits on one screen (F), unless the user has configured less otherwise.
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    command.spawn().ok()
+}
+
+/// Open the terminal that the report is written to. If `use_pager` is set and stdout is a tty,
+/// the report is piped through $PAGER (or less).
+pub fn open_report_terminal(use_pager: bool) -> (Box<ReportTerminal>, Option<Pager>) {
+    if use_pager && stdout_is_tty() {
+        if let Some(mut child) = start_pager() {
+            let stdin = child.stdin.take().expect("stdin is piped");
+            if let Some(t) = term::TerminfoTerminal::new(Box::new(stdin) as Box<dyn Write + Send>) {
+                // Terminate silently instead of panicking on failed writes if the user quits the
+                // pager before the whole report has been written.
+                unsafe {
+                    libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+                }
+                return (Box::new(t), Some(Pager { child }));
+            }
+        }
+    }
+    let t = term::TerminfoTerminal::new(Box::new(io::stdout()) as Box<dyn Write + Send>)
+        .expect("Could not open terminal");
+    (Box::new(t), None)
+}
@@ -0,0 +1,151 @@
+use libraries::{reroot, LibResolveProblem, LibraryDependencies};
+use symbols::SymbolSummary;
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// An environment variable listing plugin directories (e.g., GST_PLUGIN_PATH), with the
+/// separator between the directories.
+#[derive(Debug, Clone)]
+pub struct PluginEnv {
+    pub variable: String,
+    pub separator: char,
+}
+
+impl FromStr for PluginEnv {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let variable = parts.next().unwrap();
+        let separator = match parts.next() {
+            None => Some(':'),
+            Some(separator) if separator.chars().count() == 1 => separator.chars().next(),
+            Some(_) => None,
+        };
+        match separator {
+            Some(separator) if !variable.is_empty() => Ok(PluginEnv {
+                variable: variable.to_owned(),
+                separator,
+            }),
+            _ => Err(format!(
+                "Invalid plugin variable '{}'. Expected <variable>[:<separator character>]",
+                s
+            )),
+        }
+    }
+}
+
+fn is_shared_object(path: &Path) -> bool {
+    let is_so = path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".so") || name.contains(".so."));
+    let mut magic = [0u8; 4];
+    is_so && fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok() && magic == ELF_MAGIC
+}
+
+/// Collect the shared objects in `dir` and (as plugin systems usually group plugins by type in
+/// subdirectories) all directories below it. Symbolic links to directories are not followed.
+fn collect_shared_objects(dir: &Path, plugins: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(ref file_type) if file_type.is_dir() => collect_shared_objects(&path, plugins),
+            Ok(_) if is_shared_object(&path) => plugins.push(path),
+            _ => {}
+        }
+    }
+}
+
+impl PluginEnv {
+    /// The plugins in the directories listed in the variable (in the environment of linkcheck),
+    /// in order of the directories.
+    pub fn plugins(&self, sysroot: Option<&Path>) -> Vec<PathBuf> {
+        let value = env::var(&self.variable).unwrap_or_default();
+        let mut plugins = Vec::new();
+        for dir in value.split(self.separator).filter(|d| !d.is_empty()) {
+            let mut dir_plugins = Vec::new();
+            collect_shared_objects(&reroot(Path::new(dir), sysroot), &mut dir_plugins);
+            dir_plugins.sort();
+            for plugin in dir_plugins {
+                if !plugins.contains(&plugin) {
+                    plugins.push(plugin);
+                }
+            }
+        }
+        plugins
+    }
+}
+
+/// Whether a plugin can be loaded into a process of the analyzed files (the host).
+pub struct PluginCheck {
+    pub plugin: PathBuf,
+    pub variable: String,
+    /// The plugin could not be analyzed at all.
+    pub error: Option<String>,
+    /// Library resolution problems of the closure of the plugin.
+    pub problems: Vec<String>,
+    /// Symbols that neither the closure of the plugin nor the host provides.
+    pub unresolved_symbols: Vec<String>,
+}
+
+impl PluginCheck {
+    pub fn is_loadable(&self) -> bool {
+        self.error.is_none() && self.problems.is_empty() && self.unresolved_symbols.is_empty()
+    }
+}
+
+/// Check a plugin against the host it is loaded into: Dependencies that the host has already
+/// loaded (by name) are available to the plugin, and so are the symbols the host exports.
+pub fn check_plugin(
+    plugin: &Path,
+    variable: &str,
+    libs: &Result<LibraryDependencies, String>,
+    host: &SymbolSummary,
+    host_libs: &HashSet<String>,
+) -> PluginCheck {
+    let mut check = PluginCheck {
+        plugin: plugin.to_path_buf(),
+        variable: variable.to_owned(),
+        error: None,
+        problems: Vec::new(),
+        unresolved_symbols: Vec::new(),
+    };
+    let libs = match libs {
+        Ok(libs) => libs,
+        Err(err) => {
+            check.error = Some(err.clone());
+            return check;
+        }
+    };
+
+    for problem in libs.problems.iter() {
+        if let LibResolveProblem::Unresolved { lib_name, .. } = problem {
+            if host_libs.contains(lib_name) {
+                continue;
+            }
+        }
+        check.problems.push(problem.to_string());
+    }
+
+    let summary = SymbolSummary::from_libs(libs);
+    check.unresolved_symbols = summary
+        .unresolved
+        .keys()
+        .filter(|symbol| !summary.defined.contains_key(*symbol) && host.exporters(symbol).is_none())
+        .cloned()
+        .collect();
+    check.unresolved_symbols.sort();
+    check
+}
@@ -0,0 +1,465 @@
+use goblin::elf::Elf;
+
+use files::FileProvider;
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{reroot, ElfArch, Library, LibraryDependencies, LibraryLocations};
+use symbols::demangle;
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const LD_SO_PRELOAD: &str = "/etc/ld.so.preload";
+const LD_PRELOAD: &str = "LD_PRELOAD";
+
+/// A library that is loaded before all others, either for all processes (/etc/ld.so.preload) or
+/// because of the environment ($LD_PRELOAD).
+pub struct PreloadEntry {
+    pub name: String,
+    pub source: &'static str,
+}
+
+/// Read all preload entries in the order the loader processes them: $LD_PRELOAD first, then
+/// /etc/ld.so.preload (below `sysroot`, if given). Both are lists separated by spaces or colons.
+pub fn preload_entries(ld_preload: Option<&str>, sysroot: Option<&Path>) -> Vec<PreloadEntry> {
+    let split = |s: &str, source: &'static str| {
+        s.split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .map(|name| PreloadEntry {
+                name: name.to_owned(),
+                source,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut entries = ld_preload
+        .map(|s| split(s, LD_PRELOAD))
+        .unwrap_or_default();
+    if let Ok(content) = fs::read_to_string(reroot(Path::new(LD_SO_PRELOAD), sysroot)) {
+        entries.extend(split(&content, LD_SO_PRELOAD));
+    }
+    entries
+}
+
+pub enum PreloadProblem {
+    Missing {
+        name: String,
+        source: &'static str,
+    },
+    WrongArchitecture {
+        path: PathBuf,
+        source: &'static str,
+        description: String,
+    },
+    InsecureLocation {
+        path: PathBuf,
+        source: &'static str,
+        reason: String,
+    },
+}
+
+impl PreloadProblem {
+    /// The file that has to be changed to fix the problem: the preload list for missing entries,
+    /// the preloaded library otherwise.
+    pub fn owner(&self) -> &Path {
+        match self {
+            &PreloadProblem::Missing { source, .. } => Path::new(source),
+            &PreloadProblem::WrongArchitecture { ref path, .. }
+            | &PreloadProblem::InsecureLocation { ref path, .. } => path,
+        }
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        match *self {
+            PreloadProblem::Missing {
+                ref name,
+                source,
+            } => fingerprint("missing-preload", &[source, name]),
+            PreloadProblem::WrongArchitecture {
+                ref path,
+                source,
+                ..
+            } => fingerprint("wrong-architecture-preload", &[source, &normalize_path(path)]),
+            PreloadProblem::InsecureLocation {
+                ref path,
+                source,
+                ..
+            } => fingerprint("insecure-preload", &[source, &normalize_path(path)]),
+        }
+    }
+}
+
+impl fmt::Display for PreloadProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreloadProblem::Missing {
+                name,
+                source,
+            } => write!(f, "{} entry '{}' does not exist", source, name),
+            PreloadProblem::WrongArchitecture {
+                path,
+                source,
+                description,
+            } => write!(
+                f,
+                "{} entry {:?} {} and is silently skipped by the loader",
+                source, path, description
+            ),
+            PreloadProblem::InsecureLocation {
+                path,
+                source,
+                reason,
+            } => write!(f, "{} entry {:?} {}", source, path, reason),
+        }
+    }
+}
+
+fn architecture(elf: &Elf) -> (bool, u16) {
+    (elf.is_64, elf.header.e_machine)
+}
+
+/// Describe why `path` may be replaced by other (unprivileged) users, if it can be.
+fn insecurity_reason(path: &Path) -> Option<String> {
+    if path.is_relative() {
+        return Some("is relative to the working directory of the process".to_owned());
+    }
+    let is_writable_by_others =
+        |p: &Path| fs::metadata(p).map(|m| m.permissions().mode() & 0o022 != 0).unwrap_or(false);
+    if is_writable_by_others(path) {
+        return Some("is writable by other users".to_owned());
+    }
+    path.ancestors()
+        .skip(1)
+        .find(|dir| is_writable_by_others(dir))
+        .map(|dir| format!("is located below {:?}, which is writable by other users", dir))
+}
+
+/// Find the file of a preload entry in `files`: Names with a slash are paths (below `sysroot`),
+/// others are searched in `locations` like the loader does: Files of another architecture than
+/// `arch` are skipped, but if there are only such files, the first one is returned.
+pub fn locate_preload(
+    name: &str,
+    arch: Option<ElfArch>,
+    locations: Option<&LibraryLocations>,
+    files: &dyn FileProvider,
+    sysroot: Option<&Path>,
+) -> Option<PathBuf> {
+    if name.contains('/') {
+        Some(reroot(Path::new(name), sysroot)).filter(|path| files.exists(path))
+    } else {
+        locations.and_then(|l| match l.try_find_compatible_library(name, arch, files) {
+            (Some((path, _)), _) => Some(path),
+            (None, skipped) => skipped.into_iter().next(),
+        })
+    }
+}
+
+/// Check that every preload entry exists, matches the architecture of `root` and cannot be
+/// replaced by other users. Entries without a slash are searched in `locations` (those of the
+/// root), like the loader does.
+pub fn audit_preloads(
+    entries: &[PreloadEntry],
+    libs: &LibraryDependencies,
+    sysroot: Option<&Path>,
+) -> Vec<PreloadProblem> {
+    let root = libs.opened_libs
+        .get(&libs.root)
+        .expect("The root is always opened");
+    let locations = libs.search_locations.get(&libs.root);
+    let root_arch = architecture(root.get_elf());
+    let root_elf_arch = ElfArch::of_file(root.get_path(), &*libs.files);
+
+    let mut problems = Vec::new();
+    for entry in entries {
+        let path = locate_preload(&entry.name, root_elf_arch, locations, &*libs.files, sysroot);
+        let path = match path {
+            Some(path) => path,
+            None => {
+                problems.push(PreloadProblem::Missing {
+                    name: entry.name.clone(),
+                    source: entry.source,
+                });
+                continue;
+            }
+        };
+
+        if let Some(reason) = insecurity_reason(&path) {
+            problems.push(PreloadProblem::InsecureLocation {
+                path: path.clone(),
+                source: entry.source,
+                reason,
+            });
+        }
+
+        let description = match libs.open_library(&path) {
+            Ok(lib) => {
+                let arch = architecture(lib.get_elf());
+                if arch == root_arch {
+                    continue;
+                }
+                format!(
+                    "is a {}-bit object for machine {} (expected {}-bit, machine {})",
+                    if arch.0 { 64 } else { 32 },
+                    arch.1,
+                    if root_arch.0 { 64 } else { 32 },
+                    root_arch.1
+                )
+            }
+            Err(e) => format!("cannot be loaded ({})", e),
+        };
+        problems.push(PreloadProblem::WrongArchitecture {
+            path,
+            source: entry.source,
+            description,
+        });
+    }
+    problems
+}
+
+/// A library to inject in front of the lookup scope like $LD_PRELOAD does (see --preload),
+/// optionally with the symbols it is expected to override ('lib=symbol,...').
+#[derive(Debug, Clone)]
+pub struct PreloadSpec {
+    pub name: String,
+    pub expected: Vec<String>,
+}
+
+impl FromStr for PreloadSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            return Err(format!("Invalid preload '{}'. Expected <lib>[=<symbol>,...]", s));
+        }
+        Ok(PreloadSpec {
+            name: name.to_owned(),
+            expected: parts
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .map(|symbol| symbol.trim())
+                .filter(|symbol| !symbol.is_empty())
+                .map(|symbol| symbol.to_owned())
+                .collect(),
+        })
+    }
+}
+
+/// A simulated preload (see `PreloadSpec`) and its closure.
+pub struct Preloaded {
+    pub spec: PreloadSpec,
+    pub libs: LibraryDependencies,
+}
+
+/// The effect of a simulated preload on one symbol.
+pub enum Interposition {
+    /// The preload comes first in the lookup scope, so all references bind to its definition
+    /// instead of those of the shadowed objects. `expected` is None if no symbols were expected.
+    Overrides {
+        preload: PathBuf,
+        symbol: String,
+        shadowed: Vec<PathBuf>,
+        expected: Option<bool>,
+    },
+    /// The preload does not override a symbol it is expected to override.
+    NotOverridden {
+        preload: PathBuf,
+        symbol: String,
+        reason: String,
+    },
+}
+
+impl Interposition {
+    pub fn preload(&self) -> &Path {
+        match self {
+            &Interposition::Overrides { ref preload, .. }
+            | &Interposition::NotOverridden { ref preload, .. } => preload,
+        }
+    }
+
+    /// Whether an expected override does not happen.
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Interposition::NotOverridden { .. } => true,
+            Interposition::Overrides { .. } => false,
+        }
+    }
+
+    /// Whether the preload overrides a symbol that it is not expected to override.
+    pub fn is_unintended(&self) -> bool {
+        matches!(
+            self,
+            &Interposition::Overrides {
+                expected: Some(false),
+                ..
+            }
+        )
+    }
+
+    /// A stable fingerprint of the interposition (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let (kind, preload, symbol) = match self {
+            Interposition::Overrides {
+                preload,
+                symbol,
+                ..
+            } => ("preload-override", preload, symbol),
+            Interposition::NotOverridden {
+                preload,
+                symbol,
+                ..
+            } => ("preload-not-overridden", preload, symbol),
+        };
+        fingerprint(kind, &[&normalize_path(preload), symbol])
+    }
+}
+
+impl fmt::Display for Interposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Interposition::Overrides {
+                ref preload,
+                ref symbol,
+                ref shadowed,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "{:?}: Overrides {} of {}",
+                    preload,
+                    demangle(symbol).unwrap_or(symbol.clone()),
+                    shadowed
+                        .iter()
+                        .map(|p| format!("{:?}", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )?;
+                if expected == Some(false) {
+                    write!(f, ", which is not among the expected symbols")?;
+                }
+                Ok(())
+            }
+            Interposition::NotOverridden {
+                preload,
+                symbol,
+                reason,
+            } => write!(
+                f,
+                "{:?}: Does not override {}, as it {}",
+                preload,
+                demangle(symbol).unwrap_or(symbol.clone()),
+                reason
+            ),
+        }
+    }
+}
+
+/// The names of the symbols that the library exports (without version suffixes).
+fn plain_exports(lib: &Library) -> HashSet<&str> {
+    lib.symbols()
+        .exported
+        .iter()
+        .filter(|name| !name.contains('@'))
+        .map(|name| name.as_str())
+        .collect()
+}
+
+/// Determine which symbols the simulated preloads override, in the order the loader builds the
+/// lookup scope: the analyzed file, the preloads, and then the dependencies (of the file and of
+/// the preloads). Without expected symbols, all overrides are reported as they are; otherwise,
+/// expected symbols that are not overridden and unexpected overrides are reported.
+pub fn find_interpositions(libs: &LibraryDependencies, preloads: &[Preloaded]) -> Vec<Interposition> {
+    let root_exports = libs.opened_libs
+        .get(&libs.root)
+        .map(|root| plain_exports(root))
+        .unwrap_or_default();
+    let preload_paths = preloads.iter().map(|p| &p.libs.root).collect::<Vec<_>>();
+
+    // The other objects of the process, which the preloads come before.
+    let mut others = Vec::new();
+    for closure in Some(libs).into_iter().chain(preloads.iter().map(|p| &p.libs)) {
+        for (path, lib) in closure.opened_libs.iter() {
+            if path != &libs.root && !preload_paths.contains(&path)
+                && !others.iter().any(|&(p, _)| p == path)
+            {
+                others.push((path, plain_exports(lib)));
+            }
+        }
+    }
+    others.sort_by_key(|&(path, _)| path);
+
+    let mut interpositions = Vec::new();
+    for (i, preload) in preloads.iter().enumerate() {
+        let path = &preload.libs.root;
+        let exports = match preload.libs.opened_libs.get(path) {
+            Some(lib) => plain_exports(lib),
+            None => continue,
+        };
+        let earlier_preload = |symbol: &str| {
+            preloads[..i]
+                .iter()
+                .find(|p| {
+                    p.libs.opened_libs
+                        .get(&p.libs.root)
+                        .is_some_and(|lib| plain_exports(lib).contains(symbol))
+                })
+                .map(|p| &p.libs.root)
+        };
+        let shadowed = |symbol: &str| {
+            others
+                .iter()
+                .filter(|&(_, exports)| exports.contains(symbol))
+                .map(|&(p, _)| p.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut symbols = exports.iter().cloned().collect::<Vec<_>>();
+        symbols.sort();
+        for symbol in symbols {
+            if root_exports.contains(symbol) || earlier_preload(symbol).is_some() {
+                continue;
+            }
+            let shadowed = shadowed(symbol);
+            let expected = if preload.spec.expected.is_empty() {
+                None
+            } else {
+                Some(preload.spec.expected.iter().any(|e| e == symbol))
+            };
+            if !shadowed.is_empty() {
+                interpositions.push(Interposition::Overrides {
+                    preload: path.clone(),
+                    symbol: symbol.to_owned(),
+                    shadowed,
+                    expected,
+                });
+            }
+        }
+
+        for symbol in preload.spec.expected.iter() {
+            let reason = if !exports.contains(symbol.as_str()) {
+                "does not export it".to_owned()
+            } else if root_exports.contains(symbol.as_str()) {
+                "is exported by the analyzed file, which comes first in the lookup scope"
+                    .to_owned()
+            } else if let Some(earlier) = earlier_preload(symbol) {
+                format!("is exported by the earlier preload {:?}", earlier)
+            } else if shadowed(symbol).is_empty() {
+                "is not defined by any other object, so there is nothing to override".to_owned()
+            } else {
+                continue;
+            };
+            interpositions.push(Interposition::NotOverridden {
+                preload: path.clone(),
+                symbol: symbol.clone(),
+                reason,
+            });
+        }
+    }
+    interpositions
+}
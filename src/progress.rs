@@ -0,0 +1,165 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use libc;
+use libraries::Progress;
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The minimal time between two JSON progress events (besides those of start and end).
+const JSON_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the progress of the analysis is shown on stderr (see --progress).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressMode {
+    /// A progress bar if stderr is a terminal and more than one file is analyzed
+    Auto,
+    Bar,
+    /// One JSON object per line, at most once per second
+    Json,
+    None,
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ProgressMode::Auto),
+            "bar" => Ok(ProgressMode::Bar),
+            "json" => Ok(ProgressMode::Json),
+            "none" => Ok(ProgressMode::None),
+            other => Err(format!(
+                "Invalid progress mode '{}'. Expected auto, bar, json or none",
+                other
+            )),
+        }
+    }
+}
+
+fn stderr_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDERR_FILENO) == 1 }
+}
+
+/// Shows the progress of the analysis of all files on stderr.
+pub struct ProgressReporter {
+    mode: ProgressMode,
+    bar: Mutex<Option<ProgressBar>>,
+    phase: Mutex<String>,
+    start: Instant,
+    last_event: Mutex<Option<Instant>>,
+    total: AtomicUsize,
+    files: AtomicUsize,
+    libraries: AtomicUsize,
+}
+
+impl ProgressReporter {
+    pub fn new(mode: ProgressMode) -> Self {
+        ProgressReporter {
+            mode,
+            bar: Mutex::new(None),
+            phase: Mutex::new(String::new()),
+            start: Instant::now(),
+            last_event: Mutex::new(None),
+            total: AtomicUsize::new(0),
+            files: AtomicUsize::new(0),
+            libraries: AtomicUsize::new(0),
+        }
+    }
+
+    /// Write a JSON progress event, unless the last one was written less than a second ago (and
+    /// the event is not forced).
+    fn json_event(&self, event: &str, current: Option<&Path>, force: bool) {
+        let now = Instant::now();
+        {
+            let mut last_event = self.last_event.lock().expect("Progress lock poisoned");
+            if !force && last_event.is_some_and(|last| now - last < JSON_EVENT_INTERVAL) {
+                return;
+            }
+            *last_event = Some(now);
+        }
+        let mut value = json!({
+            "event": event,
+            "phase": *self.phase.lock().expect("Progress lock poisoned"),
+            "files_done": self.files.load(Ordering::SeqCst),
+            "files_total": self.total.load(Ordering::SeqCst),
+            "libraries": self.libraries.load(Ordering::SeqCst),
+            "elapsed_ms": (now - self.start).as_millis() as u64,
+        });
+        if let Some(current) = current {
+            value["current"] = json!(current);
+        }
+        eprintln!("{}", value);
+    }
+
+    fn update_bar(&self, current: &Path) {
+        if let Some(ref bar) = *self.bar.lock().expect("Progress lock poisoned") {
+            bar.set_position(self.files.load(Ordering::SeqCst) as u64);
+            bar.set_message(format!(
+                "{} libraries, {}",
+                self.libraries.load(Ordering::SeqCst),
+                current.display()
+            ));
+        }
+    }
+
+    /// End the progress output of all phases (removing the progress bar).
+    pub fn finish(&self) {
+        match self.mode {
+            ProgressMode::Json => self.json_event("finished", None, true),
+            _ => {
+                if let Some(bar) = self.bar.lock().expect("Progress lock poisoned").take() {
+                    bar.finish_and_clear();
+                }
+            }
+        }
+    }
+}
+
+impl Progress for ProgressReporter {
+    fn start(&self, phase: &str, total: usize) {
+        if let Some(bar) = self.bar.lock().expect("Progress lock poisoned").take() {
+            bar.finish_and_clear();
+        }
+        *self.phase.lock().expect("Progress lock poisoned") = phase.to_owned();
+        self.total.store(total, Ordering::SeqCst);
+        self.files.store(0, Ordering::SeqCst);
+        let show_bar = match self.mode {
+            ProgressMode::Auto => total > 1 && stderr_is_terminal(),
+            ProgressMode::Bar => true,
+            ProgressMode::Json => {
+                self.json_event("started", None, true);
+                false
+            }
+            ProgressMode::None => false,
+        };
+        if show_bar {
+            let bar =
+                ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stderr());
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] {prefix} {bar:30} {pos}/{len} files, {wide_msg}",
+                ).expect("Valid progress bar template"),
+            );
+            bar.set_prefix(phase.to_owned());
+            *self.bar.lock().expect("Progress lock poisoned") = Some(bar);
+        }
+    }
+
+    fn library_analyzed(&self, root: &Path, _path: &Path) {
+        let _ = self.libraries.fetch_add(1, Ordering::SeqCst);
+        match self.mode {
+            ProgressMode::Json => self.json_event("progress", Some(root), false),
+            _ => self.update_bar(root),
+        }
+    }
+
+    fn file_analyzed(&self, root: &Path) {
+        let _ = self.files.fetch_add(1, Ordering::SeqCst);
+        match self.mode {
+            ProgressMode::Json => self.json_event("progress", Some(root), false),
+            _ => self.update_bar(root),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use libc;
+use libraries::ResolveSettings;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::CStr;
+use std::mem;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The machine linkcheck runs on.
+#[derive(Serialize)]
+pub struct Host {
+    pub hostname: String,
+    pub os: String,
+    pub release: String,
+    pub machine: String,
+}
+
+impl Host {
+    fn current() -> Host {
+        let field = |chars: &[libc::c_char]| {
+            unsafe { CStr::from_ptr(chars.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        let mut uts: libc::utsname = unsafe { mem::zeroed() };
+        if unsafe { libc::uname(&mut uts) } != 0 {
+            return Host {
+                hostname: String::new(),
+                os: String::new(),
+                release: String::new(),
+                machine: String::new(),
+            };
+        }
+        Host {
+            hostname: field(&uts.nodename),
+            os: field(&uts.sysname),
+            release: field(&uts.release),
+            machine: field(&uts.machine),
+        }
+    }
+}
+
+/// Format `time` as UTC timestamp in RFC 3339 format (e.g., 2024-05-01T12:34:56Z).
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // Convert the days since the epoch to a civil date (see "chrono-Compatible Low-Level Date
+    // Algorithms" by Howard Hinnant).
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// How a report was produced, so that it can be interpreted and reproduced later: the tool, its
+/// invocation and the inputs that do not show up in the report itself.
+#[derive(Serialize)]
+pub struct Provenance {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub command_line: Vec<String>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub host: Host,
+    /// The search locations after expansion (defaults, --as-user and --expand-env)
+    pub search_methods: Vec<String>,
+    pub sysroot: Option<PathBuf>,
+    pub parse_profile: &'static str,
+    pub identity: String,
+    pub traversal: String,
+    pub user: Option<String>,
+    /// The environment variables the analysis read, with their values (null if unset)
+    pub environment: BTreeMap<String, Option<String>>,
+}
+
+impl Provenance {
+    /// Collect the provenance of a run that started at `started`, used `settings` and read the
+    /// given variables of the environment of the loading process.
+    pub fn collect(
+        started: SystemTime,
+        settings: &ResolveSettings,
+        hardened_parse: bool,
+        user: Option<String>,
+        variables: &[&str],
+    ) -> Provenance {
+        Provenance {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            command_line: env::args().collect(),
+            started_at: utc_timestamp(started),
+            finished_at: utc_timestamp(SystemTime::now()),
+            host: Host::current(),
+            search_methods: settings.search_methods.iter().map(|m| m.to_string()).collect(),
+            sysroot: settings.sysroot.clone(),
+            parse_profile: if hardened_parse { "hardened" } else { "default" },
+            identity: settings.identity.to_string(),
+            traversal: settings.traversal.to_string(),
+            user,
+            environment: variables
+                .iter()
+                .map(|&name| {
+                    let value = settings
+                        .environment
+                        .var_os(name)
+                        .map(|v| v.to_string_lossy().into_owned());
+                    (name.to_owned(), value)
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use libraries::{ErrorMsg, LibResolveProblem};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A library that is provided by the runtime environment instead of a file of the analyzed system
+/// (e.g., libcuda.so.1, which the container runtime injects from the GPU driver), optionally
+/// with a stub file listing the symbols it exports.
+#[derive(Debug, Clone)]
+pub struct ProvidedLibrary {
+    pub name: String,
+    pub stub: Option<PathBuf>,
+}
+
+/// A comma separated list of provided libraries ('name' or 'name=stub file').
+#[derive(Debug, Clone)]
+pub struct ProvidedLibraries(pub Vec<ProvidedLibrary>);
+
+impl FromStr for ProvidedLibraries {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|entry| {
+                let mut parts = entry.splitn(2, '=');
+                let name = parts.next().unwrap_or("").trim();
+                if name.is_empty() {
+                    return Err(format!("Invalid provided library '{}' (in '{}')", entry, s));
+                }
+                Ok(ProvidedLibrary {
+                    name: name.to_owned(),
+                    stub: parts.next().map(PathBuf::from),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(ProvidedLibraries)
+    }
+}
+
+/// The libraries assumed to be provided (see --assume-provided) and the symbols their stubs
+/// export.
+#[derive(Default)]
+pub struct AssumedProvided {
+    names: HashSet<String>,
+    symbols: HashSet<String>,
+}
+
+impl AssumedProvided {
+    /// Collect the provided libraries and read their stub files: one symbol per line (with an
+    /// optional @VERSION suffix to only match that version), '#' starts a comment.
+    pub fn load(lists: &[ProvidedLibraries]) -> Result<Self, Box<dyn Error>> {
+        let mut provided = AssumedProvided::default();
+        for lib in lists.iter().flat_map(|list| list.0.iter()) {
+            let _ = provided.names.insert(lib.name.clone());
+            if let Some(ref stub) = lib.stub {
+                let content = fs::read_to_string(stub).map_err(|e| {
+                    ErrorMsg(format!("Cannot read stub of {} {:?}: {}", lib.name, stub, e))
+                })?;
+                provided.symbols.extend(
+                    content
+                        .lines()
+                        .map(|line| line.split('#').next().unwrap_or("").trim())
+                        .filter(|symbol| !symbol.is_empty())
+                        .map(|symbol| symbol.to_owned()),
+                );
+            }
+        }
+        Ok(provided)
+    }
+
+    pub fn is_provided(&self, lib_name: &str) -> bool {
+        self.names.contains(lib_name)
+    }
+
+    /// Whether the problem is only that a provided library has no (usable) file.
+    pub fn covers(&self, problem: &LibResolveProblem) -> bool {
+        match problem {
+            &LibResolveProblem::Unresolved { ref lib_name, .. }
+            | &LibResolveProblem::UnresolvedButPreviouslyResolved { ref lib_name, .. }
+            | &LibResolveProblem::ArchMismatch { ref lib_name, .. } => {
+                self.is_provided(lib_name)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the stub of a provided library exports the (possibly versioned) symbol.
+    pub fn provides_symbol(&self, symbol: &str) -> bool {
+        let name = symbol.split('@').next().unwrap_or(symbol);
+        self.symbols.contains(symbol) || self.symbols.contains(name)
+    }
+}
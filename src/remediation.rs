@@ -0,0 +1,240 @@
+use cxxruntime::CxxRuntimeProblem;
+use libraries::{LibResolveProblem, LibraryDependencies};
+use model::Remediation;
+use preload::PreloadProblem;
+use rpath_usage::RpathUsage;
+use symbols::exported_symbol_names;
+use versions::NonDefaultVersionOnly;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories with more libraries than this (i.e., system library directories) are not searched
+/// for libraries that could provide unresolved symbols.
+const MAX_SIBLING_LIBRARIES: usize = 64;
+
+fn parent(path: &Path) -> PathBuf {
+    let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    path.parent().unwrap_or(Path::new("/")).to_path_buf()
+}
+
+/// Directories of the libraries of the closure that contain a file named `lib_name`.
+fn closure_directories_with(libs: &LibraryDependencies, lib_name: &str) -> Vec<PathBuf> {
+    let mut directories = libs.opened_libs
+        .keys()
+        .filter_map(|path| path.parent())
+        .filter(|dir| dir.join(lib_name).is_file())
+        .map(|dir| dir.to_path_buf())
+        .collect::<Vec<_>>();
+    directories.sort();
+    directories.dedup();
+    directories
+}
+
+/// Remediations of a library resolution problem.
+pub fn lib_problem_remediations(
+    problem: &LibResolveProblem,
+    libs: &LibraryDependencies,
+) -> Vec<Remediation> {
+    match problem {
+        LibResolveProblem::Unresolved {
+            dependent_lib,
+            lib_name,
+            ..
+        } => {
+            let directories = closure_directories_with(libs, lib_name);
+            if directories.is_empty() {
+                vec![Remediation::InstallPackage {
+                    library: lib_name.clone(),
+                    package: None,
+                }]
+            } else {
+                directories
+                    .into_iter()
+                    .map(|directory| Remediation::AddRpath {
+                        object: dependent_lib.clone(),
+                        directory,
+                    })
+                    .collect()
+            }
+        }
+        LibResolveProblem::UnresolvedButPreviouslyResolved {
+            dependent_lib,
+            prev_resolved_path,
+            ..
+        } => vec![Remediation::AddRpath {
+            object: dependent_lib.clone(),
+            directory: parent(prev_resolved_path),
+        }],
+        LibResolveProblem::ResolveConflict {
+            dependent_lib,
+            prev_resolved_path,
+            ..
+        } => vec![Remediation::RebuildAgainst {
+            object: dependent_lib.clone(),
+            library: prev_resolved_path.clone(),
+        }],
+        &LibResolveProblem::RelativeLocation {
+            ref dependent_lib,
+            ref resolve_path,
+            ..
+        }
+        | &LibResolveProblem::EnvironmentDependent {
+            ref dependent_lib,
+            ref resolve_path,
+            ..
+        } => vec![Remediation::AddRpath {
+            object: dependent_lib.clone(),
+            directory: parent(resolve_path),
+        }],
+        &LibResolveProblem::IgnoredInSecureExecution { .. }
+        | &LibResolveProblem::OutsideAllowedPrefixes { .. }
+        | &LibResolveProblem::LoaderMismatch { .. }
+        | &LibResolveProblem::CaseMismatch { .. }
+        | &LibResolveProblem::ArchMismatch { .. } => Vec::new(),
+    }
+}
+
+/// Remediations of the usage of RPATH/RUNPATH entries: Replace them by the used ones if some are
+/// dead.
+pub fn rpath_usage_remediations(usage: &RpathUsage) -> Vec<Remediation> {
+    if usage.dead().is_empty() {
+        return Vec::new();
+    }
+    vec![Remediation::SetRpath {
+        object: usage.object.clone(),
+        rpath: usage.consolidated(),
+    }]
+}
+
+/// Remediations of a preload problem.
+pub fn preload_problem_remediations(problem: &PreloadProblem) -> Vec<Remediation> {
+    match problem {
+        PreloadProblem::Missing { name, .. } => vec![Remediation::InstallPackage {
+            library: name.clone(),
+            package: None,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// Remediations of a symbol that is only exported in non-default versions: The dependents have
+/// to be rebuilt against the library to reference a version explicitly.
+pub fn version_problem_remediations(problem: &NonDefaultVersionOnly) -> Vec<Remediation> {
+    problem
+        .dependents
+        .iter()
+        .map(|dependent| Remediation::RebuildAgainst {
+            object: dependent.clone(),
+            library: problem.lib.clone(),
+        })
+        .collect()
+}
+
+/// Remediations of a C++ runtime problem: Rebuild the objects that need a newer runtime than the
+/// one found, or that were built before GCC 5, against the resolved runtime.
+pub fn cxx_runtime_problem_remediations(
+    problem: &CxxRuntimeProblem,
+    libs: &LibraryDependencies,
+) -> Vec<Remediation> {
+    match problem {
+        CxxRuntimeProblem::RuntimeTooOld {
+            lib,
+            runtime,
+            ..
+        } => vec![Remediation::RebuildAgainst {
+            object: lib.clone(),
+            library: runtime.clone(),
+        }],
+        CxxRuntimeProblem::MixedGenerations { old, .. } => {
+            match libs.resolved.get(OsStr::new("libstdc++.so.6")) {
+                Some(runtime) => old.iter()
+                    .map(|(object, _)| Remediation::RebuildAgainst {
+                        object: object.clone(),
+                        library: runtime.clone(),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+    }
+}
+
+/// Libraries next to the objects of a closure (in the same directory) that are not part of the
+/// closure, with the names they would be needed by and their exported symbols. Objects often
+/// lack a DT_NEEDED entry for such a library (e.g., of the same application bundle).
+pub struct SiblingLibraries {
+    by_directory: HashMap<PathBuf, Vec<(String, HashSet<String>)>>,
+}
+
+impl SiblingLibraries {
+    pub fn find(libs: &LibraryDependencies) -> Self {
+        let mut by_directory = HashMap::new();
+        for dir in libs.opened_libs.keys().filter_map(|path| path.parent()) {
+            if by_directory.contains_key(dir) {
+                continue;
+            }
+            let candidates = fs::read_dir(dir)
+                .into_iter()
+                .flat_map(|entries| entries.filter_map(|e| e.ok()))
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .is_some_and(|n| n.to_string_lossy().contains(".so"))
+                        && !libs.opened_libs.contains_key(path)
+                })
+                .collect::<Vec<_>>();
+            let mut siblings = Vec::new();
+            if candidates.len() <= MAX_SIBLING_LIBRARIES {
+                for path in candidates {
+                    if let Ok(lib) = libs.open_library(&path) {
+                        let name = lib.get_soname().to_string_lossy().into_owned();
+                        siblings.push((name, exported_symbol_names(lib.get_elf())));
+                    }
+                }
+                siblings.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+            let _ = by_directory.insert(dir.to_path_buf(), siblings);
+        }
+        SiblingLibraries {
+            by_directory,
+        }
+    }
+}
+
+/// Remediations of an unresolved symbol (referenced by the libraries named `lib_names`): Link the
+/// referencing objects against a library next to them that exports the symbol.
+pub fn unresolved_symbol_remediations(
+    symbol: &str,
+    lib_names: &HashSet<String>,
+    libs: &LibraryDependencies,
+    siblings: &SiblingLibraries,
+) -> Vec<Remediation> {
+    let mut objects = lib_names
+        .iter()
+        .filter_map(|name| match libs.lookup(OsStr::new(name)) {
+            Some(path) => Some(path),
+            None if libs.root.file_name() == Some(OsStr::new(name)) => Some(&libs.root),
+            None => None,
+        })
+        .collect::<Vec<_>>();
+    objects.sort();
+
+    let mut remediations = Vec::new();
+    for object in objects {
+        let dir = object.parent().unwrap_or(Path::new("/"));
+        let providers = siblings.by_directory.get(dir).into_iter().flat_map(|s| s.iter());
+        for (name, exported) in providers {
+            let remediation = Remediation::AddNeeded {
+                object: object.clone(),
+                library: name.clone(),
+            };
+            if exported.contains(symbol) && !remediations.contains(&remediation) {
+                remediations.push(remediation);
+            }
+        }
+    }
+    remediations
+}
@@ -0,0 +1,293 @@
+use files::RealFs;
+use libraries::{expand_embedded_entry, is_origin_relative, ld_so_conf_directories, ErrorMsg,
+                LibSearchMethod, LibraryCache};
+use preload::LD_SO_PRELOAD;
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+const URL_PREFIX: &str = "ssh://";
+const INCLUDE_PREFIX: &str = "include ";
+
+/// Whether the given --sysroot refers to a remote machine (ssh://[user@]host[:port]/[path]).
+pub fn is_remote_sysroot(sysroot: &Path) -> bool {
+    sysroot
+        .to_str()
+        .map(|s| s.starts_with(URL_PREFIX))
+        .unwrap_or(false)
+}
+
+/// $XDG_CACHE_HOME/linkcheck/ssh, falling back to ~/.cache/linkcheck/ssh.
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("linkcheck").join("ssh"))
+}
+
+/// Quote a path for an sftp batch command.
+fn sftp_arg(path: &Path) -> String {
+    let mut quoted = String::new();
+    for c in path.to_string_lossy().chars() {
+        if c == '\\' || c == '"' || c == '\'' || c.is_whitespace() {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// A sysroot on a remote machine. The files that are needed for the analysis (ld.so.conf files,
+/// /etc/ld.so.preload, the loader and all candidates for needed libraries) are fetched via sftp
+/// into a local cache directory, which is then used as the actual sysroot.
+///
+/// Fetched libraries are kept in the cache and reused by later runs; configuration files are
+/// fetched again on every run. Remove the cache directory to refetch everything.
+pub struct RemoteSysroot {
+    destination: String, // [user@]host for sftp
+    port: Option<String>,
+    remote_root: PathBuf,
+    pub root: PathBuf, // Local mirror of remote_root
+    listings: HashMap<PathBuf, HashSet<String>>,
+}
+
+impl RemoteSysroot {
+    pub fn open(url: &Path) -> Result<RemoteSysroot, Box<dyn Error>> {
+        let url = url.to_str()
+            .filter(|s| s.starts_with(URL_PREFIX))
+            .ok_or_else(|| ErrorMsg(format!("{:?} is not an ssh:// url", url)))?;
+        let rest = &url[URL_PREFIX.len()..];
+        let (authority, remote_root) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (destination, port) = match authority.rfind(':') {
+            Some(i) => (&authority[..i], Some(authority[i + 1..].to_string())),
+            None => (authority, None),
+        };
+        if destination.is_empty() {
+            return Err(Box::new(ErrorMsg(format!("No host given in {:?}", url))));
+        }
+
+        let root = cache_dir()
+            .ok_or_else(|| ErrorMsg("Could not determine the cache directory".to_string()))?
+            .join(authority)
+            .join(Path::new(remote_root).strip_prefix("/")?);
+        fs::create_dir_all(&root)?;
+        Ok(RemoteSysroot {
+            destination: destination.to_string(),
+            port,
+            remote_root: PathBuf::from(remote_root),
+            root,
+            listings: HashMap::new(),
+        })
+    }
+
+    /// The path on the remote machine of the given absolute path on the analyzed system.
+    fn remote_path(&self, path: &Path) -> PathBuf {
+        self.remote_root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// The path inside the local mirror of the given absolute path on the analyzed system.
+    fn local_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Run the given sftp batch commands and return their output, split per command. Commands
+    /// prefixed with '-' may fail without aborting the batch.
+    fn sftp(&self, commands: &[String]) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        let mut sftp = Command::new("sftp");
+        sftp.arg("-q").arg("-b").arg("-");
+        if let Some(ref port) = self.port {
+            sftp.arg("-P").arg(port);
+        }
+        let mut child = sftp.arg(&self.destination)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| ErrorMsg(format!("Could not run sftp: {}", e)))?;
+        {
+            let stdin = child.stdin.as_mut().expect("stdin is piped");
+            for command in commands {
+                writeln!(stdin, "{}", command)?;
+            }
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Box::new(ErrorMsg(format!(
+                "sftp session with {} failed",
+                self.destination
+            ))));
+        }
+
+        // sftp echoes every batch command (prefixed by its prompt) before its output.
+        let mut outputs = Vec::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if line.starts_with("sftp> ") {
+                outputs.push(Vec::new());
+            } else if let Some(output) = outputs.last_mut() {
+                output.push(line.to_string());
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Fetch the given files (or glob patterns) of the analyzed system into the local mirror.
+    /// Missing files are ignored.
+    fn fetch(&self, paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+        let mut commands = Vec::new();
+        for path in paths {
+            let local = self.local_path(path);
+            let local_dir = local.parent().expect("Fetched paths are absolute");
+            fs::create_dir_all(local_dir)?;
+            commands.push(format!(
+                "-get {} {}/",
+                sftp_arg(&self.remote_path(path)),
+                sftp_arg(local_dir)
+            ));
+        }
+        if !commands.is_empty() {
+            self.sftp(&commands)?;
+        }
+        Ok(())
+    }
+
+    /// List the entries of the given directories of the analyzed system (cached).
+    fn list(&mut self, dirs: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+        let dirs = dirs.iter()
+            .filter(|dir| !self.listings.contains_key(*dir))
+            .cloned()
+            .collect::<Vec<_>>();
+        if dirs.is_empty() {
+            return Ok(());
+        }
+        let commands = dirs.iter()
+            .map(|dir| format!("-ls -1 {}", sftp_arg(&self.remote_path(dir))))
+            .collect::<Vec<_>>();
+        let outputs = self.sftp(&commands)?;
+        for (dir, output) in dirs.into_iter().zip(outputs.into_iter().chain(::std::iter::repeat(Vec::new()))) {
+            let names = output
+                .iter()
+                .filter_map(|line| Path::new(line.trim()).file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            let _ = self.listings.insert(dir, names);
+        }
+        Ok(())
+    }
+
+    /// Fetch the ld.so.conf file at `path` and (recursively) all files it includes.
+    fn fetch_ld_so_conf(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.fetch(&[path.to_path_buf()])?;
+        let content = match fs::read_to_string(self.local_path(path)) {
+            Ok(content) => content,
+            Err(_) => return Ok(()),
+        };
+        for line in content.lines().map(|l| l.trim()) {
+            if !line.starts_with(INCLUDE_PREFIX) {
+                continue;
+            }
+            let pattern = path.parent()
+                .unwrap_or(Path::new("/"))
+                .join(line[INCLUDE_PREFIX.len()..].trim());
+            self.fetch(::std::slice::from_ref(&pattern))?;
+
+            let local_pattern = self.local_path(&pattern);
+            for included in ::glob::glob(&local_pattern.to_string_lossy())? {
+                let included = Path::new("/").join(included?.strip_prefix(&self.root)?);
+                self.fetch_ld_so_conf(&included)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch everything that is needed to analyze the given (local) files against the remote
+    /// system: the loader configuration, the loaders and all candidates for the (transitively)
+    /// needed libraries in the ld.so.conf directories, the fixed search directories and the
+    /// RPATH/RUNPATH directories of the fetched objects. Files are read with `cache`.
+    pub fn mirror(
+        &mut self,
+        files: &[PathBuf],
+        search_methods: &[LibSearchMethod],
+        cache: &LibraryCache,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut dirs = Vec::new();
+        for method in search_methods {
+            match method {
+                LibSearchMethod::LDConfig(conf_file) => {
+                    self.fetch_ld_so_conf(conf_file)?;
+                    if self.local_path(conf_file).is_file() {
+                        let files = Arc::new(RealFs);
+                        for dir in ld_so_conf_directories(conf_file, files, Some(&self.root))? {
+                            dirs.push(Path::new("/").join(dir.strip_prefix(&self.root)?));
+                        }
+                    }
+                }
+                LibSearchMethod::Fixed(dir) if dir.is_absolute() => dirs.push(dir.clone()),
+                _ => {}
+            }
+        }
+        self.fetch(&[PathBuf::from(LD_SO_PRELOAD)])?;
+        self.list(&dirs)?;
+
+        let mut fetched = HashSet::new();
+        let mut pending = files.to_vec();
+        while !pending.is_empty() {
+            let mut to_fetch = Vec::new();
+            for file in pending.drain(..) {
+                let lib = match cache.get_or_open(&file, &RealFs) {
+                    Ok(lib) => lib,
+                    Err(_) => continue,
+                };
+                // The RPATH/RUNPATH entries of fetched objects are directories on the remote
+                // system (those of the analyzed files as well, unless relative to $ORIGIN). As
+                // RPATH is inherited, they are searched for the dependencies of all objects.
+                let remote_file = file.strip_prefix(&self.root)
+                    .ok()
+                    .map(|path| Path::new("/").join(path));
+                let mut new_dirs = Vec::new();
+                for (_, entry) in lib.embedded_search_paths() {
+                    let dir = match remote_file {
+                        Some(ref remote_file) => expand_embedded_entry(&entry, remote_file, None),
+                        None if is_origin_relative(&entry) => continue,
+                        None => PathBuf::from(entry),
+                    };
+                    if dir.is_absolute() && !dirs.contains(&dir) && !new_dirs.contains(&dir) {
+                        new_dirs.push(dir);
+                    }
+                }
+                self.list(&new_dirs)?;
+                dirs.extend(new_dirs);
+
+                let elf = lib.get_elf();
+                if let Some(interpreter) = elf.interpreter {
+                    to_fetch.push(PathBuf::from(interpreter));
+                }
+                for lib_name in elf.libraries.iter().filter(|name| !name.contains('/')) {
+                    for dir in dirs.iter() {
+                        if self.listings[dir].contains(*lib_name) {
+                            to_fetch.push(dir.join(lib_name));
+                        }
+                    }
+                }
+            }
+            to_fetch.retain(|path| fetched.insert(path.clone()));
+
+            let missing = to_fetch
+                .iter()
+                .filter(|path| !self.local_path(path).is_file())
+                .cloned()
+                .collect::<Vec<_>>();
+            self.fetch(&missing)?;
+            pending = to_fetch.iter().map(|path| self.local_path(path)).collect();
+        }
+        Ok(())
+    }
+}
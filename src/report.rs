@@ -0,0 +1,827 @@
+use serde_json::{self, Value};
+
+use budgets::BudgetViolation;
+use cxxruntime::CxxRuntimeProblem;
+use dispatch::DriverProblem;
+use dlopen_targets::DlopenProblem;
+use duplicates::{MismatchedDuplicate, SelfImportedSymbol};
+use dynamic_tags::UnknownDynamicTag;
+use executable_symbols::ExecutableSymbol;
+use harden::HardeningAudit;
+use fingerprint::{fingerprint, normalize_path};
+use initializers::UnresolvedData;
+use libraries::{ErrorMsg, LibraryDependencies};
+use objinfo::ObjectInfo;
+use min_versions::MinimumVersion;
+use model::*;
+use mounts::MountProblem;
+use packaging::PackagingProblem;
+use preload::{Interposition, PreloadProblem};
+use provenance::Provenance;
+use remediation::*;
+use rpath_usage::RpathUsage;
+use search_paths::SearchPathOddity;
+use textrel::TextRelocations;
+use underlinking::UnderlinkedLibrary;
+use symbols::{demangle, SymbolClass};
+use unused_dependencies::UnusedDependency;
+use versions::{MissingVersions, NonDefaultVersionOnly};
+use wine::PeCheck;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_FINGERPRINT: &str = "linkcheckFinding/v1";
+const INFORMATION_URI: &str = "https://github.com/ftilde/linkcheck";
+
+/// All findings for one analyzed file (after filtering according to the command line options).
+pub struct Report {
+    pub libs: LibraryDependencies,
+    pub packaging_problems: Vec<PackagingProblem>,
+    pub preload_problems: Vec<PreloadProblem>,
+    /// The symbols that simulated preloads override (see --preload).
+    pub interpositions: Vec<Interposition>,
+    pub search_path_oddities: Vec<SearchPathOddity>,
+    /// The RPATH/RUNPATH entries of the objects and their usage (see --rpath-usage).
+    pub rpath_usages: Vec<RpathUsage>,
+    /// Dynamic entries of the objects whose effect is not simulated.
+    pub unknown_dynamic_tags: Vec<UnknownDynamicTag>,
+    /// The exploit mitigations of the analyzed file (see the harden subcommand).
+    pub hardening: Option<HardeningAudit>,
+    /// The newest required version per runtime library and version family (see --min-versions).
+    pub minimum_versions: Vec<MinimumVersion>,
+    /// Libraries on noexec or network file systems (see --check-mounts).
+    pub mount_problems: Vec<MountProblem>,
+    /// Objects of the closure with relocations in read-only segments.
+    pub text_relocations: Vec<TextRelocations>,
+    /// Exceeded budgets of the closure (see --max-deps and --max-closure-size).
+    pub budget_violations: Vec<BudgetViolation>,
+    /// Reasons why the analyzed file cannot be loaded with dlopen (see --dlopen-target).
+    pub dlopen_problems: Vec<DlopenProblem>,
+    pub unused_dependencies: Vec<UnusedDependency>,
+    /// Unresolved symbols and the names of the libraries referencing them, sorted by symbol.
+    pub unresolved_symbols: Vec<(String, HashSet<String>)>,
+    /// Libraries referencing unresolved symbols as data (which fails when they are loaded).
+    pub unresolved_data: Vec<UnresolvedData>,
+    /// Symbols that are forwarded by a graphics dispatch library, with the drivers providing them.
+    pub dispatched_symbols: Vec<(String, Vec<PathBuf>)>,
+    /// Drivers of the graphics dispatch libraries in the closure that they cannot use.
+    pub driver_problems: Vec<DriverProblem>,
+    pub version_problems: Vec<NonDefaultVersionOnly>,
+    /// Required versions (DT_VERNEED) that the resolved providers do not define.
+    pub missing_versions: Vec<MissingVersions>,
+    pub cxx_runtime_problems: Vec<CxxRuntimeProblem>,
+    pub executable_symbols: Vec<ExecutableSymbol>,
+    /// Libraries that reference symbols none of their direct dependencies provide.
+    pub underlinked_libraries: Vec<UnderlinkedLibrary>,
+    /// Duplicate symbols and the names of the libraries exporting them, sorted by symbol.
+    pub duplicate_symbols: Vec<(String, HashSet<String>)>,
+    /// The classes of the duplicate symbols whose class is special (e.g., IFUNC or TLS).
+    pub symbol_classes: HashMap<String, SymbolClass>,
+    /// Duplicate symbols whose definitions differ in type or size.
+    pub mismatched_duplicates: Vec<MismatchedDuplicate>,
+    /// Duplicate symbols that one of their providers imports as well.
+    pub self_imported_symbols: Vec<SelfImportedSymbol>,
+}
+
+/// Fingerprint of an unresolved or duplicate symbol of the closure of `root`.
+pub fn symbol_fingerprint(kind: &str, root: &Path, symbol: &str) -> String {
+    fingerprint(kind, &[&normalize_path(root), symbol])
+}
+
+/// Something linkcheck assumed or decided on its own (e.g., default search locations), which
+/// affects all reports of a run.
+#[derive(Serialize)]
+pub struct Notice {
+    pub id: &'static str,
+    pub message: String,
+}
+
+impl Notice {
+    fn to_sarif(&self) -> Value {
+        json!({
+            "descriptor": { "id": self.id },
+            "level": "note",
+            "message": { "text": self.message },
+        })
+    }
+}
+
+impl fmt::Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The SARIF result of a finding.
+fn sarif_result(finding: &Finding) -> Value {
+    let mut result = json!({
+        "ruleId": finding.rule,
+        "level": finding.level,
+        "message": { "text": finding.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": finding.location.to_string_lossy() }
+            }
+        }],
+        "partialFingerprints": { SARIF_FINGERPRINT: finding.fingerprint },
+    });
+    // SARIF fixes describe concrete file edits, so remediations are properties.
+    if !finding.remediations.is_empty() {
+        result["properties"] = json!({ "remediations": finding.remediations });
+    }
+    result
+}
+
+fn closure(libs: &LibraryDependencies) -> Vec<ClosureEntry> {
+    let mut resolved = libs.resolved.iter().collect::<Vec<_>>();
+    resolved.sort();
+    resolved
+        .into_iter()
+        .map(|(name, path)| ClosureEntry {
+            name: name.to_string_lossy().into_owned(),
+            path: path.clone(),
+            info: libs.opened_libs.get(path).map(|lib| ObjectInfo::of_library(lib)),
+        })
+        .collect()
+}
+
+fn edges(libs: &LibraryDependencies) -> Vec<Edge> {
+    let mut edges = libs.resolutions
+        .iter()
+        .flat_map(|(path, resolutions)| {
+            resolutions.iter().map(move |r| Edge {
+                dependent: r.dependent_lib.clone(),
+                dependency: path.clone(),
+                name: r.lib_name.clone(),
+                directory: r.directory.clone(),
+                origin: r.origin,
+            })
+        })
+        .collect::<Vec<_>>();
+    edges.sort_by(|a, b| (&a.dependent, &a.name).cmp(&(&b.dependent, &b.name)));
+    edges
+}
+
+fn symbol_groups<F>(
+    kind: &str,
+    libs: &LibraryDependencies,
+    depths: &HashMap<&PathBuf, usize>,
+    symbols: &[(String, HashSet<String>)],
+    classes: &HashMap<String, SymbolClass>,
+    remediations: F,
+) -> Vec<SymbolGroup>
+where
+    F: Fn(&str, &HashSet<String>) -> Vec<Remediation>,
+{
+    symbols
+        .iter()
+        .map(|(symbol, lib_names)| {
+            let mut libraries = lib_names.iter().cloned().collect::<Vec<_>>();
+            libraries.sort();
+            let owners = libraries
+                .iter()
+                .map(|name| {
+                    libs.lookup(OsStr::new(name))
+                        .cloned()
+                        .unwrap_or(PathBuf::from(name))
+                })
+                .collect::<Vec<_>>();
+            let depth = owners.iter().filter_map(|path| depths.get(path).cloned()).min();
+            SymbolGroup {
+                symbol: symbol.clone(),
+                demangled: demangle(symbol),
+                libraries,
+                owners,
+                class: classes.get(symbol).map(|class| class.label()),
+                fingerprint: symbol_fingerprint(kind, &libs.root, symbol),
+                depth,
+                remediations: remediations(symbol, lib_names),
+            }
+        })
+        .collect()
+}
+
+impl Report {
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self.to_result()).expect("Results are serializable")
+    }
+
+    /// The analysis result of the report as plain data (see `AnalysisResult`).
+    pub fn to_result(&self) -> AnalysisResult {
+        let libs = &self.libs;
+        let mut env_resolutions = libs.environment_dependent_resolutions().collect::<Vec<_>>();
+        env_resolutions.sort_by_key(|&(path, _)| path);
+        let siblings = if self.unresolved_symbols.is_empty() {
+            None
+        } else {
+            Some(SiblingLibraries::find(libs))
+        };
+        let depths = libs.depths();
+
+        let mut result = AnalysisResult {
+            file: libs.root.clone(),
+            counts: Counts::default(),
+            resolved: closure(libs),
+            edges: edges(libs),
+            library_resolution_problems: libs.problems
+                .iter()
+                .map(|p| {
+                    Problem::new(p.dependent_lib(), "error", p.to_string(), p.fingerprint())
+                        .with_remediations(lib_problem_remediations(p, libs))
+                })
+                .collect(),
+            environment_dependent_resolutions: env_resolutions
+                .into_iter()
+                .map(|(path, r)| EnvironmentDependentResolution {
+                    library: path.clone(),
+                    name: r.lib_name.clone(),
+                    dependent_library: r.dependent_lib.clone(),
+                    origin: r.origin,
+                    directory: r.directory.clone(),
+                })
+                .collect(),
+            skipped_incompatible_candidates: libs.skipped_incompatible
+                .iter()
+                .map(|skipped| SkippedLibraryCandidate {
+                    dependent_library: skipped.dependent_lib.clone(),
+                    name: skipped.lib_name.clone(),
+                    path: skipped.path.clone(),
+                    resolved_path: skipped.resolve_path.clone(),
+                    message: skipped.to_string(),
+                    fingerprint: skipped.fingerprint(),
+                })
+                .collect(),
+            packaging_problems: self
+                .packaging_problems
+                .iter()
+                .map(|p| Problem::new(p.lib(), "warning", p.to_string(), p.fingerprint()))
+                .collect(),
+            preload_problems: self
+                .preload_problems
+                .iter()
+                .map(|p| {
+                    Problem::new(p.owner(), "error", p.to_string(), p.fingerprint())
+                        .with_remediations(preload_problem_remediations(p))
+                })
+                .collect(),
+            interpositions: self
+                .interpositions
+                .iter()
+                .map(|i| {
+                    let level = if i.is_error() {
+                        "error"
+                    } else if i.is_unintended() {
+                        "warning"
+                    } else {
+                        "note"
+                    };
+                    Problem::new(i.preload(), level, i.to_string(), i.fingerprint())
+                })
+                .collect(),
+            search_path_oddities: self
+                .search_path_oddities
+                .iter()
+                .map(|o| Problem::new(o.object(), "warning", o.to_string(), o.fingerprint()))
+                .collect(),
+            rpath_usages: self
+                .rpath_usages
+                .iter()
+                .map(|u| RpathUsageResult {
+                    object: u.object.clone(),
+                    origin: u.origin,
+                    entries: u.entries
+                        .iter()
+                        .map(|e| RpathEntryUsage {
+                            entry: e.entry.clone(),
+                            directory: e.directory.clone(),
+                            resolutions: e.resolutions,
+                        })
+                        .collect(),
+                    consolidated: Some(u.consolidated()).filter(|_| !u.dead().is_empty()),
+                    message: u.to_string(),
+                    fingerprint: u.fingerprint(),
+                    remediations: rpath_usage_remediations(u),
+                })
+                .collect(),
+            unknown_dynamic_tags: self
+                .unknown_dynamic_tags
+                .iter()
+                .map(|t| UnknownDynamicTagResult {
+                    library: t.lib.clone(),
+                    tag: t.tag,
+                    name: t.name(),
+                    message: t.to_string(),
+                    fingerprint: t.fingerprint(),
+                })
+                .collect(),
+            hardening: self.hardening.clone(),
+            minimum_versions: self.minimum_versions.clone(),
+            mount_problems: self
+                .mount_problems
+                .iter()
+                .map(|p| {
+                    let level = if p.is_error() { "error" } else { "warning" };
+                    Problem::new(p.lib(), level, p.to_string(), p.fingerprint())
+                })
+                .collect(),
+            text_relocations: self
+                .text_relocations
+                .iter()
+                .map(|r| Problem::new(r.lib(), "warning", r.to_string(), r.fingerprint()))
+                .collect(),
+            budget_violations: self
+                .budget_violations
+                .iter()
+                .map(|v| Problem::new(v.root(), "error", v.to_string(), v.fingerprint()))
+                .collect(),
+            dlopen_problems: self
+                .dlopen_problems
+                .iter()
+                .map(|p| {
+                    let level = if p.is_error() { "error" } else { "warning" };
+                    Problem::new(p.file(), level, p.to_string(), p.fingerprint())
+                })
+                .collect(),
+            unused_dependencies: self
+                .unused_dependencies
+                .iter()
+                .map(|u| {
+                    let level = if u.is_speculative() { "warning" } else { "note" };
+                    Problem::new(u.object(), level, u.to_string(), u.fingerprint())
+                })
+                .collect(),
+            unresolved_symbols: symbol_groups(
+                UNRESOLVED_SYMBOL,
+                libs,
+                &depths,
+                &self.unresolved_symbols,
+                &HashMap::new(),
+                |symbol, lib_names| match siblings {
+                    Some(ref siblings) => {
+                        unresolved_symbol_remediations(symbol, lib_names, libs, siblings)
+                    }
+                    None => Vec::new(),
+                },
+            ),
+            unresolved_data: self
+                .unresolved_data
+                .iter()
+                .map(|d| Problem::new(d.lib(), "error", d.to_string(), d.fingerprint()))
+                .collect(),
+            dispatched_symbols: self
+                .dispatched_symbols
+                .iter()
+                .map(|(symbol, drivers)| DispatchedSymbol {
+                    symbol: symbol.clone(),
+                    drivers: drivers.clone(),
+                })
+                .collect(),
+            driver_problems: self
+                .driver_problems
+                .iter()
+                .map(|p| {
+                    Problem::new(&p.dispatch_lib, "warning", p.to_string(), p.fingerprint())
+                })
+                .collect(),
+            version_problems: self
+                .version_problems
+                .iter()
+                .map(|p| {
+                    let level = if p.is_error() { "error" } else { "warning" };
+                    Problem::new(&p.lib, level, p.to_string(), p.fingerprint())
+                        .with_remediations(version_problem_remediations(p))
+                })
+                .collect(),
+            missing_versions: self
+                .missing_versions
+                .iter()
+                .map(|m| {
+                    let level = if m.is_error() { "error" } else { "warning" };
+                    Problem::new(m.lib(), level, m.to_string(), m.fingerprint())
+                })
+                .collect(),
+            cxx_runtime_problems: self
+                .cxx_runtime_problems
+                .iter()
+                .map(|p| {
+                    Problem::new(p.owner(), "warning", p.to_string(), p.fingerprint())
+                        .with_remediations(cxx_runtime_problem_remediations(p, libs))
+                })
+                .collect(),
+            executable_symbols: self
+                .executable_symbols
+                .iter()
+                .map(|s| {
+                    let level = if s.is_error() { "error" } else { "note" };
+                    Problem::new(s.executable(), level, s.to_string(), s.fingerprint())
+                })
+                .collect(),
+            underlinked_libraries: self
+                .underlinked_libraries
+                .iter()
+                .map(|u| Problem::new(u.lib(), "warning", u.to_string(), u.fingerprint()))
+                .collect(),
+            duplicate_symbols: symbol_groups(
+                DUPLICATE_SYMBOL,
+                libs,
+                &depths,
+                &self.duplicate_symbols,
+                &self.symbol_classes,
+                |_, _| Vec::new(),
+            ),
+            mismatched_duplicate_symbols: self
+                .mismatched_duplicates
+                .iter()
+                .map(|d| {
+                    Problem::new(d.root(), "error", d.to_string(), d.fingerprint()).with_owners(
+                        d.definitions.iter().map(|def| def.lib.clone()).collect(),
+                    )
+                })
+                .collect(),
+            self_imported_symbols: self
+                .self_imported_symbols
+                .iter()
+                .map(|s| Problem::new(s.lib(), "warning", s.to_string(), s.fingerprint()))
+                .collect(),
+        };
+        for problems in vec![
+            &mut result.library_resolution_problems,
+            &mut result.packaging_problems,
+            &mut result.preload_problems,
+            &mut result.interpositions,
+            &mut result.search_path_oddities,
+            &mut result.mount_problems,
+            &mut result.text_relocations,
+            &mut result.budget_violations,
+            &mut result.dlopen_problems,
+            &mut result.unused_dependencies,
+            &mut result.unresolved_data,
+            &mut result.driver_problems,
+            &mut result.version_problems,
+            &mut result.missing_versions,
+            &mut result.cxx_runtime_problems,
+            &mut result.executable_symbols,
+            &mut result.underlinked_libraries,
+            &mut result.mismatched_duplicate_symbols,
+            &mut result.self_imported_symbols,
+        ] {
+            for problem in problems.iter_mut() {
+                problem.depth = depths.get(&problem.library).cloned();
+            }
+        }
+        result.counts = Counts::of_result(&result);
+        result
+    }
+}
+
+/// The report (or the analysis error) of one analyzed file as JSON, including the notices and the
+/// provenance of the run.
+pub fn report_to_json(
+    file: &Path,
+    report: &Result<Report, String>,
+    notices: &[Notice],
+    provenance: &Provenance,
+) -> Value {
+    let mut value = match report {
+        Ok(report) => report.to_json(),
+        Err(err) => json!({
+            "file": file,
+            "error": err,
+        }),
+    };
+    value["notices"] = serde_json::to_value(notices).expect("Notices are serializable");
+    value["provenance"] = serde_json::to_value(provenance).expect("Provenance is serializable");
+    value
+}
+
+/// The JSON representation of the check of a PE file (see `PeAnalysisResult`).
+pub fn pe_check_to_json(check: &PeCheck, notices: &[Notice], provenance: &Provenance) -> Value {
+    let result = PeAnalysisResult {
+        file: check.file.clone(),
+        dlls: check.dlls.clone(),
+        problems: check
+            .problems
+            .iter()
+            .map(|p| Problem::new(p.object(), "error", p.to_string(), p.fingerprint()))
+            .collect(),
+        error: check.error.clone(),
+    };
+    let mut value = serde_json::to_value(result).expect("Results are serializable");
+    value["notices"] = serde_json::to_value(notices).expect("Notices are serializable");
+    value["provenance"] = serde_json::to_value(provenance).expect("Provenance is serializable");
+    value
+}
+
+/// All reports as a single SARIF 2.1.0 log with one run. Notices are reported as tool execution
+/// notifications of the invocation, which also carries the provenance of the run.
+pub fn reports_to_sarif(
+    reports: &[(PathBuf, Result<Report, String>)],
+    notices: &[Notice],
+    provenance: &Provenance,
+) -> Value {
+    let results = reports
+        .iter()
+        .flat_map(|(file, report)| match report {
+            Ok(report) => report.to_result().findings(),
+            Err(err) => vec![Finding::analysis_error(file, err)],
+        })
+        .map(|finding| sarif_result(&finding))
+        .collect::<Vec<_>>();
+
+    json!({
+        "version": "2.1.0",
+        "$schema": SARIF_SCHEMA,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": RULES
+                        .iter()
+                        .map(|rule| json!({
+                            "id": rule.id,
+                            "shortDescription": { "text": rule.description },
+                        }))
+                        .collect::<Vec<_>>(),
+                }
+            },
+            "invocations": [{
+                "executionSuccessful": true,
+                "commandLine": provenance.command_line.join(" "),
+                "startTimeUtc": provenance.started_at,
+                "endTimeUtc": provenance.finished_at,
+                "machine": provenance.host.hostname,
+                "environmentVariables": provenance
+                    .environment
+                    .iter()
+                    .filter_map(|(name, value)| value.as_ref().map(|v| (name.clone(), json!(v))))
+                    .collect::<serde_json::Map<_, _>>(),
+                "toolExecutionNotifications": notices
+                    .iter()
+                    .map(|n| n.to_sarif())
+                    .collect::<Vec<_>>(),
+                "properties": { "provenance": provenance },
+            }],
+            "results": results,
+        }],
+    })
+}
+
+/// Write `value` (pretty-printed) to the file at `path`.
+pub fn write_json_file(path: &Path, value: &Value) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(path)?;
+    serde_json::to_writer_pretty(&mut file, value)?;
+    writeln!(file)?;
+    Ok(())
+}
+
+/// The format of the report printed to stdout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!(
+                "Invalid report format '{}'. Valid formats are: text, json",
+                other
+            )),
+        }
+    }
+}
+
+/// The format of the per-file report files written with --out-dir.
+#[derive(Debug, Clone, Copy)]
+pub enum OutputFormat {
+    Json,
+    Sarif,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "sarif" => Ok(OutputFormat::Sarif),
+            other => Err(format!(
+                "Invalid output format '{}'. Valid formats are: json, sarif",
+                other
+            )),
+        }
+    }
+}
+
+/// Expand the placeholders {name} (file name), {stem} (file name without extension) and {index}
+/// (position on the command line, starting at 1) of an output file name template.
+pub fn expand_out_template(template: &str, file: &Path, index: usize) -> String {
+    let name = file.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = file.file_stem()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    template
+        .replace("{name}", &name)
+        .replace("{stem}", &stem)
+        .replace("{index}", &index.to_string())
+}
+
+/// Write one report file per analyzed file into `out_dir`, named according to `template`.
+pub fn write_report_files(
+    reports: &[(PathBuf, Result<Report, String>)],
+    out_dir: &Path,
+    template: &str,
+    format: OutputFormat,
+    notices: &[Notice],
+    provenance: &Provenance,
+) -> Result<(), Box<dyn Error>> {
+    // Check for name clashes before writing anything.
+    let mut out_paths = Vec::new();
+    for (i, (file, _)) in reports.iter().enumerate() {
+        let out_path = out_dir.join(expand_out_template(template, file, i + 1));
+        if out_paths.contains(&out_path) {
+            return Err(Box::new(ErrorMsg(format!(
+                "Output template '{}' yields {:?} for multiple files. Use {{index}} to make the \
+                 names unique.",
+                template, out_path
+            ))));
+        }
+        out_paths.push(out_path);
+    }
+
+    fs::create_dir_all(out_dir)?;
+    for (entry, out_path) in reports.iter().zip(out_paths) {
+        let (file, report) = entry;
+        let value = match format {
+            OutputFormat::Json => report_to_json(file, report, notices, provenance),
+            OutputFormat::Sarif => {
+                reports_to_sarif(::std::slice::from_ref(entry), notices, provenance)
+            }
+        };
+        write_json_file(&out_path, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exit_codes::{reports_status, FailOn, FindingKind, Status};
+    use files::MemoryFs;
+    use testing::{elf, settings, NEEDED_D_TAG};
+
+    use std::time::SystemTime;
+
+    /// The report for the closure of /app/bin/app, which needs libfoo.so (in /lib) and the
+    /// missing libmissing.so, with the given symbol findings.
+    fn report(
+        unresolved_symbols: Vec<(String, HashSet<String>)>,
+        duplicate_symbols: Vec<(String, HashSet<String>)>,
+    ) -> Report {
+        let mut files = MemoryFs::new();
+        files.add_file(
+            "app/bin/app",
+            elf(&[(NEEDED_D_TAG, "libfoo.so"), (NEEDED_D_TAG, "libmissing.so")]),
+        );
+        files.add_file("lib/libfoo.so", elf(&[]));
+        let libs =
+            LibraryDependencies::try_find_for_elf(Path::new("/app/bin/app"), &settings(files, None))
+                .expect("The closure can be resolved");
+        Report {
+            libs,
+            packaging_problems: Vec::new(),
+            preload_problems: Vec::new(),
+            interpositions: Vec::new(),
+            search_path_oddities: Vec::new(),
+            rpath_usages: Vec::new(),
+            unknown_dynamic_tags: Vec::new(),
+            hardening: None,
+            minimum_versions: Vec::new(),
+            mount_problems: Vec::new(),
+            text_relocations: Vec::new(),
+            budget_violations: Vec::new(),
+            dlopen_problems: Vec::new(),
+            unused_dependencies: Vec::new(),
+            unresolved_symbols,
+            unresolved_data: Vec::new(),
+            dispatched_symbols: Vec::new(),
+            driver_problems: Vec::new(),
+            version_problems: Vec::new(),
+            missing_versions: Vec::new(),
+            cxx_runtime_problems: Vec::new(),
+            executable_symbols: Vec::new(),
+            underlinked_libraries: Vec::new(),
+            duplicate_symbols,
+            symbol_classes: HashMap::new(),
+            mismatched_duplicates: Vec::new(),
+            self_imported_symbols: Vec::new(),
+        }
+    }
+
+    fn names(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|&name| name.to_owned()).collect()
+    }
+
+    #[test]
+    fn findings_are_derived_from_the_report() {
+        let report = report(
+            vec![("foo_init".to_owned(), names(&["libfoo.so"]))],
+            vec![("bar".to_owned(), names(&["libfoo.so", "libunknown.so"]))],
+        );
+        let findings = report.to_result().findings();
+        let summary = findings
+            .iter()
+            .map(|finding| (finding.rule, finding.level, finding.owners.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("library-resolution", "error", vec![PathBuf::from("/app/bin/app")]),
+                (UNRESOLVED_SYMBOL, "error", vec![PathBuf::from("/lib/libfoo.so")]),
+                (
+                    DUPLICATE_SYMBOL,
+                    "warning",
+                    vec![PathBuf::from("/lib/libfoo.so"), PathBuf::from("libunknown.so")],
+                ),
+            ]
+        );
+        assert!(findings[0].message.contains("libmissing.so"));
+        assert_eq!(findings[1].message, "Unresolved symbol foo_init (referenced by libfoo.so)");
+    }
+
+    #[test]
+    fn reports_map_to_exit_codes() {
+        let reports = vec![
+            (PathBuf::from("/app/bin/app"), Ok(report(Vec::new(), Vec::new()))),
+            (PathBuf::from("/app/bin/broken"), Err("not an ELF file".to_owned())),
+        ];
+        let status = reports_status(&reports);
+        assert_eq!(
+            status,
+            Status::of(vec![FindingKind::LibProblems, FindingKind::AnalysisError])
+        );
+        assert_eq!(FailOn::from_str("").unwrap().exit_code(&status), 0);
+        assert_eq!(FailOn::from_str("lib-problems,warnings").unwrap().exit_code(&status), 1);
+
+        let duplicates = vec![("bar".to_owned(), names(&["libfoo.so", "libbar.so"]))];
+        let reports = vec![(PathBuf::from("/app/bin/app"), Ok(report(Vec::new(), duplicates)))];
+        let status = reports_status(&reports);
+        assert_eq!(
+            status,
+            Status::of(vec![FindingKind::LibProblems, FindingKind::Warnings])
+        );
+        assert_eq!(FailOn::from_str("lib-problems,warnings").unwrap().exit_code(&status), 5);
+    }
+
+    #[test]
+    fn sarif_contains_all_findings() {
+        let reports = vec![
+            (
+                PathBuf::from("/app/bin/app"),
+                Ok(report(vec![("foo_init".to_owned(), names(&["libfoo.so"]))], Vec::new())),
+            ),
+            (PathBuf::from("/app/bin/broken"), Err("not an ELF file".to_owned())),
+        ];
+        let provenance = Provenance::collect(
+            SystemTime::now(),
+            &settings(MemoryFs::new(), None),
+            false,
+            None,
+            &[],
+        );
+        let sarif = reports_to_sarif(&reports, &[], &provenance);
+
+        let results = sarif["runs"][0]["results"].as_array().expect("Results are a list");
+        let rules = results
+            .iter()
+            .map(|result| result["ruleId"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(rules, vec!["library-resolution", UNRESOLVED_SYMBOL, ANALYSIS_ERROR]);
+        let broken = &results[2];
+        assert_eq!(broken["level"], "error");
+        assert_eq!(broken["message"]["text"], "not an ELF file");
+        assert_eq!(
+            broken["partialFingerprints"][SARIF_FINGERPRINT],
+            Finding::analysis_error(Path::new("/app/bin/broken"), "").fingerprint
+        );
+        let descriptors = sarif["runs"][0]["tool"]["driver"]["rules"]
+            .as_array()
+            .expect("Rules are a list");
+        assert_eq!(descriptors.len(), RULES.len());
+    }
+}
@@ -0,0 +1,294 @@
+use files::{FileProvider, ImageFs};
+use libraries::{reroot, try_find_for_elfs, Environment, ErrorMsg, LibSearchMethod,
+                LibraryCache, LibraryDependencies, LibraryIdentity, ResolveSettings, Traversal};
+use symbols::SymbolSummary;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::sync::Arc;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// The root directory of an image. Squashfs images are extracted to a temporary directory, which
+/// is removed again when the image is dropped.
+pub struct Image {
+    pub root: PathBuf,
+    extracted: bool,
+}
+
+impl Image {
+    pub fn open(path: &Path) -> Result<Image, Box<dyn Error>> {
+        let metadata = fs::metadata(path).map_err(|e| ErrorMsg(format!("{:?}: {}", path, e)))?;
+        if metadata.is_dir() {
+            // The resolved paths must be absolute, and the symbolic links of the image are
+            // resolved relative to its root.
+            return Ok(Image {
+                root: fs::canonicalize(path)?,
+                extracted: false,
+            });
+        }
+
+        let root = ::std::env::temp_dir().join(format!("linkcheck-rootfs-{}", process::id()));
+        let status = Command::new("unsquashfs")
+            .arg("-no-progress")
+            .arg("-d")
+            .arg(&root)
+            .arg(path)
+            .stdout(process::Stdio::null())
+            .status()
+            .map_err(|e| ErrorMsg(format!("Could not run unsquashfs to extract {:?}: {}", path, e)))?;
+        let image = Image {
+            root,
+            extracted: true,
+        };
+        if !status.success() {
+            return Err(Box::new(ErrorMsg(format!(
+                "unsquashfs failed to extract {:?}",
+                path
+            ))));
+        }
+        Ok(image)
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        if self.extracted {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+/// The loader's default search order inside the image (including the lib64 directories of 64 bit
+/// systems). LD_LIBRARY_PATH is not considered, because the image is analyzed as a closed world.
+pub fn image_search_methods(root: &Path) -> Vec<LibSearchMethod> {
+    vec![
+        LibSearchMethod::RPath,
+        LibSearchMethod::RunPath,
+        LibSearchMethod::LDConfig(PathBuf::from("/etc/ld.so.conf")),
+        LibSearchMethod::Fixed(root.join("usr/lib64")),
+        LibSearchMethod::Fixed(root.join("lib64")),
+        LibSearchMethod::Fixed(root.join("usr/lib")),
+        LibSearchMethod::Fixed(root.join("lib")),
+    ]
+}
+
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| magic == ELF_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Find all executables (files with an interpreter) and shared libraries below `dir`. Symbolic
+/// links are not followed.
+fn find_elf_files(
+    dir: &Path,
+    files: &dyn FileProvider,
+    cache: &LibraryCache,
+    executables: &mut Vec<(PathBuf, String)>,
+    libraries: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if file_type.is_dir() {
+            find_elf_files(&path, files, cache, executables, libraries)?;
+            continue;
+        }
+        if !file_type.is_file() || !is_elf(&path) {
+            continue;
+        }
+        let lib = match cache.get_or_open(&path, files) {
+            Ok(lib) => lib,
+            Err(_) => continue,
+        };
+        let elf = lib.get_elf();
+        let is_named_like_lib = path.file_name()
+            .is_some_and(|n| n.to_string_lossy().contains(".so"));
+        match elf.interpreter {
+            Some(interpreter) if !is_named_like_lib => {
+                executables.push((path, interpreter.to_owned()));
+            }
+            _ if elf.is_lib => libraries.push(path),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Which binaries of an image use a library.
+#[derive(Default)]
+pub struct LibraryUsage {
+    /// Binaries that load the library
+    pub users: BTreeSet<PathBuf>,
+    /// Binaries that would no longer start if the library was removed, because no other file
+    /// could take its place
+    pub dependents: BTreeSet<PathBuf>,
+}
+
+/// The results of analyzing all executables of an image against the image's own libraries.
+pub struct RootfsAnalysis {
+    pub root: PathBuf,
+    /// Every executable, its interpreter and the analysis of its closure.
+    pub executables: Vec<(PathBuf, String, Result<LibraryDependencies, String>)>,
+    /// All shared libraries found in the image.
+    pub libraries: Vec<PathBuf>,
+    files: Arc<ImageFs>,
+}
+
+impl RootfsAnalysis {
+    /// Analyze the image at `root`, reading all files with `cache` (and thus its parse profile).
+    pub fn analyze(root: &Path, cache: &LibraryCache) -> Result<RootfsAnalysis, Box<dyn Error>> {
+        let files = Arc::new(ImageFs::new(root));
+        let mut executables = Vec::new();
+        let mut libraries = Vec::new();
+        find_elf_files(root, &*files, cache, &mut executables, &mut libraries)?;
+        executables.sort();
+        libraries.sort();
+
+        let settings = ResolveSettings {
+            search_methods: image_search_methods(root),
+            secure_execution: false,
+            sysroot: Some(root.to_path_buf()),
+            identity: LibraryIdentity::default(),
+            traversal: Traversal::default(),
+            environment: Environment::default(),
+            files: files.clone(),
+        };
+        let jobs = executables
+            .iter()
+            .map(|(path, _)| (path.clone(), settings.clone()))
+            .collect::<Vec<_>>();
+        let mut results = try_find_for_elfs(&jobs, cache);
+
+        // Anything resolved outside of the image would not be available on the target.
+        for libs in results.iter_mut().filter_map(|r| r.as_mut().ok()) {
+            libs.report_outside_prefixes(&[root.to_path_buf()]);
+        }
+
+        Ok(RootfsAnalysis {
+            root: root.to_path_buf(),
+            executables: executables
+                .into_iter()
+                .zip(results)
+                .map(|((path, interpreter), libs)| (path, interpreter, libs))
+                .collect(),
+            libraries,
+            files,
+        })
+    }
+
+    /// The path of a file as seen on the target system (i.e., relative to the image root).
+    pub fn image_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root)
+            .map(|p| Path::new("/").join(p))
+            .unwrap_or(path.to_path_buf())
+    }
+
+    /// Executables that cannot be started, with the reasons why.
+    pub fn startup_problems(&self) -> Vec<(PathBuf, Vec<String>)> {
+        let mut problems = Vec::new();
+        for (path, interpreter, libs) in self.executables.iter() {
+            let mut reasons = Vec::new();
+            if !self.files.exists(&reroot(Path::new(interpreter), Some(&self.root))) {
+                reasons.push(format!("Interpreter {} does not exist in the image", interpreter));
+            }
+            match libs {
+                Ok(libs) => reasons.extend(libs.problems.iter().map(|p| p.to_string())),
+                Err(err) => reasons.push(err.clone()),
+            }
+            if !reasons.is_empty() {
+                problems.push((self.image_path(path), reasons));
+            }
+        }
+        problems
+    }
+
+    /// Libraries of the image that are not part of the closure of any executable. (They may still
+    /// be loaded at runtime using dlopen, though.)
+    pub fn unused_libraries(&self) -> Vec<PathBuf> {
+        let used = self.executables
+            .iter()
+            .filter_map(|(_, _, libs)| libs.as_ref().ok())
+            .flat_map(|libs| libs.opened_libs.keys())
+            .filter_map(|path| self.files.canonicalize(path).ok())
+            .collect::<HashSet<_>>();
+        self.libraries
+            .iter()
+            .filter(|lib| {
+                self.files
+                    .canonicalize(lib)
+                    .map(|lib| !used.contains(&lib))
+                    .unwrap_or(true)
+            })
+            .map(|lib| self.image_path(lib))
+            .collect()
+    }
+
+    /// Symbols that are needed by some executable's closure, but not defined within it, together
+    /// with the executables affected.
+    pub fn unsatisfiable_symbols(&self) -> BTreeMap<String, BTreeSet<PathBuf>> {
+        let mut symbols = BTreeMap::new();
+        for (path, _, libs) in self.executables.iter() {
+            let libs = match libs {
+                Ok(libs) => libs,
+                &Err(_) => continue,
+            };
+            let summary = SymbolSummary::from_libs(libs);
+            for symbol in summary.unresolved.keys() {
+                if !summary.defined.contains_key(symbol) {
+                    let _ = symbols
+                        .entry(symbol.clone())
+                        .or_insert(BTreeSet::new())
+                        .insert(self.image_path(path));
+                }
+            }
+        }
+        symbols
+    }
+    /// Invert the closures of all executables: For every library (by its real path inside the
+    /// image), find the binaries using it and those that depend on it.
+    pub fn library_usage(&self) -> BTreeMap<PathBuf, LibraryUsage> {
+        let mut usage = BTreeMap::<PathBuf, LibraryUsage>::new();
+        for (path, _, libs) in self.executables.iter() {
+            let libs = match libs {
+                Ok(libs) => libs,
+                &Err(_) => continue,
+            };
+            let binary = self.image_path(path);
+            for (lib_path, resolutions) in libs.resolutions.iter() {
+                let real_path = self.files
+                    .canonicalize(lib_path)
+                    .unwrap_or(lib_path.clone());
+
+                // Removing the library only breaks the binary if, for some dependent library, no
+                // other file with the same name is found in any of its search locations.
+                let irreplaceable = resolutions.iter().any(|resolution| {
+                    let locations = match libs.search_locations.get(&resolution.dependent_lib) {
+                        Some(locations) => locations,
+                        None => return true,
+                    };
+                    !locations.iter().any(|(dir, _)| {
+                        let candidate = dir.join(&resolution.lib_name);
+                        self.files.exists(&candidate)
+                            && self.files.canonicalize(&candidate).ok().as_ref() != Some(&real_path)
+                    })
+                });
+
+                let entry = usage.entry(self.image_path(&real_path)).or_default();
+                let _ = entry.users.insert(binary.clone());
+                if irreplaceable {
+                    let _ = entry.dependents.insert(binary.clone());
+                }
+            }
+        }
+        usage
+    }
+}
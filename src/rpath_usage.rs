@@ -0,0 +1,125 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{expand_embedded_entry, LibraryDependencies, INHERITED_RPATH};
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// An RPATH or RUNPATH entry and how many dependencies of its object it produced.
+pub struct RpathEntry {
+    /// The entry as it is written in the object (e.g., with $ORIGIN)
+    pub entry: String,
+    pub directory: PathBuf,
+    pub resolutions: usize,
+}
+
+/// The RPATH (or RUNPATH) entries of one object, with their usage.
+pub struct RpathUsage {
+    pub object: PathBuf,
+    pub origin: &'static str,
+    pub entries: Vec<RpathEntry>,
+}
+
+impl RpathUsage {
+    /// The entries that did not produce any dependency (possibly because an earlier entry
+    /// provides the same libraries).
+    pub fn dead(&self) -> Vec<&RpathEntry> {
+        self.entries.iter().filter(|e| e.resolutions == 0).collect()
+    }
+
+    /// The minimal search path that resolves the dependencies the same way: the used entries in
+    /// their original order, separated by colons (as `patchelf --set-rpath` expects them).
+    pub fn consolidated(&self) -> String {
+        let mut entries: Vec<&str> = Vec::new();
+        for entry in self.entries.iter().filter(|e| e.resolutions > 0) {
+            if !entries.contains(&entry.entry.as_str()) {
+                entries.push(&entry.entry);
+            }
+        }
+        entries.join(":")
+    }
+
+    /// A stable fingerprint of the usage (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "rpath-consolidation",
+            &[&normalize_path(&self.object), self.origin],
+        )
+    }
+}
+
+impl fmt::Display for RpathUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dead = self.dead();
+        write!(
+            f,
+            "{:?}: {} of {} {} entries are used",
+            self.object,
+            self.entries.len() - dead.len(),
+            self.entries.len(),
+            self.origin
+        )?;
+        if !dead.is_empty() {
+            write!(
+                f,
+                " (dead: {}), the minimal {} is {:?}",
+                dead.iter()
+                    .map(|e| e.entry.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                self.origin,
+                self.consolidated()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Count how many dependencies each RPATH and RUNPATH entry of the objects of the closure
+/// produced, including those of the objects that inherit the RPATH entries. Only DT_NEEDED
+/// entries are considered, so entries that are only used for libraries loaded at runtime
+/// (dlopen) appear dead as well.
+pub fn find_rpath_usages(libs: &LibraryDependencies, sysroot: Option<&Path>) -> Vec<RpathUsage> {
+    let mut objects = libs.opened_libs.keys().collect::<Vec<_>>();
+    objects.sort_by_key(|object| (*object != &libs.root, *object));
+
+    let mut usages = Vec::new();
+    for object in objects {
+        let embedded = libs.opened_libs[object].embedded_search_paths();
+        for origin in &["rpath", "runpath"] {
+            // Only the first of several entries with the same directory can produce dependencies.
+            let mut seen = HashSet::new();
+            let entries = embedded
+                .iter()
+                .filter(|&&(o, _)| o == *origin)
+                .map(|(_, entry)| {
+                    let directory = expand_embedded_entry(entry, object, sysroot);
+                    let first = seen.insert(directory.clone());
+                    let resolutions = libs.resolutions
+                        .values()
+                        .flat_map(|resolutions| resolutions.iter())
+                        .filter(|r| {
+                            let from_origin = r.origin == *origin
+                                || (*origin == "rpath" && r.origin == INHERITED_RPATH);
+                            first && &r.entry_owner == object && from_origin
+                                && r.directory == directory
+                        })
+                        .count();
+                    RpathEntry {
+                        entry: entry.clone(),
+                        directory,
+                        resolutions,
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !entries.is_empty() {
+                usages.push(RpathUsage {
+                    object: object.clone(),
+                    origin,
+                    entries,
+                });
+            }
+        }
+    }
+    usages
+}
@@ -0,0 +1,244 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{LibraryDependencies, INHERITED_RPATH};
+
+use libc;
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Search location origins that are baked into the object itself and can thus be cleaned up by
+/// rebuilding (or patching) it.
+const EMBEDDED_ORIGINS: &[&str] = &["rpath", "runpath"];
+
+pub enum SearchPathOddity {
+    Duplicate {
+        object: PathBuf,
+        directory: PathBuf,
+        origin: &'static str,
+        first_origin: &'static str,
+    },
+    Shadowed {
+        object: PathBuf,
+        directory: PathBuf,
+        origin: &'static str,
+        lib_names: Vec<String>,
+    },
+    Unused {
+        object: PathBuf,
+        directory: PathBuf,
+        origin: &'static str,
+    },
+    /// The entry exists, but cannot be used as search directory (the loader silently skips it).
+    Inaccessible {
+        object: PathBuf,
+        directory: PathBuf,
+        origin: &'static str,
+        reason: &'static str,
+    },
+}
+
+impl SearchPathOddity {
+    /// The object that carries the search path entry.
+    pub fn object(&self) -> &Path {
+        match self {
+            &SearchPathOddity::Duplicate { ref object, .. }
+            | &SearchPathOddity::Shadowed { ref object, .. }
+            | &SearchPathOddity::Unused { ref object, .. }
+            | &SearchPathOddity::Inaccessible { ref object, .. } => object,
+        }
+    }
+
+    /// A stable fingerprint of the oddity (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let (kind, directory, origin) = match *self {
+            SearchPathOddity::Duplicate {
+                ref directory,
+                origin,
+                ..
+            } => ("duplicate-search-path", directory, origin),
+            SearchPathOddity::Shadowed {
+                ref directory,
+                origin,
+                ..
+            } => ("shadowed-search-path", directory, origin),
+            SearchPathOddity::Unused {
+                ref directory,
+                origin,
+                ..
+            } => ("unused-search-path", directory, origin),
+            SearchPathOddity::Inaccessible {
+                ref directory,
+                origin,
+                ..
+            } => ("inaccessible-search-path", directory, origin),
+        };
+        fingerprint(
+            kind,
+            &[
+                &normalize_path(self.object()),
+                origin,
+                &directory.to_string_lossy(),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for SearchPathOddity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchPathOddity::Duplicate {
+                object,
+                directory,
+                origin,
+                first_origin,
+            } => write!(
+                f,
+                "{:?}: {} entry {:?} is a duplicate of an earlier {} entry",
+                object, origin, directory, first_origin
+            ),
+            SearchPathOddity::Shadowed {
+                object,
+                directory,
+                origin,
+                lib_names,
+            } => write!(
+                f,
+                "{:?}: {} entry {:?} is fully shadowed by earlier entries (it only provides {})",
+                object,
+                origin,
+                directory,
+                lib_names.join(", ")
+            ),
+            SearchPathOddity::Unused {
+                object,
+                directory,
+                origin,
+            } => write!(
+                f,
+                "{:?}: {} entry {:?} does not provide any dependency",
+                object, origin, directory
+            ),
+            SearchPathOddity::Inaccessible {
+                object,
+                directory,
+                origin,
+                reason,
+            } => write!(
+                f,
+                "{:?}: {} entry {:?} {}, so the loader silently skips it",
+                object, origin, directory, reason
+            ),
+        }
+    }
+}
+
+/// Why the loader cannot use an existing search path entry (for the current user), if it cannot.
+/// Entries that do not exist at all are not considered here.
+fn inaccessibility(directory: &Path) -> Option<&'static str> {
+    match fs::metadata(directory) {
+        Ok(ref metadata) if !metadata.is_dir() => return Some("is not a directory"),
+        Ok(_) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            return Some("is not accessible (permission denied)")
+        }
+        Err(_) => {
+            let is_link = fs::symlink_metadata(directory)
+                .map(|m| m.file_type().is_symlink())
+                .unwrap_or(false);
+            return if is_link {
+                Some("is a dangling symbolic link")
+            } else {
+                None
+            };
+        }
+    }
+    // Looking up files in the directory requires search (execute) permission.
+    let c_path = CString::new(directory.as_os_str().as_bytes()).ok()?;
+    if unsafe { libc::access(c_path.as_ptr(), libc::X_OK) } != 0 {
+        Some("is not searchable (permission denied)")
+    } else {
+        None
+    }
+}
+
+/// Find duplicate, shadowed and unused search path entries that are embedded in the analyzed
+/// objects (i.e., RPATH and RUNPATH entries), and entries of any origin that the loader cannot use.
+/// Entries that are not embedded are only reported once (for the first object using them).
+pub fn find_search_path_oddities(libs: &LibraryDependencies) -> Vec<SearchPathOddity> {
+    let mut objects = libs.search_locations.keys().collect::<Vec<_>>();
+    objects.sort_by_key(|object| (*object != &libs.root, *object));
+
+    let mut oddities = Vec::new();
+    let mut checked = HashSet::new();
+    for object in objects {
+        let locations = libs.search_locations[object].iter().collect::<Vec<_>>();
+        let needed = libs.needed.get(object).map(|n| n.as_slice()).unwrap_or(&[]);
+
+        for (i, &&(ref directory, origin)) in locations.iter().enumerate() {
+            let embedded = EMBEDDED_ORIGINS.contains(&origin);
+            if embedded || checked.insert((directory, origin)) {
+                if let Some(reason) = inaccessibility(directory) {
+                    oddities.push(SearchPathOddity::Inaccessible {
+                        object: object.clone(),
+                        directory: directory.clone(),
+                        origin,
+                        reason,
+                    });
+                    continue;
+                }
+            }
+            if !embedded {
+                continue;
+            }
+            let earlier = &locations[..i];
+
+            if let Some(&&(_, first_origin)) = earlier.iter().find(|&&(d, _)| d == directory) {
+                oddities.push(SearchPathOddity::Duplicate {
+                    object: object.clone(),
+                    directory: directory.clone(),
+                    origin,
+                    first_origin,
+                });
+                continue;
+            }
+
+            let provided = needed
+                .iter()
+                .filter(|lib_name| directory.join(lib_name).exists())
+                .collect::<Vec<_>>();
+            // Objects without DT_RUNPATH that the object loads search its DT_RPATH as well.
+            let inherited = origin == "rpath" && libs.resolutions
+                .values()
+                .flat_map(|resolutions| resolutions.iter())
+                .any(|r| {
+                    &r.entry_owner == object && r.origin == INHERITED_RPATH
+                        && &r.directory == directory
+                });
+
+            if provided.is_empty() && !inherited {
+                oddities.push(SearchPathOddity::Unused {
+                    object: object.clone(),
+                    directory: directory.clone(),
+                    origin,
+                });
+            } else if !inherited && provided.iter().all(|lib_name| {
+                earlier
+                    .iter()
+                    .any(|&(d, _)| d.join(lib_name).exists())
+            }) {
+                oddities.push(SearchPathOddity::Shadowed {
+                    object: object.clone(),
+                    directory: directory.clone(),
+                    origin,
+                    lib_names: provided.iter().map(|s| s.to_string()).collect(),
+                });
+            }
+        }
+    }
+    oddities
+}
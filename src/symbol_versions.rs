@@ -0,0 +1,169 @@
+use goblin::elf::Elf;
+
+use libraries::Library;
+
+use std::collections::{HashMap, HashSet};
+
+const DT_VERSYM: u64 = 0x6ffffff0;
+const DT_VERDEF: u64 = 0x6ffffffc;
+const DT_VERDEFNUM: u64 = 0x6ffffffd;
+const DT_VERNEED: u64 = 0x6ffffffe;
+const DT_VERNEEDNUM: u64 = 0x6fffffff;
+
+const VER_FLG_WEAK: u16 = 0x2;
+const VERSYM_HIDDEN: u16 = 0x8000;
+const VERSYM_INDEX: u16 = 0x7fff;
+/// Version indices 0 (local) and 1 (global) denote unversioned symbols.
+const FIRST_VERSION_INDEX: u16 = 2;
+
+/// The symbol versions of a library.
+#[derive(Default)]
+pub struct SymbolVersions {
+    /// Exported symbols and their versions (name and whether it is the default version). An
+    /// unversioned definition is represented as a default version with an empty name.
+    pub exported: HashMap<String, Vec<(String, bool)>>,
+    /// The version of each versioned definition (name and whether it is the default version), by
+    /// index in the dynamic symbol table.
+    pub definitions: HashMap<usize, (String, bool)>,
+    /// Undefined symbols that are referenced without version.
+    pub unversioned_references: HashSet<String>,
+    /// Undefined symbols that are referenced with a version, and the name of the version.
+    pub versioned_references: HashMap<String, String>,
+    /// Names of the versions that the library defines (e.g., GLIBCXX_3.4.21).
+    pub defined: HashSet<String>,
+    /// The first version the library defines after the base version. The loader binds references
+    /// without version to it even if it is not the default version (for objects that were linked
+    /// before the library was versioned).
+    pub oldest: Option<String>,
+    /// Versions that the library requires, by the name of the library that has to provide them.
+    pub required: HashMap<String, HashSet<String>>,
+    /// Required versions marked as weak (VER_FLG_WEAK), whose absence the loader only warns about.
+    pub weakly_required: HashSet<String>,
+}
+
+/// Map version indices to names using the version definitions (DT_VERDEF) and requirements
+/// (DT_VERNEED) of a library. The defined and required version names are recorded in `versions`.
+fn version_names(
+    lib: &Library,
+    elf: &Elf,
+    find_tag: &dyn Fn(u64) -> Option<u64>,
+    versions: &mut SymbolVersions,
+) -> HashMap<u16, String> {
+    let mut names = HashMap::new();
+    let name_at = |offset: u32| {
+        elf.dynstrtab
+            .get(offset as usize)
+            .and_then(|name| name.ok())
+            .map(|name| name.to_owned())
+    };
+
+    // Verdef: vd_version (u16), vd_flags (u16), vd_ndx (u16), vd_cnt (u16), vd_hash (u32),
+    // vd_aux (u32), vd_next (u32); Verdaux: vda_name (u32), vda_next (u32)
+    if let (Some(mut vaddr), Some(num)) = (find_tag(DT_VERDEF), find_tag(DT_VERDEFNUM)) {
+        for _ in 0..num {
+            let index = match lib.read_u16_at_vaddr(elf, vaddr + 4) {
+                Some(index) => index,
+                None => break,
+            };
+            let aux = lib.read_u32_at_vaddr(elf, vaddr + 12).unwrap_or(0);
+            if let Some(name) = lib.read_u32_at_vaddr(elf, vaddr + aux as u64).and_then(&name_at) {
+                let _ = versions.defined.insert(name.clone());
+                let _ = names.insert(index, name);
+            }
+            match lib.read_u32_at_vaddr(elf, vaddr + 16) {
+                Some(next) if next != 0 => vaddr += next as u64,
+                _ => break,
+            }
+        }
+    }
+
+    // Verneed: vn_version (u16), vn_cnt (u16), vn_file (u32), vn_aux (u32), vn_next (u32);
+    // Vernaux: vna_hash (u32), vna_flags (u16), vna_other (u16), vna_name (u32), vna_next (u32)
+    if let (Some(mut vaddr), Some(num)) = (find_tag(DT_VERNEED), find_tag(DT_VERNEEDNUM)) {
+        for _ in 0..num {
+            let count = lib.read_u16_at_vaddr(elf, vaddr + 2).unwrap_or(0);
+            let file = lib.read_u32_at_vaddr(elf, vaddr + 4)
+                .and_then(&name_at)
+                .unwrap_or_default();
+            let mut aux_vaddr = vaddr + lib.read_u32_at_vaddr(elf, vaddr + 8).unwrap_or(0) as u64;
+            for _ in 0..count {
+                let flags = lib.read_u16_at_vaddr(elf, aux_vaddr + 4).unwrap_or(0);
+                let index = lib.read_u16_at_vaddr(elf, aux_vaddr + 6);
+                let name = lib.read_u32_at_vaddr(elf, aux_vaddr + 8).and_then(&name_at);
+                if let (Some(index), Some(name)) = (index, name) {
+                    if flags & VER_FLG_WEAK != 0 {
+                        let _ = versions.weakly_required.insert(name.clone());
+                    }
+                    let _ = versions
+                        .required
+                        .entry(file.clone())
+                        .or_default()
+                        .insert(name.clone());
+                    let _ = names.insert(index, name);
+                }
+                match lib.read_u32_at_vaddr(elf, aux_vaddr + 12) {
+                    Some(next) if next != 0 => aux_vaddr += next as u64,
+                    _ => break,
+                }
+            }
+            match lib.read_u32_at_vaddr(elf, vaddr + 12) {
+                Some(next) if next != 0 => vaddr += next as u64,
+                _ => break,
+            }
+        }
+    }
+    names
+}
+
+impl SymbolVersions {
+    pub fn of_library(lib: &Library) -> SymbolVersions {
+        let elf = lib.get_elf();
+        let mut versions = SymbolVersions::default();
+        let dyns = elf.dynamic.as_ref().map_or(&[][..], |d| &d.dyns[..]);
+        let find_tag = |tag| dyns.iter().find(|d| d.d_tag == tag).map(|d| d.d_val);
+        // Without a version table, all symbols are unversioned.
+        let versym = find_tag(DT_VERSYM);
+        let names = version_names(lib, elf, &find_tag, &mut versions);
+        versions.oldest = names
+            .get(&FIRST_VERSION_INDEX)
+            .filter(|name| versions.defined.contains(*name))
+            .cloned();
+
+        for (i, sym) in elf.dynsyms.iter().enumerate() {
+            let name = match elf.dynstrtab.get(sym.st_name).and_then(|n| n.ok()) {
+                Some(name) if !name.is_empty() => name,
+                _ => continue,
+            };
+            let entry = versym
+                .and_then(|versym| lib.read_u16_at_vaddr(elf, versym + 2 * i as u64))
+                .unwrap_or(1);
+            let index = entry & VERSYM_INDEX;
+            if sym.st_shndx == 0 {
+                if index < FIRST_VERSION_INDEX {
+                    let _ = versions.unversioned_references.insert(name.to_owned());
+                } else if let Some(version) = names.get(&index) {
+                    let _ = versions
+                        .versioned_references
+                        .insert(name.to_owned(), version.clone());
+                }
+            } else if sym.st_bind() != 0 {
+                let version = if index < FIRST_VERSION_INDEX {
+                    (String::new(), true)
+                } else {
+                    let version = (
+                        names.get(&index).cloned().unwrap_or(format!("#{}", index)),
+                        entry & VERSYM_HIDDEN == 0,
+                    );
+                    let _ = versions.definitions.insert(i, version.clone());
+                    version
+                };
+                versions
+                    .exported
+                    .entry(name.to_owned())
+                    .or_insert(Vec::new())
+                    .push(version);
+            }
+        }
+        versions
+    }
+}
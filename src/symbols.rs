@@ -1,16 +1,30 @@
-use libraries::LibraryDependencies;
+use cpp_demangle;
+use goblin::elf::Elf;
+use goblin::elf::sym::Sym;
+use libraries::{Library, LibraryDependencies};
+use regex::Regex;
+use symbol_versions::SymbolVersions;
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 //const TYPE_NOTYPE: u8 = 0;
-//const TYPE_OBJECT: u8 = 1;
-//const TYPE_FUNC: u8 = 2;
+const TYPE_OBJECT: u8 = 1;
+const TYPE_FUNC: u8 = 2;
 //const TYPE_SECTION: u8 = 3;
 //const TYPE_FILE: u8 = 4;
+const TYPE_TLS: u8 = 6;
+const TYPE_GNU_IFUNC: u8 = 10;
 
 //const BIND_LOCAL: u8 = 0;
 const BIND_GLOBAL: u8 = 1;
-//const BIND_WEAK: u8 = 2;
+const BIND_WEAK: u8 = 2;
+const BIND_GNU_UNIQUE: u8 = 10;
 
 const NDX_UNDEFINED: usize = 0;
 //const NDX_ABS: usize = 65521;
@@ -18,52 +32,551 @@ const NDX_UNDEFINED: usize = 0;
 //const VIS_DEFAULT: u8 = 0;
 const VIS_HIDDEN: u8 = 2;
 
+/// Whether the symbol (with the given name) is exported, i.e., can be used by other libraries.
+/// Like the loader, weak and unique definitions are used just as global ones.
+fn is_exported(name: &str, sym: &Sym) -> bool {
+    let bind = sym.st_bind();
+    !name.is_empty() && (bind == BIND_GLOBAL || bind == BIND_WEAK || bind == BIND_GNU_UNIQUE)
+        && sym.st_other != VIS_HIDDEN && sym.st_shndx != NDX_UNDEFINED
+}
+
+/// What a dynamic symbol is, as far as it matters for how the loader binds references to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolClass {
+    Function,
+    /// A function whose address is chosen by a resolver when it is bound (STT_GNU_IFUNC)
+    IndirectFunction,
+    Object,
+    /// A thread-local variable (STT_TLS), which is accessed through the TLS block of its module
+    ThreadLocal,
+    /// A symbol of which the loader uses a single definition in the whole process, even across
+    /// namespaces and RTLD_LOCAL (STB_GNU_UNIQUE, e.g., static members of C++ templates)
+    Unique,
+    Other,
+}
+
+impl SymbolClass {
+    pub fn of_symbol(sym: &Sym) -> Self {
+        if sym.st_bind() == BIND_GNU_UNIQUE {
+            return SymbolClass::Unique;
+        }
+        match sym.st_type() {
+            TYPE_FUNC => SymbolClass::Function,
+            TYPE_GNU_IFUNC => SymbolClass::IndirectFunction,
+            TYPE_OBJECT => SymbolClass::Object,
+            TYPE_TLS => SymbolClass::ThreadLocal,
+            _ => SymbolClass::Other,
+        }
+    }
+
+    /// Whether duplicates and interposition of the symbol behave differently than for ordinary
+    /// functions and objects, so that the class is shown along with the symbol.
+    pub fn is_special(&self) -> bool {
+        matches!(
+            *self,
+            SymbolClass::IndirectFunction | SymbolClass::ThreadLocal | SymbolClass::Unique
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            SymbolClass::Function => "function",
+            SymbolClass::IndirectFunction => "ifunc",
+            SymbolClass::Object => "object",
+            SymbolClass::ThreadLocal => "tls",
+            SymbolClass::Unique => "unique",
+            SymbolClass::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for SymbolClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// Count the symbols exported by the given library.
+pub fn count_exported_symbols(elf: &Elf) -> usize {
+    elf.dynsyms
+        .iter()
+        .filter(|sym| {
+            elf.dynstrtab
+                .get(sym.st_name)
+                .and_then(|name| name.ok())
+                .is_some_and(|name| is_exported(name, sym))
+        })
+        .count()
+}
+
+/// The names of all symbols exported by the given library.
+pub fn exported_symbol_names(elf: &Elf) -> HashSet<String> {
+    elf.dynsyms
+        .iter()
+        .filter_map(|sym| {
+            elf.dynstrtab
+                .get(sym.st_name)
+                .and_then(|name| name.ok())
+                .filter(|name| is_exported(name, &sym))
+                .map(|name| name.to_owned())
+        })
+        .collect()
+}
+
+/// The names of all symbols that the given library references (non-weakly) without defining them.
+pub fn strongly_undefined_symbol_names(elf: &Elf) -> HashSet<String> {
+    elf.dynsyms
+        .iter()
+        .filter(|sym| sym.st_shndx == NDX_UNDEFINED && sym.st_bind() == BIND_GLOBAL)
+        .filter_map(|sym| elf.dynstrtab.get(sym.st_name).and_then(|name| name.ok()))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+/// The names of global symbols that are defined in the (static) symbol table of the given object,
+/// but not exported via its dynamic symbol table. Empty for stripped objects.
+pub fn unexported_global_symbol_names(elf: &Elf) -> HashSet<String> {
+    let exported = exported_symbol_names(elf);
+    elf.syms
+        .iter()
+        .filter_map(|sym| {
+            elf.strtab
+                .get(sym.st_name)
+                .and_then(|name| name.ok())
+                .filter(|name| is_exported(name, &sym) && !exported.contains(*name))
+                .map(|name| name.to_owned())
+        })
+        .collect()
+}
+
+/// Demangle a C++ symbol name (keeping a version suffix, see `versioned_name`). Returns `None`
+/// for names that are not mangled.
+pub fn demangle(name: &str) -> Option<String> {
+    let (name, version) = match name.find('@') {
+        Some(i) => (&name[..i], &name[i..]),
+        None => (name, ""),
+    };
+    cpp_demangle::Symbol::new(name)
+        .ok()
+        .map(|symbol| format!("{}{}", symbol, version))
+}
+
+/// A symbol of some library in the closure that matches a search pattern.
+pub struct SymbolMatch {
+    pub lib: PathBuf,
+    pub name: String,
+    pub demangled: Option<String>,
+    pub role: &'static str,
+    pub class: SymbolClass,
+}
+
+/// Search the dynamic symbols of all opened libraries for symbols whose mangled or demangled name
+/// matches `pattern`.
+pub fn grep_symbols(libs: &LibraryDependencies, pattern: &Regex) -> Vec<SymbolMatch> {
+    let mut lib_paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    lib_paths.sort();
+
+    let mut matches = Vec::new();
+    for lib_path in lib_paths {
+        let elf = libs.opened_libs[lib_path].get_elf();
+        for sym in elf.dynsyms.iter() {
+            let name = match elf.dynstrtab.get(sym.st_name).and_then(|name| name.ok()) {
+                Some(name) if !name.is_empty() => name,
+                _ => continue,
+            };
+            let demangled = demangle(name);
+            let is_match = pattern.is_match(name)
+                || demangled.as_ref().is_some_and(|d| pattern.is_match(d));
+            if !is_match {
+                continue;
+            }
+
+            let role = if sym.st_shndx == NDX_UNDEFINED {
+                "undefined"
+            } else if is_exported(name, &sym) {
+                "exported"
+            } else {
+                "defined"
+            };
+            matches.push(SymbolMatch {
+                lib: lib_path.clone(),
+                name: name.to_owned(),
+                demangled,
+                role,
+                class: SymbolClass::of_symbol(&sym),
+            });
+        }
+    }
+    matches
+}
+
+/// The name of a symbol together with a version (e.g., memcpy@GLIBC_2.14).
+pub fn versioned_name(name: &str, version: &str) -> String {
+    format!("{}@{}", name, version)
+}
+
+/// The names of the dynamic symbols of one library, by role. Symbols that are referenced with a
+/// version are listed as undefined with their versioned name (see `versioned_name`), so that
+/// they only match definitions of that version. Each definition is listed once: with its plain
+/// name if it is unversioned or in its default version (which unversioned references match), and
+/// with its versioned name otherwise. References of the default version match the plain name via
+/// `default_versions`.
+#[derive(Debug)]
+pub struct LibrarySymbols {
+    pub exported: Vec<String>,
+    /// The exported symbols that are defined weakly (a subset of `exported`)
+    pub weakly_exported: Vec<String>,
+    /// The symbols that are referenced strongly, which the loader has to resolve
+    pub undefined: Vec<String>,
+    /// The symbols that are referenced weakly, which may remain unresolved (null)
+    pub weakly_undefined: Vec<String>,
+    pub defined: Vec<String>,
+    /// The exported symbols whose class is special (see `SymbolClass::is_special`)
+    pub special_classes: Vec<(String, SymbolClass)>,
+    /// The versioned names of the exports that are defined in their default version, with the
+    /// plain name under which they are listed
+    pub default_versions: Vec<(String, String)>,
+}
+
+impl LibrarySymbols {
+    pub fn of_library(lib: &Library) -> LibrarySymbols {
+        let elf = lib.get_elf();
+        let versions = SymbolVersions::of_library(lib);
+        let mut symbols = LibrarySymbols {
+            exported: Vec::new(),
+            weakly_exported: Vec::new(),
+            undefined: Vec::new(),
+            weakly_undefined: Vec::new(),
+            defined: Vec::new(),
+            special_classes: Vec::new(),
+            default_versions: Vec::new(),
+        };
+        for (i, sym) in elf.dynsyms.iter().enumerate() {
+            if let Some(name) = elf.dynstrtab.get(sym.st_name) {
+                let name = name.expect("Symbol is not valid utf8");
+                if name.is_empty() {
+                    continue;
+                }
+                let definition_name = match versions.definitions.get(&i) {
+                    Some(&(ref version, false)) => versioned_name(name, version),
+                    _ => name.to_string(),
+                };
+
+                let weak = sym.st_bind() == BIND_WEAK;
+                if is_exported(name, &sym) {
+                    symbols.exported.push(definition_name.clone());
+                    if weak {
+                        symbols.weakly_exported.push(definition_name.clone());
+                    }
+                    let class = SymbolClass::of_symbol(&sym);
+                    if class.is_special() {
+                        symbols.special_classes.push((definition_name.clone(), class));
+                    }
+                    if let Some(&(ref version, true)) = versions.definitions.get(&i) {
+                        symbols
+                            .default_versions
+                            .push((versioned_name(name, version), name.to_string()));
+                    }
+                }
+                if sym.st_shndx == NDX_UNDEFINED {
+                    let reference = match versions.versioned_references.get(name) {
+                        Some(version) => versioned_name(name, version),
+                        None => name.to_string(),
+                    };
+                    if weak {
+                        symbols.weakly_undefined.push(reference);
+                    } else {
+                        symbols.undefined.push(reference);
+                    }
+                } else {
+                    symbols.defined.push(definition_name);
+                }
+            }
+        }
+        symbols
+    }
+
+    /// Whether a reference with the given (possibly versioned) name binds to an export of the
+    /// library.
+    pub fn exports(&self, reference: &str) -> bool {
+        self.exported.iter().any(|name| name == reference)
+            || self.default_versions
+                .iter()
+                .any(|(versioned, _)| versioned == reference)
+    }
+
+    /// The names that references to the exports of the library can use: the names of the exports
+    /// and the versioned names of those defined in their default version.
+    pub fn export_names(&self) -> impl Iterator<Item = &String> {
+        self.exported
+            .iter()
+            .chain(self.default_versions.iter().map(|(versioned, _)| versioned))
+    }
+}
+
+/// Apply `f` to all items in parallel (using one thread per available core) and return the
+/// results in the order of the items.
+pub(crate) fn parallel_map<T: Send, R: Send, F: Fn(T) -> R + Sync>(items: Vec<T>, f: F) -> Vec<R> {
+    let num_threads = ::std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(items.len());
+    let num_items = items.len();
+    let items = items
+        .into_iter()
+        .map(|item| Mutex::new(Some(item)))
+        .collect::<Vec<_>>();
+    let next_item = AtomicUsize::new(0);
+    let results = Mutex::new((0..num_items).map(|_| None).collect::<Vec<_>>());
+
+    ::std::thread::scope(|scope| {
+        for _ in 0..num_threads {
+            let _ = scope.spawn(|| loop {
+                let i = next_item.fetch_add(1, Ordering::SeqCst);
+                if i >= num_items {
+                    break;
+                }
+                let item = items[i]
+                    .lock()
+                    .expect("Item lock poisoned")
+                    .take()
+                    .expect("Every item is taken once");
+                let result = f(item);
+                results.lock().expect("Results lock poisoned")[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("Results lock poisoned")
+        .into_iter()
+        .map(|r| r.expect("Every item has been processed"))
+        .collect()
+}
+
+/// The shard (out of `num_shards`) that is responsible for the given symbol name.
+fn shard_of(name: &str, num_shards: usize) -> usize {
+    if num_shards == 1 {
+        return 0;
+    }
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    (hasher.finish() % num_shards as u64) as usize
+}
+
+fn add_lib(map: &mut HashMap<String, HashSet<String>>, name: &str, lib_name: &str) {
+    // Avoid allocating the name for symbols that are already known.
+    if let Some(lib_names) = map.get_mut(name) {
+        let _ = lib_names.insert(lib_name.to_string());
+        return;
+    }
+    let _ = map.insert(name.to_string(), Some(lib_name.to_string()).into_iter().collect());
+}
+
+/// The dynamic symbols of a set of libraries. Each symbol is mapped to the names of the
+/// libraries that export, reference (without defining) or define it. Weak references are kept
+/// apart from strong ones, as the loader leaves them null if there is no definition. References
+/// of a default version are listed under the plain name of the definition (see `aliases`).
+#[derive(Default)]
 pub struct SymbolSummary {
     pub exported: HashMap<String, HashSet<String>>,
+    /// The libraries that export the symbol weakly (a subset of those in `exported`)
+    pub weakly_exported: HashMap<String, HashSet<String>>,
     pub unresolved: HashMap<String, HashSet<String>>,
+    pub weakly_unresolved: HashMap<String, HashSet<String>>,
     pub defined: HashMap<String, HashSet<String>>,
+    /// The classes of the exported symbols whose class is special (see `SymbolClass::is_special`)
+    pub special_classes: HashMap<String, SymbolClass>,
+    /// The versioned names of definitions in their default version, mapped to their plain name
+    pub aliases: HashMap<String, String>,
 }
 
 impl SymbolSummary {
+    /// Summarize the symbols of all resolved libraries and of the root, whose exports (e.g., of
+    /// an executable linked with -rdynamic) satisfy references of its libraries as well. The
+    /// definitions of a filter that a resolved filtee provides are left out, as the loader looks
+    /// them up in the filtee first. The symbol lists of the libraries are extracted in parallel.
+    /// They are then merged in two parallel steps: Each worker sorts the symbols of some
+    /// libraries into per-shard maps (sharded by symbol name), and then the maps of each shard are
+    /// merged. As the shards are disjoint, they are simply combined at the end.
     pub fn from_libs(libs: &LibraryDependencies) -> SymbolSummary {
-        let mut summary = SymbolSummary {
-            exported: HashMap::new(),
-            unresolved: HashMap::new(),
-            defined: HashMap::new(),
-        };
-        for (lib_name, lib_path) in libs.resolved.iter() {
-            let elf = libs.opened_libs.get(lib_path).unwrap().get_elf();
-            for sym in elf.dynsyms.iter() {
-                if let Some(name) = elf.dynstrtab.get(sym.st_name) {
-                    let name = name.expect("Symbol is not valid utf8");
-
-                    if !name.is_empty() && sym.st_bind() == BIND_GLOBAL
-                        && sym.st_other != VIS_HIDDEN
-                        && sym.st_shndx != NDX_UNDEFINED
-                    {
-                        let entry = summary
-                            .exported
-                            .entry(name.to_string())
-                            .or_insert(HashSet::new());
-                        let _ = entry.insert(lib_name.to_string_lossy().to_string());
-                    }
-                    if !name.is_empty() && sym.st_shndx == NDX_UNDEFINED {
-                        let entry = summary
-                            .unresolved
-                            .entry(name.to_string())
-                            .or_insert(HashSet::new());
-                        let _ = entry.insert(lib_name.to_string_lossy().to_string());
-                    }
-                    if !name.is_empty() && sym.st_shndx != NDX_UNDEFINED {
-                        let entry = summary
-                            .defined
-                            .entry(name.to_string())
-                            .or_insert(HashSet::new());
-                        let _ = entry.insert(lib_name.to_string_lossy().to_string());
-                    }
+        let mut resolved = libs.resolved
+            .iter()
+            .map(|(lib_name, lib_path)| {
+                (
+                    lib_name.to_string_lossy().to_string(),
+                    lib_path,
+                    &libs.opened_libs[lib_path],
+                )
+            })
+            .collect::<Vec<_>>();
+        // The root is usually registered by its DT_SONAME (or file name) as well, but it has to
+        // be part of the summary regardless.
+        if !libs.resolved.values().any(|path| path == &libs.root) {
+            if let Some(root) = libs.opened_libs.get(&libs.root) {
+                resolved.push((
+                    root.get_name().to_string_lossy().to_string(),
+                    &libs.root,
+                    root,
+                ));
+            }
+        }
+        let lists = parallel_map(resolved, |(lib_name, lib_path, lib)| {
+            let from_filtees = libs.resolved_filtees(lib_path)
+                .filter_map(|filtee| libs.opened_libs.get(filtee))
+                .flat_map(|filtee| filtee.symbols().exported.iter())
+                .collect::<HashSet<_>>();
+            (lib_name, lib.symbols(), from_filtees)
+        });
+
+        let num_shards = ::std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let chunk_size = lists.len().div_ceil(num_shards);
+        let chunks = lists.chunks(chunk_size.max(1)).collect::<Vec<_>>();
+        let partials = parallel_map(chunks, |chunk| {
+            let mut shards = (0..num_shards)
+                .map(|_| SymbolSummary::default())
+                .collect::<Vec<_>>();
+            for &(ref lib_name, symbols, ref from_filtees) in chunk {
+                let exported = symbols.exported.iter().filter(|name| !from_filtees.contains(name));
+                for name in exported {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    add_lib(&mut shard.exported, name, lib_name);
+                }
+                let weakly_exported = symbols
+                    .weakly_exported
+                    .iter()
+                    .filter(|name| !from_filtees.contains(name));
+                for name in weakly_exported {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    add_lib(&mut shard.weakly_exported, name, lib_name);
+                }
+                for name in symbols.undefined.iter() {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    add_lib(&mut shard.unresolved, name, lib_name);
+                }
+                for name in symbols.weakly_undefined.iter() {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    add_lib(&mut shard.weakly_unresolved, name, lib_name);
+                }
+                let defined = symbols.defined.iter().filter(|name| !from_filtees.contains(name));
+                for name in defined {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    add_lib(&mut shard.defined, name, lib_name);
+                }
+                for &(ref name, class) in symbols.special_classes.iter() {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    let _ = shard.special_classes.entry(name.clone()).or_insert(class);
+                }
+                for (versioned, name) in symbols.default_versions.iter() {
+                    let shard = &mut shards[shard_of(name, num_shards)];
+                    let _ = shard.aliases.insert(versioned.clone(), name.clone());
                 }
             }
+            shards
+        });
+
+        // Regroup the partial summaries by shard.
+        let mut shards = (0..num_shards).map(|_| Vec::new()).collect::<Vec<_>>();
+        for partial in partials {
+            for (i, shard) in partial.into_iter().enumerate() {
+                shards[i].push(shard);
+            }
         }
+        let merged = parallel_map(shards, |partials| {
+            let mut partials = partials.into_iter();
+            let mut shard = partials.next().unwrap_or_default();
+            for partial in partials {
+                shard.merge(partial);
+            }
+            shard
+        });
+
+        let mut merged = merged.into_iter();
+        let mut summary = merged.next().unwrap_or_default();
+        for shard in merged {
+            summary.exported.extend(shard.exported);
+            summary.weakly_exported.extend(shard.weakly_exported);
+            summary.unresolved.extend(shard.unresolved);
+            summary.weakly_unresolved.extend(shard.weakly_unresolved);
+            summary.defined.extend(shard.defined);
+            summary.special_classes.extend(shard.special_classes);
+            summary.aliases.extend(shard.aliases);
+        }
+        summary.apply_aliases();
         summary
     }
+
+    /// List the references of default versions under the plain name of their definitions.
+    fn apply_aliases(&mut self) {
+        let aliases = &self.aliases;
+        for references in [&mut self.unresolved, &mut self.weakly_unresolved] {
+            let aliased = references
+                .keys()
+                .filter(|name| aliases.contains_key(*name))
+                .cloned()
+                .collect::<Vec<_>>();
+            for versioned in aliased {
+                let lib_names = references.remove(&versioned).unwrap_or_default();
+                references
+                    .entry(aliases[&versioned].clone())
+                    .or_insert(HashSet::new())
+                    .extend(lib_names);
+            }
+        }
+    }
+
+    /// The libraries exporting a definition that a reference with the given (possibly versioned)
+    /// name binds to.
+    pub fn exporters(&self, reference: &str) -> Option<&HashSet<String>> {
+        self.exported
+            .get(reference)
+            .or_else(|| self.aliases.get(reference).and_then(|name| self.exported.get(name)))
+    }
+
+    /// Whether some library references the symbol (strongly or weakly), so that it matters which
+    /// definition the reference binds to.
+    pub fn is_referenced(&self, symbol: &str) -> bool {
+        self.unresolved.contains_key(symbol) || self.weakly_unresolved.contains_key(symbol)
+    }
+
+    /// The libraries among `lib_names` whose definitions of the exported symbol count as
+    /// duplicates. Weak definitions rank below strong ones: They are meant to be overridden by a
+    /// strong definition or, like the copies of C++ inline functions and templates, to be merged.
+    /// Unique symbols never count, as the loader binds all references to a single definition by
+    /// design.
+    pub fn duplicate_exporters(
+        &self,
+        symbol: &str,
+        lib_names: &HashSet<String>,
+    ) -> HashSet<String> {
+        if self.special_classes.get(symbol) == Some(&SymbolClass::Unique) {
+            return HashSet::new();
+        }
+        match self.weakly_exported.get(symbol) {
+            Some(weak) => lib_names.difference(weak).cloned().collect(),
+            None => lib_names.clone(),
+        }
+    }
+
+    /// Add the symbols of another summary (e.g., of another root in the same link namespace).
+    pub fn merge(&mut self, other: SymbolSummary) {
+        for (mine, theirs) in [(&mut self.exported, other.exported),
+            (&mut self.weakly_exported, other.weakly_exported),
+            (&mut self.unresolved, other.unresolved),
+            (&mut self.weakly_unresolved, other.weakly_unresolved),
+            (&mut self.defined, other.defined)] {
+            for (name, lib_names) in theirs {
+                mine.entry(name).or_insert(HashSet::new()).extend(lib_names);
+            }
+        }
+        for (name, class) in other.special_classes {
+            let _ = self.special_classes.entry(name).or_insert(class);
+        }
+        self.aliases.extend(other.aliases);
+        self.apply_aliases();
+    }
 }
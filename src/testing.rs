@@ -0,0 +1,88 @@
+//! Fixtures for the tests of the resolution and the reports: in-memory ELF files, which are
+//! analyzed from a `MemoryFs`.
+
+use files::MemoryFs;
+use libraries::{Environment, LibSearchMethod, LibraryIdentity, ResolveSettings, Traversal};
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub const NEEDED_D_TAG: u64 = 1;
+const STRTAB_D_TAG: u64 = 5;
+const STRSZ_D_TAG: u64 = 10;
+
+fn put(bytes: &mut Vec<u8>, value: u64, size: usize) {
+    bytes.extend(&value.to_le_bytes()[..size]);
+}
+
+/// A minimal 64-bit little-endian x86_64 shared object with the given string valued dynamic
+/// entries (and no symbols): one PT_LOAD segment mapping the whole file at address 0 and a
+/// PT_DYNAMIC segment.
+pub fn elf(entries: &[(u64, &str)]) -> Vec<u8> {
+    const HEADERS_SIZE: usize = 64 + 2 * 56;
+    let mut strtab = vec![0u8];
+    let mut dyns = Vec::new();
+    for &(tag, value) in entries {
+        dyns.push((tag, strtab.len() as u64));
+        strtab.extend(value.as_bytes());
+        strtab.push(0);
+    }
+    dyns.push((STRTAB_D_TAG, HEADERS_SIZE as u64));
+    dyns.push((STRSZ_D_TAG, strtab.len() as u64));
+    dyns.push((0, 0));
+    let dynamic_offset = (HEADERS_SIZE + strtab.len() + 7) & !7;
+    let size = (dynamic_offset + dyns.len() * 16) as u64;
+
+    let mut bytes = b"\x7fELF\x02\x01\x01".to_vec();
+    bytes.resize(16, 0);
+    put(&mut bytes, 3, 2); // ET_DYN
+    put(&mut bytes, 62, 2); // EM_X86_64
+    put(&mut bytes, 1, 4);
+    put(&mut bytes, 0, 8); // e_entry
+    put(&mut bytes, 64, 8); // e_phoff
+    put(&mut bytes, 0, 8); // e_shoff
+    put(&mut bytes, 0, 4);
+    for &value in &[64, 56, 2, 64, 0, 0] {
+        put(&mut bytes, value, 2);
+    }
+    let dynamic_size = size - dynamic_offset as u64;
+    for &(p_type, offset, size) in &[(1, 0, size), (2, dynamic_offset as u64, dynamic_size)] {
+        put(&mut bytes, p_type, 4);
+        put(&mut bytes, 4, 4); // PF_R
+        for &value in &[offset, offset, offset, size, size, 8] {
+            put(&mut bytes, value, 8);
+        }
+    }
+    bytes.extend(strtab);
+    bytes.resize(dynamic_offset, 0);
+    for (tag, value) in dyns {
+        put(&mut bytes, tag, 8);
+        put(&mut bytes, value, 8);
+    }
+    bytes
+}
+
+/// Settings to resolve the closure of a file in `files`: DT_RPATH, LD_LIBRARY_PATH (set to
+/// `ld_library_path`, if any), DT_RUNPATH and /lib are searched in this order.
+pub fn settings(files: MemoryFs, ld_library_path: Option<&str>) -> ResolveSettings {
+    let mut vars = HashMap::new();
+    if let Some(value) = ld_library_path {
+        let _ = vars.insert(OsString::from("LD_LIBRARY_PATH"), OsString::from(value));
+    }
+    ResolveSettings {
+        search_methods: vec![
+            LibSearchMethod::RPath,
+            LibSearchMethod::LDLibraryPath,
+            LibSearchMethod::RunPath,
+            LibSearchMethod::Fixed(PathBuf::from("/lib")),
+        ],
+        secure_execution: false,
+        sysroot: None,
+        identity: LibraryIdentity::default(),
+        traversal: Traversal::default(),
+        environment: Environment::Snapshot(vars),
+        files: Arc::new(files),
+    }
+}
@@ -0,0 +1,92 @@
+use goblin::elf::dyn::DT_TEXTREL;
+use goblin::elf::program_header::{PF_W, PT_LOAD};
+
+use fingerprint::{fingerprint, normalize_path};
+use libraries::{Library, LibraryDependencies};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const DF_TEXTREL: u64 = 0x4;
+
+/// An object of the closure with relocations in read-only segments (text relocations). The
+/// loader has to make these segments writable while relocating them, which W^X policies (e.g.,
+/// SELinux without execmod) forbid, and the patched pages cannot be shared between processes.
+pub struct TextRelocations {
+    pub lib: PathBuf,
+    /// The dynamic entries that announce the text relocations (DT_TEXTREL, DF_TEXTREL)
+    pub indicators: Vec<&'static str>,
+    /// The number of dynamic relocations that patch read-only segments
+    pub count: usize,
+}
+
+impl TextRelocations {
+    /// The object with the text relocations.
+    pub fn lib(&self) -> &Path {
+        &self.lib
+    }
+
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint("text-relocations", &[&normalize_path(&self.lib)])
+    }
+}
+
+impl fmt::Display for TextRelocations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: Has text relocations ({}; relocations in read-only segments: {}). The loader \
+             makes the segments writable while relocating, which W^X policies forbid and which \
+             slows down startup. Compile all of its objects with -fPIC",
+            self.lib,
+            self.indicators.join(", "),
+            self.count
+        )
+    }
+}
+
+/// Check whether the object has text relocations.
+fn check_text_relocations(lib: &Library) -> Option<TextRelocations> {
+    let elf = lib.get_elf();
+    let mut indicators = Vec::new();
+    if elf.dynamic
+        .as_ref()
+        .is_some_and(|dynamic| dynamic.dyns.iter().any(|d| d.d_tag == DT_TEXTREL))
+    {
+        indicators.push("DT_TEXTREL");
+    }
+    if lib.dynamic_flags().0 & DF_TEXTREL != 0 {
+        indicators.push("DF_TEXTREL");
+    }
+    if indicators.is_empty() {
+        return None;
+    }
+
+    let read_only = elf.program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD && ph.p_flags & PF_W == 0)
+        .map(|ph| ph.p_vaddr..ph.p_vaddr + ph.p_memsz)
+        .collect::<Vec<_>>();
+    let count = elf.dynrelas
+        .iter()
+        .chain(elf.dynrels.iter())
+        .chain(elf.pltrelocs.iter())
+        .filter(|reloc| read_only.iter().any(|range| range.contains(&reloc.r_offset)))
+        .count();
+    Some(TextRelocations {
+        lib: lib.get_path().to_path_buf(),
+        indicators,
+        count,
+    })
+}
+
+/// Find the objects of the closure (including the analyzed file) with text relocations.
+pub fn find_text_relocations(libs: &LibraryDependencies) -> Vec<TextRelocations> {
+    let mut paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| check_text_relocations(&libs.opened_libs[path]))
+        .collect()
+}
@@ -0,0 +1,91 @@
+use term;
+
+use pager::ReportTerminal;
+
+use std::str::FromStr;
+
+/// How severe a reported finding is. Determines how section headers are styled.
+#[derive(Debug, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// The styles that are used for the different severities.
+#[derive(Debug, Clone, Copy)]
+#[derive(Default)]
+pub enum Theme {
+    /// Errors red, warnings yellow, infos dim
+    #[default]
+    Default,
+    /// Bright colors for dark or low-contrast terminals
+    HighContrast,
+    /// No colors, severities are distinguished by text attributes only
+    Monochrome,
+}
+
+pub const THEME_NAMES: &[&str] = &["default", "high-contrast", "monochrome"];
+
+
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Theme::Default),
+            "high-contrast" => Ok(Theme::HighContrast),
+            "monochrome" => Ok(Theme::Monochrome),
+            other => Err(format!(
+                "Invalid theme '{}'. Valid themes are: {}",
+                other,
+                THEME_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+fn set_attr_if_supported(t: &mut ReportTerminal, attr: term::Attr) {
+    if t.supports_attr(attr) {
+        t.attr(attr).unwrap();
+    }
+}
+
+impl Theme {
+    /// Set the style for text of the given severity. Use `t.reset()` to return to normal text.
+    pub fn apply(&self, t: &mut ReportTerminal, severity: Severity) {
+        match (*self, severity) {
+            (Theme::Default, Severity::Error) => {
+                t.fg(term::color::RED).unwrap();
+                t.attr(term::Attr::Bold).unwrap();
+            }
+            (Theme::Default, Severity::Warning) => {
+                t.fg(term::color::YELLOW).unwrap();
+                t.attr(term::Attr::Bold).unwrap();
+            }
+            (Theme::Default, Severity::Info) => {
+                set_attr_if_supported(t, term::Attr::Dim);
+            }
+            (Theme::HighContrast, Severity::Error) => {
+                t.fg(term::color::BRIGHT_RED).unwrap();
+                t.attr(term::Attr::Bold).unwrap();
+            }
+            (Theme::HighContrast, Severity::Warning) => {
+                t.fg(term::color::BRIGHT_YELLOW).unwrap();
+                t.attr(term::Attr::Bold).unwrap();
+            }
+            (Theme::HighContrast, Severity::Info) => {
+                t.fg(term::color::BRIGHT_WHITE).unwrap();
+            }
+            (Theme::Monochrome, Severity::Error) => {
+                t.attr(term::Attr::Bold).unwrap();
+                set_attr_if_supported(t, term::Attr::Underline(true));
+            }
+            (Theme::Monochrome, Severity::Warning) => {
+                t.attr(term::Attr::Bold).unwrap();
+            }
+            (Theme::Monochrome, Severity::Info) => {
+                set_attr_if_supported(t, term::Attr::Dim);
+            }
+        }
+    }
+}
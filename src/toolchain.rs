@@ -0,0 +1,90 @@
+use libraries::{ErrorMsg, LibSearchMethod};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Compilers that are tried (prefixed with the toolchain prefix) in this order.
+const COMPILERS: &[&str] = &["gcc", "cc", "clang"];
+const LIBRARIES_PREFIX: &str = "libraries: =";
+
+/// The sysroot and default library directories of a (cross) compiler toolchain.
+pub struct Toolchain {
+    pub sysroot: Option<PathBuf>,
+    pub library_dirs: Vec<PathBuf>,
+}
+
+/// Run the compiler with the given argument and return its output if it succeeded.
+fn compiler_output(compiler: &str, arg: &str) -> Option<String> {
+    Command::new(compiler)
+        .arg(arg)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl Toolchain {
+    /// Ask the compiler of the toolchain with the given prefix (e.g., aarch64-linux-gnu-) for its
+    /// sysroot (-print-sysroot) and library search directories (-print-search-dirs).
+    pub fn query(prefix: &str) -> Result<Toolchain, Box<dyn Error>> {
+        let (compiler, search_dirs) = COMPILERS
+            .iter()
+            .map(|compiler| format!("{}{}", prefix, compiler))
+            .filter_map(|compiler| {
+                compiler_output(&compiler, "-print-search-dirs").map(|out| (compiler, out))
+            })
+            .next()
+            .ok_or_else(|| {
+                ErrorMsg(format!(
+                    "No compiler found for toolchain prefix {:?} (tried {})",
+                    prefix,
+                    COMPILERS.join(", ")
+                ))
+            })?;
+
+        // The sysroot is printed with redundant ".." components as well. It must be canonical, so
+        // that the library directories inside it can be recognized as such.
+        let sysroot = compiler_output(&compiler, "-print-sysroot")
+            .map(|out| out.trim().to_string())
+            .filter(|sysroot| !sysroot.is_empty())
+            .and_then(|sysroot| fs::canonicalize(sysroot).ok())
+            .filter(|sysroot| sysroot != Path::new("/"));
+
+        // The directories are listed with redundant ".." components. Normalize them and drop
+        // those that do not exist.
+        let mut seen = HashSet::new();
+        let library_dirs = search_dirs
+            .lines()
+            .filter(|line| line.starts_with(LIBRARIES_PREFIX))
+            .flat_map(|line| line[LIBRARIES_PREFIX.len()..].split(':'))
+            .filter_map(|dir| fs::canonicalize(dir).ok())
+            .filter(|dir| dir.is_dir() && seen.insert(dir.clone()))
+            .collect();
+
+        Ok(Toolchain {
+            sysroot,
+            library_dirs,
+        })
+    }
+
+    /// The search order of the loader on the target: RPATH, RUNPATH and the ld.so.conf of the
+    /// sysroot (if any), followed by the library directories of the compiler. Those outside of
+    /// the sysroot (i.e., internal ones of the compiler) are searched on the host as they are.
+    /// LD_LIBRARY_PATH is not considered, because it belongs to the host.
+    pub fn search_methods(&self) -> Vec<LibSearchMethod> {
+        let mut methods = vec![LibSearchMethod::RPath, LibSearchMethod::RunPath];
+        if let Some(ref sysroot) = self.sysroot {
+            if sysroot.join("etc/ld.so.conf").is_file() {
+                methods.push(LibSearchMethod::LDConfig(PathBuf::from("/etc/ld.so.conf")));
+            }
+        }
+        methods.extend(self.library_dirs.iter().map(|dir| match self.sysroot {
+            Some(ref sysroot) if !dir.starts_with(sysroot) => LibSearchMethod::Host(dir.clone()),
+            _ => LibSearchMethod::Fixed(dir.clone()),
+        }));
+        methods
+    }
+}
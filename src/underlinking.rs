@@ -0,0 +1,118 @@
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+use unused_dependencies::first_definitions;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The maximum number of symbols listed per providing object.
+const MAX_LISTED_SYMBOLS: usize = 5;
+
+/// A library that references symbols which none of its direct dependencies provide (i.e., that
+/// would fail to link with -Wl,--no-undefined). It only works because other objects of the
+/// closure happen to be loaded in the global scope.
+pub struct UnderlinkedLibrary {
+    pub lib: PathBuf,
+    /// The objects that the references bind to, with the symbols taken from them
+    pub providers: BTreeMap<PathBuf, Vec<String>>,
+}
+
+impl UnderlinkedLibrary {
+    /// The underlinked library.
+    pub fn lib(&self) -> &Path {
+        &self.lib
+    }
+
+    /// The number of symbols that the library takes from objects it does not depend on.
+    pub fn symbol_count(&self) -> usize {
+        self.providers.values().map(|symbols| symbols.len()).sum()
+    }
+
+    /// A stable fingerprint of the finding (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint("underlinked-library", &[&normalize_path(&self.lib)])
+    }
+}
+
+impl fmt::Display for UnderlinkedLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let providers = self.providers
+            .iter()
+            .map(|(provider, symbols)| {
+                let mut listed = symbols
+                    .iter()
+                    .take(MAX_LISTED_SYMBOLS)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if symbols.len() > MAX_LISTED_SYMBOLS {
+                    listed.push(format!("{} more", symbols.len() - MAX_LISTED_SYMBOLS));
+                }
+                format!("{:?} ({})", provider, listed.join(", "))
+            })
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "{:?}: References {} symbol(s) that none of its DT_NEEDED dependencies provide, so it \
+             only works because other loaded objects define them: {}. Link it against the \
+             providing libraries (and with -Wl,--no-undefined)",
+            self.lib,
+            self.symbol_count(),
+            providers.join(", ")
+        )
+    }
+}
+
+/// Find the shared libraries of the closure with strong references that bind to objects which
+/// are neither the library's direct dependencies nor their filtees. References that stay
+/// unresolved or bind to the analyzed file are left to the respective other checks.
+pub fn find_underlinked_libraries(libs: &LibraryDependencies) -> Vec<UnderlinkedLibrary> {
+    let definitions = first_definitions(libs);
+    let mut paths = libs.opened_libs
+        .keys()
+        .filter(|path| *path != &libs.root)
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut underlinked = Vec::new();
+    for path in paths {
+        let mut direct = libs.resolved_dependencies(path).collect::<HashSet<_>>();
+        let filtees = direct
+            .iter()
+            .flat_map(|dependency| libs.resolved_filtees(dependency))
+            .collect::<Vec<_>>();
+        direct.extend(filtees);
+
+        let mut providers = BTreeMap::new();
+        for symbol in libs.opened_libs[path].symbols().undefined.iter() {
+            let provider = match definitions.get(symbol) {
+                Some(provider) => *provider,
+                None => continue,
+            };
+            if provider == &libs.root || provider == path || direct.contains(provider) {
+                continue;
+            }
+            let provided = direct.iter().any(|dependency| {
+                libs.opened_libs
+                    .get(*dependency)
+                    .is_some_and(|lib| lib.symbols().exports(symbol))
+            });
+            if !provided {
+                providers
+                    .entry(provider.clone())
+                    .or_insert(Vec::new())
+                    .push(symbol.clone());
+            }
+        }
+        if !providers.is_empty() {
+            for symbols in providers.values_mut() {
+                symbols.sort();
+            }
+            underlinked.push(UnderlinkedLibrary {
+                lib: path.clone(),
+                providers,
+            });
+        }
+    }
+    underlinked
+}
@@ -0,0 +1,197 @@
+use fingerprint::{fingerprint, normalize_path};
+use initializers::initializer_count;
+use libraries::LibraryDependencies;
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A DT_NEEDED entry whose library none of the references of the object bind to (like the
+/// unused direct dependencies of `ldd -u`).
+pub enum UnusedDependency {
+    /// The library has no constructors either, so the entry is most likely a leftover of linking
+    /// without --as-needed and can be removed.
+    Speculative {
+        object: PathBuf,
+        lib_name: String,
+        library: PathBuf,
+    },
+    /// The library has constructors, so it may be needed for their side effects (e.g.,
+    /// registering plugins or interposing allocators) and should only be removed with care.
+    ConstructorsOnly {
+        object: PathBuf,
+        lib_name: String,
+        library: PathBuf,
+    },
+    /// The library provides symbols that the object references, but the references bind to
+    /// definitions that come earlier in load order, so the library is not used at runtime (though
+    /// --as-needed would keep it).
+    Shadowed {
+        object: PathBuf,
+        lib_name: String,
+        library: PathBuf,
+        /// The objects whose definitions the references bind to instead
+        bound_to: Vec<PathBuf>,
+    },
+}
+
+impl UnusedDependency {
+    /// The object that carries the DT_NEEDED entry.
+    pub fn object(&self) -> &Path {
+        match self {
+            &UnusedDependency::Speculative { ref object, .. }
+            | &UnusedDependency::ConstructorsOnly { ref object, .. }
+            | &UnusedDependency::Shadowed { ref object, .. } => object,
+        }
+    }
+
+    /// Whether the entry can most likely be removed (as opposed to being needed for constructors).
+    pub fn is_speculative(&self) -> bool {
+        matches!(self, &UnusedDependency::Speculative { .. })
+    }
+
+    /// Whether the library provides used symbols, but other definitions take precedence.
+    pub fn is_shadowed(&self) -> bool {
+        matches!(self, &UnusedDependency::Shadowed { .. })
+    }
+
+    /// A stable fingerprint of the unused dependency (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let (kind, lib_name) = match self {
+            UnusedDependency::Speculative { lib_name, .. } => {
+                ("speculative-dependency", lib_name)
+            }
+            UnusedDependency::ConstructorsOnly { lib_name, .. } => {
+                ("constructors-only-dependency", lib_name)
+            }
+            UnusedDependency::Shadowed { lib_name, .. } => {
+                ("shadowed-dependency", lib_name)
+            }
+        };
+        fingerprint(kind, &[&normalize_path(self.object()), lib_name])
+    }
+}
+
+impl fmt::Display for UnusedDependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnusedDependency::Speculative {
+                object,
+                lib_name,
+                library,
+            } => write!(
+                f,
+                "{:?}: Dependency {:?} ({:?}) provides none of the used symbols and has no constructors. Probably linked without --as-needed",
+                object, lib_name, library
+            ),
+            UnusedDependency::ConstructorsOnly {
+                object,
+                lib_name,
+                library,
+            } => write!(
+                f,
+                "{:?}: Dependency {:?} ({:?}) provides none of the used symbols, but may be needed for its constructors",
+                object, lib_name, library
+            ),
+            UnusedDependency::Shadowed {
+                object,
+                lib_name,
+                library,
+                bound_to,
+            } => write!(
+                f,
+                "{:?}: Dependency {:?} ({:?}) provides used symbols, but they bind to earlier definitions in load order (in {}), so it is not used at runtime",
+                object,
+                lib_name,
+                library,
+                bound_to
+                    .iter()
+                    .map(|path| format!("{:?}", path))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Map each symbol exported in the global lookup scope (the analyzed file and its dependencies in
+/// load order) to the object whose definition references bind to, i.e., the first one.
+pub fn first_definitions(libs: &LibraryDependencies) -> HashMap<&String, &PathBuf> {
+    let mut scope = vec![&libs.root];
+    for (_, path) in libs.needed_in_load_order() {
+        if let Some(path) = path.filter(|path| !scope.contains(path)) {
+            scope.push(path);
+        }
+    }
+    let mut definitions = HashMap::new();
+    for path in scope {
+        if let Some(lib) = libs.opened_libs.get(path) {
+            for symbol in lib.symbols().export_names() {
+                let _ = definitions.entry(symbol).or_insert(path);
+            }
+        }
+    }
+    definitions
+}
+
+/// Find the (resolved) DT_NEEDED entries of all objects of the closure that none of the
+/// references of the object bind to. Entries whose library does not provide any referenced symbol
+/// are those that linking with --as-needed would drop.
+pub fn find_unused_dependencies(libs: &LibraryDependencies) -> Vec<UnusedDependency> {
+    let definitions = first_definitions(libs);
+    let mut objects = libs.needed.keys().collect::<Vec<_>>();
+    objects.sort_by_key(|object| (*object != &libs.root, *object));
+
+    let mut unused = Vec::new();
+    for object in objects {
+        let symbols = libs.opened_libs[object].symbols();
+        let undefined = symbols
+            .undefined
+            .iter()
+            .chain(symbols.weakly_undefined.iter())
+            .collect::<HashSet<_>>();
+        let bound = undefined
+            .iter()
+            .filter_map(|symbol| definitions.get(symbol).cloned())
+            .collect::<HashSet<_>>();
+        for lib_name in libs.needed[object].iter() {
+            let library = match libs.lookup(OsStr::new(lib_name)) {
+                Some(library) => library,
+                None => continue,
+            };
+            if bound.contains(library) {
+                continue;
+            }
+            let lib = &libs.opened_libs[library];
+            let mut bound_to = lib.symbols()
+                .export_names()
+                .filter(|symbol| undefined.contains(symbol))
+                .filter_map(|symbol| definitions.get(symbol).map(|path| (*path).clone()))
+                .collect::<Vec<_>>();
+            bound_to.sort();
+            bound_to.dedup();
+            unused.push(if !bound_to.is_empty() {
+                UnusedDependency::Shadowed {
+                    object: object.clone(),
+                    lib_name: lib_name.clone(),
+                    library: library.clone(),
+                    bound_to,
+                }
+            } else if initializer_count(lib) > 0 {
+                UnusedDependency::ConstructorsOnly {
+                    object: object.clone(),
+                    lib_name: lib_name.clone(),
+                    library: library.clone(),
+                }
+            } else {
+                UnusedDependency::Speculative {
+                    object: object.clone(),
+                    lib_name: lib_name.clone(),
+                    library: library.clone(),
+                }
+            });
+        }
+    }
+    unused
+}
@@ -0,0 +1,126 @@
+use libraries::{reroot, Environment, ErrorMsg, LibSearchMethod};
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PASSWD: &str = "/etc/passwd";
+
+/// The home directory of the user `name` according to the passwd file of the analyzed system.
+fn home_of(name: &str, sysroot: Option<&Path>) -> Result<String, Box<dyn Error>> {
+    let passwd = reroot(Path::new(PASSWD), sysroot);
+    let content = fs::read_to_string(&passwd)
+        .map_err(|e| ErrorMsg(format!("Cannot read {:?}: {}", passwd, e)))?;
+    content
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 6 && fields[0] == name)
+        .map(|fields| fields[5].to_owned())
+        .ok_or_else(|| {
+            let message = format!("User '{}' not found in {:?}", name, passwd);
+            Box::new(ErrorMsg(message)) as Box<dyn Error>
+        })
+}
+
+/// Read an environment snapshot: NUL separated (as /proc/<pid>/environ or `env -0`) or line
+/// separated (as `env` or systemd environment files, whose comment lines are skipped)
+/// assignments.
+fn read_snapshot(path: &Path) -> Result<HashMap<OsString, OsString>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| ErrorMsg(format!("Cannot read environment snapshot {:?}: {}", path, e)))?;
+    let separator = if content.contains('\0') { '\0' } else { '\n' };
+    Ok(content
+        .split(separator)
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => {
+                    Some((OsString::from(name.trim()), OsString::from(value)))
+                }
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Expand a leading `~` of every colon separated entry and `$HOME`/`${HOME}` references in
+/// `value` to `home`, as the shell of the user would.
+fn expand_home(value: &str, home: &str) -> String {
+    value
+        .split(':')
+        .map(|entry| {
+            let entry = if entry == "~" || entry.starts_with("~/") {
+                format!("{}{}", home, &entry[1..])
+            } else {
+                entry.to_owned()
+            };
+            entry.replace("${HOME}", home).replace("$HOME", home)
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// A user whose environment the analyzed files are loaded in (see --as-user).
+pub struct User {
+    /// The home directory (as set in the environment of the user)
+    pub home: String,
+    pub environment: Environment,
+}
+
+impl User {
+    /// Build the environment of the user: The snapshot (if given) or the environment of
+    /// linkcheck, with HOME (looked up in the passwd file of the analyzed system), USER and
+    /// LOGNAME of the user (unless set by the snapshot) and home relative values expanded.
+    pub fn lookup(
+        name: &str,
+        snapshot: Option<&Path>,
+        sysroot: Option<&Path>,
+    ) -> Result<User, Box<dyn Error>> {
+        let mut vars = match snapshot {
+            Some(snapshot) => read_snapshot(snapshot)?,
+            None => env::vars_os()
+                .filter(|(name, _)| name != "HOME" && name != "USER" && name != "LOGNAME")
+                .collect(),
+        };
+        if let Entry::Vacant(entry) = vars.entry(OsString::from("HOME")) {
+            let _ = entry.insert(OsString::from(home_of(name, sysroot)?));
+        }
+        for &variable in ["USER", "LOGNAME"].iter() {
+            let _ = vars.entry(OsString::from(variable))
+                .or_insert(OsString::from(name));
+        }
+
+        let home = vars[&OsString::from("HOME")].to_string_lossy().to_string();
+        let vars = vars.into_iter()
+            .map(|(name, value)| {
+                let value = OsString::from(expand_home(&value.to_string_lossy(), &home));
+                (name, value)
+            })
+            .collect();
+        Ok(User {
+            home,
+            environment: Environment::Snapshot(vars),
+        })
+    }
+
+    /// Expand home relative paths of the search method (if any) as the user.
+    /// Expand home relative entries of a (colon separated) value given for the environment.
+    pub fn expand_value(&self, value: &str) -> String {
+        expand_home(value, &self.home)
+    }
+
+    pub fn expand_search_method(&self, method: LibSearchMethod) -> LibSearchMethod {
+        let expand = |path: PathBuf| PathBuf::from(expand_home(&path.to_string_lossy(), &self.home));
+        match method {
+            LibSearchMethod::LDConfig(conf_file) => LibSearchMethod::LDConfig(expand(conf_file)),
+            LibSearchMethod::Fixed(dir) => LibSearchMethod::Fixed(expand(dir)),
+            LibSearchMethod::Host(dir) => LibSearchMethod::Host(expand(dir)),
+            other => other,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use cxxruntime::LIBSTDCXX;
+use fingerprint::{fingerprint, normalize_path};
+use libraries::LibraryDependencies;
+use symbol_versions::SymbolVersions;
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A library exports a symbol only in non-default versions (foo@V1, but no foo@@V2), while other
+/// libraries reference it without a version.
+pub struct NonDefaultVersionOnly {
+    pub lib: PathBuf,
+    pub symbol: String,
+    pub versions: Vec<String>,
+    pub dependents: Vec<PathBuf>,
+    /// Whether one of the versions is the oldest version of the library, which the loader still
+    /// binds the references to (see `SymbolVersions::oldest`)
+    pub binds_oldest: bool,
+}
+
+impl NonDefaultVersionOnly {
+    /// Whether the loader fails to resolve the references: It does not bind references without
+    /// version to non-default versions other than the oldest one.
+    pub fn is_error(&self) -> bool {
+        !self.binds_oldest
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "non-default-version-only",
+            &[&normalize_path(&self.lib), &self.symbol],
+        )
+    }
+}
+
+impl fmt::Display for NonDefaultVersionOnly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} is only exported as {}, but referenced without version by {}. {}",
+            self.lib,
+            self.symbol,
+            self.versions
+                .iter()
+                .map(|v| format!("{}@{}", self.symbol, v))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.dependents
+                .iter()
+                .map(|d| format!("{:?}", d))
+                .collect::<Vec<_>>()
+                .join(", "),
+            if self.binds_oldest {
+                "Existing binaries still run, because the loader binds them to the oldest \
+                 version, but linking against this library again will fail to resolve it"
+            } else {
+                "The loader does not bind references without version to non-default versions \
+                 (except the oldest one), so the symbol cannot be resolved for them"
+            }
+        )
+    }
+}
+
+/// Find symbols of the closure that are only exported in non-default versions but referenced
+/// without version, unless another object of the closure provides a default version that the
+/// references can bind to instead.
+pub fn find_non_default_version_only(libs: &LibraryDependencies) -> Vec<NonDefaultVersionOnly> {
+    let mut paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    paths.sort();
+    let versions = paths
+        .iter()
+        .map(|&path| (path, SymbolVersions::of_library(&libs.opened_libs[path])))
+        .collect::<Vec<_>>();
+
+    let mut problems = Vec::new();
+    for &(lib, ref lib_versions) in versions.iter() {
+        let mut symbols = lib_versions.exported.iter().collect::<Vec<_>>();
+        symbols.sort_by_key(|&(symbol, _)| symbol);
+        for (symbol, symbol_versions) in symbols {
+            let has_default = |versions: &[(String, bool)]| {
+                versions.iter().any(|&(_, is_default)| is_default)
+            };
+            if has_default(symbol_versions) {
+                continue;
+            }
+            let provided_elsewhere = versions.iter().any(|&(other, ref other_versions)| {
+                other != lib
+                    && other_versions
+                        .exported
+                        .get(symbol)
+                        .into_iter()
+                        .any(|v| has_default(v))
+            });
+            if provided_elsewhere {
+                continue;
+            }
+            let dependents = versions
+                .iter()
+                .filter(|&&(other, ref other_versions)| {
+                    other != lib && other_versions.unversioned_references.contains(symbol)
+                })
+                .map(|&(other, _)| other.clone())
+                .collect::<Vec<_>>();
+            if dependents.is_empty() {
+                continue;
+            }
+            problems.push(NonDefaultVersionOnly {
+                lib: lib.clone(),
+                symbol: symbol.clone(),
+                versions: symbol_versions.iter().map(|(v, _)| v.clone()).collect(),
+                dependents,
+                binds_oldest: symbol_versions
+                    .iter()
+                    .any(|(v, _)| lib_versions.oldest.as_ref() == Some(v)),
+            });
+        }
+    }
+    problems
+}
+
+/// Versions that an object requires from a library (DT_VERNEED), but which the resolved library
+/// does not define (DT_VERDEF).
+pub struct MissingVersions {
+    pub lib: PathBuf,
+    /// The name of the library that has to define the versions
+    pub lib_name: String,
+    pub provider: PathBuf,
+    /// The missing versions, because of which the loader refuses to load the object
+    pub missing: Vec<String>,
+    /// The missing versions that are only required weakly (VER_FLG_WEAK)
+    pub weakly_missing: Vec<String>,
+    /// Whether the provider has no version definitions at all, which the loader only warns about
+    pub unversioned: bool,
+}
+
+impl MissingVersions {
+    /// The object requiring the versions.
+    pub fn lib(&self) -> &Path {
+        &self.lib
+    }
+
+    /// Whether loading the object fails.
+    pub fn is_error(&self) -> bool {
+        !self.unversioned && !self.missing.is_empty()
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        fingerprint(
+            "missing-version",
+            &[&normalize_path(&self.lib), &normalize_path(&self.provider)],
+        )
+    }
+}
+
+impl fmt::Display for MissingVersions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unversioned {
+            let mut versions = self.missing.clone();
+            versions.extend(self.weakly_missing.iter().cloned());
+            return write!(
+                f,
+                "{:?}: Requires versions {} of {}, but {:?} has no version information. The \
+                 loader only warns about this, but the library is likely not the expected one",
+                self.lib,
+                versions.join(", "),
+                self.lib_name,
+                self.provider
+            );
+        }
+        if !self.missing.is_empty() {
+            write!(
+                f,
+                "{:?}: Requires versions {} of {}, which {:?} does not define, so loading fails \
+                 with \"version `{}' not found\"",
+                self.lib,
+                self.missing.join(", "),
+                self.lib_name,
+                self.provider,
+                self.missing[0]
+            )?;
+        } else {
+            write!(f, "{:?}: ", self.lib)?;
+        }
+        if !self.weakly_missing.is_empty() {
+            write!(
+                f,
+                "{}Weakly requires versions {} of {}, which {:?} does not define (the loader only \
+                 warns about this)",
+                if self.missing.is_empty() { "" } else { ". " },
+                self.weakly_missing.join(", "),
+                self.lib_name,
+                self.provider
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the objects of the closure whose required versions (DT_VERNEED) the resolved providers do
+/// not define. Versions required from libstdc++ are left to the C++ runtime check, unless the
+/// resolved libstdc++ is unversioned.
+pub fn find_missing_versions(libs: &LibraryDependencies) -> Vec<MissingVersions> {
+    let mut paths = libs.opened_libs.keys().collect::<Vec<_>>();
+    paths.sort();
+
+    let mut problems = Vec::new();
+    for path in paths {
+        let versions = SymbolVersions::of_library(&libs.opened_libs[path]);
+        let mut required = versions.required.iter().collect::<Vec<_>>();
+        required.sort_by_key(|&(lib_name, _)| lib_name);
+        for (lib_name, required_versions) in required {
+            let provider = match libs.lookup(OsStr::new(lib_name)) {
+                Some(provider) => provider,
+                None => continue,
+            };
+            let defined = match libs.opened_libs.get(provider) {
+                Some(lib) => SymbolVersions::of_library(lib).defined,
+                None => continue,
+            };
+            // Every version definition table contains at least the base version (the soname).
+            let unversioned = defined.is_empty();
+            if lib_name == LIBSTDCXX && !unversioned {
+                continue;
+            }
+            let (mut weakly_missing, mut missing): (Vec<_>, Vec<_>) = required_versions
+                .iter()
+                .filter(|version| !defined.contains(*version))
+                .cloned()
+                .partition(|version| versions.weakly_required.contains(version));
+            if missing.is_empty() && weakly_missing.is_empty() {
+                continue;
+            }
+            missing.sort();
+            weakly_missing.sort();
+            problems.push(MissingVersions {
+                lib: path.clone(),
+                lib_name: lib_name.clone(),
+                provider: provider.clone(),
+                missing,
+                weakly_missing,
+                unversioned,
+            });
+        }
+    }
+    problems
+}
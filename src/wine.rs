@@ -0,0 +1,331 @@
+use goblin::pe::PE;
+
+use fingerprint::{fingerprint, normalize_path};
+use libraries::ErrorMsg;
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const PE_MAGIC: &[u8] = b"MZ";
+
+/// Prefixes of API set names, which the loader maps to the implementing DLLs itself (so they
+/// need not exist as files).
+const API_SET_PREFIXES: &[&str] = &["api-ms-win-", "ext-ms-"];
+
+/// Whether the file is a PE file (i.e., starts with a DOS header).
+pub fn is_pe(path: &Path) -> bool {
+    let mut magic = [0u8; 2];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| magic == PE_MAGIC)
+        .unwrap_or(false)
+}
+
+/// A Wine prefix (e.g., ~/.wine or a Proton compatdata pfx directory), whose drive_c contains
+/// the system DLLs that PE files are run against.
+pub struct WinePrefix {
+    pub root: PathBuf,
+}
+
+impl WinePrefix {
+    pub fn open(path: &Path) -> Result<WinePrefix, Box<dyn Error>> {
+        if !path.join("drive_c/windows").is_dir() {
+            return Err(Box::new(ErrorMsg(format!(
+                "{:?} is not a Wine prefix (it has no drive_c/windows directory)",
+                path
+            ))));
+        }
+        Ok(WinePrefix {
+            root: path.to_path_buf(),
+        })
+    }
+
+    /// The system directories searched for DLLs after the application directory: 32 bit files
+    /// use syswow64 in 64 bit prefixes and system32 otherwise.
+    fn system_dirs(&self, is_64: bool) -> Vec<PathBuf> {
+        let windows = self.root.join("drive_c/windows");
+        let syswow64 = windows.join("syswow64");
+        let system = if !is_64 && syswow64.is_dir() {
+            syswow64
+        } else {
+            windows.join("system32")
+        };
+        vec![system, windows]
+    }
+}
+
+/// A problem of the closure of a PE file.
+pub enum PeProblem {
+    MissingDll {
+        object: PathBuf,
+        dll: String,
+    },
+    /// The DLL only exists for the other architecture (32 vs. 64 bit), which the loader skips.
+    WrongArchitecture {
+        object: PathBuf,
+        dll: String,
+        path: PathBuf,
+    },
+    MissingImports {
+        object: PathBuf,
+        dll: String,
+        path: PathBuf,
+        symbols: Vec<String>,
+    },
+}
+
+impl PeProblem {
+    /// The object whose import cannot be satisfied.
+    pub fn object(&self) -> &Path {
+        match self {
+            &PeProblem::MissingDll { ref object, .. }
+            | &PeProblem::WrongArchitecture { ref object, .. }
+            | &PeProblem::MissingImports { ref object, .. } => object,
+        }
+    }
+
+    /// Whether the problem is about a DLL (as opposed to symbols).
+    pub fn is_dll_problem(&self) -> bool {
+        !matches!(self, &PeProblem::MissingImports { .. })
+    }
+
+    /// A stable fingerprint of the problem (see `fingerprint::fingerprint`).
+    pub fn fingerprint(&self) -> String {
+        let (kind, dll) = match self {
+            PeProblem::MissingDll { dll, .. } => ("pe-missing-dll", dll),
+            PeProblem::WrongArchitecture { dll, .. } => ("pe-wrong-architecture", dll),
+            PeProblem::MissingImports { dll, .. } => ("pe-missing-imports", dll),
+        };
+        fingerprint(kind, &[&normalize_path(self.object()), &dll.to_lowercase()])
+    }
+}
+
+impl fmt::Display for PeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PeProblem::MissingDll {
+                object,
+                dll,
+            } => write!(
+                f,
+                "{:?}: DLL {:?} is found neither in the application directory nor in the Wine prefix",
+                object, dll
+            ),
+            PeProblem::WrongArchitecture {
+                object,
+                dll,
+                path,
+            } => write!(
+                f,
+                "{:?}: DLL {:?} is only found as {:?}, which has the wrong architecture",
+                object, dll, path
+            ),
+            PeProblem::MissingImports {
+                object,
+                dll,
+                path,
+                symbols,
+            } => write!(
+                f,
+                "{:?}: Imports {} from {:?} ({:?}), which does not export them",
+                object,
+                symbols.join(", "),
+                dll,
+                path
+            ),
+        }
+    }
+}
+
+/// The result of checking a PE file against a Wine prefix.
+pub struct PeCheck {
+    pub file: PathBuf,
+    /// The file could not be analyzed at all.
+    pub error: Option<String>,
+    /// The DLLs of the closure (by lower case name) and the files they resolve to.
+    pub dlls: BTreeMap<String, PathBuf>,
+    pub problems: Vec<PeProblem>,
+}
+
+/// What is needed of a PE file for checking its closure.
+struct PeInfo {
+    is_64: bool,
+    /// The imported DLLs and the names of the symbols imported from them (by ordinal imports are
+    /// not checked).
+    imports: Vec<(String, Vec<String>)>,
+    /// The names of the exported symbols (None if the file has no export table, as some stub
+    /// DLLs of Wine prefixes).
+    exports: Option<HashSet<String>>,
+}
+
+impl PeInfo {
+    fn read(path: &Path) -> Result<PeInfo, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{:?}: {}", path, e))?;
+        let pe = PE::parse(&bytes).map_err(|e| format!("{:?}: {}", path, e))?;
+        let imports = pe.libraries
+            .iter()
+            .map(|dll| {
+                let symbols = pe.imports
+                    .iter()
+                    .filter(|import| import.dll.eq_ignore_ascii_case(dll))
+                    .filter(|import| !import.name.starts_with("ORDINAL "))
+                    .map(|import| import.name.to_string())
+                    .collect();
+                (dll.to_string(), symbols)
+            })
+            .collect();
+        let exports = pe.export_data.as_ref().map(|_| {
+            pe.exports
+                .iter()
+                .filter(|export| !export.name.is_empty())
+                .map(|export| export.name.to_owned())
+                .collect()
+        });
+        Ok(PeInfo {
+            is_64: pe.is_64,
+            imports,
+            exports,
+        })
+    }
+}
+
+/// Looks up files case-insensitively (as Windows does), listing every directory only once.
+struct CaseInsensitiveDirs {
+    entries: HashMap<PathBuf, HashMap<String, PathBuf>>,
+}
+
+impl CaseInsensitiveDirs {
+    fn find(&mut self, dir: &Path, name: &str) -> Option<PathBuf> {
+        self.entries
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                fs::read_dir(dir)
+                    .into_iter()
+                    .flat_map(|entries| entries.filter_map(|e| e.ok()))
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_lowercase();
+                        (name, entry.path())
+                    })
+                    .collect()
+            })
+            .get(&name.to_lowercase())
+            .cloned()
+    }
+}
+
+/// Check the closure of a PE file against a Wine prefix. DLLs are searched in the directory of
+/// the file and then in the system directories of the prefix. DLLs of the application directory
+/// are checked recursively, those of the prefix are provided by Wine and not followed further.
+pub fn check_pe_file(file: &Path, prefix: &WinePrefix) -> PeCheck {
+    let mut check = PeCheck {
+        file: file.to_path_buf(),
+        error: None,
+        dlls: BTreeMap::new(),
+        problems: Vec::new(),
+    };
+    let root = match PeInfo::read(file) {
+        Ok(root) => root,
+        Err(err) => {
+            check.error = Some(err);
+            return check;
+        }
+    };
+    let is_64 = root.is_64;
+    let app_dir = file.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut search_dirs = vec![app_dir.clone()];
+    search_dirs.extend(prefix.system_dirs(is_64));
+
+    let mut dirs = CaseInsensitiveDirs {
+        entries: HashMap::new(),
+    };
+    let mut infos = HashMap::new();
+    let mut queue = VecDeque::new();
+    let _ = infos.insert(file.to_path_buf(), Ok(root));
+    queue.push_back(file.to_path_buf());
+
+    while let Some(object) = queue.pop_front() {
+        let imports = match infos[&object] {
+            Ok(ref info) => info.imports.clone(),
+            Err(_) => continue,
+        };
+        for (dll, symbols) in imports {
+            let key = dll.to_lowercase();
+            let path = match check.dlls.get(&key) {
+                Some(path) => path.clone(),
+                None => {
+                    let mut found = None;
+                    let mut wrong_arch = None;
+                    for dir in search_dirs.iter() {
+                        let candidate = match dirs.find(dir, &dll) {
+                            Some(candidate) => candidate,
+                            None => continue,
+                        };
+                        let info = infos
+                            .entry(candidate.clone())
+                            .or_insert_with(|| PeInfo::read(&candidate));
+                        match info {
+                            &mut Ok(ref info) if info.is_64 != is_64 => {
+                                wrong_arch = wrong_arch.or(Some(candidate));
+                            }
+                            _ => {
+                                found = Some(candidate);
+                                break;
+                            }
+                        }
+                    }
+                    match (found, wrong_arch) {
+                        (Some(path), _) => {
+                            let _ = check.dlls.insert(key, path.clone());
+                            if path.parent() == Some(&app_dir) {
+                                queue.push_back(path.clone());
+                            }
+                            path
+                        }
+                        (None, Some(path)) => {
+                            check.problems.push(PeProblem::WrongArchitecture {
+                                object: object.clone(),
+                                dll,
+                                path,
+                            });
+                            continue;
+                        }
+                        (None, None) => {
+                            if !API_SET_PREFIXES.iter().any(|p| key.starts_with(p)) {
+                                check.problems.push(PeProblem::MissingDll {
+                                    object: object.clone(),
+                                    dll,
+                                });
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            // Files that cannot be parsed or have no export table cannot be checked.
+            if let Ok(PeInfo {
+                exports: Some(ref exports),
+                ..
+            }) = infos[&path]
+            {
+                let missing = symbols
+                    .into_iter()
+                    .filter(|symbol| !exports.contains(symbol))
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    check.problems.push(PeProblem::MissingImports {
+                        object: object.clone(),
+                        dll,
+                        path,
+                        symbols: missing,
+                    });
+                }
+            }
+        }
+    }
+    check
+}